@@ -63,6 +63,7 @@ use crate::database::{
     Database,
     Secret,
 };
+use crate::os::Os;
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 pub enum OAuthFlow {
@@ -286,7 +287,7 @@ pub struct BuilderIdToken {
 }
 
 impl BuilderIdToken {
-    const SECRET_KEY: &'static str = "codewhisperer:odic:token";
+    pub(crate) const SECRET_KEY: &'static str = "codewhisperer:odic:token";
 
     #[cfg(test)]
     fn test() -> Self {
@@ -563,6 +564,16 @@ pub async fn is_logged_in(database: &mut Database) -> bool {
     }
 }
 
+/// Whether credentials are stored locally. Unlike [is_logged_in], this never refreshes or
+/// validates the token, so an expired token still counts.
+pub async fn has_local_credentials(os: &Os) -> bool {
+    if os.env.get("AMAZON_Q_SIGV4").is_ok_and(|v| !v.is_empty()) {
+        return true;
+    }
+
+    matches!(os.database.get_secret(BuilderIdToken::SECRET_KEY).await, Ok(Some(_)))
+}
+
 pub async fn logout(database: &mut Database) -> Result<(), AuthError> {
     let Ok(secret_store) = Database::new().await else {
         return Ok(());
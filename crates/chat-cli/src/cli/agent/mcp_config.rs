@@ -7,7 +7,10 @@ use serde::{
     Serialize,
 };
 
-use crate::cli::chat::tools::custom_tool::CustomToolConfig;
+use crate::cli::chat::tools::custom_tool::{
+    CustomToolConfig,
+    McpConfigSource,
+};
 use crate::os::Os;
 
 // This is to mirror claude's config set up
@@ -31,6 +34,14 @@ impl McpServerConfig {
         Ok(serde_json::from_value(config)?)
     }
 
+    /// Marks every server as defined in `source`
+    pub fn with_source(mut self, source: McpConfigSource) -> Self {
+        for config in self.mcp_servers.values_mut() {
+            config.source = source;
+        }
+        self
+    }
+
     pub async fn save_to_file(&self, os: &Os, path: impl AsRef<Path>) -> eyre::Result<()> {
         let json = self.to_non_transparent_json_pretty()?;
         os.fs.write(path.as_ref(), json).await?;
@@ -54,6 +54,7 @@ pub use wrapper_types::{
     tool_settings_schema,
 };
 
+use super::chat::tools::custom_tool::McpConfigSource;
 use super::chat::tools::{
     DEFAULT_APPROVE,
     NATIVE_TOOLS,
@@ -229,7 +230,11 @@ impl Agent {
 
         if let (true, Some(legacy_mcp_config)) = (self.use_legacy_mcp_json, legacy_mcp_config) {
             for (name, legacy_server) in &legacy_mcp_config.mcp_servers {
-                if mcp_servers.mcp_servers.contains_key(name) {
+                if let Some(server) = mcp_servers.mcp_servers.get_mut(name) {
+                    server.overridden_sources.push(legacy_server.source);
+                    server
+                        .overridden_sources
+                        .extend(legacy_server.overridden_sources.iter().copied());
                     let _ = queue!(
                         output,
                         style::SetForegroundColor(Color::Yellow),
@@ -279,6 +284,48 @@ impl Agent {
         Ok(())
     }
 
+    /// A stable hash of the parts of the agent that determine which tools are offered and how they
+    /// are permitted: the enabled tools, aliases, allowed tools, tool settings (including
+    /// denylists) and mcp server configs. Equivalent configs hash the same regardless of the order
+    /// they were written in, making this suitable as a cache key.
+    #[allow(dead_code)] // Used by upcoming tool and prompt caches
+    pub fn config_fingerprint(&self) -> String {
+        use sha2::{
+            Digest,
+            Sha256,
+        };
+
+        fn canonicalize(value: serde_json::Value) -> serde_json::Value {
+            match value {
+                serde_json::Value::Object(map) => {
+                    let mut entries = map.into_iter().collect::<Vec<_>>();
+                    entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+                    serde_json::Value::Object(entries.into_iter().map(|(k, v)| (k, canonicalize(v))).collect())
+                },
+                serde_json::Value::Array(values) => {
+                    serde_json::Value::Array(values.into_iter().map(canonicalize).collect())
+                },
+                other => other,
+            }
+        }
+
+        let mut tools = self.tools.iter().collect::<Vec<_>>();
+        tools.sort();
+        tools.dedup();
+        let mut allowed_tools = self.allowed_tools.iter().collect::<Vec<_>>();
+        allowed_tools.sort();
+
+        let config = canonicalize(serde_json::json!({
+            "tools": tools,
+            "toolAliases": self.tool_aliases,
+            "allowedTools": allowed_tools,
+            "toolsSettings": self.tools_settings,
+            "mcpServers": self.mcp_servers,
+        }));
+
+        hex::encode(Sha256::digest(config.to_string()))
+    }
+
     pub fn to_str_pretty(&self) -> eyre::Result<String> {
         let mut agent_clone = self.clone();
         agent_clone.freeze();
@@ -371,6 +418,180 @@ impl Agent {
         self.tools_settings
             .retain(|target, _| !is_mcp_tool_ref(&target.to_string()));
     }
+
+    /// What changes in going from this agent to `other`
+    pub fn diff(&self, other: &Agent) -> AgentDiff {
+        let (ours, theirs) = (&self.mcp_servers.mcp_servers, &other.mcp_servers.mcp_servers);
+        let mut diff = AgentDiff {
+            added_servers: theirs
+                .keys()
+                .filter(|name| !ours.contains_key(*name))
+                .cloned()
+                .collect(),
+            removed_servers: ours
+                .keys()
+                .filter(|name| !theirs.contains_key(*name))
+                .cloned()
+                .collect(),
+            changed_servers: ours
+                .iter()
+                .filter(|(name, config)| theirs.get(*name).is_some_and(|other| other != *config))
+                .map(|(name, _)| name.clone())
+                .collect(),
+            tools_added: other
+                .tools
+                .iter()
+                .filter(|t| !self.tools.contains(t))
+                .cloned()
+                .collect(),
+            tools_removed: self
+                .tools
+                .iter()
+                .filter(|t| !other.tools.contains(t))
+                .cloned()
+                .collect(),
+            allowed_tools_added: other.allowed_tools.difference(&self.allowed_tools).cloned().collect(),
+            allowed_tools_removed: self.allowed_tools.difference(&other.allowed_tools).cloned().collect(),
+            aliases_added: other
+                .tool_aliases
+                .iter()
+                .filter(|(orig, _)| !self.tool_aliases.contains_key(*orig))
+                .map(|(orig, alias)| (orig.to_string(), alias.clone()))
+                .collect(),
+            aliases_removed: self
+                .tool_aliases
+                .iter()
+                .filter(|(orig, _)| !other.tool_aliases.contains_key(*orig))
+                .map(|(orig, alias)| (orig.to_string(), alias.clone()))
+                .collect(),
+            aliases_changed: self
+                .tool_aliases
+                .iter()
+                .filter_map(|(orig, alias)| {
+                    let new_alias = other.tool_aliases.get(orig)?;
+                    (new_alias != alias).then(|| (orig.to_string(), alias.clone(), new_alias.clone()))
+                })
+                .collect(),
+            tools_settings_changed: self
+                .tools_settings
+                .keys()
+                .chain(other.tools_settings.keys())
+                .filter(|tool| self.tools_settings.get(*tool) != other.tools_settings.get(*tool))
+                .map(|tool| tool.0.clone())
+                .collect(),
+            use_legacy_mcp_json: (self.use_legacy_mcp_json != other.use_legacy_mcp_json)
+                .then_some(other.use_legacy_mcp_json),
+        };
+        diff.sort();
+        diff
+    }
+}
+
+/// The differences between two agent configurations, as returned by [Agent::diff]. Every list is
+/// sorted so that the diff can be displayed and compared as is.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct AgentDiff {
+    pub added_servers: Vec<String>,
+    pub removed_servers: Vec<String>,
+    /// Servers present in both agents with a different configuration
+    pub changed_servers: Vec<String>,
+    pub tools_added: Vec<String>,
+    pub tools_removed: Vec<String>,
+    pub allowed_tools_added: Vec<String>,
+    pub allowed_tools_removed: Vec<String>,
+    /// (original tool name, alias)
+    pub aliases_added: Vec<(String, String)>,
+    /// (original tool name, alias)
+    pub aliases_removed: Vec<(String, String)>,
+    /// (original tool name, old alias, new alias)
+    pub aliases_changed: Vec<(String, String, String)>,
+    /// Tools whose settings, such as their denied commands or paths, were added, removed or changed
+    pub tools_settings_changed: Vec<String>,
+    /// The new `useLegacyMcpJson`, if it changed, which also changes the servers in effect
+    pub use_legacy_mcp_json: Option<bool>,
+}
+
+impl AgentDiff {
+    fn sort(&mut self) {
+        self.added_servers.sort();
+        self.removed_servers.sort();
+        self.changed_servers.sort();
+        self.tools_added.sort();
+        self.tools_removed.sort();
+        self.allowed_tools_added.sort();
+        self.allowed_tools_removed.sort();
+        self.aliases_added.sort();
+        self.aliases_removed.sort();
+        self.aliases_changed.sort();
+        self.tools_settings_changed.sort();
+        self.tools_settings_changed.dedup();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Writes the diff as a list of changes, one per line
+    pub fn print(&self, output: &mut impl Write) -> io::Result<()> {
+        let mut line = |sign: &str, color: Color, text: String| {
+            queue!(
+                output,
+                style::SetForegroundColor(color),
+                style::Print(format!("  {sign} ")),
+                style::ResetColor,
+                style::Print(text),
+                style::Print("\n"),
+            )
+        };
+
+        for name in &self.added_servers {
+            line("+", Color::Green, format!("MCP server {name}"))?;
+        }
+        for name in &self.removed_servers {
+            line("-", Color::Red, format!("MCP server {name}"))?;
+        }
+        for name in &self.changed_servers {
+            line("~", Color::Yellow, format!("MCP server {name} (configuration changed)"))?;
+        }
+        for tool in &self.tools_added {
+            line("+", Color::Green, format!("tool {tool}"))?;
+        }
+        for tool in &self.tools_removed {
+            line("-", Color::Red, format!("tool {tool}"))?;
+        }
+        for tool in &self.allowed_tools_added {
+            line("+", Color::Green, format!("trusted tool {tool}"))?;
+        }
+        for tool in &self.allowed_tools_removed {
+            line("-", Color::Red, format!("trusted tool {tool}"))?;
+        }
+        for (orig, alias) in &self.aliases_added {
+            line("+", Color::Green, format!("alias {orig} -> {alias}"))?;
+        }
+        for (orig, alias) in &self.aliases_removed {
+            line("-", Color::Red, format!("alias {orig} -> {alias}"))?;
+        }
+        for (orig, old, new) in &self.aliases_changed {
+            line("~", Color::Yellow, format!("alias {orig} -> {new} (was {old})"))?;
+        }
+        for tool in &self.tools_settings_changed {
+            line("~", Color::Yellow, format!("settings of tool {tool}"))?;
+        }
+        match self.use_legacy_mcp_json {
+            Some(true) => line(
+                "+",
+                Color::Green,
+                "MCP servers from the legacy mcp.json files".to_string(),
+            )?,
+            Some(false) => line(
+                "-",
+                Color::Red,
+                "MCP servers from the legacy mcp.json files".to_string(),
+            )?,
+            None => {},
+        }
+        output.flush()
+    }
 }
 
 /// Result of evaluating tool permissions, indicating whether a tool should be allowed,
@@ -493,11 +714,11 @@ impl Agents {
         let mut local_agents = 'local: {
             // We could be launching from the home dir, in which case the global and local agents
             // are the same set of agents. If that is the case, we simply skip this.
-            match (std::env::current_dir(), directories::home_dir(os)) {
-                (Ok(cwd), Ok(home_dir)) if cwd == home_dir => break 'local Vec::<Agent>::new(),
+            match (directories::project_root(os), directories::home_dir(os)) {
+                (Ok(root), Ok(home_dir)) if root == home_dir => break 'local Vec::<Agent>::new(),
                 _ => {
                     // noop, we keep going with the extraction of local agents (even if we have an
-                    // error retrieving cwd or home_dir)
+                    // error retrieving the project root or home_dir)
                 },
             }
 
@@ -701,7 +922,7 @@ impl Agents {
                                     break 'load_legacy_mcp_json;
                                 },
                             };
-                            global_mcp_config.replace(legacy_mcp_config);
+                            global_mcp_config.replace(legacy_mcp_config.with_source(McpConfigSource::GlobalMcpJson));
                         }
                     }
 
@@ -826,6 +1047,8 @@ impl Agents {
             "introspect" => "trusted".dark_green().bold(),
             "thinking" => "trusted (prerelease)".dark_green().bold(),
             "todo_list" => "trusted".dark_green().bold(),
+            "scratchpad_read" | "scratchpad_write" => "trusted".dark_green().bold(),
+            "processes" => "trust listing".dark_grey(),
             _ if self.trust_all_tools => "trusted".dark_grey().bold(),
             _ => "not trusted".dark_grey(),
         };
@@ -872,7 +1095,7 @@ async fn load_agents_from_entries(
 async fn load_legacy_mcp_config(os: &Os) -> eyre::Result<Option<McpServerConfig>> {
     let global_mcp_path = directories::chat_legacy_global_mcp_config(os)?;
     let global_mcp_config = match McpServerConfig::load_from_file(os, global_mcp_path).await {
-        Ok(config) => Some(config),
+        Ok(config) => Some(config.with_source(McpConfigSource::GlobalMcpJson)),
         Err(e) => {
             tracing::error!("Error loading global mcp json path: {e}.");
             None
@@ -881,7 +1104,7 @@ async fn load_legacy_mcp_config(os: &Os) -> eyre::Result<Option<McpServerConfig>
 
     let workspace_mcp_path = directories::chat_legacy_workspace_mcp_config(os)?;
     let workspace_mcp_config = match McpServerConfig::load_from_file(os, workspace_mcp_path).await {
-        Ok(config) => Some(config),
+        Ok(config) => Some(config.with_source(McpConfigSource::WorkspaceMcpJson)),
         Err(e) => {
             tracing::error!("Error loading global mcp json path: {e}.");
             None
@@ -892,7 +1115,12 @@ async fn load_legacy_mcp_config(os: &Os) -> eyre::Result<Option<McpServerConfig>
         (Some(mut wc), Some(gc)) => {
             for (server_name, config) in gc.mcp_servers {
                 // We prioritize what is in the workspace
-                wc.mcp_servers.entry(server_name).or_insert(config);
+                match wc.mcp_servers.get_mut(&server_name) {
+                    Some(workspace_config) => workspace_config.overridden_sources.push(config.source),
+                    None => {
+                        wc.mcp_servers.insert(server_name, config);
+                    },
+                }
             }
 
             Some(wc)
@@ -989,6 +1217,36 @@ mod tests {
             }
         "#;
 
+    #[test]
+    fn test_config_fingerprint() {
+        let agent = serde_json::from_str::<Agent>(INPUT).unwrap();
+        let reordered = serde_json::from_str::<Agent>(
+            r#"
+            {
+              "name": "renamed_agent",
+              "toolsSettings": {
+                "@git/git_status": { "git_user": "$GIT_USER" },
+                "fs_write": { "allowedPaths": ["~/**"] }
+              },
+              "allowedTools": ["@gits/git_status", "@fetch", "fs_read"],
+              "toolAliases": { "@gits/some_tool": "some_tool2" },
+              "tools": ["@git"],
+              "mcpServers": {
+                "git": { "args": [], "command": "git-mcp" },
+                "fetch": { "args": [], "command": "fetch3.1" }
+              }
+            }
+            "#,
+        )
+        .unwrap();
+        assert_eq!(agent.config_fingerprint(), reordered.config_fingerprint());
+        assert_eq!(agent.config_fingerprint().len(), 64);
+
+        let mut changed = agent.clone();
+        changed.mcp_servers.mcp_servers.get_mut("git").unwrap().args = vec!["--verbose".to_string()];
+        assert_ne!(agent.config_fingerprint(), changed.config_fingerprint());
+    }
+
     #[test]
     fn test_deser() {
         let agent = serde_json::from_str::<Agent>(INPUT).expect("Deserializtion failed");
@@ -1296,6 +1554,73 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_duplicate_mcp_server_definitions() {
+        let os = Os::new().await.unwrap();
+        let write_mcp_json = |path: PathBuf, servers: serde_json::Value| {
+            let os = &os;
+            async move {
+                os.fs.create_dir_all(path.parent().unwrap()).await.unwrap();
+                os.fs
+                    .write(&path, json!({ "mcpServers": servers }).to_string())
+                    .await
+                    .unwrap();
+            }
+        };
+        write_mcp_json(
+            directories::chat_legacy_global_mcp_config(&os).unwrap(),
+            json!({
+                "shared": { "command": "global-server" },
+                "global_only": { "command": "global-only-server" },
+            }),
+        )
+        .await;
+        write_mcp_json(
+            directories::chat_legacy_workspace_mcp_config(&os).unwrap(),
+            json!({ "shared": { "command": "workspace-server" } }),
+        )
+        .await;
+
+        // The workspace definition wins over the global one
+        let legacy = load_legacy_mcp_config(&os).await.unwrap().unwrap();
+        let shared = &legacy.mcp_servers["shared"];
+        assert_eq!(shared.command, "workspace-server");
+        assert_eq!(shared.source, McpConfigSource::WorkspaceMcpJson);
+        assert_eq!(shared.overridden_sources, vec![McpConfigSource::GlobalMcpJson]);
+        assert_eq!(
+            shared.duplicate_definition_warning("shared").unwrap(),
+            "Server shared is defined in both the workspace mcp.json and the global mcp.json. Using the definition from the workspace mcp.json."
+        );
+        assert!(
+            legacy.mcp_servers["global_only"]
+                .duplicate_definition_warning("global_only")
+                .is_none()
+        );
+
+        // And the agent's own definition wins over both
+        let mut agent = serde_json::from_value::<Agent>(json!({
+            "name": "agent",
+            "mcpServers": { "shared": { "command": "agent-server" } },
+            "useLegacyMcpJson": true,
+        }))
+        .unwrap();
+        agent
+            .thaw(Path::new("/agent.json"), Some(&legacy), &mut std::io::sink())
+            .unwrap();
+        let shared = &agent.mcp_servers.mcp_servers["shared"];
+        assert_eq!(shared.command, "agent-server");
+        assert_eq!(shared.overridden_sources, vec![
+            McpConfigSource::WorkspaceMcpJson,
+            McpConfigSource::GlobalMcpJson
+        ]);
+        assert!(
+            shared
+                .duplicate_definition_warning("shared")
+                .unwrap()
+                .ends_with("Using the definition from the agent config.")
+        );
+    }
+
     #[test]
     fn test_agent_model_field() {
         // Test deserialization with model field
@@ -1353,4 +1678,45 @@ mod tests {
 
         assert_eq!(agents.get_active().and_then(|a| a.model.as_ref()), None);
     }
+
+    #[test]
+    fn test_agent_diff() {
+        let agent = serde_json::from_str::<Agent>(INPUT).unwrap();
+        assert!(agent.diff(&agent).is_empty());
+
+        let mut other = agent.clone();
+        other.mcp_servers.mcp_servers.remove("fetch");
+        other.tool_aliases.insert(
+            serde_json::from_value(json!("@git/git_log")).unwrap(),
+            "log".to_string(),
+        );
+
+        let diff = agent.diff(&other);
+        assert_eq!(diff, AgentDiff {
+            removed_servers: vec!["fetch".to_string()],
+            aliases_added: vec![("@git/git_log".to_string(), "log".to_string())],
+            ..Default::default()
+        });
+
+        // The reverse diff reports the opposite changes
+        let reverse = other.diff(&agent);
+        assert_eq!(reverse.added_servers, ["fetch"]);
+        assert_eq!(reverse.aliases_removed, [(
+            "@git/git_log".to_string(),
+            "log".to_string()
+        )]);
+
+        // Denied commands and paths live in the tool settings
+        let mut other = agent.clone();
+        other.tools_settings.insert(
+            ToolSettingTarget("execute_bash".to_string()),
+            json!({ "deniedCommands": ["rm .*"] }),
+        );
+        other.use_legacy_mcp_json = !agent.use_legacy_mcp_json;
+        assert_eq!(agent.diff(&other), AgentDiff {
+            tools_settings_changed: vec!["execute_bash".to_string()],
+            use_legacy_mcp_json: Some(other.use_legacy_mcp_json),
+            ..Default::default()
+        });
+    }
 }
@@ -1,3 +1,12 @@
+use std::collections::{
+    BTreeSet,
+    HashMap,
+};
+use std::path::{
+    Path,
+    PathBuf,
+};
+
 use clap::Subcommand;
 use crossterm::queue;
 use crossterm::style::{
@@ -6,23 +15,36 @@ use crossterm::style::{
 };
 use eyre::Result;
 
+use crate::cli::chat::tool_manager::PromptBundle;
 use crate::cli::chat::{
     ChatError,
     ChatSession,
     ChatState,
 };
+use crate::database::settings::Setting;
 use crate::os::Os;
-use crate::util::command_manager::CommandManager;
+use crate::util::command_manager::{
+    CommandListing,
+    CommandManager,
+};
 use crate::util::command_types::{
+    CommandError,
     CommandScope,
     CustomCommand,
 };
+use crate::util::directories::canonicalizes_path;
 
 /// Custom commands management
 #[derive(Clone, Debug, PartialEq, Eq, Subcommand)]
 pub enum CommandsSubcommand {
     /// Add a new custom command
-    Add { name: String },
+    Add {
+        /// Name of the command to add
+        name: String,
+        /// Only print the path of the created command file
+        #[arg(long)]
+        quiet: bool,
+    },
     /// Show available commands
     Show {
         /// Filter by scope (project or global)
@@ -47,6 +69,21 @@ pub enum CommandsSubcommand {
         /// Name of the command to update
         name: String,
     },
+    /// Import all command files from a directory, e.g. when setting up a new machine
+    Import {
+        /// Directory containing the command files (*.md) to import
+        path: PathBuf,
+        /// Scope to import the commands into
+        #[arg(long, value_enum, default_value = "project")]
+        scope: CommandScope,
+        /// Overwrite existing commands with the same name
+        #[arg(long)]
+        force: bool,
+    },
+    /// Reload custom commands from disk, discarding any cached copies
+    Refresh,
+    /// Check custom commands for problems, such as names shared with MCP prompts
+    Doctor,
     /// Clear all custom commands
     Clear {
         /// Filter by scope (project or global)
@@ -66,6 +103,12 @@ impl CommandsSubcommand {
         }
 
         let result = self.execute_operation(os, session).await;
+        // Commands changed on disk must be read again rather than served from the session's cache
+        if !matches!(self, Self::Show { .. } | Self::Refresh | Self::Doctor) {
+            if let Some(manager) = &mut session.command_manager {
+                manager.clear_cache();
+            }
+        }
         Self::write_operation_result(session, result)?;
         Ok(Self::default_chat_state())
     }
@@ -85,15 +128,18 @@ impl CommandsSubcommand {
         }
     }
 
-    async fn execute_operation(&self, os: &Os, _session: &mut ChatSession) -> OperationResult {
+    async fn execute_operation(&self, os: &Os, session: &mut ChatSession) -> OperationResult {
         match self {
-            CommandsSubcommand::Add { name } => Self::handle_add(os, name).await,
+            CommandsSubcommand::Add { name, quiet } => Self::handle_add(os, session, name, *quiet).await,
             CommandsSubcommand::Show { scope, expand, name } => {
                 Self::handle_show(os, scope.clone(), *expand, name.clone()).await
             },
             CommandsSubcommand::Remove { name, force } => Self::handle_remove(os, name, *force).await,
             CommandsSubcommand::Update { name } => Self::handle_update(os, name).await,
             CommandsSubcommand::Clear { scope, force } => Self::handle_clear(os, scope.clone(), *force).await,
+            CommandsSubcommand::Import { path, scope, force } => Self::handle_import(os, path, scope, *force).await,
+            CommandsSubcommand::Refresh => Self::handle_refresh(os, session).await,
+            CommandsSubcommand::Doctor => Self::handle_doctor(os, session).await,
         }
     }
 
@@ -109,12 +155,13 @@ impl CommandsSubcommand {
         }
 
         // Otherwise, list all commands
-        match manager.list_commands_detailed(scope.as_ref()) {
-            Ok(commands) => {
-                if commands.is_empty() {
+        match manager.list_commands_with_failures(scope.as_ref()) {
+            Ok(CommandListing { commands, failures }) => {
+                if commands.is_empty() && failures.is_empty() {
                     let scope_text = match scope {
                         Some(CommandScope::Project) => " project",
                         Some(CommandScope::Global) => " global",
+                        Some(CommandScope::Shared) => " shared",
                         None => "",
                     };
                     OperationResult::Success(format!(
@@ -122,7 +169,7 @@ impl CommandsSubcommand {
                         scope_text
                     ))
                 } else {
-                    Self::format_commands_list(commands, expand)
+                    Self::format_commands_list(commands, &failures, expand)
                 }
             },
             Err(e) => OperationResult::Error(format!("Failed to list commands: {}", e)),
@@ -137,10 +184,11 @@ impl CommandsSubcommand {
                 output.push_str(&format!("📁 Scope: {}\n", match command.scope {
                     CommandScope::Project => "Project",
                     CommandScope::Global => "Global",
+                    CommandScope::Shared => "Shared (read-only)",
                 }));
                 output.push_str(&format!("📍 Path: {}\n", command.file_path.display()));
 
-                if let Some(description) = &command.frontmatter.description {
+                if let Some(description) = command.description() {
                     output.push_str(&format!("📝 Description: {}\n", description));
                 }
 
@@ -166,7 +214,7 @@ impl CommandsSubcommand {
                     "\n💡 Usage: /{}:{}\n",
                     match command.scope {
                         CommandScope::Project => "project",
-                        CommandScope::Global => "user",
+                        CommandScope::Global | CommandScope::Shared => "user",
                     },
                     command.name
                 ));
@@ -177,7 +225,12 @@ impl CommandsSubcommand {
         }
     }
 
-    fn format_commands_list(commands: Vec<CustomCommand>, expand: bool) -> OperationResult {
+    fn format_commands_list(
+        commands: Vec<CustomCommand>,
+        failures: &[(PathBuf, CommandError)],
+        expand: bool,
+    ) -> OperationResult {
+        let loaded = commands.len();
         let mut output = String::new();
         output.push_str("📁 Available Commands:\n\n");
 
@@ -185,11 +238,12 @@ impl CommandsSubcommand {
             let scope_icon = match command.scope {
                 CommandScope::Project => "📁",
                 CommandScope::Global => "🌍",
+                CommandScope::Shared => "📦",
             };
 
             let scope_text = match command.scope {
                 CommandScope::Project => "project",
-                CommandScope::Global => "user",
+                CommandScope::Global | CommandScope::Shared => "user",
             };
 
             output.push_str(&format!(
@@ -198,7 +252,7 @@ impl CommandsSubcommand {
             ));
 
             if expand {
-                if let Some(description) = &command.frontmatter.description {
+                if let Some(description) = command.description() {
                     output.push_str(&format!("     📝 {}\n", description));
                 }
                 if !command.frontmatter.tags.is_empty() {
@@ -212,6 +266,24 @@ impl CommandsSubcommand {
             }
         }
 
+        if !failures.is_empty() {
+            let failed = failures
+                .iter()
+                .map(|(path, err)| {
+                    let file = path
+                        .file_name()
+                        .map_or_else(|| path.to_string_lossy(), |f| f.to_string_lossy());
+                    format!("{file} ({err})")
+                })
+                .collect::<Vec<_>>()
+                .join(", ");
+            output.push_str(&format!(
+                "\n⚠️  {loaded} command{}, {} failed to load: {failed}\n",
+                if loaded == 1 { "" } else { "s" },
+                failures.len(),
+            ));
+        }
+
         if !expand {
             output.push_str("\n💡 Use '--expand' to see more details or specify a command name for full details.\n");
         }
@@ -219,18 +291,67 @@ impl CommandsSubcommand {
         OperationResult::Success(output)
     }
 
-    async fn handle_add(os: &Os, name: &str) -> OperationResult {
+    async fn handle_add(os: &Os, session: &ChatSession, name: &str, quiet: bool) -> OperationResult {
         let mut manager = match CommandManager::new(os) {
             Ok(manager) => manager,
             Err(e) => return OperationResult::Error(format!("Failed to initialize command manager: {}", e)),
         };
 
         match manager.add_command(name, os) {
-            Ok(message) => OperationResult::Success(message),
+            Ok(added) => {
+                let emoji = os.database.settings.get_bool(Setting::UiEmoji).unwrap_or(true);
+                let mut message = added.format(quiet, emoji);
+                if quiet {
+                    return OperationResult::Success(message);
+                }
+                if let Ok(prompts) = session.conversation.tool_manager.list_prompts().await {
+                    for collision in prompt_collisions([name], &prompts) {
+                        let warning = if emoji { "⚠️  " } else { "Warning: " };
+                        message.push_str(&format!("\n\n{warning}{collision}"));
+                    }
+                }
+                OperationResult::Success(message)
+            },
             Err(e) => OperationResult::Error(format!("Failed to add command: {}", e)),
         }
     }
 
+    async fn handle_doctor(os: &Os, session: &ChatSession) -> OperationResult {
+        let mut manager = match CommandManager::new(os) {
+            Ok(manager) => manager,
+            Err(e) => return OperationResult::Error(format!("Failed to initialize command manager: {}", e)),
+        };
+
+        let commands = match manager.list_commands_detailed(None) {
+            Ok(commands) => commands,
+            Err(e) => return OperationResult::Error(format!("Failed to list commands: {}", e)),
+        };
+        let prompts = match session.conversation.tool_manager.list_prompts().await {
+            Ok(prompts) => prompts,
+            Err(e) => return OperationResult::Error(format!("Failed to list MCP prompts: {}", e)),
+        };
+
+        let collisions = prompt_collisions(commands.iter().map(|command| command.name.as_str()), &prompts);
+        if collisions.is_empty() {
+            return OperationResult::Success(format!(
+                "✅ Checked {} commands against {} MCP prompts, no problems found.",
+                commands.len(),
+                prompts.len()
+            ));
+        }
+
+        let mut message = format!(
+            "⚠️  Found {} custom command{} sharing a name with an MCP prompt:\n",
+            collisions.len(),
+            if collisions.len() == 1 { "" } else { "s" }
+        );
+        for collision in collisions {
+            message.push_str(&format!("  • {}\n", collision));
+        }
+        message.push_str("\n💡 Rename the command, or set a promptPrefix for the server, to tell them apart.");
+        OperationResult::Success(message)
+    }
+
     async fn handle_remove(os: &Os, name: &str, force: bool) -> OperationResult {
         let mut manager = match CommandManager::new(os) {
             Ok(manager) => manager,
@@ -243,6 +364,14 @@ impl CommandsSubcommand {
             Err(_) => return OperationResult::Error(format!("Command '{}' not found", name)),
         };
 
+        if command.is_read_only() {
+            return OperationResult::Error(format!(
+                "Command '{}' is loaded from a shared directory and is read-only: {}",
+                name,
+                command.file_path.display()
+            ));
+        }
+
         // Confirmation prompt unless force is used
         if !force {
             use std::io::{
@@ -282,22 +411,106 @@ impl CommandsSubcommand {
             Err(_) => return OperationResult::Error(format!("Command '{}' not found", name)),
         };
 
+        if command.is_read_only() {
+            return OperationResult::Error(format!(
+                "Command '{}' is loaded from a shared directory and is read-only: {}",
+                name,
+                command.file_path.display()
+            ));
+        }
+
         // Open editor
-        match CommandManager::open_editor(&command.file_path) {
-            Ok(_) => OperationResult::Success(format!("✅ Command '{}' updated successfully.", name)),
+        match manager.open_editor(&command.file_path, os) {
+            Ok(notice) => OperationResult::Success(format!(
+                "{}✅ Command '{}' updated successfully.",
+                notice.map(|notice| format!("{notice}\n")).unwrap_or_default(),
+                name
+            )),
             Err(e) => OperationResult::Error(format!("Failed to open editor: {}", e)),
         }
     }
 
+    async fn handle_import(os: &Os, path: &Path, scope: &CommandScope, force: bool) -> OperationResult {
+        let mut manager = match CommandManager::new(os) {
+            Ok(manager) => manager,
+            Err(e) => return OperationResult::Error(format!("Failed to initialize command manager: {}", e)),
+        };
+
+        // Expand `~` and environment variables in the given path
+        let path = canonicalizes_path(os, &path.to_string_lossy()).map_or_else(|_| path.to_path_buf(), PathBuf::from);
+        match manager.import_from_dir(&path, scope, force) {
+            Ok(report) => {
+                let mut message = format!(
+                    "✅ Imported {} command{}",
+                    report.imported.len(),
+                    if report.imported.len() == 1 { "" } else { "s" }
+                );
+                for added in &report.imported {
+                    message.push_str(&format!("\n  • {}", added.name));
+                }
+                if !report.failures.is_empty() {
+                    message.push_str(&format!("\n\n⚠️  Skipped {}:", report.failures.len()));
+                    for (path, err) in &report.failures {
+                        message.push_str(&format!("\n  • {}: {}", path.display(), err));
+                    }
+                    if report
+                        .failures
+                        .iter()
+                        .any(|(_, err)| matches!(err, CommandError::AlreadyExists(_)))
+                    {
+                        message.push_str("\n\n💡 Use '--force' to overwrite existing commands.");
+                    }
+                }
+                OperationResult::Success(message)
+            },
+            Err(e) => OperationResult::Error(format!("Failed to import commands: {}", e)),
+        }
+    }
+
+    async fn handle_refresh(os: &Os, session: &mut ChatSession) -> OperationResult {
+        let manager = match session.command_manager(os) {
+            Ok(manager) => manager,
+            Err(e) => return OperationResult::Error(format!("Failed to initialize command manager: {}", e)),
+        };
+
+        let evicted = match manager.cache_len() {
+            0 => None,
+            _ => Some(manager.cached_names()),
+        };
+        match manager.refresh() {
+            Ok(CommandListing { commands, failures }) => {
+                let mut message = format!(
+                    "✅ Reloaded {} command{} from disk",
+                    commands.len(),
+                    if commands.len() == 1 { "" } else { "s" }
+                );
+                if let Some(evicted) = evicted {
+                    message.push_str(&format!(" (discarded cached: {})", evicted.join(", ")));
+                }
+                if !failures.is_empty() {
+                    message.push_str(&format!(
+                        ", {} failed to load. Use '/commands show' for details.",
+                        failures.len()
+                    ));
+                }
+                OperationResult::Success(message)
+            },
+            Err(e) => OperationResult::Error(format!("Failed to reload commands: {}", e)),
+        }
+    }
+
     async fn handle_clear(os: &Os, scope: Option<CommandScope>, force: bool) -> OperationResult {
         let mut manager = match CommandManager::new(os) {
             Ok(manager) => manager,
             Err(e) => return OperationResult::Error(format!("Failed to initialize command manager: {}", e)),
         };
 
-        // Get list of commands to be removed
+        // Get list of commands to be removed, skipping read-only shared commands
         let commands = match manager.list_commands_detailed(scope.as_ref()) {
-            Ok(commands) => commands,
+            Ok(commands) => commands
+                .into_iter()
+                .filter(|command| !command.is_read_only())
+                .collect::<Vec<_>>(),
             Err(e) => return OperationResult::Error(format!("Failed to list commands: {}", e)),
         };
 
@@ -305,6 +518,7 @@ impl CommandsSubcommand {
             let scope_text = match scope {
                 Some(CommandScope::Project) => " project",
                 Some(CommandScope::Global) => " global",
+                Some(CommandScope::Shared) => " shared",
                 None => "",
             };
             return OperationResult::Success(format!("No{} commands to clear.", scope_text));
@@ -319,6 +533,7 @@ impl CommandsSubcommand {
             let scope_text = match scope {
                 Some(CommandScope::Project) => " project",
                 Some(CommandScope::Global) => " global",
+                Some(CommandScope::Shared) => " shared",
                 None => "",
             };
             print!(
@@ -387,6 +602,49 @@ impl CommandsSubcommand {
     }
 }
 
+/// A custom command that shares its name with prompts offered by MCP servers.
+#[derive(Debug, PartialEq, Eq)]
+struct PromptCollision {
+    name: String,
+    servers: Vec<String>,
+}
+
+impl std::fmt::Display for PromptCollision {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "'{}' is both a custom command and an MCP prompt from {}",
+            self.name,
+            self.servers.join(", ")
+        )
+    }
+}
+
+/// Returns the commands among `command_names` that are also the name of an MCP prompt, sorted by
+/// name.
+fn prompt_collisions<'a>(
+    command_names: impl IntoIterator<Item = &'a str>,
+    prompts: &HashMap<String, Vec<PromptBundle>>,
+) -> Vec<PromptCollision> {
+    command_names
+        .into_iter()
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .filter_map(|name| {
+            let bundles = prompts.get(name)?;
+            let mut servers = bundles
+                .iter()
+                .map(|bundle| bundle.server_name.clone())
+                .collect::<Vec<_>>();
+            servers.sort();
+            Some(PromptCollision {
+                name: name.to_string(),
+                servers,
+            })
+        })
+        .collect()
+}
+
 #[derive(Debug)]
 enum OperationResult {
     Success(String),
@@ -398,15 +656,42 @@ mod tests {
     use super::*;
     use crate::util::command_types::CommandScope;
 
+    #[test]
+    fn test_prompt_collisions() {
+        let bundle = |server: &str, name: &str| PromptBundle {
+            server_name: server.to_string(),
+            prompt_get: rmcp::model::Prompt::new(name, None::<String>, None),
+        };
+        let mut prompts = HashMap::new();
+        prompts.insert("deploy".to_string(), vec![
+            bundle("ops", "deploy"),
+            bundle("infra", "deploy"),
+        ]);
+        prompts.insert("explain".to_string(), vec![bundle("docs", "explain")]);
+
+        let collisions = prompt_collisions(["review", "deploy", "deploy"], &prompts);
+        assert_eq!(collisions, vec![PromptCollision {
+            name: "deploy".to_string(),
+            servers: vec!["infra".to_string(), "ops".to_string()],
+        }]);
+        assert_eq!(
+            collisions[0].to_string(),
+            "'deploy' is both a custom command and an MCP prompt from infra, ops"
+        );
+
+        assert!(prompt_collisions(["review"], &prompts).is_empty());
+    }
+
     #[test]
     fn test_commands_add_basic() {
         let subcommand = CommandsSubcommand::Add {
             name: "test-command".to_string(),
+            quiet: false,
         };
 
         // Test that the command structure is correct
         match subcommand {
-            CommandsSubcommand::Add { name } => {
+            CommandsSubcommand::Add { name, .. } => {
                 assert_eq!(name, "test-command");
                 assert!(crate::util::command_types::CustomCommand::validate_name(&name).is_ok());
             },
@@ -418,10 +703,11 @@ mod tests {
     fn test_commands_add_invalid_name() {
         let subcommand = CommandsSubcommand::Add {
             name: "invalid name with spaces".to_string(),
+            quiet: false,
         };
 
         match subcommand {
-            CommandsSubcommand::Add { name } => {
+            CommandsSubcommand::Add { name, .. } => {
                 assert!(crate::util::command_types::CustomCommand::validate_name(&name).is_err());
             },
             _ => panic!("Expected Add subcommand"),
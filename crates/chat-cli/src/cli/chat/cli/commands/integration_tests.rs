@@ -46,7 +46,9 @@ async fn test_commands_add_integration() {
 
     assert!(add_result.is_ok(), "Add command should succeed");
 
-    let success_message = add_result.unwrap();
+    let added = add_result.unwrap();
+    assert_eq!(added.name, "integration-add-test");
+    let success_message = added.format(false, true);
     assert!(success_message.contains("✅ Command 'integration-add-test' created successfully!"));
     assert!(success_message.contains("Use '/project:integration-add-test' to execute it"));
 
@@ -0,0 +1,54 @@
+use clap::Args;
+use crossterm::style::Color;
+use crossterm::{
+    execute,
+    style,
+};
+
+use crate::cli::chat::{
+    ChatError,
+    ChatSession,
+    ChatState,
+};
+
+/// Arguments for showing tool output that was collapsed by `ui.collapseToolResults`.
+#[deny(missing_docs)]
+#[derive(Debug, PartialEq, Args)]
+pub struct ExpandArgs {
+    /// Which collapsed output to show, counting from 1. Defaults to the most recent
+    index: Option<usize>,
+}
+
+impl ExpandArgs {
+    pub async fn execute(self, session: &mut ChatSession) -> Result<ChatState, ChatError> {
+        let collapsed = &session.collapsed_tool_results;
+        let result = match self.index {
+            Some(index) => index.checked_sub(1).and_then(|index| collapsed.get(index)),
+            None => collapsed.last(),
+        };
+
+        match result {
+            Some(result) => execute!(
+                session.stdout,
+                style::SetForegroundColor(Color::DarkGrey),
+                style::Print(format!("{}\n", result.summary())),
+                style::SetForegroundColor(Color::Reset),
+                style::Print(&result.content),
+                style::Print("\n"),
+            )?,
+            None => execute!(
+                session.stderr,
+                style::SetForegroundColor(Color::Yellow),
+                style::Print(match collapsed.len() {
+                    0 => "\nNo tool output has been collapsed.\n\n".to_string(),
+                    len => format!("\nThere are only {len} collapsed tool outputs.\n\n"),
+                }),
+                style::SetForegroundColor(Color::Reset),
+            )?,
+        }
+
+        Ok(ChatState::PromptUser {
+            skip_printing_tools: true,
+        })
+    }
+}
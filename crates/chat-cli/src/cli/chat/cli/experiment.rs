@@ -55,6 +55,11 @@ static AVAILABLE_EXPERIMENTS: &[Experiment] = &[
         description: "Enables user-defined custom commands (/commands, /project:name, /user:name)",
         setting_key: Setting::EnabledCommands,
     },
+    Experiment {
+        name: "Scratchpad",
+        description: "Enables private working notes Q keeps across a long conversation",
+        setting_key: Setting::EnabledScratchpad,
+    },
     Experiment {
         name: "Themes",
         description: "Enables dynamic theme switching within chat sessions (/themes)",
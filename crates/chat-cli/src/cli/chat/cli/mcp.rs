@@ -51,12 +51,17 @@ impl McpArgs {
             .join("");
 
         for (server_name, msg) in session.conversation.tool_manager.mcp_load_record.lock().await.iter() {
+            // A cancellation is only relevant until the server reports its actual outcome
+            let last = msg.len().saturating_sub(1);
             let msg = msg
                 .iter()
-                .map(|record| match record {
-                    LoadingRecord::Err(content) | LoadingRecord::Warn(content) | LoadingRecord::Success(content) => {
-                        content.clone()
-                    },
+                .enumerate()
+                .filter_map(|(i, record)| match record {
+                    LoadingRecord::Cancelled(_) if i != last => None,
+                    LoadingRecord::Err(content)
+                    | LoadingRecord::Warn(content)
+                    | LoadingRecord::Success(content)
+                    | LoadingRecord::Cancelled(content) => Some(content.clone()),
                 })
                 .collect::<Vec<_>>()
                 .join("\n--- tools refreshed ---\n");
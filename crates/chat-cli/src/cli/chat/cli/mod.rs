@@ -4,6 +4,7 @@ pub mod commands; // NEW: Add commands module
 pub mod compact;
 pub mod context;
 pub mod editor;
+pub mod expand;
 pub mod experiment;
 pub mod hooks;
 pub mod knowledge;
@@ -12,7 +13,9 @@ pub mod model;
 pub mod persist;
 pub mod profile;
 pub mod prompts;
+pub mod status;
 pub mod subscribe;
+pub mod system;
 pub mod tangent;
 pub mod themes;
 pub mod todos;
@@ -26,6 +29,7 @@ use commands::CommandsSubcommand; // NEW: Add commands import
 use compact::CompactArgs;
 use context::ContextSubcommand;
 use editor::EditorArgs;
+use expand::ExpandArgs;
 use experiment::ExperimentArgs;
 use hooks::HooksArgs;
 use knowledge::KnowledgeSubcommand;
@@ -34,6 +38,8 @@ use model::ModelArgs;
 use persist::PersistSubcommand;
 use profile::AgentSubcommand;
 use prompts::PromptsArgs;
+use status::StatusArgs;
+use system::SystemArgs;
 use tangent::TangentArgs;
 use themes::ThemesSubcommand;
 use todos::TodoSubcommand;
@@ -85,6 +91,8 @@ pub enum SlashCommand {
     Compact(CompactArgs),
     /// View tools and permissions
     Tools(ToolsArgs),
+    /// Show tool output that was collapsed by "q settings ui.collapseToolResults true"
+    Expand(ExpandArgs),
     /// Create a new Github issue or make a feature request
     Issue(issue::IssueArgs),
     /// View changelog for Amazon Q CLI
@@ -96,6 +104,8 @@ pub enum SlashCommand {
     Hooks(HooksArgs),
     /// Show current session's context window usage
     Usage(UsageArgs),
+    /// Show whether you're logged in, the active agent and how many mcp servers have loaded
+    Status(StatusArgs),
     /// See mcp server loaded
     Mcp(McpArgs),
     /// Select a model for the current conversation session
@@ -104,6 +114,8 @@ pub enum SlashCommand {
     Experiment(ExperimentArgs),
     /// Upgrade to a Q Developer Pro subscription for increased query limits
     Subscribe(SubscribeArgs),
+    /// Set or clear a system prompt for the current conversation
+    System(SystemArgs),
     /// (Beta) Toggle tangent mode for isolated conversations. Requires "q settings
     /// chat.enableTangentMode true"
     #[command(hide = true)]
@@ -162,14 +174,17 @@ impl SlashCommand {
                     skip_printing_tools: true,
                 })
             },
+            Self::Expand(args) => args.execute(session).await,
             Self::Changelog(args) => args.execute(session).await,
             Self::Prompts(args) => args.execute(session).await,
             Self::Hooks(args) => args.execute(session).await,
             Self::Usage(args) => args.execute(os, session).await,
+            Self::Status(args) => args.execute(os, session).await,
             Self::Mcp(args) => args.execute(session).await,
             Self::Model(args) => args.execute(os, session).await,
             Self::Experiment(args) => args.execute(os, session).await,
             Self::Subscribe(args) => args.execute(os, session).await,
+            Self::System(args) => args.execute(os, session).await,
             Self::Tangent(args) => args.execute(os, session).await,
             Self::Persist(subcommand) => subcommand.execute(os, session).await,
             // Self::Root(subcommand) => {
@@ -198,19 +213,24 @@ impl SlashCommand {
             Self::PromptEditor(_) => "editor",
             Self::Compact(_) => "compact",
             Self::Tools(_) => "tools",
+            Self::Expand(_) => "expand",
             Self::Issue(_) => "issue",
             Self::Changelog(_) => "changelog",
             Self::Prompts(_) => "prompts",
             Self::Hooks(_) => "hooks",
             Self::Usage(_) => "usage",
+            Self::Status(_) => "status",
             Self::Mcp(_) => "mcp",
             Self::Model(_) => "model",
             Self::Experiment(_) => "experiment",
             Self::Subscribe(_) => "subscribe",
+            Self::System(_) => "system",
             Self::Tangent(_) => "tangent",
             Self::Persist(sub) => match sub {
                 PersistSubcommand::Save { .. } => "save",
                 PersistSubcommand::Load { .. } => "load",
+                PersistSubcommand::Conversations { .. } => "conversations",
+                PersistSubcommand::Favorite { .. } => "favorite",
             },
             Self::Todos(_) => "todos",
         }
@@ -1,3 +1,8 @@
+use chrono::{
+    Local,
+    TimeDelta,
+    Utc,
+};
 use clap::Subcommand;
 use crossterm::execute;
 use crossterm::style::{
@@ -7,6 +12,7 @@ use crossterm::style::{
 };
 
 use crate::cli::ConversationState;
+use crate::cli::chat::conversation::ExportOptions;
 use crate::cli::chat::{
     ChatError,
     ChatSession,
@@ -25,12 +31,65 @@ pub enum PersistSubcommand {
         #[arg(short, long)]
         /// Force overwrite if file already exists
         force: bool,
+        #[arg(long)]
+        /// Save a readable markdown transcript instead of the JSON conversation state
+        markdown: bool,
+        #[arg(long, requires = "markdown")]
+        /// Omit tool call arguments from the markdown transcript
+        omit_tool_calls: bool,
+        #[arg(long, requires = "markdown")]
+        /// Omit tool results from the markdown transcript
+        omit_tool_results: bool,
     },
     /// Load a previous conversation
     Load {
         /// Path to the conversation file to load
         path: String,
     },
+    /// List recently used conversations, newest first
+    Conversations {
+        #[arg(short, long, default_value_t = 10)]
+        /// Maximum number of conversations to list
+        limit: usize,
+        #[arg(long, value_parser = parse_since)]
+        /// Only list conversations used within this long ago, e.g. 30m, 12h or 7d
+        since: Option<TimeDelta>,
+        #[arg(long, conflicts_with = "since")]
+        /// Only list conversations whose messages match this full-text query
+        search: Option<String>,
+        #[arg(long, conflicts_with_all = ["since", "search"])]
+        /// List the conversations taking up the most space instead, largest first
+        largest: bool,
+    },
+    /// Mark a stored conversation as a favorite, listed above the others in /conversations
+    Favorite {
+        /// Path the conversation is stored under, as shown by /conversations
+        path: String,
+        #[arg(long)]
+        /// Remove the conversation from the favorites instead
+        remove: bool,
+    },
+}
+
+/// Parses a duration such as `90s`, `30m`, `12h`, `7d` or `2w`.
+fn parse_since(value: &str) -> Result<TimeDelta, String> {
+    let value = value.trim();
+    let split = value.find(|c: char| !c.is_ascii_digit()).unwrap_or(value.len());
+    let (amount, unit) = value.split_at(split);
+    let amount: i64 = amount
+        .parse()
+        .map_err(|err| format!("'{value}' must start with a number, e.g. 7d: {err}"))?;
+    let delta = match unit {
+        "s" => TimeDelta::try_seconds(amount),
+        "m" => TimeDelta::try_minutes(amount),
+        "h" => TimeDelta::try_hours(amount),
+        "d" => TimeDelta::try_days(amount),
+        "w" => TimeDelta::try_weeks(amount),
+        _ => return Err(format!("'{value}' must end with one of s, m, h, d or w")),
+    };
+    delta
+        .filter(|delta| Utc::now().checked_sub_signed(*delta).is_some())
+        .ok_or_else(|| format!("'{value}' is too large"))
 }
 
 impl PersistSubcommand {
@@ -56,8 +115,21 @@ impl PersistSubcommand {
         }
 
         match self {
-            Self::Save { path, force } => {
-                let contents = tri!(serde_json::to_string_pretty(&session.conversation), "export to", &path);
+            Self::Save {
+                path,
+                force,
+                markdown,
+                omit_tool_calls,
+                omit_tool_results,
+            } => {
+                let contents = if markdown {
+                    session.conversation.to_markdown(&ExportOptions {
+                        tool_call_details: !omit_tool_calls,
+                        tool_results: !omit_tool_results,
+                    })
+                } else {
+                    tri!(serde_json::to_string_pretty(&session.conversation), "export to", &path)
+                };
                 if os.fs.exists(&path) && !force {
                     execute!(
                         session.stderr,
@@ -100,14 +172,7 @@ impl PersistSubcommand {
                 };
 
                 let mut new_state: ConversationState = tri!(serde_json::from_str(&contents), "import from", &path);
-                std::mem::swap(&mut new_state.tool_manager, &mut session.conversation.tool_manager);
-                std::mem::swap(&mut new_state.mcp_enabled, &mut session.conversation.mcp_enabled);
-                std::mem::swap(&mut new_state.model_info, &mut session.conversation.model_info);
-                std::mem::swap(
-                    &mut new_state.context_manager,
-                    &mut session.conversation.context_manager,
-                );
-                std::mem::swap(&mut new_state.agents, &mut session.conversation.agents);
+                new_state.swap_session_state(&mut session.conversation);
                 session.conversation = new_state;
 
                 execute!(
@@ -117,6 +182,113 @@ impl PersistSubcommand {
                     style::SetAttribute(Attribute::Reset)
                 )?;
             },
+            Self::Conversations {
+                limit, largest: true, ..
+            } => {
+                let conversations = tri!(os.database.largest_conversations(limit), "list", "conversations");
+
+                if conversations.is_empty() {
+                    execute!(session.stderr, style::Print("\nNo conversations found\n\n"))?;
+                }
+                for (path, size) in conversations {
+                    let size = if size >= 1024 * 1024 {
+                        format!("{:.2} MB", size as f64 / (1024.0 * 1024.0))
+                    } else {
+                        format!("{:.2} KB", size as f64 / 1024.0)
+                    };
+                    execute!(
+                        session.stderr,
+                        style::Print("\n"),
+                        style::SetAttribute(Attribute::Bold),
+                        style::Print(&path),
+                        style::SetAttribute(Attribute::Reset),
+                        style::SetForegroundColor(Color::DarkGrey),
+                        style::Print(format!("  {size}\n")),
+                        style::SetForegroundColor(Color::Reset),
+                    )?;
+                }
+                execute!(session.stderr, style::Print("\n"))?;
+            },
+            Self::Conversations {
+                limit,
+                search: Some(query),
+                ..
+            } => {
+                let mut results = tri!(os.database.search_conversations(&query), "search", "conversations");
+                results.truncate(limit);
+
+                if results.is_empty() {
+                    execute!(session.stderr, style::Print("\nNo conversations found\n\n"))?;
+                }
+                for (path, snippet) in results {
+                    execute!(
+                        session.stderr,
+                        style::Print("\n"),
+                        style::SetAttribute(Attribute::Bold),
+                        style::Print(&path),
+                        style::SetAttribute(Attribute::Reset),
+                        style::Print(format!(
+                            "\n  {}\n",
+                            snippet.split_whitespace().collect::<Vec<_>>().join(" ")
+                        )),
+                    )?;
+                }
+                execute!(session.stderr, style::Print("\n"))?;
+            },
+            Self::Conversations { limit, since, .. } => {
+                let since = match since {
+                    Some(since) => Some(tri!(
+                        Utc::now().checked_sub_signed(since).ok_or("--since is too large"),
+                        "list",
+                        "conversations"
+                    )),
+                    None => None,
+                };
+                let conversations = tri!(
+                    os.database.list_recent_conversations(limit, since),
+                    "list",
+                    "conversations"
+                );
+
+                if conversations.is_empty() {
+                    execute!(session.stderr, style::Print("\nNo conversations found\n\n"))?;
+                }
+                for conversation in conversations {
+                    let last_accessed = conversation.last_accessed.map_or_else(
+                        || "unknown".to_string(),
+                        |time| time.with_timezone(&Local).format("%Y-%m-%d %H:%M").to_string(),
+                    );
+                    execute!(
+                        session.stderr,
+                        style::Print("\n"),
+                        style::SetForegroundColor(Color::Yellow),
+                        style::Print(if conversation.favorite { "★ " } else { "" }),
+                        style::SetForegroundColor(Color::Reset),
+                        style::SetAttribute(Attribute::Bold),
+                        style::Print(&conversation.path),
+                        style::SetAttribute(Attribute::Reset),
+                        style::SetForegroundColor(Color::DarkGrey),
+                        style::Print(format!("  {last_accessed}\n")),
+                        style::SetForegroundColor(Color::Reset),
+                        style::Print(format!("  {}\n", conversation.preview.as_deref().unwrap_or("(empty)"))),
+                    )?;
+                }
+                execute!(session.stderr, style::Print("\n"))?;
+            },
+            Self::Favorite { path, remove } => {
+                tri!(os.database.set_favorite(&path, !remove), "update favorite", path);
+                let message = if remove {
+                    format!("\n✔ Removed {path} from favorites\n\n")
+                } else {
+                    format!("\n✔ Added {path} to favorites\n\n")
+                };
+                execute!(
+                    session.stderr,
+                    style::SetForegroundColor(Color::Green),
+                    style::Print(message),
+                    style::SetAttribute(Attribute::Reset)
+                )?;
+            },
         }
 
         Ok(ChatState::PromptUser {
@@ -124,3 +296,22 @@ impl PersistSubcommand {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_since() {
+        assert_eq!(parse_since("90s"), Ok(TimeDelta::seconds(90)));
+        assert_eq!(parse_since("30m"), Ok(TimeDelta::minutes(30)));
+        assert_eq!(parse_since("12h"), Ok(TimeDelta::hours(12)));
+        assert_eq!(parse_since("7d"), Ok(TimeDelta::days(7)));
+        assert_eq!(parse_since("2w"), Ok(TimeDelta::weeks(2)));
+        assert!(parse_since("7").is_err());
+        assert!(parse_since("d").is_err());
+        assert!(parse_since("7y").is_err());
+        assert!(parse_since("9999999999w").is_err());
+        assert!(parse_since("9223372036854775s").is_err());
+    }
+}
@@ -134,6 +134,25 @@ fn prompt_mcp_server_selection(servers: &[McpServerInfo]) -> eyre::Result<Option
     Ok(Some(selected_servers))
 }
 
+/// Shows what changes in the active configuration when swapping to the agent `name`
+fn print_swap_preview(session: &mut ChatSession, name: &str) -> Result<(), ChatError> {
+    let agents = &session.conversation.agents;
+    let (Some(current), Some(next)) = (agents.get_active(), agents.agents.get(name)) else {
+        return Ok(());
+    };
+    let diff = current.diff(next);
+    if diff.is_empty() {
+        return Ok(());
+    }
+
+    execute!(
+        session.stderr,
+        style::Print(format!("Changes from '{}' to '{}':\n", current.name, next.name))
+    )?;
+    diff.print(&mut session.stderr)?;
+    Ok(())
+}
+
 impl AgentSubcommand {
     pub async fn execute(self, os: &mut Os, session: &mut ChatSession) -> Result<ChatState, ChatError> {
         let agents = &session.conversation.agents;
@@ -388,6 +407,7 @@ impl AgentSubcommand {
             },
             Self::Swap { name } => {
                 if let Some(name) = name {
+                    print_swap_preview(session, &name)?;
                     session.conversation.swap_agent(os, &mut session.stderr, &name).await?;
                 } else {
                     let labels = session
@@ -425,6 +445,7 @@ impl AgentSubcommand {
                     };
 
                     if let Some(name) = name {
+                        print_swap_preview(session, &name)?;
                         session.conversation.swap_agent(os, &mut session.stderr, &name).await?;
                     }
                 }
@@ -1,7 +1,4 @@
-use std::collections::{
-    HashMap,
-    VecDeque,
-};
+use std::collections::VecDeque;
 
 use clap::{
     Args,
@@ -19,7 +16,6 @@ use crossterm::{
 use thiserror::Error;
 use unicode_width::UnicodeWidthStr;
 
-use crate::cli::chat::tool_manager::PromptBundle;
 use crate::cli::chat::{
     ChatError,
     ChatSession,
@@ -37,6 +33,8 @@ pub enum GetPromptError {
     MissingClient,
     #[error("Missing prompt name")]
     MissingPromptName,
+    #[error("Invalid arguments for prompt {0}: {1}")]
+    InvalidArguments(String, String),
     #[error("Missing prompt bundle")]
     MissingPromptInfo,
     #[error(transparent)]
@@ -82,10 +80,17 @@ impl PromptsArgs {
         }
 
         let terminal_width = session.terminal_width();
-        let prompts = session.conversation.tool_manager.list_prompts().await?;
-        let mut longest_name = "";
+        let mut prompts = session.conversation.tool_manager.prompts_view().await?;
+        prompts.retain(|prompt| prompt.name.contains(search_word.as_deref().unwrap_or("")));
+        // Group by server, keeping the view's name order within each server
+        prompts.sort_by(|a, b| a.server.cmp(&b.server));
+        let longest_name = prompts
+            .iter()
+            .map(|prompt| UnicodeWidthStr::width(prompt.name.as_str()))
+            .max()
+            .unwrap_or_default();
         let arg_pos = {
-            let optimal_case = UnicodeWidthStr::width(longest_name) + terminal_width / 4;
+            let optimal_case = longest_name + terminal_width / 4;
             if optimal_case > terminal_width {
                 terminal_width / 3
             } else {
@@ -124,78 +129,57 @@ impl PromptsArgs {
             style::Print("\n"),
             style::Print(format!("{}\n", "▔".repeat(terminal_width))),
         )?;
-        let mut prompts_by_server: Vec<_> = prompts
-            .iter()
-            .fold(
-                HashMap::<&String, Vec<&PromptBundle>>::new(),
-                |mut acc, (prompt_name, bundles)| {
-                    if prompt_name.contains(search_word.as_deref().unwrap_or("")) {
-                        if prompt_name.len() > longest_name.len() {
-                            longest_name = prompt_name.as_str();
-                        }
-                        for bundle in bundles {
-                            acc.entry(&bundle.server_name)
-                                .and_modify(|b| b.push(bundle))
-                                .or_insert(vec![bundle]);
-                        }
-                    }
-                    acc
-                },
-            )
-            .into_iter()
-            .collect();
-        prompts_by_server.sort_by_key(|(server_name, _)| server_name.as_str());
-
-        for (i, (server_name, bundles)) in prompts_by_server.iter_mut().enumerate() {
-            bundles.sort_by_key(|bundle| &bundle.prompt_get.name);
-
-            if i > 0 {
-                queue!(session.stderr, style::Print("\n"))?;
+        let mut current_server = None;
+        for prompt in &prompts {
+            if current_server != Some(&prompt.server) {
+                if current_server.is_some() {
+                    queue!(session.stderr, style::Print("\n"))?;
+                }
+                queue!(
+                    session.stderr,
+                    style::SetAttribute(Attribute::Bold),
+                    style::Print(&prompt.server),
+                    style::Print(" (MCP):"),
+                    style::SetAttribute(Attribute::Reset),
+                    style::Print("\n"),
+                )?;
+                current_server = Some(&prompt.server);
             }
+
+            // Prompts offered by more than one server must be invoked with the server name
+            let name = if prompt.ambiguous {
+                format!("{}/{}", prompt.server, prompt.name)
+            } else {
+                prompt.name.clone()
+            };
             queue!(
                 session.stderr,
-                style::SetAttribute(Attribute::Bold),
-                style::Print(server_name),
-                style::Print(" (MCP):"),
-                style::SetAttribute(Attribute::Reset),
-                style::Print("\n"),
+                style::Print("- "),
+                style::Print(&name),
+                style::Print({
+                    if !prompt.args.is_empty() {
+                        let name_width = UnicodeWidthStr::width(name.as_str());
+                        let padding = arg_pos
+                            .saturating_sub(name_width)
+                            .saturating_sub(UnicodeWidthStr::width("- "));
+                        " ".repeat(padding.max(1))
+                    } else {
+                        "\n".to_owned()
+                    }
+                })
             )?;
-            for bundle in bundles {
+            for (i, arg) in prompt.args.iter().enumerate() {
                 queue!(
                     session.stderr,
-                    style::Print("- "),
-                    style::Print(&bundle.prompt_get.name),
-                    style::Print({
-                        if bundle
-                            .prompt_get
-                            .arguments
-                            .as_ref()
-                            .is_some_and(|args| !args.is_empty())
-                        {
-                            let name_width = UnicodeWidthStr::width(bundle.prompt_get.name.as_str());
-                            let padding = arg_pos
-                                .saturating_sub(name_width)
-                                .saturating_sub(UnicodeWidthStr::width("- "));
-                            " ".repeat(padding.max(1))
-                        } else {
-                            "\n".to_owned()
-                        }
-                    })
+                    style::SetForegroundColor(Color::DarkGrey),
+                    style::Print(if prompt.required_args.contains(arg) {
+                        format!("{arg}*")
+                    } else {
+                        arg.clone()
+                    }),
+                    style::SetForegroundColor(Color::Reset),
+                    style::Print(if i < prompt.args.len() - 1 { ", " } else { "\n" }),
                 )?;
-                if let Some(args) = bundle.prompt_get.arguments.as_ref() {
-                    for (i, arg) in args.iter().enumerate() {
-                        queue!(
-                            session.stderr,
-                            style::SetForegroundColor(Color::DarkGrey),
-                            style::Print(match arg.required {
-                                Some(true) => format!("{}*", arg.name),
-                                _ => arg.name.clone(),
-                            }),
-                            style::SetForegroundColor(Color::Reset),
-                            style::Print(if i < args.len() - 1 { ", " } else { "\n" }),
-                        )?;
-                    }
-                }
             }
         }
 
@@ -277,6 +261,23 @@ impl PromptsSubcommand {
                             style::SetForegroundColor(Color::Reset),
                         )?;
                     },
+                    GetPromptError::InvalidArguments(prompt_name, reason) => {
+                        queue!(
+                            session.stderr,
+                            style::Print("\n"),
+                            style::SetForegroundColor(Color::Yellow),
+                            style::Print("Invalid arguments for prompt "),
+                            style::SetForegroundColor(Color::Cyan),
+                            style::Print(prompt_name),
+                            style::SetForegroundColor(Color::Yellow),
+                            style::Print(format!(": {reason}. Use ")),
+                            style::SetForegroundColor(Color::Cyan),
+                            style::Print("/prompts list"),
+                            style::SetForegroundColor(Color::Yellow),
+                            style::Print(" to see its arguments.\n"),
+                            style::SetForegroundColor(Color::Reset),
+                        )?;
+                    },
                     _ => return Err(ChatError::Custom(e.to_string().into())),
                 }
                 execute!(session.stderr, style::Print("\n"))?;
@@ -0,0 +1,61 @@
+use clap::Args;
+use crossterm::style::{
+    self,
+    Attribute,
+    Color,
+};
+use crossterm::{
+    execute,
+    queue,
+};
+
+use crate::cli::chat::{
+    ChatError,
+    ChatSession,
+    ChatState,
+};
+use crate::os::Os;
+
+/// Arguments for the status command that summarizes the state of the CLI without making any
+/// network requests.
+#[deny(missing_docs)]
+#[derive(Debug, PartialEq, Args)]
+pub struct StatusArgs;
+
+impl StatusArgs {
+    pub async fn execute(self, os: &Os, session: &mut ChatSession) -> Result<ChatState, ChatError> {
+        let summary = session.status_summary(os).await;
+
+        let fields = [
+            ("Logged in", if summary.logged_in { "yes" } else { "no" }.to_string()),
+            ("Agent", summary.active_profile),
+            ("MCP servers loaded", summary.servers.loaded.to_string()),
+            ("MCP servers failed", summary.servers.failed.to_string()),
+            ("MCP servers loading", summary.servers.pending.to_string()),
+        ];
+        queue!(session.stderr, style::Print("\n"))?;
+        for (label, value) in fields {
+            queue!(
+                session.stderr,
+                style::SetAttribute(Attribute::Bold),
+                style::Print(format!("{label}: ")),
+                style::SetAttribute(Attribute::Reset),
+                style::Print(value),
+                style::Print("\n"),
+            )?;
+        }
+        if summary.servers.failed > 0 {
+            queue!(
+                session.stderr,
+                style::SetForegroundColor(Color::DarkGrey),
+                style::Print("Run /mcp to see why servers failed to load\n"),
+                style::SetForegroundColor(Color::Reset),
+            )?;
+        }
+        execute!(session.stderr, style::Print("\n"))?;
+
+        Ok(ChatState::PromptUser {
+            skip_printing_tools: true,
+        })
+    }
+}
@@ -0,0 +1,72 @@
+use clap::{
+    Args,
+    Subcommand,
+};
+use crossterm::execute;
+use crossterm::style::{
+    self,
+    Color,
+};
+
+use crate::cli::chat::conversation_store::ConversationStore;
+use crate::cli::chat::{
+    ChatError,
+    ChatSession,
+    ChatState,
+};
+use crate::os::Os;
+
+/// Arguments for the system command that overrides the system prompt for the current conversation.
+#[derive(Debug, PartialEq, Args)]
+pub struct SystemArgs {
+    #[command(subcommand)]
+    pub subcommand: Option<SystemSubcommand>,
+}
+
+#[derive(Debug, PartialEq, Subcommand)]
+pub enum SystemSubcommand {
+    /// Set a system prompt that applies to every turn of this conversation
+    Set {
+        #[arg(required = true, trailing_var_arg = true)]
+        prompt: Vec<String>,
+    },
+    /// Remove the system prompt override
+    Clear,
+}
+
+impl SystemArgs {
+    pub async fn execute(self, os: &mut Os, session: &mut ChatSession) -> Result<ChatState, ChatError> {
+        let message = match self.subcommand {
+            None => match session.conversation.system_prompt_override() {
+                Some(prompt) => format!("\nSystem prompt: {}\n\n", prompt),
+                None => "\nNo system prompt override set.\n\n".to_string(),
+            },
+            Some(SystemSubcommand::Set { prompt }) => {
+                session.conversation.set_system_prompt_override(prompt.join(" "));
+                "\nSystem prompt set for this conversation.\n\n".to_string()
+            },
+            Some(SystemSubcommand::Clear) => match session.conversation.clear_system_prompt_override() {
+                Some(_) => "\nSystem prompt override cleared.\n\n".to_string(),
+                None => "\nNo system prompt override set.\n\n".to_string(),
+            },
+        };
+
+        if let Ok(cwd) = std::env::current_dir() {
+            let store = ConversationStore::new(os);
+            if let Err(err) = store.save(&mut os.database, &cwd, &mut session.conversation) {
+                tracing::warn!(?err, "Failed to persist system prompt override");
+            }
+        }
+
+        execute!(
+            session.stderr,
+            style::SetForegroundColor(Color::Green),
+            style::Print(message),
+            style::SetForegroundColor(Color::Reset)
+        )?;
+
+        Ok(ChatState::PromptUser {
+            skip_printing_tools: true,
+        })
+    }
+}
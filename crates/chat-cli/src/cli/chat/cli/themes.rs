@@ -12,6 +12,7 @@ use themes::{
     ThemeRenderer,
 };
 
+use crate::cli::chat::themes::ThemeSource;
 use crate::cli::chat::{
     ChatError,
     ChatSession,
@@ -33,6 +34,14 @@ pub enum ThemesSubcommand {
     Current,
     /// Interactive theme selection
     Select,
+    /// Install a theme from a file path or URL
+    Install {
+        /// Path or URL of the .theme file
+        source: String,
+        /// Overwrite an installed theme with the same name
+        #[arg(short, long)]
+        force: bool,
+    },
 }
 
 impl ThemesSubcommand {
@@ -48,6 +57,7 @@ impl ThemesSubcommand {
             Self::Preview { ref name } => Self::execute_preview(os, session, name).await,
             Self::Current => Self::execute_current(os, session).await,
             Self::Select => Self::execute_select(os, session).await,
+            Self::Install { ref source, force } => Self::execute_install(os, session, source, force).await,
         }
     }
 
@@ -177,6 +187,39 @@ impl ThemesSubcommand {
         Ok(Self::default_chat_state())
     }
 
+    async fn execute_install(
+        os: &mut Os,
+        session: &mut ChatSession,
+        source: &str,
+        force: bool,
+    ) -> Result<ChatState, ChatError> {
+        let result = match &session.theme_manager {
+            Some(manager) => manager.install_theme(os, ThemeSource::from(source), force).await,
+            None => match crate::cli::chat::themes::ThemeManager::new(os) {
+                Ok(manager) => manager.install_theme(os, ThemeSource::from(source), force).await,
+                Err(e) => return Err(ChatError::Custom(format!("Failed to install theme: {e}").into())),
+            },
+        };
+
+        match result {
+            Ok(name) => queue!(
+                session.stderr,
+                style::SetForegroundColor(Color::Green),
+                style::Print(format!("\n✓ Installed theme: {name}\n")),
+                style::SetForegroundColor(Color::Reset),
+                style::Print(format!("Switch to it with: /themes switch {name}\n\n")),
+            )?,
+            Err(e) => queue!(
+                session.stderr,
+                style::SetForegroundColor(Color::Red),
+                style::Print(format!("\n❌ Failed to install theme: {e}\n\n")),
+                style::SetForegroundColor(Color::Reset)
+            )?,
+        }
+
+        Ok(Self::default_chat_state())
+    }
+
     async fn execute_select(os: &mut Os, session: &mut ChatSession) -> Result<ChatState, ChatError> {
         let manager = Self::get_theme_manager();
         let themes = manager.list_themes();
@@ -1,7 +1,4 @@
-use std::collections::{
-    BTreeSet,
-    HashSet,
-};
+use std::collections::BTreeMap;
 use std::io::Write;
 
 use clap::{
@@ -26,7 +23,15 @@ use crate::cli::chat::consts::{
     AGENT_FORMAT_TOOLS_DOC_URL,
     DUMMY_TOOL_NAME,
 };
-use crate::cli::chat::tools::ToolOrigin;
+use crate::cli::chat::tool_manager::{
+    NativeToolStatus,
+    ToolAvailability,
+    ToolManager,
+};
+use crate::cli::chat::tools::{
+    ToolOrigin,
+    ToolSpec,
+};
 use crate::cli::chat::{
     ChatError,
     ChatSession,
@@ -52,12 +57,11 @@ impl ToolsArgs {
         // No subcommand - print the current tools and their permissions.
         // Determine how to format the output nicely.
         let terminal_width = session.terminal_width();
-        let longest = session
-            .conversation
-            .tool_manager
+        let tool_manager = &session.conversation.tool_manager;
+        let longest = tool_manager
             .tn_map
-            .values()
-            .map(|info| info.host_tool_name.len())
+            .keys()
+            .map(|name| tool_manager.display_tool_name(name).len())
             .max()
             .unwrap_or(0)
             .max(
@@ -104,6 +108,7 @@ impl ToolsArgs {
             // Note that Tool is model facing and thus would have names recognized by model.
             // Here we need to convert them to their host / user facing counter part.
             let tn_map = &session.conversation.tool_manager.tn_map;
+            let schema = &session.conversation.tool_manager.schema;
             let sorted_tools = tools
                 .iter()
                 .filter_map(|FigTool::ToolSpecification(spec)| {
@@ -111,26 +116,31 @@ impl ToolsArgs {
                         return None;
                     }
 
-                    tn_map
+                    let host_tool_name = tn_map
                         .get(&spec.name)
-                        .map_or(Some(spec.name.as_str()), |info| Some(info.host_tool_name.as_str()))
+                        .map_or(spec.name.as_str(), |info| info.host_tool_name.as_str());
+                    let display_name = session.conversation.tool_manager.display_tool_name(&spec.name);
+                    Some((display_name, (host_tool_name, schema.get(&spec.name))))
                 })
-                .collect::<BTreeSet<_>>();
-
-            let to_display = sorted_tools.iter().fold(String::new(), |mut acc, tool_name| {
-                let width = longest - tool_name.len() + 4;
-                acc.push_str(
-                    format!(
-                        "- {}{:>width$}{}\n",
-                        tool_name,
-                        "",
-                        session.conversation.agents.display_label(tool_name, origin),
-                        width = width
-                    )
-                    .as_str(),
-                );
-                acc
-            });
+                .collect::<BTreeMap<_, _>>();
+
+            let to_display = sorted_tools
+                .iter()
+                .fold(String::new(), |mut acc, (display_name, (tool_name, spec))| {
+                    let width = longest.saturating_sub(display_name.len()) + 4;
+                    acc.push_str(
+                        format!(
+                            "- {}{:>width$}{}\n",
+                            display_name,
+                            "",
+                            session.conversation.agents.display_label(tool_name, origin),
+                            width = width
+                        )
+                        .as_str(),
+                    );
+                    acc.push_str(&format_examples(*spec));
+                    acc
+                });
 
             let _ = queue!(
                 session.stderr,
@@ -142,6 +152,36 @@ impl ToolsArgs {
             );
         }
 
+        let disabled = session
+            .conversation
+            .tool_manager
+            .native_tool_status()
+            .into_iter()
+            .filter_map(|(name, status)| match status {
+                NativeToolStatus::Disabled { reason } if name != DUMMY_TOOL_NAME => Some((name, reason)),
+                _ => None,
+            })
+            .collect::<BTreeMap<_, _>>();
+        if !disabled.is_empty() {
+            queue!(
+                session.stderr,
+                style::SetAttribute(Attribute::Bold),
+                style::Print("Disabled built-in tools:\n"),
+                style::SetAttribute(Attribute::Reset),
+            )?;
+            for (name, reason) in disabled {
+                queue!(
+                    session.stderr,
+                    style::Print(format!("- {name}  ")),
+                    style::SetForegroundColor(Color::DarkGrey),
+                    style::Print(reason),
+                    style::SetForegroundColor(Color::Reset),
+                    style::Print("\n"),
+                )?;
+            }
+            queue!(session.stderr, style::Print("\n"))?;
+        }
+
         let loading = session.conversation.tool_manager.pending_clients().await;
         if !loading.is_empty() {
             queue!(
@@ -202,6 +242,8 @@ Refer to the documentation for how to configure tools with your agent: https://g
 pub enum ToolsSubcommand {
     /// Show the input schema for all available tools
     Schema,
+    /// Print a JSON description of all available tools, including their origin and aliases
+    Export,
     /// Trust a specific tool or tools for the session
     Trust {
         #[arg(required = true)]
@@ -218,21 +260,17 @@ pub enum ToolsSubcommand {
     TrustAll,
     /// Reset all tools to default permission levels
     Reset,
+    /// Explain why a tool is or is not available to the model
+    Why {
+        /// Name of the tool, e.g. fs_write or server/tool
+        name: String,
+    },
 }
 
 impl ToolsSubcommand {
     pub async fn execute(self, session: &mut ChatSession) -> Result<ChatState, ChatError> {
-        // Here we need to obtain the list of host tool names
-        let existing_custom_tools = session
-            .conversation
-            .tool_manager
-            .tn_map
-            .values()
-            .cloned()
-            .collect::<HashSet<_>>();
-
-        // We also need to obtain a list of native tools since tn_map from ToolManager does not
-        // contain native tools
+        // We need to obtain a list of native tools since tn_map from ToolManager does not contain
+        // native tools
         let native_tool_names = session
             .conversation
             .tools
@@ -254,11 +292,15 @@ impl ToolsSubcommand {
                     .map_err(|e| ChatError::Custom(format!("Error converting tool schema to string: {e}").into()))?;
                 queue!(session.stderr, style::Print(schema_json), style::Print("\n"))?;
             },
+            Self::Export => {
+                let export_json = serde_json::to_string_pretty(&session.conversation.tool_manager.export_tools_json())
+                    .map_err(|e| ChatError::Custom(format!("Error converting tools to string: {e}").into()))?;
+                queue!(session.stdout, style::Print(export_json), style::Print("\n"))?;
+                session.stdout.flush()?;
+            },
             Self::Trust { tool_names } => {
-                let (valid_tools, invalid_tools): (Vec<String>, Vec<String>) =
-                    tool_names.into_iter().partition(|tool_name| {
-                        existing_custom_tools.contains(tool_name) || native_tool_names.contains(tool_name)
-                    });
+                let (tools_to_trust, invalid_tools) =
+                    resolve_tool_names(tool_names, &session.conversation.tool_manager, &native_tool_names);
 
                 if !invalid_tools.is_empty() {
                     queue!(
@@ -273,20 +315,7 @@ impl ToolsSubcommand {
                         style::SetForegroundColor(Color::Reset),
                     )?;
                 }
-                if !valid_tools.is_empty() {
-                    let tools_to_trust = valid_tools
-                        .into_iter()
-                        .filter_map(|tool_name| {
-                            if native_tool_names.contains(&tool_name) {
-                                Some(tool_name)
-                            } else {
-                                existing_custom_tools
-                                    .get(&tool_name)
-                                    .map(|info| format!("@{}{MCP_SERVER_TOOL_DELIMITER}{tool_name}", info.server_name))
-                            }
-                        })
-                        .collect::<Vec<_>>();
-
+                if !tools_to_trust.is_empty() {
                     queue!(
                         session.stderr,
                         style::SetForegroundColor(Color::Green),
@@ -316,10 +345,8 @@ impl ToolsSubcommand {
                 }
             },
             Self::Untrust { tool_names } => {
-                let (valid_tools, invalid_tools): (Vec<String>, Vec<String>) =
-                    tool_names.into_iter().partition(|tool_name| {
-                        existing_custom_tools.contains(tool_name) || native_tool_names.contains(tool_name)
-                    });
+                let (tools_to_untrust, invalid_tools) =
+                    resolve_tool_names(tool_names, &session.conversation.tool_manager, &native_tool_names);
 
                 if !invalid_tools.is_empty() {
                     queue!(
@@ -334,20 +361,7 @@ impl ToolsSubcommand {
                         style::SetForegroundColor(Color::Reset),
                     )?;
                 }
-                if !valid_tools.is_empty() {
-                    let tools_to_untrust = valid_tools
-                        .into_iter()
-                        .filter_map(|tool_name| {
-                            if native_tool_names.contains(&tool_name) {
-                                Some(tool_name)
-                            } else {
-                                existing_custom_tools
-                                    .get(&tool_name)
-                                    .map(|info| format!("@{}{MCP_SERVER_TOOL_DELIMITER}{tool_name}", info.server_name))
-                            }
-                        })
-                        .collect::<Vec<_>>();
-
+                if !tools_to_untrust.is_empty() {
                     session.conversation.agents.untrust_tools(&tools_to_untrust);
 
                     queue!(
@@ -404,6 +418,25 @@ impl ToolsSubcommand {
                     style::SetForegroundColor(Color::Reset),
                 )?;
             },
+            Self::Why { name } => {
+                let availability = session.conversation.tool_manager.explain_tool_availability(&name).await;
+                let color = match availability {
+                    ToolAvailability::Available => Color::Green,
+                    _ => Color::Yellow,
+                };
+                queue!(
+                    session.stderr,
+                    style::Print("\n"),
+                    style::SetAttribute(Attribute::Bold),
+                    style::Print(&name),
+                    style::SetAttribute(Attribute::Reset),
+                    style::Print(": "),
+                    style::SetForegroundColor(color),
+                    style::Print(availability.to_string()),
+                    style::SetForegroundColor(Color::Reset),
+                    style::Print("\n\n"),
+                )?;
+            },
         };
 
         session.stderr.flush()?;
@@ -416,10 +449,100 @@ impl ToolsSubcommand {
     pub fn name(&self) -> &'static str {
         match self {
             ToolsSubcommand::Schema => "schema",
+            ToolsSubcommand::Export => "export",
             ToolsSubcommand::Trust { .. } => "trust",
             ToolsSubcommand::Untrust { .. } => "untrust",
             ToolsSubcommand::TrustAll => "trust-all",
             ToolsSubcommand::Reset => "reset",
+            ToolsSubcommand::Why { .. } => "why",
+        }
+    }
+}
+
+/// Maps the tool names given to `/tools trust` and `/tools untrust` to the names used in the
+/// agent's allowed tools. MCP tools can be named by their name on the server or as listed by
+/// `/tools`. Returns the resolved names and the names that matched no tool.
+fn resolve_tool_names(
+    tool_names: Vec<String>,
+    tool_manager: &ToolManager,
+    native_tool_names: &[String],
+) -> (Vec<String>, Vec<String>) {
+    let mut resolved = Vec::new();
+    let mut invalid = Vec::new();
+    for tool_name in tool_names {
+        if native_tool_names.contains(&tool_name) {
+            resolved.push(tool_name);
+        } else if let Some(info) = tool_manager.find_mcp_tool(&tool_name) {
+            resolved.push(format!(
+                "@{}{MCP_SERVER_TOOL_DELIMITER}{}",
+                info.server_name, info.host_tool_name
+            ));
+        } else {
+            invalid.push(tool_name);
         }
     }
+    (resolved, invalid)
+}
+
+/// Formats the example inputs of a tool, one per line beneath its entry in `/tools`. Empty if the
+/// tool has no examples.
+fn format_examples(spec: Option<&ToolSpec>) -> String {
+    spec.and_then(|spec| spec.examples.as_ref())
+        .into_iter()
+        .flatten()
+        .fold(String::new(), |mut acc, example| {
+            acc.push_str(&format!("    e.g. {example}\n"));
+            acc
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::cli::chat::tool_manager::ToolInfo;
+
+    #[test]
+    fn test_format_examples() {
+        let tool_config =
+            serde_json::from_str::<HashMap<String, ToolSpec>>(include_str!("../tools/tool_index.json")).unwrap();
+
+        let execute_bash = tool_config.get("execute_bash");
+        assert!(execute_bash.and_then(|spec| spec.examples.as_ref()).is_some());
+        assert!(format_examples(execute_bash).contains(r#""command":"ls -la""#));
+
+        assert!(tool_config.get("dummy").unwrap().examples.is_none());
+        assert_eq!(format_examples(tool_config.get("dummy")), "");
+        assert_eq!(format_examples(None), "");
+    }
+
+    #[test]
+    fn test_resolve_tool_names() {
+        let mut tool_manager = ToolManager::default();
+        tool_manager
+            .tn_map
+            .insert("github___search_issues".to_string(), ToolInfo {
+                server_name: "github".to_string(),
+                host_tool_name: "search-issues".to_string(),
+            });
+        let native_tool_names = vec!["fs_read".to_string()];
+
+        let (resolved, invalid) = resolve_tool_names(
+            vec![
+                "fs_read".to_string(),
+                "search-issues".to_string(),
+                "github/search-issues".to_string(),
+                "github___search_issues".to_string(),
+            ],
+            &tool_manager,
+            &native_tool_names,
+        );
+        assert_eq!(resolved, vec![
+            "fs_read".to_string(),
+            "@github/search-issues".to_string(),
+            "@github/search-issues".to_string(),
+        ]);
+        assert_eq!(invalid, vec!["github___search_issues".to_string()]);
+    }
 }
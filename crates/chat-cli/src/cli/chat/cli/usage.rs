@@ -190,6 +190,23 @@ impl UsageArgs {
             )),
         )?;
 
+        let usage = session.conversation.usage_summary();
+        if usage.turns > 0 {
+            queue!(
+                session.stderr,
+                style::SetForegroundColor(Color::DarkGrey),
+                style::Print(format!(
+                    "Conversation total: ~{} tokens (~{} sent, ~{} received) over {} {}\n",
+                    usage.total_tokens(),
+                    usage.input_tokens,
+                    usage.output_tokens,
+                    usage.turns,
+                    if usage.turns == 1 { "turn" } else { "turns" }
+                )),
+                style::SetForegroundColor(Color::Reset),
+            )?;
+        }
+
         queue!(
             session.stderr,
             style::SetAttribute(Attribute::Bold),
@@ -0,0 +1,54 @@
+/// Tool output with at least this many lines is collapsed when `ui.collapseToolResults` is set.
+pub const COLLAPSE_MIN_LINES: usize = 20;
+
+/// Output printed by a tool while it ran, collapsed to a single summary line in the transcript.
+/// The full output is kept so it can be shown again with `/expand`.
+///
+/// This only affects what is displayed; the model always receives the complete tool result.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CollapsedToolResult {
+    pub tool_name: String,
+    /// What the tool operated on, such as a file path or a command.
+    pub target: Option<String>,
+    pub content: String,
+}
+
+impl CollapsedToolResult {
+    /// Collapses `content` if it is long enough to be worth hiding, otherwise returns [None] so it
+    /// can be displayed as is.
+    pub fn new(tool_name: impl Into<String>, target: Option<String>, content: impl Into<String>) -> Option<Self> {
+        let content = content.into();
+        (content.lines().count() >= COLLAPSE_MIN_LINES).then(|| Self {
+            tool_name: tool_name.into(),
+            target,
+            content,
+        })
+    }
+
+    /// A one line description of the collapsed output, e.g. `fs_read: 120 lines from src/lib.rs`.
+    pub fn summary(&self) -> String {
+        let lines = self.content.lines().count();
+        match &self.target {
+            Some(target) => format!("{}: {lines} lines from {target}", self.tool_name),
+            None => format!("{}: {lines} lines", self.tool_name),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_collapsed_tool_result() {
+        let content = (1..=120).map(|i| format!("line {i}\n")).collect::<String>();
+        let collapsed = CollapsedToolResult::new("fs_read", Some("src/lib.rs".to_string()), content.clone()).unwrap();
+        assert_eq!(collapsed.summary(), "fs_read: 120 lines from src/lib.rs");
+        assert_eq!(collapsed.content, content);
+
+        let collapsed = CollapsedToolResult::new("use_aws", None, content).unwrap();
+        assert_eq!(collapsed.summary(), "use_aws: 120 lines");
+
+        assert!(CollapsedToolResult::new("fs_read", None, "short\noutput\n").is_none());
+    }
+}
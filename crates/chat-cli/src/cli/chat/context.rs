@@ -7,6 +7,7 @@ use eyre::{
     eyre,
 };
 use glob::glob;
+use globset::GlobSetBuilder;
 use serde::{
     Deserialize,
     Deserializer,
@@ -24,7 +25,9 @@ use crate::cli::agent::hook::{
 use crate::cli::chat::ChatError;
 use crate::cli::chat::cli::hooks::HookExecutor;
 use crate::cli::chat::cli::model::ModelInfo;
+use crate::database::settings::Setting;
 use crate::os::Os;
+use crate::util::directories;
 
 #[derive(Debug, Clone)]
 pub enum ContextFilePath {
@@ -158,6 +161,76 @@ impl ContextManager {
         Ok(())
     }
 
+    /// Add the files matching the glob patterns in `chat.autoContext` to the session context.
+    ///
+    /// Files denied to `fs_read` by the agent's `deniedPaths` are skipped. Matched files are
+    /// subject to the same size limit as any other context file.
+    ///
+    /// # Returns
+    /// The paths of the files that were added
+    pub async fn add_auto_context(&mut self, os: &Os, agent: &Agent) -> Result<Vec<String>> {
+        let patterns = os
+            .database
+            .settings
+            .get(Setting::ChatAutoContext)
+            .and_then(|v| v.as_array())
+            .map(|arr| {
+                arr.iter()
+                    .filter_map(|v| v.as_str().map(str::to_string))
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        if patterns.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let cwd = os.env.current_dir()?;
+        let to_absolute = |pattern: &str| -> Result<String> {
+            let expanded = directories::canonicalizes_path(os, pattern)?;
+            Ok(cwd.join(expanded).to_string_lossy().to_string())
+        };
+
+        let deny_set = {
+            let mut builder = GlobSetBuilder::new();
+            let denied_paths = agent
+                .tools_settings
+                .get("fs_read")
+                .and_then(|settings| settings.get("deniedPaths"))
+                .and_then(|v| v.as_array())
+                .map(|arr| arr.iter().filter_map(|v| v.as_str()).collect::<Vec<_>>())
+                .unwrap_or_default();
+            for path in denied_paths {
+                directories::add_gitignore_globs(&mut builder, &to_absolute(path)?)?;
+            }
+            builder.build()?
+        };
+
+        // Globs are expanded against the real filesystem, whose root differs from the user facing
+        // one in chroot testing scenarios.
+        let root = os.fs.chroot_path("/");
+        let mut added = Vec::new();
+        for pattern in patterns {
+            let full_pattern = os.fs.chroot_path_str(to_absolute(&pattern)?);
+            let entries = glob(&full_pattern).map_err(|e| eyre!("Invalid glob pattern '{}': {}", pattern, e))?;
+            for entry in entries.flatten() {
+                if !entry.is_file() {
+                    continue;
+                }
+                let Ok(relative) = entry.strip_prefix(&root) else {
+                    continue;
+                };
+                let path = Path::new("/").join(relative).to_string_lossy().to_string();
+                if deny_set.is_match(&path) || self.paths.iter().any(|p| p == path.as_str()) {
+                    continue;
+                }
+                self.paths.push(ContextFilePath::Session(path.clone()));
+                added.push(path);
+            }
+        }
+
+        Ok(added)
+    }
+
     /// Remove paths from the context configuration.
     ///
     /// # Arguments
@@ -432,6 +505,47 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_add_auto_context() -> Result<()> {
+        let mut os = Os::new().await.unwrap();
+        let mut manager = create_test_context_manager(None).expect("Failed to create test context manager");
+
+        os.fs.create_dir_all("project/docs").await?;
+        os.fs.create_dir_all("project/src").await?;
+        os.fs.write("project/README.md", "readme").await?;
+        os.fs.write("project/docs/architecture.md", "architecture").await?;
+        os.fs.write("project/docs/secret.md", "secret").await?;
+        os.fs.write("project/src/main.rs", "fn main() {}").await?;
+
+        let mut agent = Agent::default();
+        agent.tools_settings.insert(
+            crate::cli::agent::ToolSettingTarget("fs_read".to_string()),
+            serde_json::json!({ "deniedPaths": ["/project/docs/secret.md"] }),
+        );
+
+        assert!(manager.add_auto_context(&os, &agent).await?.is_empty());
+
+        os.database
+            .settings
+            .set(
+                Setting::ChatAutoContext,
+                serde_json::json!(["project/README.md", "project/docs/*.md", "project/*.txt"]),
+            )
+            .await?;
+        let mut added = manager.add_auto_context(&os, &agent).await?;
+        added.sort();
+        assert_eq!(added, vec!["/project/README.md", "/project/docs/architecture.md"]);
+
+        let files = manager.get_context_files(&os).await?;
+        assert_eq!(files.len(), 2);
+        assert!(files.iter().any(|(_, content)| content == "readme"));
+        assert!(files.iter().any(|(_, content)| content == "architecture"));
+
+        // Already present files are not added twice
+        assert!(manager.add_auto_context(&os, &agent).await?.is_empty());
+        Ok(())
+    }
+
     #[test]
     fn test_calc_max_context_files_size() {
         assert_eq!(
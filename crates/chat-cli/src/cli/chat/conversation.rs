@@ -38,16 +38,20 @@ use super::context::{
     ContextManager,
     calc_max_context_files_size,
 };
+use super::conversation_store::ConversationStore;
 use super::line_tracker::FileLineTracker;
 use super::message::{
     AssistantMessage,
     ToolUseResult,
+    ToolUseResultBlock,
     UserMessage,
+    UserMessageContent,
 };
 use super::parser::RequestMetadata;
 use super::token_counter::{
     CharCount,
     CharCounter,
+    TokenCount,
     TokenCounter,
 };
 use super::tool_manager::ToolManager;
@@ -78,6 +82,7 @@ use crate::cli::chat::cli::model::{
     get_model_info,
 };
 use crate::cli::chat::tools::custom_tool::CustomToolConfig;
+use crate::cli::chat::tools::scratchpad::Scratchpad;
 use crate::os::Os;
 
 pub const CONTEXT_ENTRY_START_HEADER: &str = "--- CONTEXT ENTRY BEGIN ---\n";
@@ -91,6 +96,24 @@ pub struct HistoryEntry {
     request_metadata: Option<RequestMetadata>,
 }
 
+/// Controls which parts of a conversation are included by [ConversationState::to_markdown].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExportOptions {
+    /// Include the arguments of each tool call. When false, only the tool name is kept.
+    pub tool_call_details: bool,
+    /// Include the results returned by tool calls.
+    pub tool_results: bool,
+}
+
+impl Default for ExportOptions {
+    fn default() -> Self {
+        Self {
+            tool_call_details: true,
+            tool_results: true,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct McpServerInfo {
     pub name: String,
@@ -142,6 +165,46 @@ pub struct ConversationState {
     /// Tangent mode checkpoint - stores main conversation when in tangent mode
     #[serde(default, skip_serializing_if = "Option::is_none")]
     tangent_state: Option<ConversationCheckpoint>,
+    /// System prompt set with `/system`, prepended to the context on every turn of this
+    /// conversation.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    system_prompt_override: Option<String>,
+    /// Approximate token usage accumulated over every turn of this conversation.
+    #[serde(default)]
+    usage: UsageSummary,
+    /// Working notes the model keeps with the scratchpad tools. Saved with the conversation but
+    /// not part of the transcript.
+    #[serde(default, skip_serializing_if = "Scratchpad::is_empty")]
+    pub scratchpad: Scratchpad,
+    /// The path and stored version this conversation was loaded from or last saved as, to detect
+    /// saves by other sessions. [None] for conversations that have not been saved yet.
+    #[serde(skip)]
+    db_version: Option<(String, i64)>,
+}
+
+/// Approximate token usage of a conversation. The backend does not report token counts, so these
+/// are estimated from the size of each request and response.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UsageSummary {
+    /// Number of completed request/response turns.
+    pub turns: usize,
+    /// Tokens sent to the model. The history and context are resent on every turn, so this grows
+    /// faster than the conversation itself.
+    pub input_tokens: usize,
+    /// Tokens received from the model.
+    pub output_tokens: usize,
+}
+
+impl UsageSummary {
+    pub fn total_tokens(&self) -> usize {
+        self.input_tokens + self.output_tokens
+    }
+
+    fn record_turn(&mut self, input: CharCount, output: CharCount) {
+        self.turns += 1;
+        self.input_tokens += TokenCount::from(input).value();
+        self.output_tokens += TokenCount::from(output).value();
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -204,6 +267,10 @@ impl ConversationState {
             file_line_tracker: HashMap::new(),
             mcp_enabled,
             tangent_state: None,
+            system_prompt_override: None,
+            usage: UsageSummary::default(),
+            scratchpad: Scratchpad::default(),
+            db_version: None,
         }
     }
 
@@ -215,6 +282,62 @@ impl ConversationState {
         &self.history
     }
 
+    /// The prompt of the first user message in the conversation, if any.
+    pub fn first_user_prompt(&self) -> Option<&str> {
+        self.history.iter().find_map(|entry| entry.user.prompt())
+    }
+
+    /// The text of every user prompt and assistant response in the conversation, one message per
+    /// line, as indexed for conversation search.
+    pub fn message_text(&self) -> String {
+        self.history
+            .iter()
+            .flat_map(|entry| [entry.user.prompt(), Some(entry.assistant.content())])
+            .flatten()
+            .filter(|text| !text.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// The system prompt override for this conversation, if any.
+    pub fn system_prompt_override(&self) -> Option<&str> {
+        self.system_prompt_override.as_deref()
+    }
+
+    /// Sets a system prompt that is prepended on every turn of this conversation.
+    pub fn set_system_prompt_override(&mut self, prompt: impl Into<String>) {
+        self.system_prompt_override = Some(prompt.into());
+    }
+
+    /// Removes the system prompt override, returning the previous one if set.
+    pub fn clear_system_prompt_override(&mut self) -> Option<String> {
+        self.system_prompt_override.take()
+    }
+
+    /// The stored version this conversation was loaded from or last saved as under `path`.
+    pub fn db_version(&self, path: &str) -> Option<i64> {
+        self.db_version
+            .as_ref()
+            .and_then(|(saved_path, version)| (saved_path == path).then_some(*version))
+    }
+
+    /// The path this conversation was loaded from or last saved under in the database.
+    pub fn db_path(&self) -> Option<&str> {
+        self.db_version.as_ref().map(|(path, _)| path.as_str())
+    }
+
+    /// Sets the path and stored version the next save is based on. [None] overwrites whatever is
+    /// stored.
+    pub fn set_db_version(&mut self, version: Option<(String, i64)>) {
+        self.db_version = version;
+    }
+
+    /// Token usage accumulated over every turn of this conversation, including turns since
+    /// cleared from the history.
+    pub fn usage_summary(&self) -> UsageSummary {
+        self.usage
+    }
+
     /// Clears the conversation history and summary.
     pub fn clear(&mut self) {
         self.next_message = None;
@@ -383,26 +506,106 @@ impl ConversationState {
         self.next_message = Some(msg);
     }
 
-    /// Sets the response message according to the currently set [Self::next_message].
+    /// Sets the response message according to the currently set [Self::next_message] and saves
+    /// the conversation for the working directory, overwriting saves by other sessions.
     pub fn push_assistant_message(
         &mut self,
         os: &mut Os,
         message: AssistantMessage,
         request_metadata: Option<RequestMetadata>,
     ) {
+        self.add_assistant_message(message, request_metadata);
+
+        if let Ok(cwd) = os.env.current_dir() {
+            let store = ConversationStore::new(os);
+            if let Err(err) = store.save(&mut os.database, &cwd, self) {
+                if ConversationStore::conflict(&err).is_some() {
+                    // The conversation on screen is the one the user is continuing, so it wins
+                    warn!(?err, "overwriting a conversation saved by another session");
+                    self.db_version = None;
+                    store.save(&mut os.database, &cwd, self).ok();
+                }
+            }
+        }
+    }
+
+    /// Sets the response message according to the currently set [Self::next_message] without
+    /// saving the conversation, for callers that handle save conflicts themselves.
+    pub fn add_assistant_message(&mut self, message: AssistantMessage, request_metadata: Option<RequestMetadata>) {
         debug_assert!(self.next_message.is_some(), "next_message should exist");
         let next_user_message = self.next_message.take().expect("next user message should exist");
 
+        let input = self.history.iter().fold(
+            next_user_message.char_count(),
+            |acc, HistoryEntry { user, assistant, .. }| acc + user.char_count() + assistant.char_count(),
+        ) + self.context_message_length.unwrap_or_default().into();
+        self.usage.record_turn(input, message.char_count());
+
         self.append_assistant_transcript(&message);
         self.history.push_back(HistoryEntry {
             user: next_user_message,
             assistant: message,
             request_metadata,
         });
+    }
+
+    /// Takes over the session state that isn't persisted with a conversation, such as the tool
+    /// manager and agents, from `other`, e.g. when replacing the current conversation with one
+    /// read from storage.
+    pub fn swap_session_state(&mut self, other: &mut Self) {
+        std::mem::swap(&mut self.tool_manager, &mut other.tool_manager);
+        std::mem::swap(&mut self.mcp_enabled, &mut other.mcp_enabled);
+        std::mem::swap(&mut self.model_info, &mut other.model_info);
+        std::mem::swap(&mut self.context_manager, &mut other.context_manager);
+        std::mem::swap(&mut self.agents, &mut other.agents);
+    }
+
+    /// Renders the conversation history as a human readable markdown transcript.
+    pub fn to_markdown(&self, options: &ExportOptions) -> String {
+        let mut md = format!("# Conversation {}\n", self.conversation_id);
+
+        for HistoryEntry { user, assistant, .. } in &self.history {
+            if let Some(prompt) = user.prompt() {
+                md.push_str(&format!("\n## User\n\n{}\n", prompt.trim()));
+            }
+
+            if options.tool_results {
+                let results = match user.content() {
+                    UserMessageContent::CancelledToolUses { tool_use_results, .. }
+                    | UserMessageContent::ToolUseResults { tool_use_results } => tool_use_results.as_slice(),
+                    UserMessageContent::Prompt { .. } => &[],
+                };
+                for result in results {
+                    md.push_str(&format!(
+                        "\n## Tool result ({:?})\n\n`{}`\n",
+                        result.status, result.tool_use_id
+                    ));
+                    for block in &result.content {
+                        let (lang, text) = match block {
+                            ToolUseResultBlock::Json(value) => {
+                                ("json", serde_json::to_string_pretty(value).unwrap_or_default())
+                            },
+                            ToolUseResultBlock::Text(text) => ("", text.clone()),
+                        };
+                        md.push_str(&format!("\n```{lang}\n{}\n```\n", text.trim_end()));
+                    }
+                }
+            }
 
-        if let Ok(cwd) = std::env::current_dir() {
-            os.database.set_conversation_by_path(cwd, self).ok();
+            md.push_str("\n## Assistant\n");
+            if !assistant.content().trim().is_empty() {
+                md.push_str(&format!("\n{}\n", assistant.content().trim()));
+            }
+            for tool_use in assistant.tool_uses().unwrap_or_default() {
+                md.push_str(&format!("\n**Tool use:** `{}` (`{}`)\n", tool_use.name, tool_use.id));
+                if options.tool_call_details {
+                    let args = serde_json::to_string_pretty(&tool_use.args).unwrap_or_default();
+                    md.push_str(&format!("\n```json\n{args}\n```\n"));
+                }
+            }
         }
+
+        md
     }
 
     /// Returns the conversation id.
@@ -457,17 +660,23 @@ impl ConversationState {
     ///    intervention here is to substitute the ambiguous, partial name with a dummy.
     /// 3. The model had decided to call a tool that does not exist. The intervention here is to
     ///    substitute the non-existent tool name with a dummy.
+    ///
+    /// When the dummy is left out of the schema with `tools.includeDummy`, the ambiguous or
+    /// non-existent tool uses are dropped from the message instead and their tool results kept as
+    /// plain text, while the message's other tool uses and results are kept as they are.
     pub fn enforce_tool_use_history_invariants(&mut self) {
-        enforce_tool_use_history_invariants(&mut self.history, &self.tools);
+        enforce_tool_use_history_invariants(&mut self.history, &mut self.next_message, &self.tools);
     }
 
-    pub fn add_tool_results(&mut self, tool_results: Vec<ToolUseResult>) {
+    pub fn add_tool_results(&mut self, mut tool_results: Vec<ToolUseResult>) {
         debug_assert!(self.next_message.is_none());
+        self.order_tool_results(&mut tool_results);
         self.next_message = Some(UserMessage::new_tool_use_results(tool_results));
     }
 
-    pub fn add_tool_results_with_images(&mut self, tool_results: Vec<ToolUseResult>, images: Vec<ImageBlock>) {
+    pub fn add_tool_results_with_images(&mut self, mut tool_results: Vec<ToolUseResult>, images: Vec<ImageBlock>) {
         debug_assert!(self.next_message.is_none());
+        self.order_tool_results(&mut tool_results);
         self.next_message = Some(UserMessage::new_tool_use_results_with_images(
             tool_results,
             images,
@@ -475,6 +684,21 @@ impl ConversationState {
         ));
     }
 
+    /// Sorts `tool_results` into the order the tools were requested in by the latest assistant
+    /// message, regardless of the order they completed in. Results for unknown tool use ids keep
+    /// their relative order and are placed last.
+    fn order_tool_results(&self, tool_results: &mut [ToolUseResult]) {
+        let Some(tool_uses) = self.history.back().and_then(|entry| entry.assistant.tool_uses()) else {
+            return;
+        };
+        let order = tool_uses
+            .iter()
+            .enumerate()
+            .map(|(i, tool_use)| (tool_use.id.as_str(), i))
+            .collect::<HashMap<_, _>>();
+        tool_results.sort_by_key(|result| order.get(result.tool_use_id.as_str()).copied().unwrap_or(usize::MAX));
+    }
+
     /// Sets the next user message with "cancelled" tool results.
     pub fn abandon_tool_use(&mut self, tools_to_be_abandoned: &[QueuedTool], deny_input: String) {
         self.next_message = Some(UserMessage::new_cancelled_tool_uses(
@@ -542,6 +766,7 @@ impl ConversationState {
                 acc
             });
         self.tool_manager.has_new_stuff.store(false, Ordering::Release);
+        self.tool_manager.dump_schema_if_requested();
         // We call this in [Self::enforce_conversation_invariants] as well. But we need to call it
         // here as well because when it's being called in [Self::enforce_conversation_invariants]
         // it is only checking the last entry.
@@ -680,7 +905,8 @@ impl ConversationState {
                 v.retain(|tool| match tool {
                     Tool::ToolSpecification(tool_spec) => tool_spec.name == DUMMY_TOOL_NAME,
                 });
-                true
+                // The dummy tool may be excluded from the schema via tools.includeDummy.
+                !v.is_empty()
             },
             ToolOrigin::McpServer(_) => false,
         });
@@ -742,7 +968,8 @@ Return only the JSON configuration, no additional text.",
                 v.retain(|tool| match tool {
                     Tool::ToolSpecification(tool_spec) => tool_spec.name == DUMMY_TOOL_NAME,
                 });
-                true
+                // The dummy tool may be excluded from the schema via tools.includeDummy.
+                !v.is_empty()
             },
             ToolOrigin::McpServer(_) => false,
         });
@@ -777,6 +1004,14 @@ Return only the JSON configuration, no additional text.",
     ) -> (Option<Vec<HistoryEntry>>, Vec<(String, String)>) {
         let mut context_content = String::new();
         let mut dropped_context_files = Vec::new();
+        if let Some(system_prompt) = &self.system_prompt_override {
+            context_content.push_str(CONTEXT_ENTRY_START_HEADER);
+            context_content.push_str("Follow this instruction for the entire conversation: ");
+            context_content.push_str(system_prompt);
+            context_content.push('\n');
+            context_content.push_str(CONTEXT_ENTRY_END_HEADER);
+        }
+
         if let Some((summary, _)) = &self.latest_summary {
             context_content.push_str(CONTEXT_ENTRY_START_HEADER);
             context_content.push_str("This summary contains ALL relevant information from our previous conversation including tool uses, results, code analysis, and file operations. YOU MUST reference this information when answering questions and explicitly acknowledge specific details from the summary when they're relevant to the current question.\n\n");
@@ -1144,12 +1379,19 @@ fn enforce_conversation_invariants(
         }
     }
 
-    enforce_tool_use_history_invariants(history, tools);
+    enforce_tool_use_history_invariants(history, next_message, tools);
 
     valid_history_range
 }
 
-fn enforce_tool_use_history_invariants(history: &mut VecDeque<HistoryEntry>, tools: &HashMap<ToolOrigin, Vec<Tool>>) {
+fn enforce_tool_use_history_invariants(
+    history: &mut VecDeque<HistoryEntry>,
+    next_message: &mut Option<UserMessage>,
+    tools: &HashMap<ToolOrigin, Vec<Tool>>,
+) {
+    let has_dummy = tools.values().flatten().any(|tool| match tool {
+        Tool::ToolSpecification(tool_specification) => tool_specification.name == DUMMY_TOOL_NAME,
+    });
     let tool_names: HashSet<_> = tools
         .values()
         .flat_map(|tools| {
@@ -1160,8 +1402,9 @@ fn enforce_tool_use_history_invariants(history: &mut VecDeque<HistoryEntry>, too
         .filter(|name| *name != DUMMY_TOOL_NAME)
         .collect();
 
-    for HistoryEntry { assistant, .. } in history {
-        if let AssistantMessage::ToolUse { tool_uses, .. } = assistant {
+    for i in 0..history.len() {
+        let mut unresolved = Vec::new();
+        if let AssistantMessage::ToolUse { tool_uses, .. } = &mut history[i].assistant {
             for tool_use in tool_uses {
                 if tool_names.contains(tool_use.name.as_str()) {
                     continue;
@@ -1192,7 +1435,50 @@ fn enforce_tool_use_history_invariants(history: &mut VecDeque<HistoryEntry>, too
                 }
 
                 // Otherwise, we have to replace it with a dummy.
-                tool_use.name = DUMMY_TOOL_NAME.to_string();
+                if has_dummy {
+                    tool_use.name = DUMMY_TOOL_NAME.to_string();
+                } else {
+                    unresolved.push(tool_use.id.clone());
+                }
+            }
+        }
+        if unresolved.is_empty() {
+            continue;
+        }
+
+        // Without the dummy there is no tool to point the unresolved tool uses at, so drop them
+        // from the message and turn their results into plain text.
+        let assistant = &mut history[i].assistant;
+        let mut all_unresolved = false;
+        if let AssistantMessage::ToolUse {
+            message_id,
+            content,
+            tool_uses,
+        } = assistant
+        {
+            all_unresolved = tool_uses.len() == unresolved.len();
+            if all_unresolved {
+                let mut content = std::mem::take(content);
+                if content.is_empty() {
+                    // To avoid validation errors with empty content
+                    let names = tool_uses.iter().map(|t| t.name.as_str()).collect::<Vec<_>>();
+                    content = format!("<tool use: {}>", names.join(", "));
+                }
+                *assistant = AssistantMessage::new_response(message_id.take(), content);
+            } else {
+                tool_uses.retain(|tool_use| !unresolved.contains(&tool_use.id));
+            }
+        }
+        let user = match history.get_mut(i + 1) {
+            Some(HistoryEntry { user, .. }) => Some(user),
+            None => next_message.as_mut(),
+        };
+        if let Some(user) = user {
+            if all_unresolved {
+                user.replace_content_with_tool_use_results();
+            } else {
+                let ids = unresolved.iter().map(String::as_str).collect::<Vec<_>>();
+                user.replace_tool_use_results_with_text(&ids);
             }
         }
     }
@@ -1214,6 +1500,12 @@ mod tests {
         Agents,
     };
     use crate::cli::chat::tool_manager::ToolManager;
+    use crate::cli::chat::tools;
+    use crate::cli::chat::tools::OutputKind;
+    use crate::cli::chat::tools::scratchpad::{
+        ScratchpadRead,
+        ScratchpadWrite,
+    };
 
     const AMAZONQ_FILENAME: &str = "AmazonQ.md";
     const AGENTS_FILENAME: &str = "AGENTS.md";
@@ -1481,6 +1773,278 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn test_to_markdown_export_options() {
+        let mut os = Os::new().await.unwrap();
+        let mut tool_manager = ToolManager::default();
+        let mut conversation = ConversationState::new(
+            "fake_conv_id",
+            Agents::default(),
+            tool_manager.load_tools(&mut os, &mut vec![]).await.unwrap(),
+            tool_manager,
+            None,
+            &os,
+            false,
+        )
+        .await;
+
+        conversation.set_next_user_message("read my file".to_string()).await;
+        conversation.push_assistant_message(
+            &mut os,
+            AssistantMessage::new_tool_use(None, "Reading it now.".to_string(), vec![AssistantToolUse {
+                id: "tooluse_1".to_string(),
+                name: "fs_read".to_string(),
+                orig_name: "fs_read".to_string(),
+                args: serde_json::json!({ "path": "/secret/path.txt" }),
+                orig_args: serde_json::json!({ "path": "/secret/path.txt" }),
+            }]),
+            None,
+        );
+        conversation.add_tool_results(vec![ToolUseResult {
+            tool_use_id: "tooluse_1".to_string(),
+            content: vec![ToolUseResultBlock::Text("file contents".to_string())],
+            status: ToolResultStatus::Success,
+        }]);
+        conversation.push_assistant_message(
+            &mut os,
+            AssistantMessage::new_response(None, "The file says hello.".to_string()),
+            None,
+        );
+
+        let all = conversation.to_markdown(&ExportOptions::default());
+        for expected in [
+            "read my file",
+            "Reading it now.",
+            "`fs_read`",
+            "/secret/path.txt",
+            "file contents",
+            "The file says hello.",
+        ] {
+            assert!(all.contains(expected), "expected {expected:?} in:\n{all}");
+        }
+
+        let no_calls = conversation.to_markdown(&ExportOptions {
+            tool_call_details: false,
+            ..Default::default()
+        });
+        assert!(no_calls.contains("`fs_read`"));
+        assert!(!no_calls.contains("/secret/path.txt"));
+        assert!(no_calls.contains("file contents"));
+        assert!(no_calls.contains("The file says hello."));
+
+        let no_results = conversation.to_markdown(&ExportOptions {
+            tool_results: false,
+            ..Default::default()
+        });
+        assert!(no_results.contains("/secret/path.txt"));
+        assert!(!no_results.contains("file contents"));
+        assert!(no_results.contains("The file says hello."));
+    }
+
+    #[tokio::test]
+    async fn test_tool_results_keep_request_order() {
+        let mut os = Os::new().await.unwrap();
+        let mut tool_manager = ToolManager::default();
+        let mut conversation = ConversationState::new(
+            "fake_conv_id",
+            Agents::default(),
+            tool_manager.load_tools(&mut os, &mut vec![]).await.unwrap(),
+            tool_manager,
+            None,
+            &os,
+            false,
+        )
+        .await;
+
+        let ids = ["tooluse_a", "tooluse_b", "tooluse_c"];
+        conversation.set_next_user_message("run the tools".to_string()).await;
+        conversation.push_assistant_message(
+            &mut os,
+            AssistantMessage::new_tool_use(
+                None,
+                String::new(),
+                ids.iter()
+                    .map(|id| AssistantToolUse {
+                        id: (*id).to_string(),
+                        name: "mock".to_string(),
+                        ..Default::default()
+                    })
+                    .collect(),
+            ),
+            None,
+        );
+
+        // Run mock tools concurrently, collecting results in completion order. Each tool waits for
+        // the one before it to finish so that they complete in the order b, c, a.
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let (b_done, c_start) = tokio::sync::oneshot::channel();
+        let (c_done, a_start) = tokio::sync::oneshot::channel();
+        for (id, start, done) in [
+            (ids[0], Some(a_start), None),
+            (ids[1], None, Some(b_done)),
+            (ids[2], Some(c_start), Some(c_done)),
+        ] {
+            let tx = tx.clone();
+            tokio::spawn(async move {
+                if let Some(start) = start {
+                    start.await.unwrap();
+                }
+                tx.send(ToolUseResult {
+                    tool_use_id: id.to_string(),
+                    content: vec![ToolUseResultBlock::Text(id.to_string())],
+                    status: ToolResultStatus::Success,
+                })
+                .unwrap();
+                if let Some(done) = done {
+                    done.send(()).unwrap();
+                }
+            });
+        }
+        drop(tx);
+        let mut results = Vec::new();
+        while let Some(result) = rx.recv().await {
+            results.push(result);
+        }
+        assert_eq!(results.iter().map(|r| r.tool_use_id.as_str()).collect::<Vec<_>>(), [
+            "tooluse_b",
+            "tooluse_c",
+            "tooluse_a"
+        ]);
+
+        conversation.add_tool_results(results);
+        let ordered = conversation.next_message.as_ref().unwrap().tool_use_results().unwrap();
+        assert_eq!(ordered.iter().map(|r| r.tool_use_id.as_str()).collect::<Vec<_>>(), ids);
+    }
+
+    #[tokio::test]
+    async fn test_usage_summary() {
+        let mut os = Os::new().await.unwrap();
+        let mut tool_manager = ToolManager::default();
+        let mut conversation = ConversationState::new(
+            "fake_conv_id",
+            Agents::default(),
+            tool_manager.load_tools(&mut os, &mut vec![]).await.unwrap(),
+            tool_manager,
+            None,
+            &os,
+            false,
+        )
+        .await;
+        assert_eq!(conversation.usage_summary(), UsageSummary::default());
+
+        // 400 chars is 100 tokens
+        let text = "a".repeat(400);
+        for _ in 0..2 {
+            conversation.set_next_user_message(text.clone()).await;
+            conversation.push_assistant_message(&mut os, AssistantMessage::new_response(None, text.clone()), None);
+        }
+
+        // The second turn resends the first turn as input
+        let usage = conversation.usage_summary();
+        assert_eq!(usage, UsageSummary {
+            turns: 2,
+            input_tokens: 100 + 300,
+            output_tokens: 200,
+        });
+        assert_eq!(usage.total_tokens(), 600);
+
+        // Usage is persisted with the conversation
+        os.database.set_conversation_by_path("/usage", &conversation).unwrap();
+        let (_, reloaded) = os.database.most_recent_conversation("/usage").unwrap().unwrap();
+        assert_eq!(reloaded.usage_summary(), usage);
+    }
+
+    #[tokio::test]
+    async fn test_scratchpad_persists() {
+        let mut os = Os::new().await.unwrap();
+        let mut tool_manager = ToolManager::default();
+        let mut conversation = ConversationState::new(
+            "fake_conv_id",
+            Agents::default(),
+            tool_manager.load_tools(&mut os, &mut vec![]).await.unwrap(),
+            tool_manager,
+            None,
+            &os,
+            false,
+        )
+        .await;
+
+        let write = tools::Tool::ScratchpadWrite(ScratchpadWrite {
+            content: "the flaky test is in the parser".to_string(),
+            append: false,
+        });
+        let mut line_tracker = HashMap::new();
+        write
+            .invoke(
+                &os,
+                &mut std::io::sink(),
+                &mut line_tracker,
+                None,
+                &mut conversation.scratchpad,
+            )
+            .await
+            .unwrap();
+
+        // The notes survive a reload and can be read back, but stay out of the transcript
+        os.database
+            .set_conversation_by_path("/scratchpad", &conversation)
+            .unwrap();
+        let (_, mut reloaded) = os.database.most_recent_conversation("/scratchpad").unwrap().unwrap();
+        let read = tools::Tool::ScratchpadRead(ScratchpadRead {});
+        let output = read
+            .invoke(
+                &os,
+                &mut std::io::sink(),
+                &mut line_tracker,
+                None,
+                &mut reloaded.scratchpad,
+            )
+            .await
+            .unwrap();
+        assert!(matches!(output.output, OutputKind::Text(text) if text == "the flaky test is in the parser"));
+        assert!(reloaded.transcript.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_system_prompt_override() {
+        let mut os = Os::new().await.unwrap();
+        let mut tool_manager = ToolManager::default();
+        let mut conversation = ConversationState::new(
+            "fake_conv_id",
+            Agents::default(),
+            tool_manager.load_tools(&mut os, &mut vec![]).await.unwrap(),
+            tool_manager,
+            None,
+            &os,
+            false,
+        )
+        .await;
+        assert!(conversation.context_messages(&os, None).await.0.is_none());
+
+        conversation.set_system_prompt_override("respond in French");
+        os.database
+            .set_conversation_by_path("/system/prompt", &conversation)
+            .unwrap();
+
+        // The override survives a reload and is applied on every turn
+        let (_, mut reloaded) = os.database.most_recent_conversation("/system/prompt").unwrap().unwrap();
+        assert_eq!(reloaded.system_prompt_override(), Some("respond in French"));
+        for _ in 0..2 {
+            let context = reloaded.context_messages(&os, None).await.0.unwrap();
+            assert!(context[0].user.prompt().unwrap().contains("respond in French"));
+        }
+
+        assert_eq!(
+            reloaded.clear_system_prompt_override().as_deref(),
+            Some("respond in French")
+        );
+        os.database
+            .set_conversation_by_path("/system/prompt", &reloaded)
+            .unwrap();
+        let (_, reloaded) = os.database.most_recent_conversation("/system/prompt").unwrap().unwrap();
+        assert_eq!(reloaded.system_prompt_override(), None);
+    }
+
     #[tokio::test]
     async fn test_tangent_mode() {
         let mut os = Os::new().await.unwrap();
@@ -1684,4 +2248,95 @@ mod tests {
         conversation.exit_tangent_mode_with_tail();
         assert_eq!(conversation.history.len(), main_history_len);
     }
+
+    #[test]
+    fn test_unresolved_tool_use_without_dummy() {
+        let tool = |name: &str| {
+            Tool::ToolSpecification(ToolSpecification {
+                name: name.to_string(),
+                description: String::new(),
+                input_schema: ToolInputSchema { json: None },
+            })
+        };
+        for include_dummy in [true, false] {
+            let mut native_tools = vec![tool("fs_read")];
+            if include_dummy {
+                native_tools.push(tool(DUMMY_TOOL_NAME));
+            }
+            let tools = HashMap::from([(ToolOrigin::Native, native_tools)]);
+            let mut history = VecDeque::from([HistoryEntry {
+                user: UserMessage::new_prompt("read it".to_string(), None),
+                assistant: AssistantMessage::new_tool_use(None, "Reading it now.".to_string(), vec![
+                    AssistantToolUse {
+                        id: "tooluse_1".to_string(),
+                        name: "missing_tool".to_string(),
+                        orig_name: "missing_tool".to_string(),
+                        ..Default::default()
+                    },
+                ]),
+                request_metadata: None,
+            }]);
+            let mut next_message = Some(UserMessage::new_tool_use_results(vec![ToolUseResult {
+                tool_use_id: "tooluse_1".to_string(),
+                content: vec![ToolUseResultBlock::Text("no such tool".to_string())],
+                status: ToolResultStatus::Error,
+            }]));
+
+            enforce_tool_use_history_invariants(&mut history, &mut next_message, &tools);
+
+            let next_message = next_message.unwrap();
+            if include_dummy {
+                assert_eq!(history[0].assistant.tool_uses().unwrap()[0].name, DUMMY_TOOL_NAME);
+                assert!(next_message.has_tool_use_results());
+            } else {
+                assert!(history[0].assistant.tool_uses().is_none());
+                assert_eq!(history[0].assistant.content(), "Reading it now.");
+                assert_eq!(next_message.prompt(), Some("no such tool"));
+            }
+        }
+    }
+
+    #[test]
+    fn test_partially_unresolved_tool_uses_without_dummy() {
+        let tools = HashMap::from([(ToolOrigin::Native, vec![Tool::ToolSpecification(ToolSpecification {
+            name: "fs_read".to_string(),
+            description: String::new(),
+            input_schema: ToolInputSchema { json: None },
+        })])]);
+        let tool_use = |id: &str, name: &str| AssistantToolUse {
+            id: id.to_string(),
+            name: name.to_string(),
+            orig_name: name.to_string(),
+            ..Default::default()
+        };
+        let tool_result = |id: &str, text: &str, status| ToolUseResult {
+            tool_use_id: id.to_string(),
+            content: vec![ToolUseResultBlock::Text(text.to_string())],
+            status,
+        };
+        let mut history = VecDeque::from([HistoryEntry {
+            user: UserMessage::new_prompt("read it".to_string(), None),
+            assistant: AssistantMessage::new_tool_use(None, "Reading it now.".to_string(), vec![
+                tool_use("tooluse_1", "fs_read"),
+                tool_use("tooluse_2", "missing_tool"),
+            ]),
+            request_metadata: None,
+        }]);
+        let mut next_message = Some(UserMessage::new_tool_use_results(vec![
+            tool_result("tooluse_1", "hello", ToolResultStatus::Success),
+            tool_result("tooluse_2", "no such tool", ToolResultStatus::Error),
+        ]));
+
+        enforce_tool_use_history_invariants(&mut history, &mut next_message, &tools);
+
+        // Only the unresolved tool use is dropped, the other one and its result are kept
+        let tool_uses = history[0].assistant.tool_uses().unwrap();
+        assert_eq!(tool_uses.len(), 1);
+        assert_eq!(tool_uses[0].id, "tooluse_1");
+        let next_message = next_message.unwrap();
+        let results = next_message.tool_use_results().unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].tool_use_id, "tooluse_1");
+        assert_eq!(next_message.prompt(), Some("no such tool"));
+    }
 }
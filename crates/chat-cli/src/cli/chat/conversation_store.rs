@@ -0,0 +1,319 @@
+use std::path::{
+    Path,
+    PathBuf,
+};
+use std::time::SystemTime;
+
+use eyre::Result;
+use tracing::warn;
+
+use super::conversation::ConversationState;
+use crate::database::settings::Setting;
+use crate::database::{
+    ConflictError,
+    Database,
+    DatabaseError,
+};
+use crate::os::{
+    Fs,
+    Os,
+};
+use crate::util::directories::project_root;
+
+/// Where conversations are saved, set with `conversations.storage`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ConversationStorage {
+    /// The central database, keyed by the working directory
+    #[default]
+    Db,
+    /// One file per conversation in the project's `.amazonq/conversations` directory
+    Project,
+    /// Both the database and the project directory
+    Both,
+}
+
+impl ConversationStorage {
+    fn from_setting(os: &Os) -> Self {
+        match os
+            .database
+            .settings
+            .get_string(Setting::ConversationsStorage)
+            .as_deref()
+        {
+            None | Some("db") => Self::Db,
+            Some("project") => Self::Project,
+            Some("both") => Self::Both,
+            Some(other) => {
+                warn!(
+                    storage = other,
+                    "unknown conversations.storage, expected db, project or both"
+                );
+                Self::Db
+            },
+        }
+    }
+}
+
+/// Saves and restores conversations according to [ConversationStorage].
+#[derive(Debug, Clone)]
+pub struct ConversationStore {
+    storage: ConversationStorage,
+    fs: Fs,
+    /// The project's `.amazonq/conversations` directory, if the project root could be determined
+    project_dir: Option<PathBuf>,
+}
+
+impl ConversationStore {
+    pub fn new(os: &Os) -> Self {
+        Self {
+            storage: ConversationStorage::from_setting(os),
+            fs: os.fs.clone(),
+            project_dir: project_root(os)
+                .ok()
+                .map(|root| root.join(".amazonq").join("conversations")),
+        }
+    }
+
+    /// Saves `state` as the conversation for `cwd`, or under the path it was resumed from when that
+    /// is a subdirectory of `cwd`, so that resuming doesn't duplicate it. Fails with a
+    /// [crate::database::ConflictError] if another session saved the conversation in the
+    /// database since `state` was loaded or last saved, see [Self::conflict].
+    pub fn save(&self, database: &mut Database, cwd: &Path, state: &mut ConversationState) -> Result<()> {
+        if self.storage != ConversationStorage::Project {
+            let path = state
+                .db_path()
+                .map_or_else(|| cwd.to_string_lossy().into_owned(), str::to_string);
+            let version = database.set_conversation_by_path(&path, state)?;
+            state.set_db_version(Some((path, version)));
+        }
+        if let Some(dir) = self.project_dir() {
+            self.fs.create_dir_all_sync(dir)?;
+            let path = dir.join(format!("{}.json", state.conversation_id()));
+            self.fs.write_sync(&path, serde_json::to_string_pretty(state)?)?;
+            // With both storages the database save above already indexed the conversation.
+            if self.storage == ConversationStorage::Project {
+                database.index_conversation(&path.to_string_lossy(), state)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The most recently saved conversation to resume in `cwd`. The database is preferred over
+    /// project files when both are used, since only its copy carries the version that
+    /// [Self::save] checks for conflicting saves.
+    pub async fn most_recent(&self, database: &Database, cwd: &Path) -> Result<Option<ConversationState>> {
+        if self.storage != ConversationStorage::Project {
+            if let Some((path, state)) = database.most_recent_conversation(cwd)? {
+                database.touch_conversation(&path)?;
+                return Ok(Some(state));
+            }
+        }
+        match self.project_dir() {
+            Some(dir) => most_recent_file(&self.fs, dir).await,
+            None => Ok(None),
+        }
+    }
+
+    /// The conflict a [Self::save] failed with, if another session saved the conversation first
+    pub fn conflict(err: &eyre::Report) -> Option<&ConflictError> {
+        match err.downcast_ref::<DatabaseError>() {
+            Some(DatabaseError::Conflict(conflict)) => Some(conflict),
+            _ => None,
+        }
+    }
+
+    fn project_dir(&self) -> Option<&Path> {
+        match self.storage {
+            ConversationStorage::Db => None,
+            ConversationStorage::Project | ConversationStorage::Both => self.project_dir.as_deref(),
+        }
+    }
+}
+
+/// The most recently modified conversation file in `dir`. Files whose modification time can't be
+/// read, e.g. on a fake [Fs], count as the oldest.
+async fn most_recent_file(fs: &Fs, dir: &Path) -> Result<Option<ConversationState>> {
+    let entries = match fs.read_dir_sync(dir) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err.into()),
+    };
+    let mut newest: Option<(SystemTime, PathBuf)> = None;
+    for path in entries {
+        if path.extension().is_none_or(|ext| ext != "json") {
+            continue;
+        }
+        let modified = fs
+            .metadata(&path)
+            .await
+            .and_then(|metadata| metadata.modified())
+            .unwrap_or(SystemTime::UNIX_EPOCH);
+        if newest.as_ref().is_none_or(|(newest, _)| modified >= *newest) {
+            newest = Some((modified, path));
+        }
+    }
+
+    match newest {
+        Some((_, path)) => Ok(Some(serde_json::from_str(&fs.read_to_string_sync(path)?)?)),
+        None => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::agent::Agents;
+    use crate::cli::chat::message::AssistantMessage;
+    use crate::cli::chat::tool_manager::ToolManager;
+    use crate::database::tests::test_conversation;
+
+    async fn setup(storage: &str) -> (Os, ConversationState) {
+        let mut os = Os::new().await.unwrap();
+        os.fs.create_dir_all("/repo/.amazonq").await.unwrap();
+        os.env.set_current_dir_for_test("/repo");
+        os.database
+            .settings
+            .set(Setting::ConversationsStorage, storage)
+            .await
+            .unwrap();
+
+        let mut tool_manager = ToolManager::default();
+        let mut conversation = ConversationState::new(
+            "fake_conv_id",
+            Agents::default(),
+            tool_manager.load_tools(&mut os, &mut vec![]).await.unwrap(),
+            tool_manager,
+            None,
+            &os,
+            false,
+        )
+        .await;
+        conversation.scratchpad.write("remember the parser", false).unwrap();
+        (os, conversation)
+    }
+
+    #[tokio::test]
+    async fn test_project_storage_round_trip() {
+        let (mut os, mut conversation) = setup("project").await;
+        conversation
+            .set_next_user_message("why does the parser hang".to_string())
+            .await;
+        conversation.add_assistant_message(AssistantMessage::new_response(None, "It loops.".to_string()), None);
+        let store = ConversationStore::new(&os);
+        store
+            .save(&mut os.database, Path::new("/repo"), &mut conversation)
+            .unwrap();
+
+        let file = "/repo/.amazonq/conversations/fake_conv_id.json";
+        assert!(os.fs.exists(file));
+        assert!(os.database.most_recent_conversation("/repo").unwrap().is_none());
+
+        let restored = store
+            .most_recent(&os.database, Path::new("/repo"))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(restored.conversation_id(), "fake_conv_id");
+        assert_eq!(restored.scratchpad.as_str(), "remember the parser");
+
+        // The project file is searchable like a conversation saved in the database
+        let results = os.database.search_conversations("parser").unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].0, file);
+    }
+
+    #[tokio::test]
+    async fn test_both_storage() {
+        let (mut os, mut conversation) = setup("both").await;
+        let store = ConversationStore::new(&os);
+        store
+            .save(&mut os.database, Path::new("/repo"), &mut conversation)
+            .unwrap();
+
+        assert!(os.fs.exists("/repo/.amazonq/conversations/fake_conv_id.json"));
+        let (_, from_db) = os.database.most_recent_conversation("/repo").unwrap().unwrap();
+        assert_eq!(from_db.scratchpad.as_str(), "remember the parser");
+
+        // The database copy is resumed, so saving it again still detects conflicting saves
+        let mut restored = store
+            .most_recent(&os.database, Path::new("/repo"))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(restored.db_version("/repo"), Some(1));
+        let mut other_session = restored.clone();
+        store
+            .save(&mut os.database, Path::new("/repo"), &mut other_session)
+            .unwrap();
+        let err = store
+            .save(&mut os.database, Path::new("/repo"), &mut restored)
+            .unwrap_err();
+        assert!(ConversationStore::conflict(&err).is_some());
+
+        // Falls back to the project files when the database has no conversation
+        let empty_database = Database::new().await.unwrap();
+        let restored = store
+            .most_recent(&empty_database, Path::new("/repo"))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(restored.conversation_id(), "fake_conv_id");
+    }
+
+    #[tokio::test]
+    async fn test_most_recent_file() {
+        let (os, conversation) = setup("project").await;
+        let dir = Path::new("/repo/.amazonq/conversations");
+        os.fs.create_dir_all(dir).await.unwrap();
+        let now = SystemTime::now();
+        for (id, age) in [("old", 20), ("newest", 0), ("middle", 10)] {
+            let conversation = test_conversation(&os, id).await;
+            let path = dir.join(format!("{id}.json"));
+            os.fs
+                .write(&path, serde_json::to_string(&conversation).unwrap())
+                .await
+                .unwrap();
+            std::fs::File::options()
+                .write(true)
+                .open(os.fs.chroot_path(&path))
+                .unwrap()
+                .set_modified(now - std::time::Duration::from_secs(age))
+                .unwrap();
+        }
+        os.fs.write(dir.join("notes.txt"), "not a conversation").await.unwrap();
+
+        let restored = most_recent_file(&os.fs, dir).await.unwrap().unwrap();
+        assert_eq!(restored.conversation_id(), "newest");
+        assert!(most_recent_file(&os.fs, Path::new("/missing")).await.unwrap().is_none());
+
+        // Fake file systems have no modification times, but still resume a conversation
+        let fs = Fs::from_slice(&[(
+            "/repo/.amazonq/conversations/fake_conv_id.json",
+            &serde_json::to_string(&conversation).unwrap(),
+        )]);
+        let restored = most_recent_file(&fs, dir).await.unwrap().unwrap();
+        assert_eq!(restored.conversation_id(), "fake_conv_id");
+    }
+
+    #[tokio::test]
+    async fn test_resume_from_subdirectory_saves_in_place() {
+        let (mut os, mut conversation) = setup("db").await;
+        let store = ConversationStore::new(&os);
+        store
+            .save(&mut os.database, Path::new("/repo/sub"), &mut conversation)
+            .unwrap();
+
+        // Resuming in the parent picks up the subdirectory's conversation and saves it back there.
+        let mut resumed = store
+            .most_recent(&os.database, Path::new("/repo"))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(resumed.db_path(), Some("/repo/sub"));
+        store.save(&mut os.database, Path::new("/repo"), &mut resumed).unwrap();
+
+        assert!(os.database.get_conversation_by_path("/repo").unwrap().is_none());
+        let stored = os.database.get_conversation_by_path("/repo/sub").unwrap().unwrap();
+        assert_eq!(stored.db_version("/repo/sub"), Some(2));
+    }
+}
@@ -0,0 +1,128 @@
+use std::fmt;
+
+use tracing::{
+    Span,
+    info,
+    info_span,
+};
+
+/// Identifies the tool calls the model made in one turn, so that their tracing spans, telemetry
+/// and audit entries can be correlated.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CorrelationId(String);
+
+impl CorrelationId {
+    pub fn new() -> Self {
+        let id = uuid::Uuid::new_v4().simple().to_string();
+        Self(id[..12].to_string())
+    }
+
+    /// The span a tool call runs in
+    pub fn tool_call_span(&self, tool_use_id: &str, tool_name: &str) -> Span {
+        info_span!("tool_call", correlation_id = %self, tool_use_id, tool_name)
+    }
+}
+
+impl Default for CorrelationId {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Display for CorrelationId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// The outcome of a tool call, written to the log at the info level
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuditEntry<'a> {
+    pub correlation_id: &'a CorrelationId,
+    pub tool_use_id: &'a str,
+    pub tool_name: &'a str,
+    /// Whether the call was trusted rather than approved by the user
+    pub trusted: bool,
+    pub success: bool,
+}
+
+impl AuditEntry<'_> {
+    pub fn record(&self) {
+        info!(
+            correlation_id = %self.correlation_id,
+            tool_use_id = self.tool_use_id,
+            tool_name = self.tool_name,
+            trusted = self.trusted,
+            success = self.success,
+            "audit: tool call"
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+    use std::sync::{
+        Arc,
+        Mutex,
+    };
+
+    use super::*;
+
+    /// Collects formatted log lines
+    #[derive(Clone, Default)]
+    struct Logs(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Logs {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_correlation_id_in_span_and_audit_entry() {
+        let id = CorrelationId::new();
+        assert_eq!(id.to_string().len(), 12);
+        assert_ne!(id, CorrelationId::new());
+
+        let logs = Logs::default();
+        let writer = logs.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_writer(move || writer.clone())
+            .with_ansi(false)
+            .without_time()
+            .finish();
+        tracing::subscriber::with_default(subscriber, || {
+            for (tool_use_id, tool_name) in [("tooluse_1", "fs_read"), ("tooluse_2", "execute_bash")] {
+                let span = id.tool_call_span(tool_use_id, tool_name);
+                span.in_scope(|| {
+                    AuditEntry {
+                        correlation_id: &id,
+                        tool_use_id,
+                        tool_name,
+                        trusted: true,
+                        success: tool_name == "fs_read",
+                    }
+                    .record();
+                });
+            }
+        });
+
+        // Each audit entry is logged within its call's span, and both carry the turn's id
+        let logs = String::from_utf8(logs.0.lock().unwrap().clone()).unwrap();
+        let lines = logs.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 2);
+        for (line, (tool_use_id, tool_name, success)) in lines
+            .iter()
+            .zip([("tooluse_1", "fs_read", true), ("tooluse_2", "execute_bash", false)])
+        {
+            assert!(line.ends_with(&format!(
+                "tool_call{{correlation_id={id} tool_use_id=\"{tool_use_id}\" tool_name=\"{tool_name}\"}}: chat_cli::cli::chat::correlation: audit: tool call correlation_id={id} tool_use_id=\"{tool_use_id}\" tool_name=\"{tool_name}\" trusted=true success={success}"
+            )));
+        }
+    }
+}
@@ -1,3 +1,6 @@
+use std::future::Future;
+use std::time::Duration;
+
 use eyre::Result;
 use rustyline::error::ReadlineError;
 
@@ -11,7 +14,16 @@ use super::skim_integration::SkimCommandSelector;
 use crate::os::Os;
 
 #[derive(Debug)]
-pub struct InputSource(inner::Inner);
+pub struct InputSource {
+    inner: inner::Inner,
+    /// How long [Self::read_line] waits for a line before giving up, see [Self::set_idle_timeout]
+    idle_timeout: Option<Duration>,
+    /// Whether a [Self::read_line] gave up because of the idle timeout, until
+    /// [Self::take_timed_out]
+    timed_out: bool,
+    /// A read that timed out and is still waiting for the terminal, see [PendingRead]
+    pending: Option<PendingRead>,
+}
 
 mod inner {
     use rustyline::Editor;
@@ -19,15 +31,16 @@ mod inner {
 
     use super::super::prompt::ChatHelper;
 
+    pub type ChatEditor = Editor<ChatHelper, FileHistory>;
+
     #[allow(clippy::large_enum_variant)]
     #[derive(Debug)]
     pub enum Inner {
-        Readline(Editor<ChatHelper, FileHistory>),
+        /// The editor is missing while a read that timed out still holds it, see
+        /// [super::PendingRead]
+        Readline(Option<ChatEditor>),
         #[allow(dead_code)]
-        Mock {
-            index: usize,
-            lines: Vec<String>,
-        },
+        Mock { index: usize, lines: Vec<String> },
     }
 }
 
@@ -38,12 +51,40 @@ impl Drop for InputSource {
 }
 impl InputSource {
     pub fn new(os: &Os, sender: PromptQuerySender, receiver: PromptQueryResponseReceiver) -> Result<Self> {
-        Ok(Self(inner::Inner::Readline(rl(os, sender, receiver)?)))
+        Ok(Self::from_inner(inner::Inner::Readline(Some(rl(
+            os, sender, receiver,
+        )?))))
+    }
+
+    fn from_inner(inner: inner::Inner) -> Self {
+        Self {
+            inner,
+            idle_timeout: None,
+            timed_out: false,
+            pending: None,
+        }
+    }
+
+    /// Makes [Self::read_line] give up after waiting `timeout` for a line, or wait indefinitely if
+    /// `None`. A read that times out returns `Ok(None)` and sets [Self::timed_out].
+    pub fn set_idle_timeout(&mut self, timeout: Option<Duration>) {
+        self.idle_timeout = timeout;
+    }
+
+    /// Whether a read gave up because no input arrived within the idle timeout. Stays set until
+    /// [Self::take_timed_out] so that callers can tell a timeout apart from Ctrl+C or Ctrl+D.
+    pub fn timed_out(&self) -> bool {
+        self.timed_out
+    }
+
+    /// Like [Self::timed_out], but clears the flag.
+    pub fn take_timed_out(&mut self) -> bool {
+        std::mem::take(&mut self.timed_out)
     }
 
     /// Save history to file
     pub fn save_history(&mut self) -> Result<()> {
-        if let inner::Inner::Readline(rl) = &mut self.0 {
+        if let inner::Inner::Readline(Some(rl)) = &mut self.inner {
             if let Some(helper) = rl.helper() {
                 let history_path = helper.get_history_path();
 
@@ -72,7 +113,7 @@ impl InputSource {
 
         use crate::database::settings::Setting;
 
-        if let inner::Inner::Readline(rl) = &mut self.0 {
+        if let inner::Inner::Readline(Some(rl)) = &mut self.inner {
             let key_char = match os.database.settings.get_string(Setting::SkimCommandKey) {
                 Some(key) if key.len() == 1 => key.chars().next().unwrap_or('s'),
                 _ => 's', // Default to 's' if setting is missing or invalid
@@ -88,16 +129,45 @@ impl InputSource {
         }
     }
 
+    /// An input source that reads `lines` and then reaches the end of input, or times out if an
+    /// idle timeout is set.
     #[allow(dead_code)]
     pub fn new_mock(lines: Vec<String>) -> Self {
-        Self(inner::Inner::Mock { index: 0, lines })
+        Self::from_inner(inner::Inner::Mock { index: 0, lines })
     }
 
-    pub fn read_line(&mut self, prompt: Option<&str>) -> Result<Option<String>, ReadlineError> {
-        match &mut self.0 {
-            inner::Inner::Readline(rl) => {
+    pub async fn read_line(&mut self, prompt: Option<&str>) -> Result<Option<String>, ReadlineError> {
+        let idle_timeout = self.idle_timeout;
+        if idle_timeout.is_some() && self.pending.is_none() {
+            // The editor, and with it the history, is out of reach until a timed read returns
+            self.save_history().ok();
+        }
+        match &mut self.inner {
+            inner::Inner::Readline(editor) => {
                 let prompt = prompt.unwrap_or_default();
-                let curr_line = rl.readline(prompt);
+                let (rl, curr_line) = match (self.pending.take(), editor.take(), idle_timeout) {
+                    (None, Some(mut rl), None) => {
+                        let line = rl.readline(prompt);
+                        (rl, line)
+                    },
+                    (pending, rl, timeout) => {
+                        // An earlier read that timed out still owns the terminal, so wait on it
+                        let Some(pending) = pending.or_else(|| rl.map(|rl| PendingRead::start(rl, prompt))) else {
+                            return Ok(None);
+                        };
+                        match pending.wait(timeout).await {
+                            Ok(Some(read)) => read,
+                            // The reading thread panicked and took the editor with it
+                            Ok(None) => return Ok(None),
+                            Err(pending) => {
+                                self.pending = Some(pending);
+                                self.timed_out = true;
+                                return Ok(None);
+                            },
+                        }
+                    },
+                };
+                let rl = editor.insert(rl);
                 match curr_line {
                     Ok(line) => {
                         if Self::should_append_history(&line) {
@@ -111,7 +181,15 @@ impl InputSource {
             },
             inner::Inner::Mock { index, lines } => {
                 *index += 1;
-                Ok(lines.get(*index - 1).cloned())
+                let line = lines.get(*index - 1).cloned();
+                // Once out of lines, wait for input that never comes until the idle timeout
+                if line.is_none()
+                    && idle_timeout.is_some()
+                    && wait_idle(idle_timeout, std::future::pending::<()>()).await.is_none()
+                {
+                    self.timed_out = true;
+                }
+                Ok(line)
             },
         }
     }
@@ -131,27 +209,128 @@ impl InputSource {
     // We're keeping this method for potential future use
     #[allow(dead_code)]
     pub fn set_buffer(&mut self, content: &str) {
-        if let inner::Inner::Readline(rl) = &mut self.0 {
+        if let inner::Inner::Readline(Some(rl)) = &mut self.inner {
             // Add to history so user can access it with up arrow
             let _ = rl.add_history_entry(content);
         }
     }
 }
 
+/// Waits for `read`, or gives up with `None` once `timeout` passes.
+async fn wait_idle<F: Future>(timeout: Option<Duration>, read: F) -> Option<F::Output> {
+    match timeout {
+        Some(timeout) => tokio::time::timeout(timeout, read).await.ok(),
+        None => Some(read.await),
+    }
+}
+
+/// A line read running on its own thread so that waiting for it can be cut short by the idle
+/// timeout. A blocked terminal read can't be cancelled, and one on a blocking task would hold up
+/// the shutdown of the runtime, so it gets a thread of its own. A read that times out is kept
+/// rather than abandoned, and the next [InputSource::read_line] resumes waiting on it.
+#[derive(Debug)]
+struct PendingRead {
+    receiver: tokio::sync::oneshot::Receiver<(inner::ChatEditor, rustyline::Result<String>)>,
+    /// The terminal settings from before the read put the terminal in raw mode
+    #[cfg(unix)]
+    termios: Option<nix::sys::termios::Termios>,
+    /// The raw mode settings of the read, while the terminal is restored in between waits
+    #[cfg(unix)]
+    raw_termios: Option<nix::sys::termios::Termios>,
+}
+
+impl PendingRead {
+    fn start(mut rl: inner::ChatEditor, prompt: &str) -> Self {
+        #[cfg(unix)]
+        let termios = nix::sys::termios::tcgetattr(std::io::stdin()).ok();
+
+        let (tx, receiver) = tokio::sync::oneshot::channel();
+        let prompt = prompt.to_string();
+        std::thread::spawn(move || {
+            let line = rl.readline(&prompt);
+            tx.send((rl, line)).ok();
+        });
+        Self {
+            receiver,
+            #[cfg(unix)]
+            termios,
+            #[cfg(unix)]
+            raw_termios: None,
+        }
+    }
+
+    /// Waits up to `timeout` for the line, handing the editor back along with it, or `None` if the
+    /// reading thread died. Gives itself back on timeout, with the terminal out of raw mode until
+    /// the next wait.
+    #[allow(clippy::result_large_err)]
+    async fn wait(
+        mut self,
+        timeout: Option<Duration>,
+    ) -> Result<Option<(inner::ChatEditor, rustyline::Result<String>)>, Self> {
+        #[cfg(unix)]
+        if let Some(raw_termios) = self.raw_termios.take() {
+            nix::sys::termios::tcsetattr(std::io::stdin(), nix::sys::termios::SetArg::TCSANOW, &raw_termios).ok();
+        }
+
+        match wait_idle(timeout, &mut self.receiver).await {
+            Some(read) => Ok(read.ok()),
+            None => {
+                #[cfg(unix)]
+                {
+                    self.raw_termios = nix::sys::termios::tcgetattr(std::io::stdin()).ok();
+                }
+                self.restore_terminal();
+                Err(self)
+            },
+        }
+    }
+
+    /// Takes the terminal out of the raw mode the read put it in.
+    fn restore_terminal(&self) {
+        #[cfg(unix)]
+        if let Some(termios) = &self.termios {
+            nix::sys::termios::tcsetattr(std::io::stdin(), nix::sys::termios::SetArg::TCSANOW, termios).ok();
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use std::task::Poll;
+
     use super::*;
 
-    #[test]
-    fn test_mock_input_source() {
+    #[tokio::test]
+    async fn test_mock_input_source() {
         let l1 = "Hello,".to_string();
         let l2 = "Line 2".to_string();
         let l3 = "World!".to_string();
         let mut input = InputSource::new_mock(vec![l1.clone(), l2.clone(), l3.clone()]);
 
-        assert_eq!(input.read_line(None).unwrap().unwrap(), l1);
-        assert_eq!(input.read_line(None).unwrap().unwrap(), l2);
-        assert_eq!(input.read_line(None).unwrap().unwrap(), l3);
-        assert!(input.read_line(None).unwrap().is_none());
+        assert_eq!(input.read_line(None).await.unwrap().unwrap(), l1);
+        assert_eq!(input.read_line(None).await.unwrap().unwrap(), l2);
+        assert_eq!(input.read_line(None).await.unwrap().unwrap(), l3);
+        assert!(input.read_line(None).await.unwrap().is_none());
+        assert!(!input.timed_out());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_mock_input_source_times_out() {
+        let mut input = InputSource::new_mock(vec!["Hello".to_string()]);
+        input.set_idle_timeout(Some(Duration::from_secs(60)));
+
+        assert_eq!(input.read_line(None).await.unwrap().as_deref(), Some("Hello"));
+        assert!(!input.timed_out());
+
+        {
+            let read = input.read_line(None);
+            tokio::pin!(read);
+            assert!(futures::poll!(&mut read).is_pending());
+            tokio::time::advance(Duration::from_secs(59)).await;
+            assert!(futures::poll!(&mut read).is_pending());
+            tokio::time::advance(Duration::from_secs(1)).await;
+            assert!(matches!(futures::poll!(&mut read), Poll::Ready(Ok(None))));
+        }
+        assert!(input.timed_out());
     }
 }
@@ -265,28 +265,39 @@ impl UserMessage {
 
     pub fn replace_content_with_tool_use_results(&mut self) {
         if let Some(tool_results) = self.tool_use_results() {
-            let tool_content: Vec<String> = tool_results
-                .iter()
-                .flat_map(|tr| {
-                    tr.content.iter().map(|c| match c {
-                        ToolUseResultBlock::Json(document) => serde_json::to_string(&document)
-                            .map_err(|err| error!(?err, "failed to serialize tool result"))
-                            .unwrap_or_default(),
-                        ToolUseResultBlock::Text(s) => s.clone(),
-                    })
-                })
-                .collect::<_>();
-            let mut tool_content = tool_content.join(" ");
-            if tool_content.is_empty() {
-                // To avoid validation errors with empty content, we need to make sure
-                // something is set.
-                tool_content.push_str("<tool result redacted>");
-            }
-            let prompt = truncate_safe(&tool_content, MAX_USER_MESSAGE_SIZE).to_string();
+            let prompt = tool_use_results_text(tool_results);
             self.content = UserMessageContent::Prompt { prompt };
         }
     }
 
+    /// Replaces the results of the tool uses with `tool_use_ids` with their content as plain text,
+    /// keeping the results of any other tool uses.
+    pub fn replace_tool_use_results_with_text(&mut self, tool_use_ids: &[&str]) {
+        let (prompt, tool_use_results) = match &mut self.content {
+            UserMessageContent::Prompt { .. } => return,
+            UserMessageContent::CancelledToolUses {
+                prompt,
+                tool_use_results,
+            } => (prompt.take(), std::mem::take(tool_use_results)),
+            UserMessageContent::ToolUseResults { tool_use_results } => (None, std::mem::take(tool_use_results)),
+        };
+        let (replaced, tool_use_results): (Vec<_>, Vec<_>) = tool_use_results
+            .into_iter()
+            .partition(|result| tool_use_ids.contains(&result.tool_use_id.as_str()));
+        let prompt = match (prompt, replaced.is_empty()) {
+            (prompt, true) => prompt,
+            (Some(prompt), false) => Some(format!("{prompt}\n\n{}", tool_use_results_text(&replaced))),
+            (None, false) => Some(tool_use_results_text(&replaced)),
+        };
+        self.content = match prompt {
+            Some(prompt) => UserMessageContent::CancelledToolUses {
+                prompt: Some(prompt),
+                tool_use_results,
+            },
+            None => UserMessageContent::ToolUseResults { tool_use_results },
+        };
+    }
+
     /// Returns a formatted [String] containing [Self::additional_context], [Self::timestamp], and
     /// [Self::prompt].
     fn content_with_context(&self) -> String {
@@ -384,6 +395,28 @@ fn truncate_safe_tool_use_results(tool_use_results: &mut [ToolUseResult], max_by
     }
 }
 
+/// The content of `tool_use_results` as plain text for a prompt.
+fn tool_use_results_text(tool_use_results: &[ToolUseResult]) -> String {
+    let tool_content: Vec<String> = tool_use_results
+        .iter()
+        .flat_map(|tr| {
+            tr.content.iter().map(|c| match c {
+                ToolUseResultBlock::Json(document) => serde_json::to_string(&document)
+                    .map_err(|err| error!(?err, "failed to serialize tool result"))
+                    .unwrap_or_default(),
+                ToolUseResultBlock::Text(s) => s.clone(),
+            })
+        })
+        .collect::<_>();
+    let mut tool_content = tool_content.join(" ");
+    if tool_content.is_empty() {
+        // To avoid validation errors with empty content, we need to make sure
+        // something is set.
+        tool_content.push_str("<tool result redacted>");
+    }
+    truncate_safe(&tool_content, MAX_USER_MESSAGE_SIZE).to_string()
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum ToolUseResultBlock {
     Json(serde_json::Value),
@@ -480,6 +513,16 @@ impl AssistantMessage {
             AssistantMessage::Response { .. } => None,
         }
     }
+
+    /// Replaces the text content of the message with the result of `f`.
+    pub fn map_content(mut self, f: impl FnOnce(&str) -> String) -> Self {
+        match &mut self {
+            AssistantMessage::Response { content, .. } | AssistantMessage::ToolUse { content, .. } => {
+                *content = f(content);
+            },
+        }
+        self
+    }
 }
 
 impl From<AssistantMessage> for AssistantResponseMessage {
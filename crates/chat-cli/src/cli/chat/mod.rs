@@ -1,22 +1,28 @@
 pub mod cli;
+mod collapse;
 mod consts;
 pub mod context;
 mod conversation;
+mod conversation_store;
+mod correlation;
 mod input_source;
-mod message;
+pub mod message;
 mod parse;
 use std::path::MAIN_SEPARATOR;
 mod line_tracker;
 mod parser;
 mod prompt;
 mod prompt_parser;
+mod secret_mask;
 pub mod server_messenger;
 #[cfg(unix)]
 mod skim_integration;
+mod status;
 pub mod themes;
 mod token_counter;
 pub mod tool_manager;
 pub mod tools;
+mod transcript;
 pub mod util;
 use std::borrow::Cow;
 use std::collections::{
@@ -48,8 +54,14 @@ use cli::model::{
     get_available_models,
     select_model,
 };
+use collapse::CollapsedToolResult;
 pub use conversation::ConversationState;
 use conversation::TokenWarningLevel;
+use conversation_store::ConversationStore;
+use correlation::{
+    AuditEntry,
+    CorrelationId,
+};
 use crossterm::style::{
     Attribute,
     Color,
@@ -86,6 +98,10 @@ use parser::{
 };
 use regex::Regex;
 use rmcp::model::PromptMessage;
+use secret_mask::{
+    SecretMasker,
+    mask_secrets,
+};
 use spinners::{
     Spinner,
     Spinners,
@@ -99,6 +115,7 @@ use tokio::sync::{
     broadcast,
 };
 use tool_manager::{
+    LoadingEvent,
     PromptQuery,
     PromptQueryResult,
     ToolManager,
@@ -113,12 +130,17 @@ use tools::{
     ToolSpec,
 };
 use tracing::{
+    Instrument,
     debug,
     error,
     info,
     trace,
     warn,
 };
+use transcript::{
+    Recorded,
+    Transcript,
+};
 use util::images::RichImageBlock;
 use util::ui::draw_box;
 use util::{
@@ -150,6 +172,7 @@ use crate::cli::chat::cli::prompts::{
 };
 use crate::cli::chat::message::UserMessage;
 use crate::cli::chat::util::sanitize_unicode_tags;
+use crate::database::ConflictError;
 use crate::database::settings::Setting;
 use crate::os::Os;
 use crate::telemetry::core::{
@@ -165,6 +188,8 @@ use crate::telemetry::{
     TelemetryResult,
     get_error_reason,
 };
+use crate::util::command_manager::CommandManager;
+use crate::util::command_types::CommandError;
 use crate::util::{
     MCP_SERVER_TOOL_DELIMITER,
     directories,
@@ -257,6 +282,32 @@ impl ChatArgs {
         let stdout = std::io::stdout();
         let mut stderr = std::io::stderr();
 
+        // Loaded before the agents so that MCP servers are started with the project variables
+        if os
+            .database
+            .settings
+            .get_bool(Setting::SessionLoadDotenv)
+            .unwrap_or(false)
+        {
+            let override_existing = os
+                .database
+                .settings
+                .get_bool(Setting::SessionDotenvOverride)
+                .unwrap_or(false);
+            match os.load_project_dotenv(override_existing).await {
+                Ok(loaded) => debug!(loaded, "loaded variables from .env"),
+                Err(err) => {
+                    execute!(
+                        stderr,
+                        style::SetForegroundColor(Color::Yellow),
+                        style::Print("WARNING: "),
+                        style::SetForegroundColor(Color::Reset),
+                        style::Print(format!("Failed to load .env: {err}\n")),
+                    )?;
+                },
+            }
+        }
+
         let args: Vec<String> = std::env::args().collect();
         if args
             .iter()
@@ -404,13 +455,24 @@ impl ChatArgs {
         let (prompt_request_sender, prompt_request_receiver) = tokio::sync::broadcast::channel::<PromptQuery>(5);
         let (prompt_response_sender, prompt_response_receiver) =
             tokio::sync::broadcast::channel::<PromptQueryResult>(5);
-        let mut tool_manager = ToolManagerBuilder::default()
+        let mut tool_manager_builder = ToolManagerBuilder::default()
             .prompt_query_result_sender(prompt_response_sender)
             .prompt_query_receiver(prompt_request_receiver)
             .prompt_query_sender(prompt_request_sender.clone())
             .prompt_query_result_receiver(prompt_response_receiver.resubscribe())
             .conversation_id(&conversation_id)
-            .agent(agents.get_active().cloned().unwrap_or_default())
+            .agent(agents.get_active().cloned().unwrap_or_default());
+        if self.no_interactive {
+            // Nothing draws the loading display without a terminal, so log its progress instead.
+            let (events_tx, mut events_rx) = tokio::sync::mpsc::unbounded_channel();
+            tokio::spawn(async move {
+                while let Some(event) = events_rx.recv().await {
+                    log_loading_event(event);
+                }
+            });
+            tool_manager_builder = tool_manager_builder.loading_event_sender(events_tx);
+        }
+        let mut tool_manager = tool_manager_builder
             .build(os, Box::new(std::io::stderr()), !self.no_interactive)
             .await?;
         let tool_config = tool_manager.load_tools(os, &mut stderr).await?;
@@ -610,9 +672,11 @@ impl From<parser::RecvError> for ChatError {
 
 pub struct ChatSession {
     /// For output read by humans and machine
-    pub stdout: std::io::Stdout,
+    pub stdout: Recorded<std::io::Stdout>,
     /// For display output, only read by humans
-    pub stderr: std::io::Stderr,
+    pub stderr: Recorded<std::io::Stderr>,
+    /// Where the rendered session is recorded, set with `session.recordTranscriptPath`
+    transcript: Option<Transcript>,
     initial_input: Option<String>,
     /// Whether we're starting a new conversation or continuing an old one.
     existing_conversation: bool,
@@ -631,6 +695,8 @@ pub struct ChatSession {
     /// Used to track the time taken from initially prompting the user to tool execute
     /// completion.
     tool_turn_start_time: Option<Instant>,
+    /// Correlates the spans, telemetry and audit entries of the tool uses in [Self::tool_uses].
+    tool_turn_correlation_id: CorrelationId,
     /// [RequestMetadata] about the ongoing operation.
     user_turn_request_metadata: Vec<RequestMetadata>,
     /// Telemetry events to be sent as part of the conversation. The HashMap key is tool_use_id.
@@ -649,6 +715,13 @@ pub struct ChatSession {
     pub theme_manager: Option<themes::ThemeManager>,
     /// Current token usage percentage for display in themed prompts
     token_usage_percent: Option<f32>,
+    /// Tool output hidden behind a summary line, in the order it was produced. See `/expand`.
+    collapsed_tool_results: Vec<CollapsedToolResult>,
+    /// Set when saving the conversation found that another session saved it first. The user is
+    /// asked how to resolve it before the next prompt.
+    save_conflict: Option<ConflictError>,
+    /// Custom commands loaded during the session, created on first use. See `/commands refresh`.
+    command_manager: Option<CommandManager>,
 }
 
 impl ChatSession {
@@ -672,14 +745,22 @@ impl ChatSession {
     ) -> Result<Self> {
         // Reload prior conversation
         let mut existing_conversation = false;
-        let previous_conversation = std::env::current_dir()
-            .ok()
-            .and_then(|cwd| os.database.get_conversation_by_path(cwd).ok())
-            .flatten();
+        let previous_conversation = match resume_conversation {
+            // Pick up the most recently accessed conversation in or beneath the cwd.
+            true => match os.env.current_dir() {
+                Ok(cwd) => ConversationStore::new(os)
+                    .most_recent(&os.database, &cwd)
+                    .await
+                    .ok()
+                    .flatten(),
+                Err(_) => None,
+            },
+            false => None,
+        };
 
         // Only restore conversations where there were actual messages.
         // Prevents edge case where user clears conversation then exits without chatting.
-        let conversation = match resume_conversation
+        let mut conversation = match resume_conversation
             && previous_conversation
                 .as_ref()
                 .is_some_and(|cs| !cs.history().is_empty())
@@ -723,6 +804,23 @@ impl ChatSession {
             },
         };
 
+        if !existing_conversation {
+            if let (Some(cm), Some(agent)) = (conversation.context_manager.as_mut(), conversation.agents.get_active()) {
+                match cm.add_auto_context(os, agent).await {
+                    Ok(added) if !added.is_empty() => {
+                        execute!(
+                            stderr,
+                            style::SetForegroundColor(Color::DarkGrey),
+                            style::Print(format!("Auto-loaded context: {}\n", added.join(", "))),
+                            style::ResetColor,
+                        )?;
+                    },
+                    Ok(_) => (),
+                    Err(err) => warn!(?err, "failed to load auto context"),
+                }
+            }
+        }
+
         // Spawn a task for listening and broadcasting sigints.
         let (ctrlc_tx, ctrlc_rx) = tokio::sync::broadcast::channel(4);
         tokio::spawn(async move {
@@ -758,9 +856,12 @@ impl ChatSession {
             }
         }
 
+        let transcript = Transcript::open(os, conversation.conversation_id());
+
         Ok(Self {
-            stdout,
-            stderr,
+            stdout: Recorded::new(stdout, transcript.clone()),
+            stderr: Recorded::new(stderr, transcript.clone()),
+            transcript,
             initial_input: input,
             existing_conversation,
             input_source,
@@ -771,6 +872,7 @@ impl ChatSession {
             user_turn_request_metadata: vec![],
             pending_tool_index: None,
             tool_turn_start_time: None,
+            tool_turn_correlation_id: CorrelationId::new(),
             tool_use_telemetry_events: HashMap::new(),
             tool_use_status: ToolUseStatus::Idle,
             failed_request_ids: Vec::new(),
@@ -781,9 +883,22 @@ impl ChatSession {
             wrap,
             theme_manager,
             token_usage_percent: None,
+            collapsed_tool_results: Vec::new(),
+            save_conflict: None,
+            command_manager: None,
         })
     }
 
+    /// The [CommandManager] of the session, which caches the custom commands it loads until
+    /// `/commands refresh`
+    pub fn command_manager(&mut self, os: &Os) -> Result<&mut CommandManager, CommandError> {
+        let manager = match self.command_manager.take() {
+            Some(manager) => manager,
+            None => CommandManager::new(os)?,
+        };
+        Ok(self.command_manager.insert(manager))
+    }
+
     pub async fn next(&mut self, os: &mut Os) -> Result<(), ChatError> {
         // Update conversation state with new tool information
         self.conversation.update_state(false).await;
@@ -895,14 +1010,14 @@ impl ChatSession {
                             .conversation
                             .as_sendable_conversation_state(os, &mut self.stderr, false)
                             .await?;
-                        self.conversation.push_assistant_message(
-                            os,
+                        self.conversation.add_assistant_message(
                             AssistantMessage::new_response(
                                 None,
                                 "Tool uses were interrupted, waiting for the next user prompt".to_string(),
                             ),
                             None,
                         );
+                        self.save_conversation(os)?;
                     },
                     _ => (),
                 }
@@ -1948,9 +2063,25 @@ impl ChatSession {
         // Update token usage for themed prompts
         self.update_token_usage(os).await;
 
+        if let Some(conflict) = self.save_conflict.take() {
+            self.resolve_save_conflict(os, conflict).await?;
+        }
+
         let prompt = self.generate_tool_trust_prompt();
-        let user_input = match self.read_user_input(&prompt, false) {
+        let idle_timeout = os
+            .database
+            .settings
+            .get_int(Setting::ChatIdleTimeoutMs)
+            .filter(|ms| *ms > 0);
+        self.input_source
+            .set_idle_timeout(idle_timeout.map(|ms| Duration::from_millis(ms as u64)));
+        let user_input = self.read_user_input(&prompt, false).await;
+        self.input_source.set_idle_timeout(None);
+        let user_input = match user_input {
             Some(input) => input,
+            None if self.input_source.take_timed_out() => {
+                return self.exit_idle(os, idle_timeout.unwrap_or_default());
+            },
             None => return Ok(ChatState::Exit),
         };
 
@@ -2206,8 +2337,6 @@ impl ChatSession {
         os: &mut Os,
         is_user_command: bool,
     ) -> Result<ChatState, ChatError> {
-        use crate::util::command_manager::CommandManager;
-
         // Check if commands feature is enabled
         if !CommandManager::is_enabled(os) {
             queue!(
@@ -2260,7 +2389,7 @@ impl ChatSession {
         }
 
         // Load and execute the command
-        let mut manager = match CommandManager::new(os) {
+        let manager = match self.command_manager(os) {
             Ok(manager) => manager,
             Err(e) => {
                 queue!(
@@ -2410,7 +2539,7 @@ impl ChatSession {
                     style::SetForegroundColor(Color::Red),
                     style::Print("Command "),
                     style::SetForegroundColor(Color::Yellow),
-                    style::Print(&tool.name),
+                    style::Print(self.conversation.tool_manager.display_tool_name(&tool.name)),
                     style::SetForegroundColor(Color::Red),
                     style::Print(" is rejected because it matches one or more rules on the denied list:"),
                     style::Print(formatted_set),
@@ -2461,6 +2590,11 @@ impl ChatSession {
 
         for tool in &self.tool_uses {
             let tool_start = std::time::Instant::now();
+            let span = self.tool_turn_correlation_id.tool_call_span(&tool.id, &tool.name);
+            let trusted = self
+                .tool_use_telemetry_events
+                .get(&tool.id)
+                .is_some_and(|ev| ev.is_trusted);
             let mut tool_telemetry = self.tool_use_telemetry_events.entry(tool.id.clone());
             tool_telemetry = tool_telemetry.and_modify(|ev| {
                 ev.is_accepted = true;
@@ -2478,15 +2612,55 @@ impl ChatSession {
                 }
             }
 
-            let invoke_result = tool
-                .tool
-                .invoke(
-                    os,
-                    &mut self.stdout,
-                    &mut self.conversation.file_line_tracker,
-                    self.conversation.agents.get_active(),
-                )
-                .await;
+            let collapse_output = os
+                .database
+                .settings
+                .get_bool(Setting::UiCollapseToolResults)
+                .unwrap_or(false);
+            let invoke_result = if collapse_output {
+                // The output is held back so that it can be replaced by a summary if it is long
+                let mut buffer = Vec::new();
+                let invoke_result = tool
+                    .tool
+                    .invoke(
+                        os,
+                        &mut buffer,
+                        &mut self.conversation.file_line_tracker,
+                        self.conversation.agents.get_active(),
+                        &mut self.conversation.scratchpad,
+                    )
+                    .instrument(span.clone())
+                    .await;
+                let target = tool.tool.display_target(os);
+                match CollapsedToolResult::new(tool.tool.display_name(), target, String::from_utf8_lossy(&buffer)) {
+                    Some(collapsed) => {
+                        let summary = collapsed.summary();
+                        self.collapsed_tool_results.push(collapsed);
+                        queue!(
+                            self.stdout,
+                            style::SetForegroundColor(Color::DarkGrey),
+                            style::Print(format!(
+                                "{summary} (collapsed, /expand {} to show)\n",
+                                self.collapsed_tool_results.len()
+                            )),
+                            style::SetForegroundColor(Color::Reset),
+                        )?;
+                    },
+                    None => self.stdout.write_all(&buffer)?,
+                }
+                invoke_result
+            } else {
+                tool.tool
+                    .invoke(
+                        os,
+                        &mut self.stdout,
+                        &mut self.conversation.file_line_tracker,
+                        self.conversation.agents.get_active(),
+                        &mut self.conversation.scratchpad,
+                    )
+                    .instrument(span.clone())
+                    .await
+            };
 
             if self.spinner.is_some() {
                 queue!(
@@ -2513,6 +2687,16 @@ impl ChatSession {
                 });
             }
             let tool_time = format!("{}.{}", tool_time.as_secs(), tool_time.subsec_millis());
+            span.in_scope(|| {
+                AuditEntry {
+                    correlation_id: &self.tool_turn_correlation_id,
+                    tool_use_id: &tool.id,
+                    tool_name: &tool.name,
+                    trusted,
+                    success: invoke_result.is_ok(),
+                }
+                .record();
+            });
             match invoke_result {
                 Ok(result) => {
                     match result.output {
@@ -2589,7 +2773,10 @@ impl ChatSession {
                         style::Print("\n"),
                         style::SetAttribute(Attribute::Bold),
                         style::SetForegroundColor(Color::Red),
-                        style::Print(format!(" ● Execution failed after {}s:\n", tool_time)),
+                        style::Print(format!(
+                            " ● Execution failed after {}s (see correlation {}):\n",
+                            tool_time, self.tool_turn_correlation_id
+                        )),
                         style::SetAttribute(Attribute::Reset),
                         style::SetForegroundColor(Color::Red),
                         style::Print(&err),
@@ -2686,6 +2873,12 @@ impl ChatSession {
             os.database.settings.get_bool(Setting::ChatDisableMarkdownRendering),
         );
         let mut response_prefix_printed = false;
+        let mut secret_masker = os
+            .database
+            .settings
+            .get_bool(Setting::PrivacyMaskOutputSecrets)
+            .unwrap_or(false)
+            .then(SecretMasker::default);
 
         let mut tool_uses = Vec::new();
         let mut tool_name_being_recvd: Option<String> = None;
@@ -2709,6 +2902,9 @@ impl ChatSession {
                         parser::ResponseEvent::ToolUseStart { name } => {
                             // We need to flush the buffer here, otherwise text will not be
                             // printed while we are receiving tool use events.
+                            if let Some(masker) = secret_masker.as_mut() {
+                                buf.push_str(&masker.finish());
+                            }
                             buf.push('\n');
                             tool_name_being_recvd = Some(name);
                         },
@@ -2723,7 +2919,10 @@ impl ChatSession {
                                 )?;
                                 response_prefix_printed = true;
                             }
-                            buf.push_str(&text);
+                            match secret_masker.as_mut() {
+                                Some(masker) => buf.push_str(&masker.push(&text)),
+                                None => buf.push_str(&text),
+                            }
                         },
                         parser::ResponseEvent::ToolUse(tool_use) => {
                             if self.spinner.is_some() {
@@ -2747,7 +2946,15 @@ impl ChatSession {
                             if message.content() == RESPONSE_TIMEOUT_CONTENT {
                                 error!(?request_id, ?message, "Encountered an unexpected model response");
                             }
-                            self.conversation.push_assistant_message(os, message, Some(rm.clone()));
+                            let message = match secret_masker.as_mut() {
+                                Some(masker) => {
+                                    buf.push_str(&masker.finish());
+                                    message.map_content(mask_secrets)
+                                },
+                                None => message,
+                            };
+                            self.conversation.add_assistant_message(message, Some(rm.clone()));
+                            self.save_conversation(os)?;
                             self.user_turn_request_metadata.push(rm);
                             ended = true;
                         },
@@ -2787,11 +2994,11 @@ impl ChatSession {
 
                             // For stream timeouts, we'll tell the model to try and split its response into
                             // smaller chunks.
-                            self.conversation.push_assistant_message(
-                                os,
+                            self.conversation.add_assistant_message(
                                 AssistantMessage::new_response(None, RESPONSE_TIMEOUT_CONTENT.to_string()),
                                 None,
                             );
+                            self.save_conversation(os)?;
                             self.conversation
                                 .set_next_user_message(
                                     "You took too long to respond - try to split up the work into smaller steps."
@@ -2826,7 +3033,8 @@ impl ChatSession {
                                 tool_use_id, name, "The response stream ended before the entire tool use was received"
                             );
                             self.conversation
-                                .push_assistant_message(os, *message, Some(recv_error.request_metadata));
+                                .add_assistant_message(*message, Some(recv_error.request_metadata));
+                            self.save_conversation(os)?;
                             let tool_results = vec![ToolUseResult {
                                     tool_use_id,
                                     content: vec![ToolUseResultBlock::Text(
@@ -2957,7 +3165,8 @@ impl ChatSession {
 
     async fn validate_tools(&mut self, os: &Os, tool_uses: Vec<AssistantToolUse>) -> Result<ChatState, ChatError> {
         let conv_id = self.conversation.conversation_id().to_owned();
-        debug!(?tool_uses, "Validating tool uses");
+        let correlation_id = CorrelationId::new();
+        debug!(?tool_uses, %correlation_id, "Validating tool uses");
         let mut queued_tools: Vec<QueuedTool> = Vec::new();
         let mut tool_results: Vec<ToolUseResult> = Vec::new();
 
@@ -2971,6 +3180,7 @@ impl ChatSession {
             )
             .set_tool_use_id(tool_use_id.clone())
             .set_tool_name(tool_use.name.clone())
+            .correlation_id(correlation_id.to_string())
             .utterance_id(self.conversation.message_id().map(|s| s.to_string()));
             match self.conversation.tool_manager.get_tool_from_tool_use(tool_use).await {
                 Ok(mut tool) => {
@@ -3059,6 +3269,7 @@ impl ChatSession {
         self.tool_uses = queued_tools;
         self.pending_tool_index = Some(0);
         self.tool_turn_start_time = Some(Instant::now());
+        self.tool_turn_correlation_id = correlation_id;
         Ok(ChatState::ExecuteTools)
     }
 
@@ -3155,17 +3366,179 @@ impl ChatSession {
         Ok(())
     }
 
+    /// Saves the conversation for the working directory. Failures are reported rather than ending
+    /// the session. When another session saved the conversation first, interactive sessions ask
+    /// the user how to resolve it before the next prompt, see [Self::resolve_save_conflict].
+    fn save_conversation(&mut self, os: &mut Os) -> Result<(), ChatError> {
+        let cwd = os.env.current_dir()?;
+        let err = match ConversationStore::new(os).save(&mut os.database, &cwd, &mut self.conversation) {
+            Ok(()) => return Ok(()),
+            Err(err) => err,
+        };
+
+        match ConversationStore::conflict(&err) {
+            Some(conflict) if self.interactive => self.save_conflict = Some(conflict.clone()),
+            Some(_) => execute!(
+                self.stderr,
+                style::SetForegroundColor(Color::Yellow),
+                style::Print("\nThe conversation was not saved because another session saved it first.\n"),
+                style::SetForegroundColor(Color::Reset),
+            )?,
+            None => {
+                error!(?err, "failed to save conversation");
+                execute!(
+                    self.stderr,
+                    style::SetForegroundColor(Color::Yellow),
+                    style::Print(format!("\nFailed to save the conversation: {err}\n")),
+                    style::SetForegroundColor(Color::Reset),
+                )?;
+            },
+        }
+        Ok(())
+    }
+
+    /// Asks the user whether to reload the conversation another session saved, discarding the
+    /// messages of this session since, or to overwrite it with this session's conversation.
+    async fn resolve_save_conflict(&mut self, os: &Os, conflict: ConflictError) -> Result<(), ChatError> {
+        execute!(
+            self.stderr,
+            style::SetForegroundColor(Color::Yellow),
+            style::Print(format!(
+                "\nAnother session saved the conversation for {} since this one last did.\n",
+                conflict.path
+            )),
+            style::SetForegroundColor(Color::DarkGrey),
+            style::Print("Reload the saved conversation, discarding this session's new messages, or overwrite it? ["),
+            style::SetForegroundColor(Color::Green),
+            style::Print("r"),
+            style::SetForegroundColor(Color::DarkGrey),
+            style::Print("/"),
+            style::SetForegroundColor(Color::Green),
+            style::Print("o"),
+            style::SetForegroundColor(Color::DarkGrey),
+            style::Print("]:\n\n"),
+            style::SetForegroundColor(Color::Reset),
+        )?;
+
+        let reload = loop {
+            match self
+                .read_user_input("> ", true)
+                .await
+                .map(|input| input.trim().to_lowercase())
+            {
+                Some(choice) if choice == "r" || choice == "reload" => break true,
+                Some(choice) if choice == "o" || choice == "overwrite" => break false,
+                Some(_) => {},
+                // Leave both versions as they are, the next save asks again.
+                None => {
+                    self.save_conflict = Some(conflict);
+                    return Ok(());
+                },
+            }
+        };
+
+        let mut database = os.database.clone();
+        if reload {
+            match database.get_conversation_by_path(&conflict.path) {
+                Ok(Some(mut stored)) => {
+                    stored.swap_session_state(&mut self.conversation);
+                    self.conversation = stored;
+                    execute!(
+                        self.stderr,
+                        style::SetForegroundColor(Color::Green),
+                        style::Print("\n✔ Reloaded the saved conversation\n\n"),
+                        style::SetForegroundColor(Color::Reset),
+                    )?;
+                    return Ok(());
+                },
+                Ok(None) => {},
+                Err(err) => {
+                    execute!(
+                        self.stderr,
+                        style::SetForegroundColor(Color::Red),
+                        style::Print(format!("\nFailed to reload the conversation: {err}\n\n")),
+                        style::SetForegroundColor(Color::Reset),
+                    )?;
+                    return Ok(());
+                },
+            }
+        }
+
+        // Base the save on the stored version so that it overwrites that version only.
+        self.conversation
+            .set_db_version(Some((conflict.path.clone(), conflict.current)));
+        let cwd = os.env.current_dir()?;
+        match ConversationStore::new(os).save(&mut database, &cwd, &mut self.conversation) {
+            Ok(()) => execute!(
+                self.stderr,
+                style::SetForegroundColor(Color::Green),
+                style::Print("\n✔ Overwrote the saved conversation\n\n"),
+                style::SetForegroundColor(Color::Reset),
+            )?,
+            Err(err) => match ConversationStore::conflict(&err) {
+                Some(conflict) => self.save_conflict = Some(conflict.clone()),
+                None => execute!(
+                    self.stderr,
+                    style::SetForegroundColor(Color::Red),
+                    style::Print(format!("\nFailed to save the conversation: {err}\n\n")),
+                    style::SetForegroundColor(Color::Reset),
+                )?,
+            },
+        }
+        Ok(())
+    }
+
+    /// Ends the session after no input arrived for `chat.idleTimeoutMs`, saving the conversation
+    /// first.
+    fn exit_idle(&mut self, os: &Os, timeout_ms: i64) -> Result<ChatState, ChatError> {
+        let mut database = os.database.clone();
+        let saved = os
+            .env
+            .current_dir()
+            .map_err(eyre::Report::from)
+            .and_then(|cwd| ConversationStore::new(os).save(&mut database, &cwd, &mut self.conversation));
+        match saved {
+            Ok(()) => execute!(
+                self.stderr,
+                style::SetForegroundColor(Color::DarkGrey),
+                style::Print(format!(
+                    "\n\nNo input for {timeout_ms}ms (chat.idleTimeoutMs). Conversation saved, exiting.\n"
+                )),
+                style::SetForegroundColor(Color::Reset),
+            )?,
+            Err(err) => {
+                error!(?err, "failed to save conversation before idle exit");
+                execute!(
+                    self.stderr,
+                    style::SetForegroundColor(Color::Yellow),
+                    style::Print(format!(
+                        "\n\nNo input for {timeout_ms}ms (chat.idleTimeoutMs). Failed to save the conversation: {err}\nExiting.\n"
+                    )),
+                    style::SetForegroundColor(Color::Reset),
+                )?;
+            },
+        }
+        Ok(ChatState::Exit)
+    }
+
     /// Helper function to read user input with a prompt and Ctrl+C handling
-    fn read_user_input(&mut self, prompt: &str, exit_on_single_ctrl_c: bool) -> Option<String> {
+    async fn read_user_input(&mut self, prompt: &str, exit_on_single_ctrl_c: bool) -> Option<String> {
         let mut ctrl_c = false;
         loop {
-            match (self.input_source.read_line(Some(prompt)), ctrl_c) {
+            match (self.input_source.read_line(Some(prompt)).await, ctrl_c) {
                 (Ok(Some(line)), _) => {
                     if line.trim().is_empty() {
                         continue; // Reprompt if the input is empty
                     }
+                    // The prompt and what was typed are echoed by the line editor rather than
+                    // written to our output
+                    if let Some(transcript) = &self.transcript {
+                        transcript.record(format!("{prompt}{line}\n").as_bytes());
+                    }
                     return Some(line);
                 },
+                // Left for the caller to tell apart from Ctrl+C, see InputSource::take_timed_out
+                (Ok(None), _) if self.input_source.timed_out() => return None,
                 (Ok(None), false) => {
                     if exit_on_single_ctrl_c {
                         return None;
@@ -3558,12 +3931,31 @@ fn does_input_reference_file(input: &str) -> Option<ChatState> {
     None
 }
 
+/// Logs the progress of mcp servers loading in non-interactive mode.
+fn log_loading_event(event: LoadingEvent) {
+    match event {
+        LoadingEvent::Start { total } => info!(total, "loading mcp servers"),
+        LoadingEvent::Disabled { name } => info!(server = name, "mcp server is disabled"),
+        LoadingEvent::Done { name, time } => info!(server = name, time, "mcp server loaded"),
+        LoadingEvent::Warn { name, msg, time } => warn!(server = name, time, "mcp server loaded with a warning: {msg}"),
+        LoadingEvent::Error { name, msg, time } => error!(server = name, time, "mcp server failed to load: {msg}"),
+        LoadingEvent::SignInRequired { name } => warn!(server = name, "mcp server requires signing in"),
+        LoadingEvent::Stderr { name, line } => debug!(server = name, "{line}"),
+        LoadingEvent::Finished { still_loading } => {
+            if !still_loading.is_empty() {
+                warn!(?still_loading, "mcp servers still loading");
+            }
+        },
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::path::PathBuf;
 
     use super::*;
     use crate::cli::agent::Agent;
+    use crate::database::tests::test_conversation;
 
     async fn get_test_agents(os: &Os) -> Agents {
         const AGENT_PATH: &str = "/persona/TestAgent.json";
@@ -3966,6 +4358,130 @@ mod tests {
         assert!(!os.fs.exists("/file2.txt"));
     }
 
+    #[tokio::test]
+    async fn test_flow_fs_write_confirm_overwrite() {
+        let mut os = Os::new().await.unwrap();
+        let create = |path: &str| {
+            serde_json::json!([
+                "Ok",
+                {
+                    "tool_use_id": "1",
+                    "name": "fs_write",
+                    "args": {
+                        "command": "create",
+                        "file_text": "New contents",
+                        "path": path,
+                    }
+                }
+            ])
+        };
+        os.client.set_mock_output(serde_json::json!([
+            create("/existing.txt"),
+            ["Ok, I won't overwrite it."],
+            create("/new.txt"),
+            ["Done"],
+        ]));
+        os.fs.write("/existing.txt", "Original contents\n").await.unwrap();
+        os.database
+            .settings
+            .set(Setting::ToolsFsWriteConfirmOverwrite, true)
+            .await
+            .unwrap();
+
+        let agents = get_test_agents(&os).await;
+        let tool_manager = ToolManager::default();
+        let tool_config = serde_json::from_str::<HashMap<String, ToolSpec>>(include_str!("tools/tool_index.json"))
+            .expect("Tools failed to load");
+        ChatSession::new(
+            &mut os,
+            std::io::stdout(),
+            std::io::stderr(),
+            "fake_conv_id",
+            agents,
+            None,
+            InputSource::new_mock(vec![
+                "/tools trust fs_write".to_string(),
+                "overwrite the file".to_string(), // prompts despite trust since the file exists
+                "n".to_string(),
+                "create a new file".to_string(), // new files are created without prompting
+                "exit".to_string(),
+            ]),
+            false,
+            || Some(80),
+            tool_manager,
+            None,
+            tool_config,
+            true,
+            false,
+            None,
+        )
+        .await
+        .unwrap()
+        .spawn(&mut os)
+        .await
+        .unwrap();
+
+        assert_eq!(
+            os.fs.read_to_string("/existing.txt").await.unwrap(),
+            "Original contents\n"
+        );
+        assert_eq!(os.fs.read_to_string("/new.txt").await.unwrap(), "New contents\n");
+    }
+
+    #[tokio::test]
+    async fn test_flow_fs_write_trusted_overwrite() {
+        let mut os = Os::new().await.unwrap();
+        os.client.set_mock_output(serde_json::json!([
+            [
+                "Ok",
+                {
+                    "tool_use_id": "1",
+                    "name": "fs_write",
+                    "args": {
+                        "command": "create",
+                        "file_text": "New contents",
+                        "path": "/existing.txt",
+                    }
+                }
+            ],
+            ["Done"],
+        ]));
+        os.fs.write("/existing.txt", "Original contents\n").await.unwrap();
+
+        let agents = get_test_agents(&os).await;
+        let tool_manager = ToolManager::default();
+        let tool_config = serde_json::from_str::<HashMap<String, ToolSpec>>(include_str!("tools/tool_index.json"))
+            .expect("Tools failed to load");
+        ChatSession::new(
+            &mut os,
+            std::io::stdout(),
+            std::io::stderr(),
+            "fake_conv_id",
+            agents,
+            None,
+            InputSource::new_mock(vec![
+                "/tools trust fs_write".to_string(),
+                "overwrite the file".to_string(), // trusted, so no prompt unless confirmOverwrite is set
+                "exit".to_string(),
+            ]),
+            false,
+            || Some(80),
+            tool_manager,
+            None,
+            tool_config,
+            true,
+            false,
+            None,
+        )
+        .await
+        .unwrap()
+        .spawn(&mut os)
+        .await
+        .unwrap();
+
+        assert_eq!(os.fs.read_to_string("/existing.txt").await.unwrap(), "New contents\n");
+    }
+
     #[test]
     fn test_editor_content_processing() {
         // Since we no longer have template replacement, this test is simplified
@@ -4015,6 +4531,103 @@ mod tests {
         .unwrap();
     }
 
+    #[tokio::test(start_paused = true)]
+    async fn test_idle_timeout_saves_and_exits() {
+        for idle_timeout_ms in [None, Some(60_000)] {
+            let mut os = Os::new().await.unwrap();
+            os.client.set_mock_output(serde_json::Value::Array(vec![]));
+            if let Some(ms) = idle_timeout_ms {
+                os.database.settings.set(Setting::ChatIdleTimeoutMs, ms).await.unwrap();
+            }
+            let agents = get_test_agents(&os).await;
+            let tool_config = serde_json::from_str::<HashMap<String, ToolSpec>>(include_str!("tools/tool_index.json"))
+                .expect("Tools failed to load");
+
+            // The mock input never produces a line, so with a timeout set the read times out
+            ChatSession::new(
+                &mut os,
+                std::io::stdout(),
+                std::io::stderr(),
+                "fake_conv_id",
+                agents,
+                None,
+                InputSource::new_mock(vec![]),
+                false,
+                || Some(80),
+                ToolManager::default(),
+                None,
+                tool_config,
+                true,
+                false,
+                None,
+            )
+            .await
+            .unwrap()
+            .spawn(&mut os)
+            .await
+            .unwrap();
+
+            assert_eq!(
+                os.database.get_conversation_by_path("/").unwrap().is_some(),
+                idle_timeout_ms.is_some(),
+                "idle timeout {idle_timeout_ms:?}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_save_conflict_asks_to_reload_or_overwrite() {
+        for (choice, expected_version, expected_text) in [("r", 2, "from another session"), ("o", 3, "the summary")] {
+            let mut os = Os::new().await.unwrap();
+            os.client.set_mock_output(serde_json::json!([["Here is the summary"]]));
+
+            let mut stored = test_conversation(&os, "fake_conv_id").await;
+            stored.set_next_user_message("first question".to_string()).await;
+            stored.add_assistant_message(AssistantMessage::new_response(None, "first answer".to_string()), None);
+            os.database.set_conversation_by_path("/", &stored).unwrap();
+
+            let agents = get_test_agents(&os).await;
+            let tool_config = serde_json::from_str::<HashMap<String, ToolSpec>>(include_str!("tools/tool_index.json"))
+                .expect("Tools failed to load");
+            let mut session = ChatSession::new(
+                &mut os,
+                std::io::stdout(),
+                std::io::stderr(),
+                "fake_conv_id",
+                agents,
+                None,
+                InputSource::new_mock(vec![choice.to_string(), "/quit".to_string()]),
+                true,
+                || Some(80),
+                ToolManager::default(),
+                None,
+                tool_config,
+                true,
+                false,
+                None,
+            )
+            .await
+            .unwrap();
+
+            // Another session saves the conversation while this one summarizes it.
+            stored
+                .set_next_user_message("question from another session".to_string())
+                .await;
+            stored.add_assistant_message(AssistantMessage::new_response(None, "answer".to_string()), None);
+            assert_eq!(os.database.set_conversation_by_path("/", &stored).unwrap(), 2);
+
+            session.spawn(&mut os).await.unwrap();
+
+            let saved = os.database.get_conversation_by_path("/").unwrap().unwrap();
+            assert_eq!(saved.db_version("/"), Some(expected_version), "choice {choice}");
+            assert!(
+                saved.message_text().contains(expected_text),
+                "choice {choice}: {}",
+                saved.message_text()
+            );
+        }
+    }
+
     #[test]
     fn test_does_input_reference_file() {
         let tests = &[
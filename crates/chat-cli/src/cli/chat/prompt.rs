@@ -56,10 +56,12 @@ pub const COMMANDS: &[&str] = &[
     "/issue",
     "/quit",
     "/tools",
+    "/tools export",
     "/tools trust",
     "/tools untrust",
     "/tools trust-all",
     "/tools reset",
+    "/expand",
     "/mcp",
     "/model",
     "/experiment",
@@ -91,10 +93,16 @@ pub const COMMANDS: &[&str] = &[
     "/compact",
     "/compact help",
     "/usage",
+    "/status",
     "/changelog",
     "/save",
     "/load",
+    "/conversations",
+    "/favorite",
     "/subscribe",
+    "/system",
+    "/system set",
+    "/system clear",
     "/todos",
     "/todos resume",
     "/todos clear-finished",
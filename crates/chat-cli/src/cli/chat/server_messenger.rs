@@ -48,6 +48,10 @@ pub enum UpdateEventMessage {
         server_name: String,
         link: String,
     },
+    StderrLine {
+        server_name: String,
+        line: String,
+    },
     InitStart {
         server_name: String,
     },
@@ -161,6 +165,17 @@ impl Messenger for ServerMessenger {
             .map_err(|e| MessengerError::Custom(e.to_string()))?)
     }
 
+    async fn send_stderr_line(&self, line: String) -> MessengerResult {
+        Ok(self
+            .update_event_sender
+            .send(UpdateEventMessage::StderrLine {
+                server_name: self.server_name.clone(),
+                line,
+            })
+            .await
+            .map_err(|e| MessengerError::Custom(e.to_string()))?)
+    }
+
     async fn send_init_msg(&self) -> MessengerResult {
         Ok(self
             .update_event_sender
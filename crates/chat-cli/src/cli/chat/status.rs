@@ -0,0 +1,89 @@
+use super::ChatSession;
+use super::conversation::ConversationState;
+use super::tool_manager::ServerLoadCounts;
+use crate::auth::builder_id::has_local_credentials;
+use crate::os::Os;
+
+/// A cheap snapshot of the overall CLI state, e.g. for display in a status bar.
+///
+/// Collecting a summary only reads local state and never touches the network.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StatusSummary {
+    /// Whether credentials are stored locally. These may be expired.
+    pub logged_in: bool,
+    /// Name of the active agent.
+    pub active_profile: String,
+    /// MCP servers by load outcome. Servers still initializing in the background are counted
+    /// as pending.
+    pub servers: ServerLoadCounts,
+}
+
+impl StatusSummary {
+    pub async fn collect(os: &Os, conversation: &ConversationState) -> Self {
+        Self {
+            logged_in: has_local_credentials(os).await,
+            active_profile: conversation.agents.active_idx.clone(),
+            servers: conversation.tool_manager.server_load_counts().await,
+        }
+    }
+}
+
+impl ChatSession {
+    /// The [StatusSummary] of this session, shown by `/status`
+    pub async fn status_summary(&self, os: &Os) -> StatusSummary {
+        StatusSummary::collect(os, &self.conversation).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::builder_id::BuilderIdToken;
+    use crate::cli::chat::tool_manager::LoadingRecord;
+    use crate::database::tests::test_conversation;
+
+    #[tokio::test]
+    async fn test_status_summary() {
+        let os = Os::new().await.unwrap();
+        let conversation = test_conversation(&os, "fake_conv_id").await;
+
+        let summary = StatusSummary::collect(&os, &conversation).await;
+        assert!(!summary.logged_in);
+        assert_eq!(summary.servers, ServerLoadCounts::default());
+
+        os.database.set_secret(BuilderIdToken::SECRET_KEY, "{}").await.unwrap();
+        {
+            let mut record = conversation.tool_manager.mcp_load_record.lock().await;
+            record.insert("good".to_string(), vec![LoadingRecord::Success("loaded".to_string())]);
+            record.insert("bad".to_string(), vec![
+                LoadingRecord::Success("loaded".to_string()),
+                LoadingRecord::Err("crashed".to_string()),
+            ]);
+        }
+        conversation
+            .tool_manager
+            .pending_clients
+            .write()
+            .await
+            .insert("slow".to_string());
+
+        let summary = StatusSummary::collect(&os, &conversation).await;
+        assert!(summary.logged_in);
+        assert_eq!(summary.active_profile, conversation.agents.active_idx);
+        assert_eq!(summary.servers, ServerLoadCounts {
+            loaded: 1,
+            failed: 1,
+            pending: 1,
+        });
+    }
+
+    #[tokio::test]
+    async fn test_status_summary_sigv4() {
+        let os = Os::new().await.unwrap();
+        let conversation = test_conversation(&os, "fake_conv_id").await;
+
+        // SAFETY: the test environment is fake and only visible to this test
+        unsafe { os.env.set_var("AMAZON_Q_SIGV4", "1") };
+        assert!(StatusSummary::collect(&os, &conversation).await.logged_in);
+    }
+}
@@ -1,53 +1,159 @@
+use std::borrow::Cow;
 use std::collections::HashMap;
-use std::fs;
 use std::path::Path;
 
 use eyre::Result;
 
 use super::BashTheme;
+use crate::os::Fs;
 
 pub struct BashParser;
 
+/// A statement in a theme file, which may span several lines
+enum Statement<'a> {
+    /// A single line, or an assignment whose quoted value spans lines
+    Line(Cow<'a, str>),
+    /// A `VAR=<<END` assignment with the raw lines up to `END`
+    Heredoc { key: &'a str, body: String },
+    /// An assignment whose quoted value is never closed, parsed on its own as a single line
+    Unclosed(&'a str),
+}
+
 impl BashParser {
-    /// Parse a bash-style theme file
-    pub fn parse_theme_file(path: &Path) -> Result<BashTheme> {
-        let content = fs::read_to_string(path)?;
-        let mut theme = BashTheme::new(
-            path.file_stem()
-                .and_then(|s| s.to_str())
-                .unwrap_or("unknown")
-                .to_string(),
-        );
+    /// Parse a bash-style theme file, read through `fs`
+    pub fn parse_theme_file(fs: &Fs, path: &Path) -> Result<BashTheme> {
+        let content = fs.read_to_string_sync(path)?;
+        let name = path.file_stem().and_then(|s| s.to_str()).unwrap_or("unknown");
+        Ok(Self::parse_theme_content(name, &Self::normalize_content(&content)))
+    }
 
-        for line in content.lines() {
-            let line = line.trim();
+    /// Strips a leading UTF-8 byte order mark and converts CRLF line endings to LF, as written by
+    /// some Windows editors
+    pub fn normalize_content(content: &str) -> Cow<'_, str> {
+        let content = content.strip_prefix('\u{feff}').unwrap_or(content);
+        match content.contains("\r\n") {
+            true => Cow::Owned(content.replace("\r\n", "\n")),
+            false => Cow::Borrowed(content),
+        }
+    }
+
+    /// Parse the contents of a bash-style theme, using `name` unless it sets THEME_NAME
+    pub fn parse_theme_content(name: &str, content: &str) -> BashTheme {
+        let mut theme = BashTheme::new(name.to_string());
+
+        for statement in Self::statements(content) {
+            // Parse variable assignments: VAR="value", VAR=value or VAR=<<END ... END
+            let (key, value) = match statement {
+                Statement::Heredoc { key, body } => (key.to_string(), Self::process_escape_sequences(&body)),
+                Statement::Line(line) => match Self::parse_assignment(&line) {
+                    Some((key, value)) => (key, Self::unquote(&value)),
+                    None => continue,
+                },
+                Statement::Unclosed(line) => match Self::parse_assignment(line) {
+                    Some((key, value)) => (key, Self::unquote(&value)),
+                    None => continue,
+                },
+            };
+
+            match key.as_str() {
+                "THEME_NAME" => theme.name = value,
+                "PROMPT" => theme.prompt_template = value,
+                "Q_GIT_ENABLED" => {
+                    theme.git_enabled = value.to_lowercase() == "true";
+                },
+                // Git configuration variables
+                "Q_GIT_PREFIX" | "Q_GIT_SUFFIX" | "Q_GIT_CLEAN" | "Q_GIT_DIRTY" | "Q_GIT_STAGED"
+                | "Q_GIT_UNTRACKED" | "Q_GIT_AHEAD" | "Q_GIT_BEHIND" => {
+                    theme.set_variable(key, value);
+                },
+                _ => {
+                    theme.set_variable(key, value);
+                },
+            }
+        }
+
+        theme
+    }
+
+    /// Statements that are neither comments nor `VAR=value` assignments, which parsing silently
+    /// skips, and assignments whose quoted value is never closed
+    pub fn unparseable_lines(content: &str) -> Vec<String> {
+        Self::statements(content)
+            .into_iter()
+            .filter_map(|statement| match statement {
+                Statement::Line(line) => (!Self::parse_assignment(&line)
+                    .is_some_and(|(key, _)| Self::is_valid_key(&key)))
+                .then(|| line.into_owned()),
+                Statement::Heredoc { key, .. } => (!Self::is_valid_key(key)).then(|| format!("{key}=<<")),
+                Statement::Unclosed(line) => Some(line.to_string()),
+            })
+            .collect()
+    }
+
+    fn is_valid_key(key: &str) -> bool {
+        !key.is_empty() && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+    }
+
+    /// Whether `line` starts a new `VAR=value` assignment
+    fn starts_assignment(line: &str) -> bool {
+        line.split_once('=')
+            .is_some_and(|(key, _)| Self::is_valid_key(key.trim()))
+    }
 
-            // Skip comments and empty lines
+    /// Split theme content into statements, skipping comments and empty lines. A quoted value
+    /// whose closing quote is on a later line, or a `VAR=<<END` heredoc, spans lines up to the
+    /// closing quote or delimiter. Without one the line is treated on its own, and so is a quote
+    /// that another assignment follows before it is closed.
+    fn statements(content: &str) -> Vec<Statement<'_>> {
+        let lines = content.lines().collect::<Vec<_>>();
+        let mut statements = Vec::new();
+        let mut i = 0;
+
+        while i < lines.len() {
+            let line = lines[i].trim();
+            i += 1;
             if line.is_empty() || line.starts_with('#') {
                 continue;
             }
 
-            // Parse variable assignments: VAR="value" or VAR=value
-            if let Some((key, value)) = Self::parse_assignment(line) {
-                match key.as_str() {
-                    "THEME_NAME" => theme.name = Self::unquote(&value),
-                    "PROMPT" => theme.prompt_template = Self::unquote(&value),
-                    "Q_GIT_ENABLED" => {
-                        theme.git_enabled = Self::unquote(&value).to_lowercase() == "true";
-                    },
-                    // Git configuration variables
-                    "Q_GIT_PREFIX" | "Q_GIT_SUFFIX" | "Q_GIT_CLEAN" | "Q_GIT_DIRTY" | "Q_GIT_STAGED"
-                    | "Q_GIT_UNTRACKED" | "Q_GIT_AHEAD" | "Q_GIT_BEHIND" => {
-                        theme.set_variable(key, Self::unquote(&value));
-                    },
-                    _ => {
-                        theme.set_variable(key, Self::unquote(&value));
-                    },
+            if let Some((key, value)) = line.split_once('=') {
+                let value = value.trim();
+                if let Some(delimiter) = value.strip_prefix("<<") {
+                    let delimiter = delimiter.trim_start_matches('-').trim_matches(['\'', '"']);
+                    let end = lines[i..].iter().position(|l| l.trim() == delimiter);
+                    if let (false, Some(end)) = (delimiter.is_empty(), end) {
+                        statements.push(Statement::Heredoc {
+                            key: key.trim(),
+                            body: lines[i..i + end].join("\n"),
+                        });
+                        i += end + 1;
+                        continue;
+                    }
+                } else if let Some(quote) = value.chars().next().filter(|c| *c == '"' || *c == '\'') {
+                    if !value[1..].contains(quote) {
+                        let end = lines[i..]
+                            .iter()
+                            .position(|l| l.contains(quote) || Self::starts_assignment(l));
+                        match end {
+                            Some(end) if !Self::starts_assignment(lines[i + end]) => {
+                                let joined = std::iter::once(line)
+                                    .chain(lines[i..=i + end].iter().copied())
+                                    .collect::<Vec<_>>()
+                                    .join("\n");
+                                statements.push(Statement::Line(Cow::Owned(joined)));
+                                i += end + 1;
+                            },
+                            _ => statements.push(Statement::Unclosed(line)),
+                        }
+                        continue;
+                    }
                 }
             }
+
+            statements.push(Statement::Line(Cow::Borrowed(line)));
         }
 
-        Ok(theme)
+        statements
     }
 
     /// Substitute variables in a template string with git support
@@ -161,6 +267,47 @@ impl BashParser {
         result
     }
 
+    /// Collect the names of all variables referenced by a template, in order of first appearance.
+    ///
+    /// Both `$VAR` and `${VAR}` forms are recognized, as well as the variable tested by a
+    /// `${VAR:+text}` conditional and any references nested inside its text.
+    pub fn referenced_variables(template: &str) -> Vec<String> {
+        let mut names: Vec<String> = Vec::new();
+        let mut chars = template.chars().peekable();
+
+        while let Some(ch) = chars.next() {
+            if ch != '$' {
+                continue;
+            }
+
+            // Braced references are scanned for their name only; the remainder (e.g. the
+            // conditional text) is picked up by the outer loop.
+            if chars.peek() == Some(&'{') {
+                chars.next(); // consume '{'
+            }
+
+            let mut name = String::new();
+            while let Some(&next_ch) = chars.peek() {
+                let valid = if name.is_empty() {
+                    next_ch.is_ascii_alphabetic() || next_ch == '_'
+                } else {
+                    next_ch.is_ascii_alphanumeric() || next_ch == '_'
+                };
+                if !valid {
+                    break;
+                }
+                name.push(next_ch);
+                chars.next();
+            }
+
+            if !name.is_empty() && !names.contains(&name) {
+                names.push(name);
+            }
+        }
+
+        names
+    }
+
     /// Get default git configuration variables
     #[allow(dead_code)]
     pub fn get_default_git_vars() -> HashMap<String, String> {
@@ -327,6 +474,19 @@ mod tests {
         assert_eq!(result3, " (main✓)");
     }
 
+    #[test]
+    fn test_referenced_variables() {
+        let template = "$COLOR[$AGENT] ${GIT_BRANCH:+${BLUE}git:($GIT_BRANCH)$RESET} $ $1 ${MODEL}> ";
+        assert_eq!(BashParser::referenced_variables(template), vec![
+            "COLOR",
+            "AGENT",
+            "GIT_BRANCH",
+            "BLUE",
+            "RESET",
+            "MODEL"
+        ]);
+    }
+
     #[test]
     fn test_parse_assignment_with_inline_comments() {
         // Test quoted value with inline comment
@@ -520,10 +680,39 @@ PROMPT="$Q_AGENT_COLOR[$Q_AGENT]$RESET_COLOR > ""#;
         use std::path::Path;
 
         // Test with non-existent file (line 13-14)
-        let result = BashParser::parse_theme_file(Path::new("/nonexistent/file.theme"));
+        let result = BashParser::parse_theme_file(&Fs::Real, Path::new("/nonexistent/file.theme"));
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_parse_theme_file_in_memory() {
+        let fs = Fs::from_slice(&[("/themes/sunset.theme", "PROMPT=\"> \"\nQ_GIT_ENABLED=true\n")]);
+        let theme = BashParser::parse_theme_file(&fs, Path::new("/themes/sunset.theme")).unwrap();
+        assert_eq!(theme.name, "sunset");
+        assert_eq!(theme.prompt_template, "> ");
+        assert!(theme.git_enabled);
+    }
+
+    #[test]
+    fn test_parse_theme_file_with_bom_and_crlf() {
+        let clean =
+            "# Sunset\nPROMPT=\"$CYAN> $RESET\"\nMULTI=\"one\ntwo\"\nBODY=<<END\n  indented\nEND\nQ_GIT_ENABLED=true\n";
+        let windows = format!("\u{feff}{}", clean.replace('\n', "\r\n"));
+        let fs = Fs::from_slice(&[
+            ("/themes/clean.theme", clean),
+            ("/themes/windows.theme", windows.as_str()),
+        ]);
+
+        let parsed = BashParser::parse_theme_file(&fs, Path::new("/themes/clean.theme")).unwrap();
+        let from_windows = BashParser::parse_theme_file(&fs, Path::new("/themes/windows.theme")).unwrap();
+        assert_eq!(from_windows.prompt_template, parsed.prompt_template);
+        assert_eq!(from_windows.variables, parsed.variables);
+        assert_eq!(from_windows.git_enabled, parsed.git_enabled);
+        assert_eq!(from_windows.get_variable("MULTI"), Some(&"one\ntwo".to_string()));
+        assert_eq!(from_windows.get_variable("BODY"), Some(&"  indented".to_string()));
+        assert!(BashParser::unparseable_lines(&BashParser::normalize_content(&windows)).is_empty());
+    }
+
     #[test]
     fn test_parse_assignment_edge_cases() {
         // Test line without equals sign (line 246)
@@ -560,6 +749,42 @@ PROMPT="$Q_AGENT_COLOR[$Q_AGENT]$RESET_COLOR > ""#;
         assert_eq!(result, "");
     }
 
+    #[test]
+    fn test_parse_multiline_quoted_value() {
+        let content = "THEME_NAME=\"multi\"\nPROMPT=\"$GREEN╭─ $CWD\n╰─$ $RESET\" # two lines\nNEXT=after\n";
+        let theme = BashParser::parse_theme_content("fallback", content);
+        assert_eq!(theme.name, "multi");
+        assert_eq!(theme.prompt_template, "$GREEN╭─ $CWD\n╰─$ $RESET");
+        assert_eq!(theme.get_variable("NEXT"), Some(&"after".to_string()));
+        assert!(BashParser::unparseable_lines(content).is_empty());
+
+        // Single line parsing is unchanged, including an unclosed quote
+        let theme = BashParser::parse_theme_content("single", "KEY=\"unclosed\nOTHER='x'\n");
+        assert_eq!(theme.get_variable("KEY"), Some(&"\"unclosed".to_string()));
+        assert_eq!(theme.get_variable("OTHER"), Some(&"x".to_string()));
+
+        // An unclosed quote doesn't swallow the assignments after it, even ones with quotes
+        let content = "KEY=\"unclosed\nOTHER=plain\nPROMPT=\"> \"\n";
+        let theme = BashParser::parse_theme_content("unclosed", content);
+        assert_eq!(theme.get_variable("KEY"), Some(&"\"unclosed".to_string()));
+        assert_eq!(theme.get_variable("OTHER"), Some(&"plain".to_string()));
+        assert_eq!(theme.prompt_template, "> ");
+        assert_eq!(BashParser::unparseable_lines(content), ["KEY=\"unclosed"]);
+    }
+
+    #[test]
+    fn test_parse_heredoc_value() {
+        let content = "PROMPT=<<END\n  \\033[36m$CWD\\033[0m\nsay \"hi\" it's $USER\nEND\nQ_GIT_ENABLED=true\n";
+        let theme = BashParser::parse_theme_content("heredoc", content);
+        assert_eq!(theme.prompt_template, "  \x1b[36m$CWD\x1b[0m\nsay \"hi\" it's $USER");
+        assert!(theme.git_enabled);
+        assert!(BashParser::unparseable_lines(content).is_empty());
+
+        // A quoted delimiter works the same
+        let theme = BashParser::parse_theme_content("heredoc", "PROMPT=<<'EOF'\n> \nEOF\n");
+        assert_eq!(theme.prompt_template, "> ");
+    }
+
     #[test]
     fn test_process_escape_sequences_error_cases() {
         // Test invalid octal sequence (line 271-273)
@@ -8,6 +8,7 @@ mod cross_platform_test;
 mod integration_test;
 
 use std::collections::HashMap;
+use std::path::PathBuf;
 
 pub use theme_manager::ThemeManager;
 
@@ -39,6 +40,69 @@ impl BashTheme {
     }
 }
 
+/// A problem found while validating a theme that would otherwise silently render as empty text
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ThemeWarning {
+    /// The prompt references a variable that is neither defined by the theme nor provided by Q
+    UndefinedVariable(String),
+    /// The prompt references a git variable but the theme does not enable git
+    GitDisabled(String),
+}
+
+impl std::fmt::Display for ThemeWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ThemeWarning::UndefinedVariable(name) => write!(f, "${name} is not defined"),
+            ThemeWarning::GitDisabled(name) => {
+                write!(f, "${name} is only set when Q_GIT_ENABLED=true")
+            },
+        }
+    }
+}
+
+/// Where [ThemeManager::install_theme] reads a theme from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ThemeSource {
+    Path(PathBuf),
+    Url(String),
+}
+
+impl From<&str> for ThemeSource {
+    fn from(source: &str) -> Self {
+        if source.starts_with("https://") || source.starts_with("http://") {
+            Self::Url(source.to_string())
+        } else {
+            Self::Path(PathBuf::from(source))
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ThemeError {
+    #[error("'{0}' is not a valid theme name, only letters, digits, '-' and '_' are allowed")]
+    InvalidName(String),
+    #[error("Theme '{0}' is already installed, use --force to overwrite it")]
+    AlreadyExists(String),
+    #[error(
+        "'{original}' is not a valid file name on every platform and would be installed as '{name}', which is already installed"
+    )]
+    RenamedExists { original: String, name: String },
+    #[error("Invalid theme: {0}")]
+    Invalid(String),
+    #[error("Installing themes from a URL is disabled. Enable it with: q settings chat.enableThemeDownloads true")]
+    DownloadsDisabled,
+    #[error("Themes can only be downloaded over https://, not from '{0}'")]
+    InsecureUrl(String),
+    #[error("Theme is larger than the limit of {0} bytes")]
+    TooLarge(usize),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Request(#[from] crate::request::RequestError),
+    #[error(transparent)]
+    Reqwest(#[from] reqwest::Error),
+}
+
 /// Git information for prompt display
 #[derive(Debug, Clone)]
 pub struct GitInfo {
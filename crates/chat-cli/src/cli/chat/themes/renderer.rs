@@ -4,8 +4,35 @@ use super::bash_parser::BashParser;
 use super::{
     BashTheme,
     GitInfo,
+    ThemeWarning,
 };
 
+/// Variables provided by the renderer regardless of theme configuration
+pub const BUILTIN_VARIABLES: &[&str] = &[
+    "Q_AGENT",
+    "AGENT",
+    "Q_WARNING",
+    "Q_TANGENT",
+    "TOKEN_USAGE",
+    "MODEL",
+    "PWD",
+];
+
+/// Variables provided by the renderer only when the theme enables git
+pub const BUILTIN_GIT_VARIABLES: &[&str] = &[
+    "GIT_BRANCH",
+    "Q_GIT_BRANCH",
+    "GIT_DIRTY",
+    "GIT_MODIFIED",
+    "GIT_CLEAN",
+    "GIT_STAGED",
+    "GIT_UNTRACKED",
+    "GIT_AHEAD",
+    "GIT_BEHIND",
+    "Q_GIT_STATUS",
+    "Q_GIT_INFO",
+];
+
 pub struct ThemeRenderer<'a> {
     theme: &'a BashTheme,
 }
@@ -36,6 +63,23 @@ impl<'a> ThemeRenderer<'a> {
         BashParser::substitute_variables(&self.theme.prompt_template, &context_vars)
     }
 
+    /// Report variables referenced by the prompt template that will never have a value
+    pub fn validate(&self) -> Vec<ThemeWarning> {
+        BashParser::referenced_variables(&self.theme.prompt_template)
+            .into_iter()
+            .filter(|name| !BUILTIN_VARIABLES.contains(&name.as_str()) && !self.theme.variables.contains_key(name))
+            .filter_map(|name| {
+                if !BUILTIN_GIT_VARIABLES.contains(&name.as_str()) {
+                    Some(ThemeWarning::UndefinedVariable(name))
+                } else if !self.theme.git_enabled {
+                    Some(ThemeWarning::GitDisabled(name))
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+
     /// Build context variables for the current prompt state
     fn build_context_variables(
         &self,
@@ -312,6 +356,25 @@ mod tests {
         assert!(result.contains("test-agent"));
     }
 
+    #[test]
+    fn test_validate_reports_undefined_variables() {
+        let mut theme = create_test_theme();
+        theme.prompt_template =
+            "$Q_AGENT_COLOR[$Q_AGENT] ${Q_GIT_BRANCHH:+($Q_GIT_BRANCHH)}$Q_PROMPT_SYMBOL ".to_string();
+        theme.git_enabled = true;
+        assert_eq!(ThemeRenderer::new(&theme).validate(), vec![
+            ThemeWarning::UndefinedVariable("Q_GIT_BRANCHH".to_string())
+        ]);
+
+        // Correctly spelled git built-ins are only a problem when git is disabled
+        theme.prompt_template = "[$Q_AGENT] ${Q_GIT_BRANCH:+($Q_GIT_BRANCH)}$Q_PROMPT_SYMBOL ".to_string();
+        assert!(ThemeRenderer::new(&theme).validate().is_empty());
+        theme.git_enabled = false;
+        assert_eq!(ThemeRenderer::new(&theme).validate(), vec![ThemeWarning::GitDisabled(
+            "Q_GIT_BRANCH".to_string()
+        )]);
+    }
+
     #[test]
     fn test_render_prompt_error_handling() {
         let theme = create_test_theme();
@@ -6,14 +6,30 @@ use tracing::{
     warn,
 };
 
-use super::BashTheme;
 use super::bash_parser::BashParser;
-use crate::os::Os;
-use crate::util::directories;
+use super::renderer::ThemeRenderer;
+use super::{
+    BashTheme,
+    ThemeError,
+    ThemeSource,
+};
+use crate::database::settings::Setting;
+use crate::os::{
+    Fs,
+    Os,
+};
+use crate::util::{
+    directories,
+    sanitize_filename,
+};
+
+/// Largest theme that is downloaded by [ThemeManager::install_theme]
+const MAX_THEME_DOWNLOAD_BYTES: usize = 64 * 1024;
 
 pub struct ThemeManager {
     active_theme: Option<BashTheme>,
     theme_dir: PathBuf,
+    fs: Fs,
 }
 
 impl ThemeManager {
@@ -24,6 +40,7 @@ impl ThemeManager {
         Ok(Self {
             active_theme: None,
             theme_dir,
+            fs: os.fs.clone(),
         })
     }
 
@@ -39,14 +56,17 @@ impl ThemeManager {
         // Fallback to loading from theme files
         let theme_path = self.theme_dir.join(format!("{}.theme", name));
 
-        if !theme_path.exists() {
+        if !self.fs.exists(&theme_path) {
             debug!("Theme file not found: {}", theme_path.display());
             return Ok(()); // Graceful fallback - no theme loaded
         }
 
-        match BashParser::parse_theme_file(&theme_path) {
+        match BashParser::parse_theme_file(&self.fs, &theme_path) {
             Ok(theme) => {
                 debug!("Loaded theme: {}", theme.name);
+                for warning in ThemeRenderer::new(&theme).validate() {
+                    warn!("Theme {}: {}", name, warning);
+                }
                 self.active_theme = Some(theme);
             },
             Err(e) => {
@@ -142,6 +162,99 @@ impl ThemeManager {
         themes.dedup(); // Remove duplicates in case builtin and file themes have same name
         Ok(themes)
     }
+
+    /// Install a theme from a local file or URL into the themes directory, returning the name it
+    /// was installed under. The theme must define a PROMPT and contain only comments and
+    /// assignments. URLs are only fetched when `chat.enableThemeDownloads` is set.
+    pub async fn install_theme(&self, os: &Os, source: ThemeSource, force: bool) -> Result<String, ThemeError> {
+        let (file_name, content) = match &source {
+            ThemeSource::Path(path) => (
+                path.file_name()
+                    .and_then(|n| n.to_str())
+                    .unwrap_or_default()
+                    .to_string(),
+                os.fs.read_to_string(path).await?,
+            ),
+            ThemeSource::Url(url) => {
+                if !url.starts_with("https://") {
+                    return Err(ThemeError::InsecureUrl(url.clone()));
+                }
+                if !os
+                    .database
+                    .settings
+                    .get_bool(Setting::EnabledThemeDownloads)
+                    .unwrap_or(false)
+                {
+                    return Err(ThemeError::DownloadsDisabled);
+                }
+                let file_name = url
+                    .split(['?', '#'])
+                    .next()
+                    .and_then(|url| url.rsplit('/').next())
+                    .unwrap_or_default()
+                    .to_string();
+                let mut response = crate::request::new_client()?
+                    .get(url)
+                    .send()
+                    .await?
+                    .error_for_status()?;
+                if response
+                    .content_length()
+                    .is_some_and(|len| len > MAX_THEME_DOWNLOAD_BYTES as u64)
+                {
+                    return Err(ThemeError::TooLarge(MAX_THEME_DOWNLOAD_BYTES));
+                }
+                let mut body = Vec::new();
+                while let Some(chunk) = response.chunk().await? {
+                    if body.len() + chunk.len() > MAX_THEME_DOWNLOAD_BYTES {
+                        return Err(ThemeError::TooLarge(MAX_THEME_DOWNLOAD_BYTES));
+                    }
+                    body.extend_from_slice(&chunk);
+                }
+                let content = String::from_utf8(body)
+                    .map_err(|_err| ThemeError::Invalid("the theme is not valid UTF-8".to_string()))?;
+                (file_name, content)
+            },
+        };
+
+        let original_name = file_name.strip_suffix(".theme").unwrap_or(&file_name);
+        let name = sanitize_filename(original_name);
+        let name = name.as_str();
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+            return Err(ThemeError::InvalidName(name.to_string()));
+        }
+
+        let content = BashParser::normalize_content(&content);
+        if let Some(line) = BashParser::unparseable_lines(&content).first() {
+            return Err(ThemeError::Invalid(format!("unexpected line: {line}")));
+        }
+        let theme = BashParser::parse_theme_content(name, &content);
+        if theme.prompt_template.is_empty() {
+            return Err(ThemeError::Invalid("no PROMPT is defined".to_string()));
+        }
+        for warning in ThemeRenderer::new(&theme).validate() {
+            warn!("Theme {}: {}", name, warning);
+        }
+
+        let theme_path = self.theme_dir.join(format!("{name}.theme"));
+        if os.fs.exists(&theme_path) {
+            // A theme whose name had to be changed is never installed over another theme
+            if name != original_name {
+                return Err(ThemeError::RenamedExists {
+                    original: original_name.to_string(),
+                    name: name.to_string(),
+                });
+            }
+            if !force {
+                return Err(ThemeError::AlreadyExists(name.to_string()));
+            }
+        }
+        os.fs.create_dir_all(&self.theme_dir).await?;
+        os.fs.write(&theme_path, content.as_bytes()).await?;
+        debug!("Installed theme {} to {}", name, theme_path.display());
+
+        Ok(name.to_string())
+    }
 }
 
 impl Default for ThemeManager {
@@ -149,6 +262,7 @@ impl Default for ThemeManager {
         Self {
             active_theme: None,
             theme_dir: PathBuf::new(),
+            fs: Fs::default(),
         }
     }
 }
@@ -190,4 +304,98 @@ mod tests {
             assert!(theme.git_enabled); // powerline theme should have git support
         }
     }
+
+    #[tokio::test]
+    async fn test_install_theme_from_path() {
+        let os = Os::new().await.unwrap();
+        let manager = ThemeManager::new(&os).unwrap();
+        os.fs.create_dir_all("/shared").await.unwrap();
+        os.fs
+            .write("/shared/sunset.theme", "# A shared theme\nPROMPT=\"$CYAN> $RESET\"\n")
+            .await
+            .unwrap();
+
+        let name = manager
+            .install_theme(&os, ThemeSource::from("/shared/sunset.theme"), false)
+            .await
+            .unwrap();
+        assert_eq!(name, "sunset");
+        assert!(os.fs.exists(manager.theme_dir.join("sunset.theme")));
+
+        // Installing again requires force
+        let result = manager
+            .install_theme(&os, ThemeSource::from("/shared/sunset.theme"), false)
+            .await;
+        assert!(matches!(result, Err(ThemeError::AlreadyExists(name)) if name == "sunset"));
+        assert!(
+            manager
+                .install_theme(&os, ThemeSource::from("/shared/sunset.theme"), true)
+                .await
+                .is_ok()
+        );
+
+        // Characters that are illegal on Windows are replaced in the installed name
+        os.fs
+            .write("/shared/night:owl.theme", "PROMPT=\"$BLUE> $RESET\"\n")
+            .await
+            .unwrap();
+        let name = manager
+            .install_theme(&os, ThemeSource::from("/shared/night:owl.theme"), false)
+            .await
+            .unwrap();
+        assert_eq!(name, "night_owl");
+        assert!(os.fs.exists(manager.theme_dir.join("night_owl.theme")));
+
+        // A renamed theme is never installed over the theme it collides with
+        let result = manager
+            .install_theme(&os, ThemeSource::from("/shared/night:owl.theme"), true)
+            .await;
+        assert!(matches!(result, Err(ThemeError::RenamedExists { name, .. }) if name == "night_owl"));
+    }
+
+    #[tokio::test]
+    async fn test_install_theme_rejects_unparseable() {
+        let os = Os::new().await.unwrap();
+        let manager = ThemeManager::new(&os).unwrap();
+        os.fs.create_dir_all("/shared").await.unwrap();
+        os.fs
+            .write("/shared/broken.theme", "PROMPT=\"> \"\nif [ -n \"$X\" ]; then\n")
+            .await
+            .unwrap();
+        os.fs.write("/shared/empty.theme", "COLOR=red\n").await.unwrap();
+
+        for path in ["/shared/broken.theme", "/shared/empty.theme"] {
+            let result = manager.install_theme(&os, ThemeSource::from(path), false).await;
+            assert!(matches!(result, Err(ThemeError::Invalid(_))), "{path}: {result:?}");
+        }
+        assert!(!os.fs.exists(manager.theme_dir.join("broken.theme")));
+    }
+
+    #[tokio::test]
+    async fn test_install_theme_from_url_disabled() {
+        let os = Os::new().await.unwrap();
+        let manager = ThemeManager::new(&os).unwrap();
+        let result = manager
+            .install_theme(&os, ThemeSource::from("https://example.com/sunset.theme"), false)
+            .await;
+        assert!(matches!(result, Err(ThemeError::DownloadsDisabled)));
+    }
+
+    #[tokio::test]
+    async fn test_install_theme_from_http_url_rejected() {
+        let mut os = Os::new().await.unwrap();
+        os.database
+            .settings
+            .set(Setting::EnabledThemeDownloads, true)
+            .await
+            .unwrap();
+        let manager = ThemeManager::new(&os).unwrap();
+        let result = manager
+            .install_theme(&os, ThemeSource::from("http://example.com/sunset.theme"), false)
+            .await;
+        assert!(
+            matches!(result, Err(ThemeError::InsecureUrl(ref url)) if url == "http://example.com/sunset.theme"),
+            "{result:?}"
+        );
+    }
 }
@@ -1,5 +1,6 @@
 use std::borrow::Borrow;
 use std::collections::{
+    BTreeMap,
     HashMap,
     HashSet,
 };
@@ -38,7 +39,9 @@ use rmcp::ServiceError;
 use rmcp::model::{
     GetPromptRequestParam,
     GetPromptResult,
+    JsonObject,
     Prompt,
+    PromptArgument,
 };
 use tokio::signal::ctrl_c;
 use tokio::sync::{
@@ -48,6 +51,7 @@ use tokio::sync::{
 };
 use tokio::task::JoinHandle;
 use tracing::{
+    debug,
     error,
     info,
     warn,
@@ -62,6 +66,7 @@ use crate::api_client::model::{
 use crate::cli::agent::{
     Agent,
     McpServerConfig,
+    OriginalToolName,
 };
 use crate::cli::chat::cli::prompts::GetPromptError;
 use crate::cli::chat::consts::DUMMY_TOOL_NAME;
@@ -77,6 +82,12 @@ use crate::cli::chat::tools::fs_write::FsWrite;
 use crate::cli::chat::tools::gh_issue::GhIssue;
 use crate::cli::chat::tools::introspect::Introspect;
 use crate::cli::chat::tools::knowledge::Knowledge;
+use crate::cli::chat::tools::processes::Processes;
+use crate::cli::chat::tools::run_tests::RunTests;
+use crate::cli::chat::tools::scratchpad::{
+    ScratchpadRead,
+    ScratchpadWrite,
+};
 use crate::cli::chat::tools::thinking::Thinking;
 use crate::cli::chat::tools::todo::TodoList;
 use crate::cli::chat::tools::use_aws::UseAws;
@@ -86,17 +97,28 @@ use crate::cli::chat::tools::{
     ToolSpec,
 };
 use crate::database::Database;
-use crate::database::settings::Setting;
+use crate::database::settings::{
+    Setting,
+    Settings,
+};
 use crate::mcp_client::messenger::Messenger;
 use crate::mcp_client::{
     InitializedMcpClient,
     InnerService,
     McpClientService,
 };
-use crate::os::Os;
+use crate::os::{
+    Env,
+    Os,
+};
 use crate::telemetry::TelemetryThread;
 use crate::util::MCP_SERVER_TOOL_DELIMITER;
-use crate::util::directories::home_dir;
+use crate::util::directories::{
+    home_dir,
+    project_root,
+};
+use crate::util::env_var::Q_DUMP_TOOL_SCHEMA;
+use crate::util::retry::retry_with_backoff;
 
 const NAMESPACE_DELIMITER: &str = "___";
 // This applies for both mcp server and tool name since in the end the tool name as seen by the
@@ -105,7 +127,7 @@ const VALID_TOOL_NAME: &str = "^[a-zA-Z][a-zA-Z0-9_]*$";
 const SPINNER_CHARS: [char; 10] = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
 
 pub fn workspace_mcp_config_path(os: &Os) -> eyre::Result<PathBuf> {
-    Ok(os.env.current_dir()?.join(".amazonq").join("mcp.json"))
+    Ok(project_root(os)?.join(".amazonq").join("mcp.json"))
 }
 
 pub fn global_mcp_config_path(os: &Os) -> eyre::Result<PathBuf> {
@@ -143,6 +165,196 @@ enum LoadingMsg {
     /// that need OAuth or other authentication methods. Contains the server name and the
     /// authentication message (typically a URL or instructions).
     SignInNotice { name: String },
+    /// A line a server that is still loading printed to its stderr, shown when
+    /// `mcp.showServerStderr` is enabled.
+    ServerStderr { name: String, line: String },
+}
+
+/// Whether a native tool is made available to the model.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum NativeToolStatus {
+    Enabled,
+    /// The tool is left out of the schema, with a reason that is shown to the user
+    Disabled {
+        reason: String,
+    },
+}
+
+/// Why a tool is or is not offered to the model, as returned by
+/// [ToolManager::explain_tool_availability]. Each variant other than [ToolAvailability::Available]
+/// names the first filter that removed the tool.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ToolAvailability {
+    Available,
+    /// Neither a built-in tool nor a tool of a known MCP server
+    NotFound,
+    /// A built-in tool left out of the schema, see [NativeToolStatus]
+    NativeToolDisabled {
+        reason: String,
+    },
+    /// The server is not in the MCP servers of the agent
+    ServerNotConfigured {
+        server: String,
+    },
+    /// The server is configured with `disabled`, or disabled by `mcp.newServerPolicy`
+    ServerDisabled {
+        server: String,
+    },
+    /// The tool is not included by the tools of the agent
+    NotInAgentTools {
+        agent: String,
+    },
+    /// The tool is marked `hidden` in the server's tool config
+    Hidden {
+        server: String,
+    },
+    /// The server has not finished loading
+    ServerLoading {
+        server: String,
+    },
+    /// The tool was rejected because a tool of another server has the same name
+    Conflict {
+        conflicting_server: String,
+    },
+    /// The tool was dropped because `mcp.maxTools` was reached
+    OverToolLimit {
+        max: usize,
+    },
+    /// The server failed to load
+    ServerFailed {
+        server: String,
+        error: String,
+    },
+    /// The server loaded but does not offer a tool by that name
+    NotOffered {
+        server: String,
+    },
+}
+
+impl std::fmt::Display for ToolAvailability {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Available => write!(f, "available"),
+            Self::NotFound => write!(f, "no built-in tool or MCP server tool by that name is known"),
+            Self::NativeToolDisabled { reason } => write!(f, "built-in tool {reason}"),
+            Self::ServerNotConfigured { server } => write!(f, "no MCP server named {server} is configured"),
+            Self::ServerDisabled { server } => write!(f, "the MCP server {server} is disabled"),
+            Self::NotInAgentTools { agent } => write!(f, "not in the tools of agent {agent}"),
+            Self::Hidden { server } => write!(f, "hidden from the model by the tools config of {server}"),
+            Self::ServerLoading { server } => write!(f, "the MCP server {server} is still loading"),
+            Self::Conflict { conflicting_server } => write!(
+                f,
+                "rejected because a tool from {conflicting_server} has the same name, set an alias to avoid this"
+            ),
+            Self::OverToolLimit { max } => write!(f, "dropped because mcp.maxTools ({max}) was reached"),
+            Self::ServerFailed { server, error } => write!(f, "the MCP server {server} failed to load: {error}"),
+            Self::NotOffered { server } => write!(f, "the MCP server {server} does not offer a tool by that name"),
+        }
+    }
+}
+
+/// Disables the servers that have not been seen before when `mcp.newServerPolicy` is `disabled`,
+/// returning their names. Under the default `enabled` policy, new servers are recorded as seen
+/// instead. Servers disabled in their config are left alone until they are enabled.
+fn apply_new_server_policy(os: &Os, mcp_servers: &mut HashMap<String, CustomToolConfig>) -> Vec<String> {
+    let known = os.database.get_known_mcp_servers().unwrap_or_else(|err| {
+        error!(?err, "failed to read the known MCP servers");
+        Default::default()
+    });
+    let require_approval = os
+        .database
+        .settings
+        .get_string(Setting::McpNewServerPolicy)
+        .is_some_and(|policy| policy == "disabled");
+
+    let mut new_servers = mcp_servers
+        .iter_mut()
+        .filter(|(server_name, config)| !config.disabled && !known.contains(*server_name))
+        .map(|(server_name, config)| {
+            if require_approval {
+                config.disabled = true;
+            }
+            server_name.clone()
+        })
+        .collect::<Vec<_>>();
+    new_servers.sort();
+
+    if require_approval {
+        new_servers
+    } else {
+        if !new_servers.is_empty() {
+            if let Err(err) = os.database.add_known_mcp_servers(new_servers) {
+                error!(?err, "failed to record the known MCP servers");
+            }
+        }
+        Vec::new()
+    }
+}
+
+/// Works out which of the native tools named in the tool index are available, accounting for the
+/// platform, the settings that gate experimental tools, and the tools allowed by the agent.
+fn native_tool_status<'a>(
+    os: &Os,
+    agent: &Agent,
+    names: impl IntoIterator<Item = &'a String>,
+) -> HashMap<String, NativeToolStatus> {
+    use crate::cli::chat::tools::commands::Commands;
+    use crate::cli::chat::tools::knowledge::Knowledge;
+    use crate::cli::chat::tools::run_tests::RunTests;
+    use crate::cli::chat::tools::scratchpad::Scratchpad;
+    use crate::cli::chat::tools::thinking::Thinking;
+    use crate::cli::chat::tools::todo::TodoList;
+
+    let tool_list = &agent.tools;
+    let is_allow_all = tool_list.len() == 1 && tool_list.first().is_some_and(|n| n == "*");
+    let is_allow_native = tool_list.iter().any(|t| t.as_str() == "@builtin");
+
+    let feature_setting = |name: &str| -> Option<(bool, Setting)> {
+        Some(match name {
+            "thinking" => (Thinking::is_enabled(os), Setting::EnabledThinking),
+            "knowledge" => (Knowledge::is_enabled(os), Setting::EnabledKnowledge),
+            "todo_list" => (TodoList::is_enabled(os), Setting::EnabledTodoList),
+            "commands" => (Commands::is_enabled(os), Setting::EnabledCommands),
+            "run_tests" => (RunTests::is_enabled(os), Setting::EnabledRunTests),
+            "scratchpad_read" | "scratchpad_write" => (Scratchpad::is_enabled(os), Setting::EnabledScratchpad),
+            DUMMY_TOOL_NAME => (
+                os.database
+                    .settings
+                    .get_bool(Setting::ToolsIncludeDummy)
+                    .unwrap_or(true),
+                Setting::ToolsIncludeDummy,
+            ),
+            _ => return None,
+        })
+    };
+
+    names
+        .into_iter()
+        .map(|name| {
+            let reason = if cfg!(windows) && name == "execute_bash" {
+                Some("not available on Windows, execute_cmd is used instead".to_string())
+            } else if let Some((false, setting)) = feature_setting(name) {
+                Some(format!(
+                    "disabled, enable it with: q settings {} true",
+                    setting.as_ref()
+                ))
+            } else if name != DUMMY_TOOL_NAME
+                && !is_allow_all
+                && !is_allow_native
+                && !tool_list.contains(name)
+                && !tool_list.contains(&format!("@builtin/{name}"))
+            {
+                Some(format!("not in the tools of agent {}", agent.name))
+            } else {
+                None
+            };
+            let status = match reason {
+                Some(reason) => NativeToolStatus::Disabled { reason },
+                None => NativeToolStatus::Enabled,
+            };
+            (name.clone(), status)
+        })
+        .collect()
 }
 
 /// Used to denote the loading outcome associated with a server.
@@ -153,6 +365,43 @@ pub enum LoadingRecord {
     Success(String),
     Warn(String),
     Err(String),
+    /// The loading display was cancelled while the server was still initializing. The server
+    /// keeps loading in the background and a later record supersedes this one.
+    Cancelled(String),
+}
+
+/// A structured counterpart of the loading display, delivered instead of terminal output when a
+/// [LoadingEventSender] is given to [ToolManagerBuilder::loading_event_sender]. This allows the
+/// loading progress to be rendered by whatever is embedding the chat, and is how non-interactive
+/// sessions log it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LoadingEvent {
+    /// Loading has started for `total` servers
+    Start { total: usize },
+    /// A server is configured but disabled, and will not be loaded
+    Disabled { name: String },
+    /// A server has loaded, taking `time` seconds
+    Done { name: String, time: String },
+    /// A server has loaded with a warning
+    Warn { name: String, msg: String, time: String },
+    /// A server has failed to load
+    Error { name: String, msg: String, time: String },
+    /// A server requires the user to sign in before it can load
+    SignInRequired { name: String },
+    /// A line a loading server printed to its stderr
+    Stderr { name: String, line: String },
+    /// Loading has ended, listing the servers that had not finished loading by then
+    Finished { still_loading: Vec<String> },
+}
+
+pub type LoadingEventSender = tokio::sync::mpsc::UnboundedSender<LoadingEvent>;
+
+/// Number of MCP servers in each stage of loading.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ServerLoadCounts {
+    pub loaded: usize,
+    pub failed: usize,
+    pub pending: usize,
 }
 
 pub struct ToolManagerBuilder {
@@ -168,6 +417,8 @@ pub struct ToolManagerBuilder {
     pending_clients: Option<Arc<RwLock<HashSet<String>>>>,
     is_first_launch: bool,
     agent: Option<Arc<Mutex<Agent>>>,
+    retained_clients: HashMap<String, InitializedMcpClient>,
+    loading_event_sender: Option<LoadingEventSender>,
 }
 
 impl Default for ToolManagerBuilder {
@@ -185,6 +436,8 @@ impl Default for ToolManagerBuilder {
             pending_clients: Default::default(),
             is_first_launch: true,
             agent: Default::default(),
+            retained_clients: Default::default(),
+            loading_event_sender: Default::default(),
         }
     }
 }
@@ -204,6 +457,7 @@ impl From<&mut ToolManager> for ToolManagerBuilder {
             mcp_load_record: value.mcp_load_record.clone(),
             new_tool_specs: value.new_tool_specs.clone(),
             pending_clients: Some(value.pending_clients.clone()),
+            loading_event_sender: value.loading_event_sender.clone(),
             // if we are getting a builder from an instantiated tool manager this field would be
             // false
             is_first_launch: false,
@@ -247,6 +501,20 @@ impl ToolManagerBuilder {
         self
     }
 
+    /// Reports loading progress as [LoadingEvent]s through `sender` instead of drawing the loading
+    /// display to the terminal.
+    pub fn loading_event_sender(mut self, sender: LoadingEventSender) -> Self {
+        self.loading_event_sender.replace(sender);
+        self
+    }
+
+    /// Clients of servers that are already running and should be kept as is rather than being
+    /// started again.
+    fn retained_clients(mut self, clients: HashMap<String, InitializedMcpClient>) -> Self {
+        self.retained_clients = clients;
+        self
+    }
+
     /// Creates a [ToolManager] based on the current fields populated, which consists of the
     /// following:
     /// - Instantiates child processes associated with the list of mcp servers in scope
@@ -260,17 +528,53 @@ impl ToolManagerBuilder {
         mut output: Box<dyn Write + Send + Sync + 'static>,
         interactive: bool,
     ) -> eyre::Result<ToolManager> {
-        let McpServerConfig { mcp_servers } = match &self.agent {
+        let McpServerConfig { mut mcp_servers } = match &self.agent {
             Some(agent) => agent.lock().await.mcp_servers.clone(),
             None => Default::default(),
         };
         debug_assert!(self.conversation_id.is_some());
         let conversation_id = self.conversation_id.ok_or(eyre::eyre!("Missing conversation id"))?;
 
-        // Separate enabled and disabled servers
-        let (enabled_servers, disabled_servers): (Vec<_>, Vec<_>) = mcp_servers
+        for server_name in apply_new_server_policy(os, &mut mcp_servers) {
+            let msg = format!(
+                "{server_name} is new and disabled by mcp.newServerPolicy. Approve it with: q mcp approve --name {server_name}"
+            );
+            self.mcp_load_record
+                .lock()
+                .await
+                .entry(server_name)
+                .or_default()
+                .push(LoadingRecord::Warn(msg));
+        }
+
+        // Separate enabled and disabled servers, sorted by name so that they are initialized and
+        // displayed in the same order on every run
+        let (mut enabled_servers, mut disabled_servers): (Vec<_>, Vec<_>) = mcp_servers
             .into_iter()
             .partition(|(_, server_config)| !server_config.disabled);
+        enabled_servers.sort_by(|(a, _), (b, _)| a.cmp(b));
+        disabled_servers.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+        // Retained servers are already running, so they are neither started nor waited on again
+        let retained_clients = std::mem::take(&mut self.retained_clients);
+        let enabled_servers = enabled_servers
+            .into_iter()
+            .filter(|(server_name, _)| !retained_clients.contains_key(server_name))
+            .collect::<Vec<_>>();
+
+        // Servers defined in more than one config file are flagged in their load record
+        {
+            let mut load_record = self.mcp_load_record.lock().await;
+            for (server_name, server_config) in &enabled_servers {
+                if let Some(msg) = server_config.duplicate_definition_warning(server_name) {
+                    warn!("{msg}");
+                    load_record
+                        .entry(server_name.clone())
+                        .or_default()
+                        .push(LoadingRecord::Warn(msg));
+                }
+            }
+        }
 
         // Prepare disabled servers for display
         let disabled_servers_display: Vec<String> = disabled_servers
@@ -326,8 +630,10 @@ impl ToolManagerBuilder {
         // Spawn a task for displaying the mcp loading statuses.
         // This is only necessary when we are in interactive mode AND there are servers to load.
         // Otherwise we do not need to be spawning this.
-        let (loading_display_task, loading_status_sender) =
-            spawn_display_task(interactive, total, disabled_servers, output);
+        let (loading_display_task, loading_status_sender) = match self.loading_event_sender.clone() {
+            Some(events) => spawn_event_task(total, disabled_servers, events),
+            None => spawn_display_task(interactive, total, disabled_servers, output),
+        };
 
         // This is the orchestrator task that serves as a bridge between tool manager and mcp
         // clients for server initiated async events
@@ -346,6 +652,7 @@ impl ToolManagerBuilder {
             let conv_id = conversation_id.clone();
             let pending = pending.clone();
             let regex = Regex::new(VALID_TOOL_NAME)?;
+            let duration_format = DurationFormat::from_settings(&os.database.settings, &os.env);
 
             spawn_orchestrator_task(
                 has_new_stuff,
@@ -356,6 +663,7 @@ impl ToolManagerBuilder {
                 pending,
                 agent.clone(),
                 database,
+                duration_format,
                 regex,
                 notify_weak,
                 load_record.clone(),
@@ -369,6 +677,17 @@ impl ToolManagerBuilder {
 
         debug_assert!(messenger_builder.is_some());
         let messenger_builder = messenger_builder.unwrap();
+
+        // The tools offered by a retained server may be filtered differently under the new agent,
+        // so they are queried again
+        for (server_name, client) in retained_clients {
+            client.refetch_tools(
+                server_name.clone(),
+                messenger_builder.build_with_name(server_name.clone()),
+            );
+            clients.insert(server_name, client);
+        }
+
         let pre_initialized = enabled_servers
             .into_iter()
             .map(|(server_name, server_config)| {
@@ -439,7 +758,20 @@ impl ToolManagerBuilder {
                 }
             },
             messenger_builder: Some(messenger_builder),
+            loading_event_sender: self.loading_event_sender,
             is_first_launch: self.is_first_launch,
+            max_mcp_tools: os
+                .database
+                .settings
+                .get_int(Setting::McpMaxTools)
+                .and_then(|max| usize::try_from(max).ok()),
+            prompt_retries: os
+                .database
+                .settings
+                .get_int(Setting::McpPromptRetries)
+                .map_or(DEFAULT_PROMPT_RETRIES, |n| u32::try_from(n).unwrap_or(0)),
+            tool_name_separator: os.database.settings.get_string(Setting::McpToolNameSeparator),
+            dump_schema_path: os.env.get(Q_DUMP_TOOL_SCHEMA).ok().map(PathBuf::from),
             ..Default::default()
         })
     }
@@ -456,6 +788,22 @@ pub struct PromptBundle {
     pub prompt_get: Prompt,
 }
 
+/// A flattened view of a single prompt offered by a server, for display in UIs.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PromptView {
+    /// The server offering the prompt
+    pub server: String,
+    /// The name the prompt is invoked with, including any `promptPrefix`
+    pub name: String,
+    /// Names of the prompt's arguments, in the order the server declares them
+    pub args: Vec<String>,
+    /// Names of the arguments the server marks as required
+    pub required_args: Vec<String>,
+    /// Whether more than one server offers a prompt with this name, in which case it must be
+    /// invoked as `server/name`
+    pub ambiguous: bool,
+}
+
 #[derive(Clone, Debug)]
 pub enum PromptQuery {
     List,
@@ -565,6 +913,10 @@ pub struct ToolManager {
     /// with other buffer displayed by chat.
     loading_display_task: Option<JoinHandle<Result<(), Report>>>,
 
+    /// When set, loading progress is reported here instead of being drawn to the terminal. This is
+    /// kept so that it carries over when switching agent.
+    loading_event_sender: Option<LoadingEventSender>,
+
     /// Mapping from sanitized tool names to original tool names.
     /// This is used to handle tool name transformations that may occur during initialization
     /// to ensure tool names comply with naming requirements.
@@ -598,6 +950,26 @@ pub struct ToolManager {
     pub agent: Arc<Mutex<Agent>>,
 
     is_first_launch: bool,
+
+    /// Maximum number of MCP tools loaded across all servers, set with `mcp.maxTools`. Tools past
+    /// the cap are dropped with a warning recorded against their server.
+    max_mcp_tools: Option<usize>,
+
+    /// Times a prompt is fetched again after a transient error, set with `mcp.promptRetries`.
+    prompt_retries: u32,
+
+    /// Whether each native tool was made available to the model on the last load, and if not why.
+    native_tool_status: HashMap<String, NativeToolStatus>,
+
+    /// Separator between server and tool names when MCP tools are shown to users, set with
+    /// `mcp.toolNameSeparator`. Defaults to [MCP_SERVER_TOOL_DELIMITER].
+    tool_name_separator: Option<String>,
+
+    /// MCP tools dropped by [ToolManager::update], keyed by server and host tool name, with why.
+    dropped_mcp_tools: HashMap<(ServerName, HostToolName), ToolAvailability>,
+
+    /// Where the tool schema is written whenever it changes, set with `Q_DUMP_TOOL_SCHEMA`.
+    dump_schema_path: Option<PathBuf>,
 }
 
 impl Clone for ToolManager {
@@ -611,14 +983,176 @@ impl Clone for ToolManager {
             is_interactive: self.is_interactive,
             mcp_load_record: self.mcp_load_record.clone(),
             disabled_servers: self.disabled_servers.clone(),
+            max_mcp_tools: self.max_mcp_tools,
+            prompt_retries: self.prompt_retries,
+            native_tool_status: self.native_tool_status.clone(),
+            tool_name_separator: self.tool_name_separator.clone(),
+            dropped_mcp_tools: self.dropped_mcp_tools.clone(),
+            dump_schema_path: self.dump_schema_path.clone(),
             ..Default::default()
         }
     }
 }
 
 impl ToolManager {
+    /// Whether each native tool is available to the model, and if not the reason why. This is
+    /// populated by [ToolManager::load_tools].
+    pub fn native_tool_status(&self) -> HashMap<String, NativeToolStatus> {
+        self.native_tool_status.clone()
+    }
+
+    /// The name of a tool as shown to users. Namespaced MCP tools are rendered as `server/tool`,
+    /// while the name sent to the model keeps the internal delimiter.
+    pub fn display_tool_name(&self, model_tool_name: &str) -> String {
+        match self.tn_map.get(model_tool_name) {
+            Some(info) => self.format_display_name(info),
+            None => model_tool_name.to_string(),
+        }
+    }
+
+    /// Finds an MCP tool by a name typed by the user, either its name on the server or its display
+    /// name as listed by `/tools`.
+    pub fn find_mcp_tool(&self, name: &str) -> Option<&ToolInfo> {
+        self.tn_map
+            .values()
+            .find(|info| info.host_tool_name == name)
+            .or_else(|| self.tn_map.values().find(|info| self.format_display_name(info) == name))
+    }
+
+    fn format_display_name(&self, info: &ToolInfo) -> String {
+        let separator = self.tool_name_separator.as_deref().unwrap_or(MCP_SERVER_TOOL_DELIMITER);
+        format!("{}{separator}{}", info.server_name, info.host_tool_name)
+    }
+
+    /// Explains whether the tool `name` is offered to the model, and if not, the first filter that
+    /// removed it: the platform and feature settings for built-in tools, then for MCP tools the
+    /// agent's servers, disabled servers, the agent's tools, hidden tools, loading, name conflicts
+    /// and `mcp.maxTools`. MCP tools can be named as `server/tool`, `@server/tool`, with the
+    /// display separator, or by their name as seen by the model.
+    pub async fn explain_tool_availability(&self, name: &str) -> ToolAvailability {
+        if self.schema.contains_key(name) {
+            return ToolAvailability::Available;
+        }
+        if let Some(status) = self.native_tool_status.get(name) {
+            return match status {
+                NativeToolStatus::Enabled => ToolAvailability::Available,
+                NativeToolStatus::Disabled { reason } => {
+                    ToolAvailability::NativeToolDisabled { reason: reason.clone() }
+                },
+            };
+        }
+
+        let Some((server, tool)) = self.parse_mcp_tool_name(name) else {
+            return ToolAvailability::NotFound;
+        };
+        let agent = self.agent.lock().await;
+        let Some(config) = agent.mcp_servers.mcp_servers.get(&server) else {
+            return ToolAvailability::ServerNotConfigured { server };
+        };
+        if config.disabled || self.disabled_servers.contains(&server) {
+            return ToolAvailability::ServerDisabled { server };
+        }
+        let is_allow_all = agent.tools.len() == 1 && agent.tools.first().is_some_and(|t| t == "*");
+        let in_agent_tools = agent.tools.iter().any(|t| {
+            let t = t.strip_prefix('@').unwrap_or(t);
+            match t.split_once(MCP_SERVER_TOOL_DELIMITER) {
+                Some((s, t)) => s == server && (t.is_empty() || t == "*" || t == tool),
+                None => t == server,
+            }
+        });
+        if !is_allow_all && !in_agent_tools {
+            return ToolAvailability::NotInAgentTools {
+                agent: agent.name.clone(),
+            };
+        }
+        if config.tools.get(&tool).is_some_and(|tool_config| tool_config.hidden) {
+            return ToolAvailability::Hidden { server };
+        }
+        drop(agent);
+
+        if self.pending_clients.read().await.contains(&server) {
+            return ToolAvailability::ServerLoading { server };
+        }
+        if let Some(dropped) = self.dropped_mcp_tools.get(&(server.clone(), tool.clone())) {
+            return dropped.clone();
+        }
+        if !self.clients.contains_key(&server) {
+            if let Some(LoadingRecord::Err(error)) =
+                self.mcp_load_record.lock().await.get(&server).and_then(|r| r.last())
+            {
+                return ToolAvailability::ServerFailed {
+                    server,
+                    error: error.trim_end().to_string(),
+                };
+            }
+        }
+        let offered = self.tn_map.iter().any(|(model_tool_name, info)| {
+            info.server_name == server && info.host_tool_name == tool && self.schema.contains_key(model_tool_name)
+        });
+        if offered {
+            ToolAvailability::Available
+        } else {
+            ToolAvailability::NotOffered { server }
+        }
+    }
+
+    /// Splits the name of an MCP tool, as the model or a user would refer to it, into its server
+    /// and host tool name.
+    fn parse_mcp_tool_name(&self, name: &str) -> Option<(ServerName, HostToolName)> {
+        if let Some(info) = self.tn_map.get(name) {
+            return Some((info.server_name.clone(), info.host_tool_name.clone()));
+        }
+        let name = name.strip_prefix('@').unwrap_or(name);
+        [
+            self.tool_name_separator.as_deref(),
+            Some(MCP_SERVER_TOOL_DELIMITER),
+            Some(NAMESPACE_DELIMITER),
+        ]
+        .into_iter()
+        .flatten()
+        .find_map(|separator| name.split_once(separator))
+        .map(|(server, tool)| (server.to_string(), tool.to_string()))
+    }
+
+    /// Tallies the MCP servers that have finished loading, failed to load, or are still pending.
+    /// A server is counted as failed if the most recent record of it loading is an error.
+    pub async fn server_load_counts(&self) -> ServerLoadCounts {
+        let pending = self.pending_clients.read().await;
+        let mut counts = ServerLoadCounts {
+            pending: pending.len(),
+            ..Default::default()
+        };
+        for (server_name, records) in self.mcp_load_record.lock().await.iter() {
+            // A server still loading may already have warnings about its config on record
+            if pending.contains(server_name) {
+                continue;
+            }
+            match records.last() {
+                Some(LoadingRecord::Err(_)) => counts.failed += 1,
+                Some(LoadingRecord::Cancelled(_)) | None => {},
+                Some(_) => counts.loaded += 1,
+            }
+        }
+        counts
+    }
+
+    /// Records a terminal [LoadingRecord::Cancelled] for each server that was still loading when
+    /// the loading display was terminated, so that `/mcp` reflects every server.
+    async fn record_cancelled_loads(&self, still_loading: &[String]) {
+        let mut load_record = self.mcp_load_record.lock().await;
+        for server_name in still_loading {
+            let records = load_record.entry(server_name.clone()).or_default();
+            if !matches!(records.last(), Some(LoadingRecord::Cancelled(_))) {
+                records.push(LoadingRecord::Cancelled(format!(
+                    "Loading of {server_name} was cancelled before it finished initializing. Its tools will become available once it finishes loading in the background."
+                )));
+            }
+        }
+    }
+
     /// Swapping agent involves the following:
-    /// - Dropping all of the clients first to avoid resource contention
+    /// - Dropping the clients of servers that were removed or whose config changed first to avoid
+    ///   resource contention. Servers configured identically in both agents keep running.
     /// - Clearing fields that are already referenced by background tasks. We can't simply spawn new
     ///   instances of these fields because one or more background tasks are already depending on it
     /// - Building a new tool manager builder from the current tool manager
@@ -627,7 +1161,11 @@ impl ToolManager {
     ///   function)
     /// - Calling load tools
     pub async fn swap_agent(&mut self, os: &mut Os, output: &mut impl Write, agent: &Agent) -> eyre::Result<()> {
-        let to_evict = self.clients.drain().collect::<Vec<_>>();
+        let old_servers = self.agent.lock().await.mcp_servers.mcp_servers.clone();
+        let (retained, to_evict): (HashMap<_, _>, HashMap<_, _>) = self
+            .clients
+            .drain()
+            .partition(|(server_name, _)| server_unchanged(&old_servers, &agent.mcp_servers.mcp_servers, server_name));
         tokio::spawn(async move {
             for (server_name, initialized_client) in to_evict {
                 info!("Evicting {server_name} due to agent swap");
@@ -668,9 +1206,12 @@ impl ToolManager {
         *agent_lock = agent.clone();
         drop(agent_lock);
 
-        self.mcp_load_record.lock().await.clear();
+        self.mcp_load_record
+            .lock()
+            .await
+            .retain(|server_name, _| retained.contains_key(server_name));
 
-        let builder = ToolManagerBuilder::from(&mut *self);
+        let builder = ToolManagerBuilder::from(&mut *self).retained_clients(retained);
         let mut new_tool_manager = builder.build(os, Box::new(std::io::sink()), true).await?;
         std::mem::swap(self, &mut new_tool_manager);
 
@@ -687,32 +1228,10 @@ impl ToolManager {
         let tx = self.loading_status_sender.take();
         let notify = self.notify.take();
         self.schema = {
-            let tool_list = &self.agent.lock().await.tools;
-            let is_allow_all = tool_list.len() == 1 && tool_list.first().is_some_and(|n| n == "*");
-            let is_allow_native = tool_list.iter().any(|t| t.as_str() == "@builtin");
             let mut tool_specs =
-                serde_json::from_str::<HashMap<String, ToolSpec>>(include_str!("tools/tool_index.json"))?
-                    .into_iter()
-                    .filter(|(name, _)| {
-                        name == DUMMY_TOOL_NAME
-                            || is_allow_all
-                            || is_allow_native
-                            || tool_list.contains(name)
-                            || tool_list.contains(&format!("@builtin/{name}"))
-                    })
-                    .collect::<HashMap<_, _>>();
-            if !crate::cli::chat::tools::thinking::Thinking::is_enabled(os) {
-                tool_specs.remove("thinking");
-            }
-            if !crate::cli::chat::tools::knowledge::Knowledge::is_enabled(os) {
-                tool_specs.remove("knowledge");
-            }
-            if !crate::cli::chat::tools::todo::TodoList::is_enabled(os) {
-                tool_specs.remove("todo_list");
-            }
-            if !crate::cli::chat::tools::commands::Commands::is_enabled(os) {
-                tool_specs.remove("commands");
-            }
+                serde_json::from_str::<HashMap<String, ToolSpec>>(include_str!("tools/tool_index.json"))?;
+            self.native_tool_status = native_tool_status(os, &*self.agent.lock().await, tool_specs.keys());
+            tool_specs.retain(|name, _| matches!(self.native_tool_status.get(name), Some(NativeToolStatus::Enabled)));
 
             #[cfg(windows)]
             {
@@ -720,8 +1239,6 @@ impl ToolManager {
 
                 use crate::cli::chat::tools::InputSchema;
 
-                tool_specs.remove("execute_bash");
-
                 tool_specs.insert("execute_cmd".to_string(), ToolSpec {
                     name: "execute_cmd".to_string(),
                     description: "Execute the specified Windows command.".to_string(),
@@ -739,6 +1256,7 @@ impl ToolManager {
                     },
                         "required": ["command"]})),
                     tool_origin: ToolOrigin::Native,
+                    examples: None,
                 });
             }
 
@@ -754,16 +1272,20 @@ impl ToolManager {
             let init_timeout = os
                 .database
                 .settings
-                .get_int(Setting::McpInitTimeout)
-                .map_or(5000_u64, |s| s as u64);
+                .get(Setting::McpInitTimeout)
+                .and_then(|v| v.as_u64())
+                .or_else(|| Setting::McpInitTimeout.default_value()?.as_u64())
+                .unwrap_or_default();
             Box::pin(tokio::time::sleep(std::time::Duration::from_millis(init_timeout)))
         } else {
             // if it is non-interactive we will want to use the "mcp.noInteractiveTimeout"
             let init_timeout = os
                 .database
                 .settings
-                .get_int(Setting::McpNoInteractiveTimeout)
-                .map_or(30_000_u64, |s| s as u64);
+                .get(Setting::McpNoInteractiveTimeout)
+                .and_then(|v| v.as_u64())
+                .or_else(|| Setting::McpNoInteractiveTimeout.default_value()?.as_u64())
+                .unwrap_or_default();
             Box::pin(tokio::time::sleep(std::time::Duration::from_millis(init_timeout)))
         };
         let server_loading_fut: Pin<Box<dyn Future<Output = ()>>> = if let Some(notify) = notify {
@@ -775,7 +1297,8 @@ impl ToolManager {
         tokio::select! {
             _ = timeout_fut => {
                 if let Some(tx) = tx {
-                    let still_loading = self.pending_clients.read().await.iter().cloned().collect::<Vec<_>>();
+                    let still_loading = self.pending_clients().await;
+                    self.record_cancelled_loads(&still_loading).await;
                     let _ = tx.send(LoadingMsg::Terminate { still_loading }).await;
                     if let Some(task) = loading_display_task {
                         let _ = tokio::time::timeout(
@@ -796,14 +1319,15 @@ impl ToolManager {
             },
             _ = server_loading_fut => {
                 if let Some(tx) = tx {
-                    let still_loading = self.pending_clients.read().await.iter().cloned().collect::<Vec<_>>();
+                    let still_loading = self.pending_clients().await;
                     let _ = tx.send(LoadingMsg::Terminate { still_loading }).await;
                 }
             }
             _ = ctrl_c() => {
                 if self.is_interactive {
                     if let Some(tx) = tx {
-                        let still_loading = self.pending_clients.read().await.iter().cloned().collect::<Vec<_>>();
+                        let still_loading = self.pending_clients().await;
+                        self.record_cancelled_loads(&still_loading).await;
                         let _ = tx.send(LoadingMsg::Terminate { still_loading }).await;
                     }
                 } else {
@@ -833,34 +1357,55 @@ impl ToolManager {
     }
 
     pub async fn get_tool_from_tool_use(&mut self, value: AssistantToolUse) -> Result<Tool, ToolResult> {
-        let map_err = |parse_error| ToolResult {
+        let map_err = |parse_error: serde_path_to_error::Error<serde_json::Error>| ToolResult {
             tool_use_id: value.id.clone(),
-            content: vec![ToolResultContentBlock::Text(format!(
-                "Failed to validate tool parameters: {parse_error}. The model has either suggested tool parameters which are incompatible with the existing tools, or has suggested one or more tool that does not exist in the list of known tools."
+            content: vec![ToolResultContentBlock::Text(explain_parse_error(
+                &value.name,
+                &parse_error,
+                self.schema.get(&value.name),
             ))],
             status: ToolResultStatus::Error,
         };
 
         Ok(match value.name.as_str() {
-            "fs_read" => Tool::FsRead(serde_json::from_value::<FsRead>(value.args).map_err(map_err)?),
-            "fs_write" => Tool::FsWrite(serde_json::from_value::<FsWrite>(value.args).map_err(map_err)?),
+            "fs_read" => Tool::FsRead(serde_path_to_error::deserialize::<_, FsRead>(value.args).map_err(map_err)?),
+            "fs_write" => Tool::FsWrite(serde_path_to_error::deserialize::<_, FsWrite>(value.args).map_err(map_err)?),
             #[cfg(windows)]
-            "execute_cmd" => {
-                Tool::ExecuteCommand(serde_json::from_value::<ExecuteCommand>(value.args).map_err(map_err)?)
-            },
+            "execute_cmd" => Tool::ExecuteCommand(
+                serde_path_to_error::deserialize::<_, ExecuteCommand>(value.args).map_err(map_err)?,
+            ),
             #[cfg(not(windows))]
-            "execute_bash" => {
-                Tool::ExecuteCommand(serde_json::from_value::<ExecuteCommand>(value.args).map_err(map_err)?)
+            "execute_bash" => Tool::ExecuteCommand(
+                serde_path_to_error::deserialize::<_, ExecuteCommand>(value.args).map_err(map_err)?,
+            ),
+            "use_aws" => Tool::UseAws(serde_path_to_error::deserialize::<_, UseAws>(value.args).map_err(map_err)?),
+            "report_issue" => {
+                Tool::GhIssue(serde_path_to_error::deserialize::<_, GhIssue>(value.args).map_err(map_err)?)
+            },
+            "introspect" => {
+                Tool::Introspect(serde_path_to_error::deserialize::<_, Introspect>(value.args).map_err(map_err)?)
+            },
+            "thinking" => Tool::Thinking(serde_path_to_error::deserialize::<_, Thinking>(value.args).map_err(map_err)?),
+            "knowledge" => {
+                Tool::Knowledge(serde_path_to_error::deserialize::<_, Knowledge>(value.args).map_err(map_err)?)
             },
-            "use_aws" => Tool::UseAws(serde_json::from_value::<UseAws>(value.args).map_err(map_err)?),
-            "report_issue" => Tool::GhIssue(serde_json::from_value::<GhIssue>(value.args).map_err(map_err)?),
-            "introspect" => Tool::Introspect(serde_json::from_value::<Introspect>(value.args).map_err(map_err)?),
-            "thinking" => Tool::Thinking(serde_json::from_value::<Thinking>(value.args).map_err(map_err)?),
-            "knowledge" => Tool::Knowledge(serde_json::from_value::<Knowledge>(value.args).map_err(map_err)?),
             "commands" => Tool::Commands(
-                serde_json::from_value::<crate::cli::chat::tools::commands::Commands>(value.args).map_err(map_err)?,
+                serde_path_to_error::deserialize::<_, crate::cli::chat::tools::commands::Commands>(value.args)
+                    .map_err(map_err)?,
             ), // NEW: Add commands parsing
-            "todo_list" => Tool::Todo(serde_json::from_value::<TodoList>(value.args).map_err(map_err)?),
+            "todo_list" => Tool::Todo(serde_path_to_error::deserialize::<_, TodoList>(value.args).map_err(map_err)?),
+            "run_tests" => {
+                Tool::RunTests(serde_path_to_error::deserialize::<_, RunTests>(value.args).map_err(map_err)?)
+            },
+            "processes" => {
+                Tool::Processes(serde_path_to_error::deserialize::<_, Processes>(value.args).map_err(map_err)?)
+            },
+            "scratchpad_read" => Tool::ScratchpadRead(
+                serde_path_to_error::deserialize::<_, ScratchpadRead>(value.args).map_err(map_err)?,
+            ),
+            "scratchpad_write" => Tool::ScratchpadWrite(
+                serde_path_to_error::deserialize::<_, ScratchpadWrite>(value.args).map_err(map_err)?,
+            ),
             // Note that this name is namespaced with server_name{DELIMITER}tool_name
             name => {
                 // Note: tn_map also has tools that underwent no transformation. In otherwords, if
@@ -872,20 +1417,25 @@ impl ToolManager {
                     Some(tool_info) => Ok::<&ToolInfo, ToolResult>(tool_info),
                     None => {
                         // No match, we throw an error
+                        let candidates = self.schema.keys().chain(self.tn_map.keys()).map(String::as_str);
+                        let mut msg = format!("No tool with \"{name}\" is found.");
+                        if let Some(suggestion) = closest_tool_name(name, candidates) {
+                            msg.push_str(&format!(" Did you mean \"{suggestion}\"?"));
+                        }
+                        msg.push_str(" Only use tools from the list of available tools.");
                         Err(ToolResult {
                             tool_use_id: value.id.clone(),
-                            content: vec![ToolResultContentBlock::Text(format!(
-                                "No tool with \"{name}\" is found"
-                            ))],
+                            content: vec![ToolResultContentBlock::Text(msg)],
                             status: ToolResultStatus::Error,
                         })
                     },
                 }?;
+                let display_name = self.display_tool_name(name);
                 let Some(client) = self.clients.get_mut(server_name) else {
                     return Err(ToolResult {
                         tool_use_id: value.id,
                         content: vec![ToolResultContentBlock::Text(format!(
-                            "The tool, \"{server_name}\" is not supported by the client"
+                            "The tool \"{display_name}\" belongs to the server \"{server_name}\", which is not connected. It may have failed to initialize or been removed; run /mcp to check its status."
                         ))],
                         status: ToolResultStatus::Error,
                     });
@@ -907,6 +1457,70 @@ impl ToolManager {
         })
     }
 
+    /// Serializes the tool schema exactly as it is sent to the model, sorted by tool name. The
+    /// examples shown in `/tools` are left out since the model never sees them.
+    pub fn schema_json(&self) -> serde_json::Result<String> {
+        let schema = self
+            .schema
+            .iter()
+            .map(|(name, spec)| {
+                let spec = serde_json::json!({
+                    "name": spec.name,
+                    "description": spec.description,
+                    "input_schema": spec.input_schema.0,
+                });
+                (name, spec)
+            })
+            .collect::<BTreeMap<_, _>>();
+        serde_json::to_string_pretty(&schema)
+    }
+
+    /// Describes every tool offered to the model as a JSON array sorted by model tool name. Each
+    /// entry has the `name` the model calls, the `host_name` the server knows it by, its `origin`
+    /// (`native` or the server name), whether it is `aliased` (renamed by an alias or by name
+    /// sanitization), and its `input_schema`.
+    pub fn export_tools_json(&self) -> serde_json::Value {
+        let tools = self
+            .schema
+            .iter()
+            .collect::<BTreeMap<_, _>>()
+            .into_iter()
+            .map(|(name, spec)| {
+                let host_name = self
+                    .tn_map
+                    .get(name)
+                    .map_or(name.as_str(), |info| info.host_tool_name.as_str());
+                let origin = match &spec.tool_origin {
+                    ToolOrigin::Native => "native",
+                    ToolOrigin::McpServer(server_name) => server_name.as_str(),
+                };
+                serde_json::json!({
+                    "name": name,
+                    "host_name": host_name,
+                    "origin": origin,
+                    "aliased": host_name != name,
+                    "input_schema": spec.input_schema.0,
+                })
+            })
+            .collect();
+        serde_json::Value::Array(tools)
+    }
+
+    /// Writes [Self::schema_json] to the path in `Q_DUMP_TOOL_SCHEMA`, if it was set when the
+    /// tool manager was built.
+    pub fn dump_schema_if_requested(&self) {
+        let Some(path) = &self.dump_schema_path else {
+            return;
+        };
+        match self.schema_json() {
+            Ok(json) => match std::fs::write(path, json) {
+                Ok(()) => debug!(?path, "Dumped tool schema"),
+                Err(err) => warn!(?path, ?err, "Failed to dump tool schema"),
+            },
+            Err(err) => warn!(?err, "Failed to serialize tool schema"),
+        }
+    }
+
     /// Updates tool managers various states with new information
     pub async fn update(&mut self) {
         // A hashmap of <tool name, tool spec>
@@ -924,9 +1538,12 @@ impl ToolManager {
 
         let mut updated_servers = HashSet::<ToolOrigin>::new();
         let mut conflicts = HashMap::<ServerName, String>::new();
+        let mut truncated = HashMap::<ServerName, String>::new();
         for (server_name, (tool_name_map, specs)) in new_tools {
             // First we evict the tools that were already in the tn_map
             self.tn_map.retain(|_, tool_info| tool_info.server_name != server_name);
+            self.dropped_mcp_tools
+                .retain(|(dropped_server, _), _| *dropped_server != server_name);
 
             // And update them with the new tools queried
             // valid: tools that do not have conflicts in naming
@@ -938,11 +1555,48 @@ impl ToolManager {
             // We reject tools that are conflicting with the existing tools by not including them
             // in the tn_map. We would also want to report this error.
             if !invalid.is_empty() {
+                for (model_tool_name, tool_info) in &invalid {
+                    if let Some(existing) = self.tn_map.get(model_tool_name) {
+                        self.dropped_mcp_tools.insert(
+                            (tool_info.server_name.clone(), tool_info.host_tool_name.clone()),
+                            ToolAvailability::Conflict {
+                                conflicting_server: existing.server_name.clone(),
+                            },
+                        );
+                    }
+                }
                 let msg = invalid.into_iter().fold("The following tools are rejected because they conflict with existing tools in names. Avoid this via setting aliases for them: \n".to_string(), |mut acc, (model_tool_name, tool_info)| {
                     acc.push_str(&format!(" - {} from {}\n", model_tool_name, tool_info.server_name));
                     acc
                 });
-                conflicts.insert(server_name, msg);
+                conflicts.insert(server_name.clone(), msg);
+            }
+            // Once the cap on MCP tools is reached, further tools are dropped. Names are sorted so
+            // the same tools are kept on every load.
+            let mut valid = valid;
+            if let Some(max) = self.max_mcp_tools {
+                let room = max.saturating_sub(self.tn_map.len());
+                if valid.len() > room {
+                    let mut names = valid.keys().cloned().collect::<Vec<_>>();
+                    names.sort();
+                    let dropped = names.split_off(room);
+                    for tool_info in dropped.iter().filter_map(|name| valid.get(name)) {
+                        self.dropped_mcp_tools.insert(
+                            (tool_info.server_name.clone(), tool_info.host_tool_name.clone()),
+                            ToolAvailability::OverToolLimit { max },
+                        );
+                    }
+                    valid.retain(|model_tool_name, _| !dropped.contains(model_tool_name));
+                    truncated.insert(
+                        server_name.clone(),
+                        format!(
+                            "{} tools from {} were dropped because mcp.maxTools ({}) was reached\n",
+                            dropped.len(),
+                            server_name,
+                            max
+                        ),
+                    );
+                }
             }
             if let Some(spec) = specs.first() {
                 updated_servers.insert(spec.tool_origin.clone());
@@ -965,7 +1619,7 @@ impl ToolManager {
         self.schema.extend(tool_specs);
 
         // if block here to avoid repeatedly asking for loc
-        if !conflicts.is_empty() {
+        if !conflicts.is_empty() || !truncated.is_empty() {
             let mut record_lock = self.mcp_load_record.lock().await;
             for (server_name, msg) in conflicts {
                 let record = LoadingRecord::Err(msg);
@@ -974,6 +1628,13 @@ impl ToolManager {
                     .and_modify(|v| v.push(record.clone()))
                     .or_insert(vec![record]);
             }
+            for (server_name, msg) in truncated {
+                warn!("{}", msg.trim_end());
+                record_lock
+                    .entry(server_name)
+                    .or_default()
+                    .push(LoadingRecord::Warn(msg));
+            }
         }
     }
 
@@ -997,6 +1658,11 @@ impl ToolManager {
         }
     }
 
+    /// Returns the cached prompts from all servers, sorted by name and then server.
+    pub async fn prompts_view(&self) -> Result<Vec<PromptView>, GetPromptError> {
+        Ok(prompts_view(&self.list_prompts().await?))
+    }
+
     pub async fn get_prompt(
         &mut self,
         name: String,
@@ -1062,27 +1728,17 @@ impl ToolManager {
                     let server_name = &bundle.server_name;
                     let client = self.clients.get_mut(server_name).ok_or(GetPromptError::MissingClient)?;
                     let PromptBundle { prompt_get, .. } = bundle;
-                    let arguments = if let (Some(schema), Some(value)) = (&prompt_get.arguments, &arguments) {
-                        let params = schema.iter().zip(value.iter()).fold(
-                            HashMap::<String, String>::new(),
-                            |mut acc, (prompt_get_arg, value)| {
-                                acc.insert(prompt_get_arg.name.clone(), value.clone());
-                                acc
-                            },
-                        );
-                        Some(
-                            params
-                                .into_iter()
-                                .map(|(k, v)| (k, serde_json::Value::String(v)))
-                                .collect(),
-                        )
-                    } else {
-                        None
-                    };
+                    let arguments = prompt_arguments(&prompt_get.name, prompt_get.arguments.as_deref(), arguments)?;
 
-                    let params = GetPromptRequestParam { name, arguments };
+                    // The server knows the prompt by its own name, without any qualifier or prefix
+                    let params = GetPromptRequestParam {
+                        name: prompt_get.name.clone(),
+                        arguments,
+                    };
                     let running_service = client.get_running_service().await?;
-                    let resp = running_service.get_prompt(params).await?;
+                    let resp =
+                        get_prompt_with_retry(self.prompt_retries, || running_service.get_prompt(params.clone()))
+                            .await?;
 
                     Ok(resp)
                 },
@@ -1093,14 +1749,153 @@ impl ToolManager {
         }
     }
 
+    /// Names of the servers that have not finished loading, sorted for display.
     pub async fn pending_clients(&self) -> Vec<String> {
-        self.pending_clients.read().await.iter().cloned().collect::<Vec<_>>()
+        let mut pending = self.pending_clients.read().await.iter().cloned().collect::<Vec<_>>();
+        pending.sort();
+        pending
+    }
+}
+
+/// Default for `mcp.promptRetries`.
+const DEFAULT_PROMPT_RETRIES: u32 = 2;
+
+/// Delay before the first retry of a prompt fetch, doubling after each attempt.
+const PROMPT_RETRY_BASE_DELAY: Duration = Duration::from_millis(200);
+
+/// Assigns the positional `values` to the arguments the prompt declares, in order. Fails if a
+/// required argument has no value or if more values are supplied than there are arguments, so
+/// that a bad invocation is reported before contacting the server.
+fn prompt_arguments(
+    prompt_name: &str,
+    schema: Option<&[PromptArgument]>,
+    values: Option<Vec<String>>,
+) -> Result<Option<JsonObject>, GetPromptError> {
+    let schema = schema.unwrap_or_default();
+    let values = values.unwrap_or_default();
+
+    if values.len() > schema.len() {
+        let unknown = values[schema.len()..].join(", ");
+        return Err(GetPromptError::InvalidArguments(
+            prompt_name.to_string(),
+            match schema.len() {
+                0 => format!("it takes no arguments, but got: {unknown}"),
+                n => format!("it takes at most {n} argument(s), unexpected: {unknown}"),
+            },
+        ));
+    }
+
+    let missing = schema[values.len()..]
+        .iter()
+        .filter(|arg| arg.required == Some(true))
+        .map(|arg| arg.name.as_str())
+        .collect::<Vec<_>>();
+    if !missing.is_empty() {
+        return Err(GetPromptError::InvalidArguments(
+            prompt_name.to_string(),
+            format!("missing required argument(s): {}", missing.join(", ")),
+        ));
     }
+
+    if values.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(
+        schema
+            .iter()
+            .zip(values)
+            .map(|(arg, value)| (arg.name.clone(), serde_json::Value::String(value)))
+            .collect(),
+    ))
+}
+
+/// Fetches a prompt with `get_prompt`, retrying transport failures and timeouts. Errors reported by
+/// the server itself, such as an unknown prompt or invalid arguments, are not retried.
+async fn get_prompt_with_retry<F, Fut>(max_retries: u32, get_prompt: F) -> Result<GetPromptResult, ServiceError>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<GetPromptResult, ServiceError>>,
+{
+    retry_with_backoff(
+        max_retries,
+        PROMPT_RETRY_BASE_DELAY,
+        |err: &ServiceError| {
+            matches!(
+                err,
+                ServiceError::TransportSend(_) | ServiceError::TransportClosed | ServiceError::Timeout { .. }
+            )
+        },
+        get_prompt,
+    )
+    .await
 }
 
 type DisplayTaskJoinHandle = JoinHandle<Result<(), eyre::Report>>;
 type LoadingStatusSender = tokio::sync::mpsc::Sender<LoadingMsg>;
 
+/// Counterpart of [spawn_display_task] that forwards loading progress as [LoadingEvent]s rather
+/// than drawing it.
+fn spawn_event_task(
+    total: usize,
+    disabled_servers: Vec<(String, CustomToolConfig)>,
+    events: LoadingEventSender,
+) -> (Option<DisplayTaskJoinHandle>, Option<LoadingStatusSender>) {
+    if total == 0 && disabled_servers.is_empty() {
+        return (None, None);
+    }
+
+    let (tx, mut rx) = tokio::sync::mpsc::channel::<LoadingMsg>(50);
+    let task = tokio::task::spawn(async move {
+        // The receiving end going away only means nobody is interested in the events anymore
+        for (name, _) in disabled_servers {
+            let _ = events.send(LoadingEvent::Disabled { name });
+        }
+        if total > 0 {
+            let _ = events.send(LoadingEvent::Start { total });
+        }
+
+        while let Some(msg) = rx.recv().await {
+            let event = match msg {
+                LoadingMsg::Done { name, time } => LoadingEvent::Done { name, time },
+                LoadingMsg::Warn { name, msg, time } => LoadingEvent::Warn {
+                    name,
+                    msg: msg.to_string(),
+                    time,
+                },
+                LoadingMsg::Error { name, msg, time } => LoadingEvent::Error {
+                    name,
+                    msg: msg.to_string(),
+                    time,
+                },
+                LoadingMsg::SignInNotice { name } => LoadingEvent::SignInRequired { name },
+                LoadingMsg::ServerStderr { name, line } => LoadingEvent::Stderr { name, line },
+                LoadingMsg::Terminate { still_loading } => {
+                    let _ = events.send(LoadingEvent::Finished { still_loading });
+                    break;
+                },
+            };
+            let _ = events.send(event);
+        }
+
+        Ok(())
+    });
+
+    (Some(task), Some(tx))
+}
+
+/// Whether `server_name` is enabled with an identical config in both `old` and `new`, in which case
+/// its running client can be kept across an agent swap.
+fn server_unchanged(
+    old: &HashMap<String, CustomToolConfig>,
+    new: &HashMap<String, CustomToolConfig>,
+    server_name: &str,
+) -> bool {
+    match (old.get(server_name), new.get(server_name)) {
+        (Some(old), Some(new)) => old == new && !new.disabled,
+        _ => false,
+    }
+}
+
 /// This function spawns a background task whose sole responsibility is to listen for incoming
 /// server loading status and display them to the output.
 /// It returns a join handle to the task as well as a sender with which loading status is to be
@@ -1200,6 +1995,16 @@ fn spawn_display_task(
                                 )?;
                                 queue_oauth_message(&name, &mut output)?;
                             },
+                            LoadingMsg::ServerStderr { name, line } => {
+                                execute!(
+                                    output,
+                                    cursor::MoveToColumn(0),
+                                    cursor::MoveUp(1),
+                                    terminal::Clear(terminal::ClearType::CurrentLine),
+                                )?;
+                                queue_stderr_message(&name, &line, &mut output)?;
+                                queue_init_message(spinner_logo_idx, complete, failed, total, &mut output)?;
+                            },
                         },
                         Err(_e) => {
                             spinner_logo_idx = (spinner_logo_idx + 1) % SPINNER_CHARS.len();
@@ -1248,6 +2053,7 @@ fn spawn_orchestrator_task(
     pending: Arc<RwLock<HashSet<String>>>,
     agent: Arc<Mutex<Agent>>,
     database: Database,
+    duration_format: DurationFormat,
     regex: Regex,
     notify_weak: std::sync::Weak<Notify>,
     load_record: Arc<Mutex<HashMap<String, Vec<LoadingRecord>>>>,
@@ -1335,6 +2141,7 @@ fn spawn_orchestrator_task(
             pending: &Arc<RwLock<HashSet<String>>>,
             agent: &Arc<Mutex<Agent>>,
             database: &Database,
+            duration_format: DurationFormat,
             conv_id: &str,
             regex: &Regex,
             telemetry_clone: &TelemetryThread,
@@ -1362,7 +2169,7 @@ fn spawn_orchestrator_task(
                         .remove(&server_name)
                         .map_or("0.0".to_owned(), |init_time| {
                             let time_taken = (std::time::Instant::now() - init_time).as_secs_f64().abs();
-                            format!("{:.2}", time_taken)
+                            duration_format.format(time_taken)
                         });
                     pending.write().await.remove(&server_name);
 
@@ -1375,7 +2182,7 @@ fn spawn_orchestrator_task(
                         Err(_) => vec![],
                     };
 
-                    let (tool_filter, alias_list) = {
+                    let (tool_filter, alias_list, hidden_tools) = {
                         let agent_lock = agent.lock().await;
 
                         // We will assume all tools are allowed if the tool list consists of 1
@@ -1405,20 +2212,23 @@ fn spawn_orchestrator_task(
                             }
                         };
 
-                        let server_prefix = format!("@{server_name}");
-                        let alias_list = agent_lock.tool_aliases.iter().fold(
-                            HashMap::<HostToolName, ModelToolName>::new(),
-                            |mut acc, (full_path, model_tool_name)| {
-                                if full_path.starts_with(&server_prefix) {
-                                    if let Some((_, host_tool_name)) = full_path.split_once(MCP_SERVER_TOOL_DELIMITER) {
-                                        acc.insert(host_tool_name.to_string(), model_tool_name.clone());
-                                    }
-                                }
-                                acc
-                            },
-                        );
+                        let alias_list = server_tool_aliases(&agent_lock.tool_aliases, &server_name);
+
+                        let hidden_tools = agent_lock
+                            .mcp_servers
+                            .mcp_servers
+                            .get(&server_name)
+                            .map(|config| {
+                                config
+                                    .tools
+                                    .iter()
+                                    .filter(|(_, tool_config)| tool_config.hidden)
+                                    .map(|(host_tool_name, _)| host_tool_name.clone())
+                                    .collect::<HashSet<HostToolName>>()
+                            })
+                            .unwrap_or_default();
 
-                        (tool_filter, alias_list)
+                        (tool_filter, alias_list, hidden_tools)
                     };
 
                     match result {
@@ -1443,9 +2253,17 @@ fn spawn_orchestrator_task(
                                     description: v.description.as_ref().map(|d| d.to_string()).unwrap_or_default(),
                                     input_schema: crate::cli::chat::tools::InputSchema(v.schema_as_json_value()),
                                     tool_origin: ToolOrigin::Native,
+                                    // MCP has no dedicated field for these, so use the JSON Schema
+                                    // `examples` keyword of the input schema when present.
+                                    examples: v
+                                        .input_schema
+                                        .get("examples")
+                                        .and_then(|examples| examples.as_array())
+                                        .cloned(),
                                 })
                                 .filter(|spec| tool_filter.should_include(&spec.name))
                                 .collect::<Vec<_>>();
+                            record_stale_aliases(load_record, &server_name, &alias_list, &result_tools).await;
                             let mut sanitized_mapping = HashMap::<ModelToolName, ToolInfo>::new();
                             let process_result = process_tool_specs(
                                 database,
@@ -1457,6 +2275,7 @@ fn spawn_orchestrator_task(
                                 regex,
                                 telemetry_clone,
                                 &result_tools,
+                                &hidden_tools,
                             )
                             .await;
 
@@ -1579,30 +2398,19 @@ fn spawn_orchestrator_task(
                             error!("Received prompt list result from {server_name} without a peer. Ignoring.");
                             return;
                         }
-                        // We first need to clear all the PromptGets that are associated with
-                        // this server because PromptsListResult is declaring what is available
-                        // (and not the diff)
-                        prompts
-                            .values_mut()
-                            .for_each(|bundles| bundles.retain(|bundle| bundle.server_name != server_name));
-
-                        // And then we update them with the new comers
-                        for prompt in prompt_list_result.prompts {
-                            prompts
-                                .entry(prompt.name.clone())
-                                .and_modify(|bundles| {
-                                    bundles.push(PromptBundle {
-                                        server_name: server_name.clone(),
-                                        prompt_get: prompt.clone(),
-                                    });
-                                })
-                                .or_insert_with(|| {
-                                    vec![PromptBundle {
-                                        server_name: server_name.clone(),
-                                        prompt_get: prompt,
-                                    }]
-                                });
-                        }
+                        let prompt_prefix = agent
+                            .lock()
+                            .await
+                            .mcp_servers
+                            .mcp_servers
+                            .get(&server_name)
+                            .and_then(|config| config.prompt_prefix.clone());
+                        update_server_prompts(
+                            prompts,
+                            &server_name,
+                            prompt_prefix.as_deref(),
+                            prompt_list_result.prompts,
+                        );
                     },
                     Err(e) => {
                         error!("Error fetching prompts from server {server_name}: {:?}", e);
@@ -1654,10 +2462,27 @@ fn spawn_orchestrator_task(
                         }
                     }
                 },
-                UpdateEventMessage::InitStart { server_name, .. } => {
-                    pending.write().await.insert(server_name.clone());
-                    loading_servers.insert(server_name, std::time::Instant::now());
-                },
+                UpdateEventMessage::StderrLine { server_name, line } => {
+                    let show_stderr = database
+                        .settings
+                        .get_bool(Setting::McpShowServerStderr)
+                        .unwrap_or(false);
+                    if show_stderr && loading_servers.contains_key(&server_name) {
+                        if let Some(sender) = &loading_status_sender {
+                            let msg = LoadingMsg::ServerStderr {
+                                name: server_name,
+                                line,
+                            };
+                            if sender.send(msg).await.is_err() {
+                                loading_status_sender.take();
+                            }
+                        }
+                    }
+                },
+                UpdateEventMessage::InitStart { server_name, .. } => {
+                    pending.write().await.insert(server_name.clone());
+                    loading_servers.insert(server_name, std::time::Instant::now());
+                },
                 UpdateEventMessage::Deinit { server_name, .. } => {
                     // Only prompts are stored here so we'll just be clearing that
                     // In the future if we are also storing tools, we need to make sure that
@@ -1684,6 +2509,7 @@ fn spawn_orchestrator_task(
                             &pending,
                             &agent,
                             &database,
+                            duration_format,
                             conv_id.as_str(),
                             &regex,
                             &telemetry,
@@ -1707,6 +2533,130 @@ fn spawn_orchestrator_task(
     });
 }
 
+/// Replaces the prompts offered by `server_name` in the orchestrator's prompt map. Prompts are
+/// keyed by name, or by `prefix:name` when the server is configured with a `promptPrefix`.
+fn update_server_prompts(
+    prompts: &mut HashMap<String, Vec<PromptBundle>>,
+    server_name: &str,
+    prompt_prefix: Option<&str>,
+    server_prompts: Vec<Prompt>,
+) {
+    // We first need to clear all the PromptGets that are associated with
+    // this server because PromptsListResult is declaring what is available
+    // (and not the diff)
+    prompts
+        .values_mut()
+        .for_each(|bundles| bundles.retain(|bundle| bundle.server_name != server_name));
+    prompts.retain(|_, bundles| !bundles.is_empty());
+
+    // And then we update them with the new comers
+    for prompt in server_prompts {
+        let key = match prompt_prefix {
+            Some(prefix) => format!("{prefix}:{}", prompt.name),
+            None => prompt.name.clone(),
+        };
+        prompts.entry(key).or_default().push(PromptBundle {
+            server_name: server_name.to_string(),
+            prompt_get: prompt,
+        });
+    }
+}
+
+fn prompts_view(prompts: &HashMap<String, Vec<PromptBundle>>) -> Vec<PromptView> {
+    let mut views = prompts
+        .iter()
+        .flat_map(|(name, bundles)| {
+            bundles.iter().map(move |bundle| PromptView {
+                server: bundle.server_name.clone(),
+                name: name.clone(),
+                args: bundle
+                    .prompt_get
+                    .arguments
+                    .iter()
+                    .flatten()
+                    .map(|arg| arg.name.clone())
+                    .collect(),
+                required_args: bundle
+                    .prompt_get
+                    .arguments
+                    .iter()
+                    .flatten()
+                    .filter(|arg| arg.required == Some(true))
+                    .map(|arg| arg.name.clone())
+                    .collect(),
+                ambiguous: bundles.len() > 1,
+            })
+        })
+        .collect::<Vec<_>>();
+    views.sort_by(|a, b| a.name.cmp(&b.name).then_with(|| a.server.cmp(&b.server)));
+    views
+}
+
+/// The aliases in `tool_aliases` for tools of `server_name`, keyed by host tool name
+fn server_tool_aliases(
+    tool_aliases: &HashMap<OriginalToolName, String>,
+    server_name: &str,
+) -> HashMap<HostToolName, ModelToolName> {
+    let server_prefix = format!("@{server_name}");
+    tool_aliases
+        .iter()
+        .filter_map(
+            |(full_path, model_tool_name)| match full_path.split_once(MCP_SERVER_TOOL_DELIMITER) {
+                Some((prefix, host_tool_name)) if prefix == server_prefix => {
+                    Some((host_tool_name.to_string(), model_tool_name.clone()))
+                },
+                _ => None,
+            },
+        )
+        .collect()
+}
+
+/// Records a warning for aliases of `server_name` whose host tool is not among the tools the
+/// server listed, since those aliases have no effect.
+async fn record_stale_aliases(
+    load_record: &Arc<Mutex<HashMap<String, Vec<LoadingRecord>>>>,
+    server_name: &str,
+    alias_list: &HashMap<HostToolName, ModelToolName>,
+    result_tools: &[String],
+) {
+    let mut stale = alias_list
+        .keys()
+        .filter(|host_tool_name| !result_tools.contains(host_tool_name))
+        .map(|host_tool_name| format!("@{server_name}{MCP_SERVER_TOOL_DELIMITER}{host_tool_name}"))
+        .collect::<Vec<_>>();
+    stale.sort();
+
+    // The tool list is refreshed whenever the server reports a change, so the warning from the
+    // previous refresh is replaced rather than added to.
+    let prefix = format!("Tool aliases refer to tools that {server_name} does not provide and have no effect: ");
+    let mut load_record = load_record.lock().await;
+    let previous = load_record.get(server_name).and_then(|records| {
+        records
+            .iter()
+            .position(|record| matches!(record, LoadingRecord::Warn(msg) if msg.starts_with(&prefix)))
+    });
+    if stale.is_empty() {
+        if let (Some(records), Some(previous)) = (load_record.get_mut(server_name), previous) {
+            records.remove(previous);
+        }
+        return;
+    }
+
+    let msg = format!("{prefix}{}", stale.join(", "));
+    let records = load_record.entry(server_name.to_string()).or_default();
+    match previous {
+        Some(previous) if matches!(&records[previous], LoadingRecord::Warn(prev) if *prev == msg) => {},
+        Some(previous) => {
+            warn!("{msg}");
+            records[previous] = LoadingRecord::Warn(msg);
+        },
+        None => {
+            warn!("{msg}");
+            records.push(LoadingRecord::Warn(msg));
+        },
+    }
+}
+
 #[allow(clippy::too_many_arguments)]
 async fn process_tool_specs(
     database: &Database,
@@ -1718,6 +2668,7 @@ async fn process_tool_specs(
     regex: &Regex,
     telemetry: &TelemetryThread,
     result_tools: &[String],
+    hidden_tools: &HashSet<HostToolName>,
 ) -> eyre::Result<()> {
     // Tools are subjected to the following validations:
     // 1. ^[a-zA-Z][a-zA-Z0-9_]*$,
@@ -1727,7 +2678,11 @@ async fn process_tool_specs(
     // For non-compliance due to point 1, we shall change it on behalf of the users.
     // For the rest, we simply throw a warning and reject the tool.
     let mut out_of_spec_tool_names = Vec::<OutOfSpecName>::new();
-    let mut hasher = DefaultHasher::new();
+    let sanitize_strategy = SanitizeStrategy::from_settings(&database.settings);
+    let prefix_descriptions = database
+        .settings
+        .get_bool(Setting::McpPrefixDescriptions)
+        .unwrap_or(false);
     let mut number_of_tools = 0_usize;
 
     let number_of_tools_in_mcp_server = result_tools.len();
@@ -1741,7 +2696,7 @@ async fn process_tool_specs(
     for spec in specs.iter_mut() {
         let model_tool_name = alias_list.get(&spec.name).cloned().unwrap_or({
             if !regex.is_match(&spec.name) {
-                let mut sn = sanitize_name(spec.name.clone(), regex, &mut hasher);
+                let mut sn = sanitize_strategy.sanitize(spec.name.clone(), regex);
                 while tn_map.contains_key(&sn) {
                     sn.push('1');
                 }
@@ -1763,6 +2718,9 @@ async fn process_tool_specs(
         });
         spec.name = model_tool_name;
         spec.tool_origin = ToolOrigin::McpServer(server_name.to_string());
+        if prefix_descriptions {
+            spec.description = format!("[{server_name}] {}", spec.description);
+        }
         number_of_tools += 1;
     }
     // Native origin is the default, and since this function never reads native tools, if we still
@@ -1787,6 +2745,13 @@ async fn process_tool_specs(
             number_of_tools_in_mcp_server,
         )
         .await;
+    // Hidden tools stay in the tn_map so they can still be invoked, but are not offered to the
+    // model.
+    specs.retain(|spec| {
+        tn_map
+            .get(&spec.name)
+            .is_none_or(|info| !hidden_tools.contains(&info.host_tool_name))
+    });
     // Tool name translation. This is beyond of the scope of what is
     // considered a "server load". Reasoning being:
     // - Failures here are not related to server load
@@ -1819,6 +2784,168 @@ async fn process_tool_specs(
     }
 }
 
+/// Strategy used to transform a tool name that does not comply with [VALID_TOOL_NAME] into one
+/// that does. This is selected via `mcp.toolNameSanitization` and defaults to
+/// [SanitizeStrategy::Strip].
+///
+/// All strategies are deterministic: a given name always sanitizes to the same output, regardless
+/// of which names have been sanitized before it. Collisions between sanitized names are resolved
+/// separately in [process_tool_specs].
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum SanitizeStrategy {
+    /// Drops every character that is not alphanumeric or an underscore, e.g. `a.b-c` becomes `abc`.
+    #[default]
+    Strip,
+    /// Maps every character that is not alphanumeric or an underscore to an underscore, e.g.
+    /// `a.b-c` becomes `a_b_c`.
+    Underscore,
+}
+
+impl SanitizeStrategy {
+    pub fn from_settings(settings: &Settings) -> Self {
+        match settings.get_string(Setting::McpToolNameSanitization).as_deref() {
+            None | Some("strip") => Self::Strip,
+            Some("underscore") => Self::Underscore,
+            Some(other) => {
+                warn!(
+                    strategy = other,
+                    "unknown mcp.toolNameSanitization, expected strip or underscore"
+                );
+                Self::Strip
+            },
+        }
+    }
+
+    pub fn sanitize(&self, orig: String, regex: &Regex) -> String {
+        // A fresh hasher per name is what keeps the fallback names deterministic
+        let mut hasher = DefaultHasher::new();
+        match self {
+            Self::Strip => sanitize_name(orig, regex, &mut hasher),
+            Self::Underscore => sanitize_name_with_underscores(orig, regex, &mut hasher),
+        }
+    }
+}
+
+/// Locale-dependent formatting of the durations shown in the server loading display, controlled
+/// by the `ui.locale` and `ui.durationPrecision` settings. Defaults to `1.23`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct DurationFormat {
+    decimal_separator: char,
+    precision: usize,
+}
+
+impl Default for DurationFormat {
+    fn default() -> Self {
+        Self {
+            decimal_separator: '.',
+            precision: 2,
+        }
+    }
+}
+
+impl DurationFormat {
+    /// Languages that write decimals with a comma, e.g. `1,23`.
+    const COMMA_LANGUAGES: &[&str] = &[
+        "bg", "ca", "cs", "da", "de", "el", "es", "et", "fi", "fr", "hr", "hu", "id", "it", "lt", "lv", "nb", "nl",
+        "nn", "no", "pl", "pt", "ro", "ru", "sk", "sl", "sr", "sv", "tr", "uk", "vi",
+    ];
+
+    /// The `system` locale is read from `LC_ALL`, `LC_NUMERIC` or `LANG` in `env`.
+    pub fn from_settings(settings: &Settings, env: &Env) -> Self {
+        let mut format = match settings.get_string(Setting::UiLocale).as_deref() {
+            Some("system") => ["LC_ALL", "LC_NUMERIC", "LANG"]
+                .iter()
+                .find_map(|var| env.get(var).ok().filter(|v| !v.is_empty()))
+                .map_or_else(Self::default, |locale| Self::from_locale(&locale)),
+            Some(locale) => Self::from_locale(locale),
+            None => Self::default(),
+        };
+        if let Some(precision) = settings.get_int(Setting::UiDurationPrecision) {
+            format.precision = precision.clamp(0, 9) as usize;
+        }
+        format
+    }
+
+    /// Picks the decimal separator for a locale such as `de-DE` or `fr_FR.UTF-8`.
+    pub fn from_locale(locale: &str) -> Self {
+        let language = locale
+            .split(['-', '_', '.', '@'])
+            .next()
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        Self {
+            decimal_separator: if Self::COMMA_LANGUAGES.contains(&language.as_str()) {
+                ','
+            } else {
+                '.'
+            },
+            ..Self::default()
+        }
+    }
+
+    /// Formats a number of seconds, without the unit.
+    pub fn format(&self, secs: f64) -> String {
+        let formatted = format!("{secs:.*}", self.precision);
+        match self.decimal_separator {
+            '.' => formatted,
+            separator => formatted.replace('.', &separator.to_string()),
+        }
+    }
+}
+
+/// Builds the tool result text for tool parameters that failed to deserialize, naming the offending
+/// field and the parameters the tool requires so the model can correct its next attempt.
+fn explain_parse_error(
+    tool_name: &str,
+    error: &serde_path_to_error::Error<serde_json::Error>,
+    spec: Option<&ToolSpec>,
+) -> String {
+    let path = error.path().to_string();
+    let mut msg = format!("Failed to validate parameters for tool \"{tool_name}\": ");
+    if path == "." {
+        msg.push_str(&error.inner().to_string());
+    } else {
+        msg.push_str(&format!("field `{path}` is invalid: {}", error.inner()));
+    }
+    msg.push('.');
+
+    let required = spec
+        .and_then(|spec| spec.input_schema.0.get("required"))
+        .and_then(|required| required.as_array())
+        .map(|required| required.iter().filter_map(|v| v.as_str()).collect::<Vec<_>>())
+        .unwrap_or_default();
+    if !required.is_empty() {
+        msg.push_str(&format!(" Required parameters: {}.", required.join(", ")));
+    }
+    msg.push_str(" Check the tool's input schema and retry with corrected parameters.");
+    msg
+}
+
+/// Returns the candidate closest to `name` by edit distance, if any is close enough to plausibly be
+/// what was meant.
+fn closest_tool_name<'a>(name: &str, candidates: impl Iterator<Item = &'a str>) -> Option<&'a str> {
+    let max_distance = (name.chars().count() / 3).max(2);
+    candidates
+        .map(|candidate| (levenshtein(name, candidate), candidate))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min()
+        .map(|(_, candidate)| candidate)
+}
+
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut prev = (0..=b.len()).collect::<Vec<_>>();
+    for (i, ca) in a.chars().enumerate() {
+        let mut curr = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = prev[j] + usize::from(ca != *cb);
+            curr[j + 1] = substitution.min(prev[j + 1] + 1).min(curr[j] + 1);
+        }
+        prev = curr;
+    }
+    prev[b.len()]
+}
+
 fn sanitize_name(orig: String, regex: &regex::Regex, hasher: &mut impl Hasher) -> String {
     if regex.is_match(&orig) && !orig.contains(NAMESPACE_DELIMITER) {
         return orig;
@@ -1845,6 +2972,29 @@ fn sanitize_name(orig: String, regex: &regex::Regex, hasher: &mut impl Hasher) -
     }
 }
 
+fn sanitize_name_with_underscores(orig: String, regex: &regex::Regex, hasher: &mut impl Hasher) -> String {
+    if regex.is_match(&orig) && !orig.contains(NAMESPACE_DELIMITER) {
+        return orig;
+    }
+    let mut sanitized = orig
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '_' { c } else { '_' })
+        .collect::<String>();
+    // Runs of underscores would otherwise be able to reintroduce the namespace delimiter
+    while sanitized.contains(NAMESPACE_DELIMITER) {
+        sanitized = sanitized.replace(NAMESPACE_DELIMITER, "__");
+    }
+    let sanitized = sanitized.trim_start_matches('_');
+    match sanitized.chars().next() {
+        Some(c) if c.is_ascii_alphabetic() => sanitized.to_string(),
+        Some(_) => format!("a{}", sanitized),
+        None => {
+            hasher.write(orig.as_bytes());
+            format!("a{:03}", hasher.finish() % 1000)
+        },
+    }
+}
+
 fn queue_success_message(name: &str, time_taken: &str, output: &mut impl Write) -> eyre::Result<()> {
     Ok(queue!(
         output,
@@ -1981,6 +3131,15 @@ fn queue_warn_message(name: &str, msg: &eyre::Report, time: &str, output: &mut i
     )?)
 }
 
+fn queue_stderr_message(name: &str, line: &str, output: &mut impl Write) -> eyre::Result<()> {
+    Ok(queue!(
+        output,
+        style::SetAttribute(style::Attribute::Dim),
+        style::Print(format!("  {name}: {line}\n")),
+        style::SetAttribute(style::Attribute::Reset),
+    )?)
+}
+
 fn queue_disabled_message(name: &str, output: &mut impl Write) -> eyre::Result<()> {
     Ok(queue!(
         output,
@@ -2030,8 +3189,64 @@ fn queue_prompts_load_error_message(name: &str, msg: &eyre::Report, output: &mut
 
 #[cfg(test)]
 mod tests {
+    use rmcp::model::PromptArgument;
+
     use super::*;
 
+    fn test_spec(name: &str) -> ToolSpec {
+        ToolSpec {
+            name: name.to_string(),
+            description: format!("{name} description"),
+            input_schema: crate::cli::chat::tools::InputSchema(serde_json::json!({ "type": "object" })),
+            tool_origin: ToolOrigin::Native,
+            examples: None,
+        }
+    }
+
+    /// Validates `specs` as listed by `server_name` the way the loader does, returning their tool
+    /// name map.
+    async fn process_test_specs(
+        os: &Os,
+        server_name: &str,
+        specs: &mut Vec<ToolSpec>,
+        alias_list: &HashMap<HostToolName, ModelToolName>,
+        hidden_tools: &HashSet<HostToolName>,
+    ) -> HashMap<ModelToolName, ToolInfo> {
+        let regex = regex::Regex::new(VALID_TOOL_NAME).unwrap();
+        let result_tools = specs.iter().map(|spec| spec.name.clone()).collect::<Vec<_>>();
+        let mut tn_map = HashMap::new();
+        process_tool_specs(
+            &os.database,
+            "conv_id",
+            server_name,
+            specs,
+            &mut tn_map,
+            alias_list,
+            &regex,
+            &os.telemetry,
+            &result_tools,
+            hidden_tools,
+        )
+        .await
+        .unwrap();
+        tn_map
+    }
+
+    /// Hands processed specs to `tool_manager` as if `server_name` had just finished loading.
+    async fn add_test_specs(
+        tool_manager: &mut ToolManager,
+        server_name: &str,
+        tn_map: HashMap<ModelToolName, ToolInfo>,
+        specs: Vec<ToolSpec>,
+    ) {
+        tool_manager
+            .new_tool_specs
+            .lock()
+            .await
+            .insert(server_name.to_string(), (tn_map, specs));
+        tool_manager.update().await;
+    }
+
     #[test]
     fn test_sanitize_server_name() {
         let regex = regex::Regex::new(VALID_TOOL_NAME).unwrap();
@@ -2052,4 +3267,1058 @@ mod tests {
         let sanitized = sanitize_name(with_delim, &regex, &mut hasher);
         assert_eq!(sanitized, "abc");
     }
+
+    #[tokio::test]
+    async fn test_unknown_tool_suggests_closest_name() {
+        let mut manager = ToolManager::default();
+        for name in ["github___search_issues", "github___create_issue", "fetch___fetch"] {
+            manager.tn_map.insert(name.to_string(), ToolInfo::default());
+        }
+
+        let Err(result) = manager
+            .get_tool_from_tool_use(AssistantToolUse {
+                id: "1".to_string(),
+                name: "github___serch_issues".to_string(),
+                ..Default::default()
+            })
+            .await
+        else {
+            panic!("unknown tool should be rejected");
+        };
+        let ToolResultContentBlock::Text(text) = &result.content[0] else {
+            panic!("expected a text explanation");
+        };
+        assert!(text.contains("Did you mean \"github___search_issues\"?"), "{text}");
+
+        // Nothing is suggested when no tool is close
+        let Err(result) = manager
+            .get_tool_from_tool_use(AssistantToolUse {
+                id: "2".to_string(),
+                name: "completely_different".to_string(),
+                ..Default::default()
+            })
+            .await
+        else {
+            panic!("unknown tool should be rejected");
+        };
+        let ToolResultContentBlock::Text(text) = &result.content[0] else {
+            panic!("expected a text explanation");
+        };
+        assert!(!text.contains("Did you mean"), "{text}");
+    }
+
+    #[tokio::test]
+    async fn test_parse_error_names_field() {
+        let mut manager = ToolManager::default();
+        let Err(result) = manager
+            .get_tool_from_tool_use(AssistantToolUse {
+                id: "1".to_string(),
+                name: "use_aws".to_string(),
+                args: serde_json::json!({
+                    "service_name": "s3",
+                    "operation_name": "list-buckets",
+                    "region": 42,
+                }),
+                ..Default::default()
+            })
+            .await
+        else {
+            panic!("invalid parameters should be rejected");
+        };
+        let ToolResultContentBlock::Text(text) = &result.content[0] else {
+            panic!("expected a text explanation");
+        };
+        assert!(text.contains("\"use_aws\""), "{text}");
+        assert!(text.contains("field `region`"), "{text}");
+    }
+
+    #[test]
+    fn test_levenshtein() {
+        assert_eq!(levenshtein("fs_read", "fs_read"), 0);
+        assert_eq!(levenshtein("fs_reed", "fs_read"), 1);
+        assert_eq!(levenshtein("", "abc"), 3);
+        assert_eq!(levenshtein("kitten", "sitting"), 3);
+    }
+
+    #[test]
+    fn test_prompts_view() {
+        let mut prompts = HashMap::new();
+        let arg = |name: &str, required: bool| PromptArgument {
+            name: name.to_string(),
+            description: None,
+            required: Some(required),
+        };
+        update_server_prompts(&mut prompts, "server_a", None, vec![
+            Prompt::new(
+                "review",
+                None::<String>,
+                Some(vec![arg("file", true), arg("focus", false)]),
+            ),
+            Prompt::new("explain", None::<String>, None),
+        ]);
+        update_server_prompts(&mut prompts, "server_b", None, vec![Prompt::new(
+            "review",
+            None::<String>,
+            Some(vec![arg("diff", true)]),
+        )]);
+
+        let view = prompts_view(&prompts);
+        assert_eq!(view, vec![
+            PromptView {
+                server: "server_a".to_string(),
+                name: "explain".to_string(),
+                args: vec![],
+                required_args: vec![],
+                ambiguous: false,
+            },
+            PromptView {
+                server: "server_a".to_string(),
+                name: "review".to_string(),
+                args: vec!["file".to_string(), "focus".to_string()],
+                required_args: vec!["file".to_string()],
+                ambiguous: true,
+            },
+            PromptView {
+                server: "server_b".to_string(),
+                name: "review".to_string(),
+                args: vec!["diff".to_string()],
+                required_args: vec!["diff".to_string()],
+                ambiguous: true,
+            },
+        ]);
+    }
+
+    #[test]
+    fn test_update_server_prompts_with_prefix() {
+        let mut prompts = HashMap::new();
+        let review = || vec![Prompt::new("review", None::<String>, None)];
+        update_server_prompts(&mut prompts, "server_a", Some("a"), review());
+        update_server_prompts(&mut prompts, "server_b", Some("b"), review());
+        update_server_prompts(&mut prompts, "server_c", None, review());
+
+        let mut keys = prompts.keys().cloned().collect::<Vec<_>>();
+        keys.sort();
+        assert_eq!(keys, ["a:review", "b:review", "review"]);
+        assert!(prompts.values().all(|bundles| bundles.len() == 1));
+        assert_eq!(prompts["a:review"][0].server_name, "server_a");
+        assert_eq!(prompts["b:review"][0].prompt_get.name, "review");
+
+        // A new list from a server replaces its previous prompts
+        update_server_prompts(&mut prompts, "server_a", Some("a"), vec![]);
+        assert!(!prompts.contains_key("a:review"));
+        assert_eq!(prompts.len(), 2);
+    }
+
+    #[test]
+    fn test_prompt_prefix_config() {
+        let config = serde_json::from_value::<CustomToolConfig>(serde_json::json!({
+            "command": "server",
+            "promptPrefix": "gh"
+        }))
+        .unwrap();
+        assert_eq!(config.prompt_prefix.as_deref(), Some("gh"));
+    }
+
+    #[tokio::test]
+    async fn test_load_tools_include_dummy() {
+        let mut os = Os::new().await.unwrap();
+
+        let mut tool_manager = ToolManager::default();
+        let schema = tool_manager.load_tools(&mut os, &mut vec![]).await.unwrap();
+        assert!(schema.contains_key(DUMMY_TOOL_NAME));
+
+        os.database
+            .settings
+            .set(Setting::ToolsIncludeDummy, false)
+            .await
+            .unwrap();
+        let mut tool_manager = ToolManager::default();
+        let schema = tool_manager.load_tools(&mut os, &mut vec![]).await.unwrap();
+        assert!(!schema.contains_key(DUMMY_TOOL_NAME));
+    }
+
+    #[test]
+    fn test_display_tool_name() {
+        let mut manager = ToolManager::default();
+        let model_tool_name = format!("github{NAMESPACE_DELIMITER}search_issues");
+        manager.tn_map.insert(model_tool_name.clone(), ToolInfo {
+            server_name: "github".to_string(),
+            host_tool_name: "search-issues".to_string(),
+        });
+
+        assert_eq!(manager.display_tool_name(&model_tool_name), "github/search-issues");
+        // Names not known to the tool manager are shown as is
+        assert_eq!(manager.display_tool_name("fetch___fetch"), "fetch___fetch");
+        assert_eq!(manager.display_tool_name("fs_read"), "fs_read");
+        // The name sent to the model is unchanged
+        assert!(manager.tn_map.contains_key("github___search_issues"));
+
+        manager.tool_name_separator = Some(" > ".to_string());
+        assert_eq!(manager.display_tool_name(&model_tool_name), "github > search-issues");
+    }
+
+    #[test]
+    fn test_find_mcp_tool() {
+        let mut manager = ToolManager::default();
+        manager
+            .tn_map
+            .insert(format!("github{NAMESPACE_DELIMITER}search_issues"), ToolInfo {
+                server_name: "github".to_string(),
+                host_tool_name: "search-issues".to_string(),
+            });
+
+        let by_host_name = manager.find_mcp_tool("search-issues").unwrap();
+        assert_eq!(by_host_name.server_name, "github");
+        let by_display_name = manager.find_mcp_tool("github/search-issues").unwrap();
+        assert_eq!(by_display_name.host_tool_name, "search-issues");
+        assert!(manager.find_mcp_tool("gitlab/search-issues").is_none());
+        assert!(manager.find_mcp_tool("github___search_issues").is_none());
+
+        manager.tool_name_separator = Some(" > ".to_string());
+        assert!(manager.find_mcp_tool("github > search-issues").is_some());
+    }
+
+    #[tokio::test]
+    async fn test_native_tool_status() {
+        let mut os = Os::new().await.unwrap();
+        os.database
+            .settings
+            .set(Setting::EnabledKnowledge, false)
+            .await
+            .unwrap();
+
+        let mut tool_manager = ToolManager::default();
+        let schema = tool_manager.load_tools(&mut os, &mut vec![]).await.unwrap();
+        let status = tool_manager.native_tool_status();
+        assert!(!schema.contains_key("knowledge"));
+        assert_eq!(
+            status.get("knowledge"),
+            Some(&NativeToolStatus::Disabled {
+                reason: "disabled, enable it with: q settings chat.enableKnowledge true".to_string()
+            })
+        );
+        assert_eq!(status.get("fs_read"), Some(&NativeToolStatus::Enabled));
+
+        // Tools left out of the agent's tools are reported as such
+        tool_manager.agent.lock().await.tools = vec!["fs_read".to_string()];
+        tool_manager.load_tools(&mut os, &mut vec![]).await.unwrap();
+        let status = tool_manager.native_tool_status();
+        assert_eq!(status.get("fs_read"), Some(&NativeToolStatus::Enabled));
+        assert!(matches!(
+            status.get("fs_write"),
+            Some(NativeToolStatus::Disabled { reason }) if reason.starts_with("not in the tools of agent")
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_hidden_tool_excluded_from_schema() {
+        let os = Os::new().await.unwrap();
+        let mut specs = vec![test_spec("visible"), test_spec("internal")];
+        let hidden_tools = HashSet::from(["internal".to_string()]);
+        let tn_map = process_test_specs(&os, "server", &mut specs, &HashMap::new(), &hidden_tools).await;
+
+        let mut tool_manager = ToolManager::default();
+        add_test_specs(&mut tool_manager, "server", tn_map, specs).await;
+
+        assert!(tool_manager.schema.contains_key("visible"));
+        assert!(!tool_manager.schema.contains_key("internal"));
+        assert!(tool_manager.tn_map.contains_key("visible"));
+        assert!(tool_manager.tn_map.contains_key("internal"));
+    }
+
+    #[tokio::test]
+    async fn test_prefix_descriptions() {
+        let mut os = Os::new().await.unwrap();
+        for (enabled, expected) in [(false, "search description"), (true, "[github] search description")] {
+            os.database
+                .settings
+                .set(Setting::McpPrefixDescriptions, enabled)
+                .await
+                .unwrap();
+            let mut specs = vec![test_spec("search")];
+            process_test_specs(&os, "github", &mut specs, &HashMap::new(), &HashSet::new()).await;
+            assert_eq!(specs[0].description, expected);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_export_tools_json() {
+        let os = Os::new().await.unwrap();
+        let mut tool_manager = ToolManager::default();
+        tool_manager.schema.insert("fs_read".to_string(), test_spec("fs_read"));
+        let mut specs = vec![test_spec("search_issues"), test_spec("create_issue")];
+        let aliases = HashMap::from([("create_issue".to_string(), "new_issue".to_string())]);
+        let tn_map = process_test_specs(&os, "github", &mut specs, &aliases, &HashSet::new()).await;
+        add_test_specs(&mut tool_manager, "github", tn_map, specs).await;
+
+        assert_eq!(
+            tool_manager.export_tools_json(),
+            serde_json::json!([
+                {
+                    "name": "fs_read",
+                    "host_name": "fs_read",
+                    "origin": "native",
+                    "aliased": false,
+                    "input_schema": { "type": "object" },
+                },
+                {
+                    "name": "new_issue",
+                    "host_name": "create_issue",
+                    "origin": "github",
+                    "aliased": true,
+                    "input_schema": { "type": "object" },
+                },
+                {
+                    "name": "search_issues",
+                    "host_name": "search_issues",
+                    "origin": "github",
+                    "aliased": false,
+                    "input_schema": { "type": "object" },
+                },
+            ])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_stale_tool_aliases() {
+        let os = Os::new().await.unwrap();
+        let tool_aliases = serde_json::from_value::<HashMap<OriginalToolName, String>>(serde_json::json!({
+            "@github/create_issue": "new_issue",
+            "@github/delete_repo": "nuke_repo",
+            "@githubx/missing": "other",
+        }))
+        .unwrap();
+        let alias_list = server_tool_aliases(&tool_aliases, "github");
+        assert_eq!(
+            alias_list,
+            HashMap::from([
+                ("create_issue".to_string(), "new_issue".to_string()),
+                ("delete_repo".to_string(), "nuke_repo".to_string()),
+            ])
+        );
+
+        let tool_manager = ToolManager::default();
+        let result_tools = vec!["create_issue".to_string(), "search_issues".to_string()];
+        record_stale_aliases(&tool_manager.mcp_load_record, "github", &alias_list, &result_tools).await;
+        {
+            let records = tool_manager.mcp_load_record.lock().await;
+            assert!(matches!(
+                records.get("github").map(Vec::as_slice),
+                Some([LoadingRecord::Warn(msg)]) if msg.ends_with("have no effect: @github/delete_repo")
+            ));
+        }
+
+        // The valid alias still applies
+        let mut specs = vec![test_spec("create_issue"), test_spec("search_issues")];
+        let tn_map = process_test_specs(&os, "github", &mut specs, &alias_list, &HashSet::new()).await;
+        assert!(tn_map.contains_key("new_issue"));
+        assert!(tn_map.contains_key("search_issues"));
+
+        // Refreshing the tool list doesn't add another warning, and a fixed alias clears it
+        record_stale_aliases(&tool_manager.mcp_load_record, "github", &alias_list, &result_tools).await;
+        assert_eq!(tool_manager.mcp_load_record.lock().await["github"].len(), 1);
+        let all_tools = vec!["create_issue".to_string(), "delete_repo".to_string()];
+        record_stale_aliases(&tool_manager.mcp_load_record, "github", &alias_list, &all_tools).await;
+        assert!(tool_manager.mcp_load_record.lock().await["github"].is_empty());
+
+        // Nothing is recorded when every alias matches a tool
+        let tool_manager = ToolManager::default();
+        let result_tools = vec!["create_issue".to_string(), "delete_repo".to_string()];
+        record_stale_aliases(&tool_manager.mcp_load_record, "github", &alias_list, &result_tools).await;
+        assert!(tool_manager.mcp_load_record.lock().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_dump_schema() {
+        let mut os = Os::new().await.unwrap();
+        let mut tool_manager = ToolManager::default();
+        tool_manager.schema = tool_manager.load_tools(&mut os, &mut vec![]).await.unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("schema.json");
+        for spec in tool_manager.schema.values_mut() {
+            spec.examples = Some(vec![serde_json::json!({ "example": true })]);
+        }
+        tool_manager.dump_schema_path = Some(path.clone());
+        tool_manager.dump_schema_if_requested();
+
+        let dumped: HashMap<String, serde_json::Value> =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert!(!dumped.is_empty());
+        assert_eq!(dumped.len(), tool_manager.schema.len());
+        for (name, spec) in &tool_manager.schema {
+            let dumped_spec = &dumped[name];
+            assert_eq!(dumped_spec["name"], spec.name.as_str());
+            assert_eq!(dumped_spec["description"], spec.description.as_str());
+            assert_eq!(dumped_spec["input_schema"], spec.input_schema.0);
+            assert!(dumped_spec.get("examples").is_none());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_max_mcp_tools() {
+        let os = Os::new().await.unwrap();
+        let mut tool_manager = ToolManager {
+            max_mcp_tools: Some(5),
+            ..Default::default()
+        };
+        for (server_name, count) in [("small", 2), ("flood", 10)] {
+            let mut specs = (0..count).map(|i| test_spec(&format!("{server_name}_{i}"))).collect();
+            let tn_map = process_test_specs(&os, server_name, &mut specs, &HashMap::new(), &HashSet::new()).await;
+            add_test_specs(&mut tool_manager, server_name, tn_map, specs).await;
+        }
+
+        assert_eq!(tool_manager.tn_map.len(), 5);
+        assert_eq!(tool_manager.schema.len(), 5);
+        assert!(tool_manager.schema.contains_key("small_0"));
+        assert!(tool_manager.schema.contains_key("small_1"));
+        let flood = tool_manager
+            .schema
+            .values()
+            .filter(|spec| spec.tool_origin == ToolOrigin::McpServer("flood".to_string()))
+            .count();
+        assert_eq!(flood, 3);
+
+        let records = tool_manager.mcp_load_record.lock().await;
+        assert!(matches!(
+            records.get("flood").and_then(|r| r.last()),
+            Some(LoadingRecord::Warn(msg)) if msg.contains("7 tools from flood were dropped")
+        ));
+        assert!(!records.contains_key("small"));
+    }
+
+    #[tokio::test]
+    async fn test_explain_tool_availability() {
+        let spec = |server_name: &str, name: &str| ToolSpec {
+            tool_origin: ToolOrigin::McpServer(server_name.to_string()),
+            ..test_spec(name)
+        };
+        let info = |server_name: &str, host_tool_name: &str| ToolInfo {
+            server_name: server_name.to_string(),
+            host_tool_name: host_tool_name.to_string(),
+        };
+
+        let mut tool_manager = ToolManager::default();
+        for server_name in ["alpha", "beta"] {
+            let config = serde_json::from_value::<CustomToolConfig>(serde_json::json!({
+                "command": server_name,
+                "tools": { "internal": { "hidden": true } }
+            }))
+            .unwrap();
+            tool_manager
+                .agent
+                .lock()
+                .await
+                .mcp_servers
+                .mcp_servers
+                .insert(server_name.to_string(), config);
+        }
+        for (server_name, tools) in [
+            ("alpha", vec![("shared", "shared")]),
+            ("beta", vec![("shared", "shared"), ("beta___status", "status")]),
+        ] {
+            let tn_map = tools
+                .iter()
+                .map(|&(model_tool_name, host_tool_name)| {
+                    (model_tool_name.to_string(), info(server_name, host_tool_name))
+                })
+                .collect();
+            let specs = tools
+                .iter()
+                .map(|&(model_tool_name, _)| spec(server_name, model_tool_name))
+                .collect();
+            add_test_specs(&mut tool_manager, server_name, tn_map, specs).await;
+        }
+
+        assert_eq!(
+            tool_manager.explain_tool_availability("alpha/shared").await,
+            ToolAvailability::Available
+        );
+        assert_eq!(
+            tool_manager.explain_tool_availability("beta___status").await,
+            ToolAvailability::Available
+        );
+        assert_eq!(
+            tool_manager.explain_tool_availability("@beta/shared").await,
+            ToolAvailability::Conflict {
+                conflicting_server: "alpha".to_string()
+            }
+        );
+        assert_eq!(
+            tool_manager.explain_tool_availability("alpha/internal").await,
+            ToolAvailability::Hidden {
+                server: "alpha".to_string()
+            }
+        );
+        assert_eq!(
+            tool_manager.explain_tool_availability("alpha/missing").await,
+            ToolAvailability::NotOffered {
+                server: "alpha".to_string()
+            }
+        );
+        assert_eq!(
+            tool_manager.explain_tool_availability("gamma/tool").await,
+            ToolAvailability::ServerNotConfigured {
+                server: "gamma".to_string()
+            }
+        );
+        assert_eq!(
+            tool_manager.explain_tool_availability("nothing").await,
+            ToolAvailability::NotFound
+        );
+
+        // The agent's tools are checked before anything about the server's tools
+        tool_manager.agent.lock().await.tools = vec!["@alpha".to_string()];
+        assert_eq!(
+            tool_manager.explain_tool_availability("beta/shared").await,
+            ToolAvailability::NotInAgentTools {
+                agent: crate::cli::agent::DEFAULT_AGENT_NAME.to_string()
+            }
+        );
+        assert_eq!(
+            tool_manager.explain_tool_availability("alpha/shared").await,
+            ToolAvailability::Available
+        );
+
+        // Built-in tools left out of the agent's tools are denied with the agent named as the reason
+        let os = Os::new().await.unwrap();
+        let names = ["fs_read".to_string(), "fs_write".to_string()];
+        tool_manager.agent.lock().await.tools = vec!["@alpha".to_string(), "fs_read".to_string()];
+        tool_manager.native_tool_status = native_tool_status(&os, &*tool_manager.agent.lock().await, &names);
+        assert_eq!(
+            tool_manager.explain_tool_availability("fs_read").await,
+            ToolAvailability::Available
+        );
+        assert_eq!(
+            tool_manager.explain_tool_availability("fs_write").await,
+            ToolAvailability::NativeToolDisabled {
+                reason: format!("not in the tools of agent {}", crate::cli::agent::DEFAULT_AGENT_NAME)
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_display_shows_server_stderr() {
+        #[derive(Clone, Default)]
+        struct SharedBuf(Arc<std::sync::Mutex<Vec<u8>>>);
+        impl Write for SharedBuf {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                self.0.lock().unwrap().write(buf)
+            }
+
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let buf = SharedBuf::default();
+        let (task, tx) = spawn_display_task(true, 1, vec![], Box::new(buf.clone()));
+        let tx = tx.unwrap();
+        tx.send(LoadingMsg::ServerStderr {
+            name: "slow".to_string(),
+            line: "downloading model...".to_string(),
+        })
+        .await
+        .unwrap();
+        tx.send(LoadingMsg::Terminate {
+            still_loading: vec!["slow".to_string()],
+        })
+        .await
+        .unwrap();
+        task.unwrap().await.unwrap().unwrap();
+
+        let output = String::from_utf8(buf.0.lock().unwrap().clone()).unwrap();
+        assert!(output.contains("slow: downloading model..."));
+    }
+
+    #[tokio::test]
+    async fn test_swap_agent_keeps_unchanged_servers() {
+        let mut os = Os::new().await.unwrap();
+        let config = |command: &str| -> CustomToolConfig {
+            serde_json::from_value(serde_json::json!({ "command": command })).unwrap()
+        };
+        let agent = |servers: &[(&str, &str)]| Agent {
+            mcp_servers: McpServerConfig {
+                mcp_servers: servers
+                    .iter()
+                    .map(|&(name, command)| (name.to_string(), config(command)))
+                    .collect(),
+            },
+            ..Default::default()
+        };
+        let pending_client = || InitializedMcpClient::Pending(tokio::spawn(std::future::pending()));
+
+        let old_agent = agent(&[
+            ("shared", "shared-server"),
+            ("changed", "v1"),
+            ("removed", "old-server"),
+        ]);
+        let new_agent = agent(&[("shared", "shared-server"), ("changed", "v2")]);
+        let mut tool_manager = ToolManager {
+            conversation_id: "conv_id".to_string(),
+            agent: Arc::new(Mutex::new(old_agent)),
+            messenger_builder: Some(ServerMessengerBuilder::new(20).1),
+            clients: [
+                ("shared".to_string(), pending_client()),
+                ("changed".to_string(), pending_client()),
+                ("removed".to_string(), pending_client()),
+            ]
+            .into_iter()
+            .collect(),
+            ..Default::default()
+        };
+        tool_manager
+            .mcp_load_record
+            .lock()
+            .await
+            .insert("shared".to_string(), vec![LoadingRecord::Success("loaded".to_string())]);
+        let handle_id = |tool_manager: &ToolManager, name: &str| match tool_manager.clients.get(name) {
+            Some(InitializedMcpClient::Pending(handle)) => Some(handle.id()),
+            _ => None,
+        };
+        let shared_id = handle_id(&tool_manager, "shared").unwrap();
+
+        tool_manager
+            .swap_agent(&mut os, &mut std::io::sink(), &new_agent)
+            .await
+            .unwrap();
+
+        // The unchanged server keeps its client, while the changed one is started again
+        assert_eq!(handle_id(&tool_manager, "shared"), Some(shared_id));
+        assert!(!tool_manager.clients.contains_key("removed"));
+        assert!(tool_manager.mcp_load_record.lock().await.contains_key("shared"));
+        assert_eq!(*tool_manager.agent.lock().await, new_agent);
+    }
+
+    #[tokio::test]
+    async fn test_loading_events() {
+        let (events_tx, mut events_rx) = tokio::sync::mpsc::unbounded_channel();
+        let disabled: CustomToolConfig =
+            serde_json::from_value(serde_json::json!({ "command": "off", "disabled": true })).unwrap();
+        let (task, tx) = spawn_event_task(2, vec![("off".to_string(), disabled)], events_tx);
+        let tx = tx.unwrap();
+
+        for msg in [
+            LoadingMsg::Done {
+                name: "good".to_string(),
+                time: "0.12".to_string(),
+            },
+            LoadingMsg::Error {
+                name: "bad".to_string(),
+                msg: eyre::eyre!("crashed"),
+                time: "0.34".to_string(),
+            },
+            LoadingMsg::Terminate { still_loading: vec![] },
+        ] {
+            tx.send(msg).await.unwrap();
+        }
+        task.unwrap().await.unwrap().unwrap();
+
+        let mut events = Vec::new();
+        while let Ok(event) = events_rx.try_recv() {
+            events.push(event);
+        }
+        assert_eq!(events, [
+            LoadingEvent::Disabled {
+                name: "off".to_string()
+            },
+            LoadingEvent::Start { total: 2 },
+            LoadingEvent::Done {
+                name: "good".to_string(),
+                time: "0.12".to_string()
+            },
+            LoadingEvent::Error {
+                name: "bad".to_string(),
+                msg: "crashed".to_string(),
+                time: "0.34".to_string()
+            },
+            LoadingEvent::Finished { still_loading: vec![] },
+        ]);
+
+        // Nothing is spawned when there is nothing to load
+        let (events_tx, _events_rx) = tokio::sync::mpsc::unbounded_channel();
+        let (task, tx) = spawn_event_task(0, vec![], events_tx);
+        assert!(task.is_none() && tx.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_builder_loading_events() {
+        let mut os = Os::new().await.unwrap();
+        let broken: CustomToolConfig =
+            serde_json::from_value(serde_json::json!({ "command": "q-test-server-that-does-not-exist" })).unwrap();
+        let agent = Agent {
+            mcp_servers: McpServerConfig {
+                mcp_servers: [("broken".to_string(), broken)].into_iter().collect(),
+            },
+            ..Default::default()
+        };
+
+        let (events_tx, mut events_rx) = tokio::sync::mpsc::unbounded_channel();
+        let (prompt_request_sender, prompt_request_receiver) = tokio::sync::broadcast::channel::<PromptQuery>(5);
+        let (prompt_response_sender, prompt_response_receiver) =
+            tokio::sync::broadcast::channel::<PromptQueryResult>(5);
+        let mut tool_manager = ToolManagerBuilder::default()
+            .prompt_query_result_sender(prompt_response_sender)
+            .prompt_query_receiver(prompt_request_receiver)
+            .prompt_query_sender(prompt_request_sender)
+            .prompt_query_result_receiver(prompt_response_receiver)
+            .conversation_id("conv_id")
+            .agent(agent)
+            .loading_event_sender(events_tx)
+            .build(&mut os, Box::new(std::io::sink()), true)
+            .await
+            .unwrap();
+
+        assert_eq!(events_rx.recv().await, Some(LoadingEvent::Start { total: 1 }));
+        assert!(matches!(
+            events_rx.recv().await,
+            Some(LoadingEvent::Error { name, .. }) if name == "broken"
+        ));
+
+        tool_manager.load_tools(&mut os, &mut std::io::sink()).await.unwrap();
+        assert!(matches!(events_rx.recv().await, Some(LoadingEvent::Finished { .. })));
+    }
+
+    #[tokio::test]
+    async fn test_duplicate_server_definition_is_recorded() {
+        use crate::cli::chat::tools::custom_tool::McpConfigSource;
+
+        let mut os = Os::new().await.unwrap();
+        let mut config: CustomToolConfig =
+            serde_json::from_value(serde_json::json!({ "command": "q-test-server-that-does-not-exist" })).unwrap();
+        config.source = McpConfigSource::WorkspaceMcpJson;
+        config.overridden_sources = vec![McpConfigSource::GlobalMcpJson];
+        let agent = Agent {
+            mcp_servers: McpServerConfig {
+                mcp_servers: [("shared".to_string(), config)].into_iter().collect(),
+            },
+            ..Default::default()
+        };
+
+        let (events_tx, _events_rx) = tokio::sync::mpsc::unbounded_channel();
+        let (prompt_request_sender, prompt_request_receiver) = tokio::sync::broadcast::channel::<PromptQuery>(5);
+        let (prompt_response_sender, prompt_response_receiver) =
+            tokio::sync::broadcast::channel::<PromptQueryResult>(5);
+        let tool_manager = ToolManagerBuilder::default()
+            .prompt_query_result_sender(prompt_response_sender)
+            .prompt_query_receiver(prompt_request_receiver)
+            .prompt_query_sender(prompt_request_sender)
+            .prompt_query_result_receiver(prompt_response_receiver)
+            .conversation_id("conv_id")
+            .agent(agent)
+            .loading_event_sender(events_tx)
+            .build(&mut os, Box::new(std::io::sink()), true)
+            .await
+            .unwrap();
+
+        let records = tool_manager.mcp_load_record.lock().await;
+        assert!(matches!(
+            records["shared"].as_slice(),
+            [LoadingRecord::Warn(msg)] if msg.ends_with("Using the definition from the workspace mcp.json.")
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_loading_servers_are_sorted() {
+        let mut os = Os::new().await.unwrap();
+        let config = |disabled: bool| -> CustomToolConfig {
+            serde_json::from_value(serde_json::json!({
+                "command": "q-test-server-that-does-not-exist",
+                "disabled": disabled,
+            }))
+            .unwrap()
+        };
+        let agent = Agent {
+            mcp_servers: McpServerConfig {
+                mcp_servers: [
+                    ("zeta", true),
+                    ("mu", false),
+                    ("alpha", true),
+                    ("omega", false),
+                    ("beta", false),
+                ]
+                .into_iter()
+                .map(|(name, disabled)| (name.to_string(), config(disabled)))
+                .collect(),
+            },
+            ..Default::default()
+        };
+
+        let (events_tx, mut events_rx) = tokio::sync::mpsc::unbounded_channel();
+        let (prompt_request_sender, prompt_request_receiver) = tokio::sync::broadcast::channel::<PromptQuery>(5);
+        let (prompt_response_sender, prompt_response_receiver) =
+            tokio::sync::broadcast::channel::<PromptQueryResult>(5);
+        ToolManagerBuilder::default()
+            .prompt_query_result_sender(prompt_response_sender)
+            .prompt_query_receiver(prompt_request_receiver)
+            .prompt_query_sender(prompt_request_sender)
+            .prompt_query_result_receiver(prompt_response_receiver)
+            .conversation_id("conv_id")
+            .agent(agent)
+            .loading_event_sender(events_tx)
+            .build(&mut os, Box::new(std::io::sink()), true)
+            .await
+            .unwrap();
+
+        let mut rendered = vec![];
+        for _ in 0..6 {
+            rendered.push(match events_rx.recv().await.unwrap() {
+                LoadingEvent::Disabled { name } => format!("disabled {name}"),
+                LoadingEvent::Start { total } => format!("start {total}"),
+                LoadingEvent::Error { name, .. } => format!("error {name}"),
+                other => panic!("unexpected event: {other:?}"),
+            });
+        }
+        assert_eq!(rendered, [
+            "disabled alpha",
+            "disabled zeta",
+            "start 3",
+            "error beta",
+            "error mu",
+            "error omega",
+        ]);
+    }
+
+    #[tokio::test]
+    async fn test_new_server_policy() {
+        let mut os = Os::new().await.unwrap();
+        let config = || -> CustomToolConfig {
+            serde_json::from_value(serde_json::json!({ "command": "q-test-server-that-does-not-exist" })).unwrap()
+        };
+        let servers = |names: &[&str]| -> HashMap<String, CustomToolConfig> {
+            names.iter().map(|&name| (name.to_string(), config())).collect()
+        };
+
+        // Under the default policy, new servers are enabled and remembered
+        let mut mcp_servers = servers(&["seen"]);
+        assert!(apply_new_server_policy(&os, &mut mcp_servers).is_empty());
+        assert!(!mcp_servers["seen"].disabled);
+
+        os.database
+            .settings
+            .set(Setting::McpNewServerPolicy, "disabled")
+            .await
+            .unwrap();
+        let mut mcp_servers = servers(&["seen", "fresh"]);
+        assert_eq!(apply_new_server_policy(&os, &mut mcp_servers), ["fresh"]);
+        assert!(!mcp_servers["seen"].disabled);
+        assert!(mcp_servers["fresh"].disabled);
+
+        // The server stays disabled until it is approved
+        let mut mcp_servers = servers(&["fresh"]);
+        assert_eq!(apply_new_server_policy(&os, &mut mcp_servers), ["fresh"]);
+        os.database.add_known_mcp_servers(["fresh".to_string()]).unwrap();
+        let mut mcp_servers = servers(&["fresh"]);
+        assert!(apply_new_server_policy(&os, &mut mcp_servers).is_empty());
+        assert!(!mcp_servers["fresh"].disabled);
+
+        // Servers disabled in their config are not reported as awaiting approval
+        let mut mcp_servers = servers(&["off"]);
+        mcp_servers.get_mut("off").unwrap().disabled = true;
+        assert!(apply_new_server_policy(&os, &mut mcp_servers).is_empty());
+        assert!(!os.database.get_known_mcp_servers().unwrap().contains("off"));
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_load_is_recorded() {
+        let tool_manager = ToolManager::default();
+        tool_manager
+            .mcp_load_record
+            .lock()
+            .await
+            .insert("ready".to_string(), vec![LoadingRecord::Success("loaded".to_string())]);
+        tool_manager.pending_clients.write().await.insert("slow".to_string());
+
+        let still_loading = tool_manager.pending_clients().await;
+        tool_manager.record_cancelled_loads(&still_loading).await;
+        // Cancelling again does not stack records
+        tool_manager.record_cancelled_loads(&still_loading).await;
+
+        {
+            let records = tool_manager.mcp_load_record.lock().await;
+            assert!(matches!(records["ready"].as_slice(), [LoadingRecord::Success(_)]));
+            assert!(matches!(
+                records["slow"].as_slice(),
+                [LoadingRecord::Cancelled(msg)] if msg.contains("slow")
+            ));
+        }
+        assert_eq!(tool_manager.server_load_counts().await, ServerLoadCounts {
+            loaded: 1,
+            failed: 0,
+            pending: 1,
+        });
+    }
+
+    #[test]
+    fn test_default_sanitize_strategy() {
+        let regex = regex::Regex::new(VALID_TOOL_NAME).unwrap();
+        let strategy = SanitizeStrategy::from_settings(&Settings::default());
+        assert_eq!(strategy, SanitizeStrategy::Strip);
+
+        assert_eq!(strategy.sanitize("my.tool-name".to_string(), &regex), "mytoolname");
+        assert_eq!(strategy.sanitize("good_name".to_string(), &regex), "good_name");
+
+        // Fallback names must not depend on what was sanitized before
+        let first = strategy.sanitize("@@@".to_string(), &regex);
+        strategy.sanitize("!!!".to_string(), &regex);
+        assert_eq!(strategy.sanitize("@@@".to_string(), &regex), first);
+        assert!(regex.is_match(&first));
+    }
+
+    #[tokio::test]
+    async fn test_duration_format() {
+        assert_eq!(
+            DurationFormat::from_settings(&Settings::default(), &Env::new()).format(1.234),
+            "1.23"
+        );
+        assert_eq!(DurationFormat::from_locale("en_US.UTF-8").format(1.234), "1.23");
+
+        let mut settings = Settings::default();
+        settings.set(Setting::UiLocale, "de-DE").await.unwrap();
+        let format = DurationFormat::from_settings(&settings, &Env::new());
+        assert_eq!(format.format(1.234), "1,23");
+
+        let mut output = Vec::new();
+        queue_success_message("server", &format.format(1.234), &mut output).unwrap();
+        assert!(String::from_utf8(output).unwrap().contains("1,23 s"));
+
+        settings.set(Setting::UiDurationPrecision, 1).await.unwrap();
+        assert_eq!(
+            DurationFormat::from_settings(&settings, &Env::new()).format(1.26),
+            "1,3"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_duration_format_system_locale() {
+        let mut settings = Settings::default();
+        settings.set(Setting::UiLocale, "system").await.unwrap();
+
+        let env = Env::from_slice(&[("LANG", "de_DE.UTF-8")]);
+        assert_eq!(DurationFormat::from_settings(&settings, &env).format(1.234), "1,23");
+
+        let env = Env::from_slice(&[("LC_ALL", "en_US.UTF-8"), ("LANG", "de_DE.UTF-8")]);
+        assert_eq!(DurationFormat::from_settings(&settings, &env).format(1.234), "1.23");
+
+        assert_eq!(
+            DurationFormat::from_settings(&settings, &Env::from_slice(&[])).format(1.234),
+            "1.23"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_underscore_sanitize_strategy() {
+        let regex = regex::Regex::new(VALID_TOOL_NAME).unwrap();
+        let mut settings = Settings::default();
+        settings
+            .set(Setting::McpToolNameSanitization, "underscore")
+            .await
+            .unwrap();
+        let strategy = SanitizeStrategy::from_settings(&settings);
+        assert_eq!(strategy, SanitizeStrategy::Underscore);
+
+        assert_eq!(strategy.sanitize("my.tool.name".to_string(), &regex), "my_tool_name");
+        assert_eq!(
+            strategy.sanitize("@awslabs.cdk-mcp-server".to_string(), &regex),
+            "awslabs_cdk_mcp_server"
+        );
+        assert_eq!(strategy.sanitize("1.tool".to_string(), &regex), "a1_tool");
+
+        let sanitized = strategy.sanitize(format!("a{}b..c", NAMESPACE_DELIMITER), &regex);
+        assert!(!sanitized.contains(NAMESPACE_DELIMITER));
+        assert!(regex.is_match(&sanitized));
+
+        let all_bad = strategy.sanitize("@@@".to_string(), &regex);
+        assert!(regex.is_match(&all_bad));
+
+        // Unknown strategies fall back to the default
+        settings
+            .set(Setting::McpToolNameSanitization, "underscores")
+            .await
+            .unwrap();
+        assert_eq!(SanitizeStrategy::from_settings(&settings), SanitizeStrategy::Strip);
+    }
+
+    #[test]
+    fn test_prompt_arguments() {
+        let schema = [
+            PromptArgument {
+                name: "language".to_string(),
+                description: None,
+                required: Some(true),
+            },
+            PromptArgument {
+                name: "style".to_string(),
+                description: None,
+                required: None,
+            },
+        ];
+        let values = |values: &[&str]| Some(values.iter().map(|v| (*v).to_string()).collect::<Vec<_>>());
+
+        // Values are assigned to the declared arguments in order, and optional ones may be omitted
+        let arguments = prompt_arguments("review", Some(&schema), values(&["rust", "terse"]))
+            .unwrap()
+            .unwrap();
+        assert_eq!(arguments["language"], "rust");
+        assert_eq!(arguments["style"], "terse");
+        let arguments = prompt_arguments("review", Some(&schema), values(&["rust"]))
+            .unwrap()
+            .unwrap();
+        assert_eq!(arguments.len(), 1);
+        assert!(prompt_arguments("review", None, None).unwrap().is_none());
+
+        // A required argument is missing
+        let err = prompt_arguments("review", Some(&schema), None).unwrap_err();
+        assert!(matches!(&err, GetPromptError::InvalidArguments(name, _) if name == "review"));
+        assert!(
+            err.to_string().contains("missing required argument(s): language"),
+            "{err}"
+        );
+
+        // More values than declared arguments
+        let err = prompt_arguments("review", Some(&schema), values(&["rust", "terse", "extra"])).unwrap_err();
+        assert!(err.to_string().contains("unexpected: extra"), "{err}");
+        let err = prompt_arguments("review", None, values(&["extra"])).unwrap_err();
+        assert!(err.to_string().contains("takes no arguments"), "{err}");
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_get_prompt_with_retry() {
+        use std::cell::Cell;
+
+        let prompt = GetPromptResult {
+            description: Some("a prompt".to_string()),
+            messages: vec![],
+        };
+
+        // A transport failure is retried and the prompt from the next attempt returned
+        let calls = Cell::new(0);
+        let result = get_prompt_with_retry(2, || {
+            calls.set(calls.get() + 1);
+            let result = if calls.get() == 1 {
+                Err(ServiceError::TransportClosed)
+            } else {
+                Ok(prompt.clone())
+            };
+            async { result }
+        })
+        .await;
+        assert_eq!(result.unwrap(), prompt);
+        assert_eq!(calls.get(), 2);
+
+        // Errors from the server, such as an unknown prompt, are not retried
+        calls.set(0);
+        let result = get_prompt_with_retry(2, || {
+            calls.set(calls.get() + 1);
+            async {
+                Err(ServiceError::McpError(rmcp::ErrorData::invalid_params(
+                    "prompt not found",
+                    None,
+                )))
+            }
+        })
+        .await;
+        assert!(matches!(result, Err(ServiceError::McpError(_))));
+        assert_eq!(calls.get(), 1);
+    }
 }
@@ -68,9 +68,72 @@ pub struct CustomToolConfig {
     /// A boolean flag to denote whether or not to load this mcp server
     #[serde(default)]
     pub disabled: bool,
+    /// Per tool configuration, keyed by the name of the tool as reported by the server
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub tools: HashMap<String, McpToolConfig>,
+    /// Prefix for the prompts offered by this server, which are then exposed as `prefix:name`
+    #[serde(default, rename = "promptPrefix", skip_serializing_if = "Option::is_none")]
+    pub prompt_prefix: Option<String>,
     /// A flag to denote whether this is a server from the legacy mcp.json
     #[serde(skip)]
     pub is_from_legacy_mcp_json: bool,
+    /// The config file this definition was read from
+    #[serde(skip)]
+    pub source: McpConfigSource,
+    /// Other config files that also define this server, whose definitions were ignored in favor
+    /// of this one
+    #[serde(skip)]
+    pub overridden_sources: Vec<McpConfigSource>,
+}
+
+/// A config file MCP servers are defined in. When several define a server of the same name, the
+/// agent config takes precedence over the workspace mcp.json, which takes precedence over the
+/// global mcp.json.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum McpConfigSource {
+    #[default]
+    Agent,
+    WorkspaceMcpJson,
+    GlobalMcpJson,
+}
+
+impl std::fmt::Display for McpConfigSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Agent => write!(f, "the agent config"),
+            Self::WorkspaceMcpJson => write!(f, "the workspace mcp.json"),
+            Self::GlobalMcpJson => write!(f, "the global mcp.json"),
+        }
+    }
+}
+
+impl CustomToolConfig {
+    /// A warning naming the config files that also define server `name`, and the one whose
+    /// definition is used, or [None] if this is the only definition.
+    pub fn duplicate_definition_warning(&self, name: &str) -> Option<String> {
+        if self.overridden_sources.is_empty() {
+            return None;
+        }
+        let overridden = self
+            .overridden_sources
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join(" and ");
+        Some(format!(
+            "Server {name} is defined in both {} and {overridden}. Using the definition from {}.",
+            self.source, self.source
+        ))
+    }
+}
+
+/// Configuration for an individual tool provided by an mcp server
+#[derive(Clone, Serialize, Deserialize, Debug, Default, Eq, PartialEq, JsonSchema)]
+pub struct McpToolConfig {
+    /// A boolean flag to keep the tool invocable while excluding it from the tools offered to the
+    /// model
+    #[serde(default)]
+    pub hidden: bool,
 }
 
 pub fn default_timeout() -> u64 {
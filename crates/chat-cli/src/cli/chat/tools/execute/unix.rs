@@ -16,6 +16,10 @@ use super::{
     format_output,
 };
 use crate::os::Os;
+use crate::util::process_registry::{
+    EXECUTE_JOB_ENV,
+    ProcessRegistry,
+};
 
 /// Run a bash command on Unix systems.
 /// # Arguments
@@ -34,17 +38,22 @@ pub async fn run_command<W: Write>(
 
     // Set up environment variables with user agent metadata for CloudTrail tracking
     let env_vars = env_vars_with_user_agent(os);
+    let job_id = uuid::Uuid::new_v4().simple().to_string();
 
     // We need to maintain a handle on stderr and stdout, but pipe it to the terminal as well
     let mut child = tokio::process::Command::new(shell)
         .arg("-c")
         .arg(command)
         .envs(env_vars)
+        .env(EXECUTE_JOB_ENV, &job_id)
         .stdin(Stdio::inherit())
         .stdout(Stdio::piped())
         .stderr(Stdio::piped())
         .spawn()
         .wrap_err_with(|| format!("Unable to spawn command '{}'", command))?;
+    let _tracked = child
+        .id()
+        .map(|pid| ProcessRegistry::global().track_job(pid, job_id, command));
 
     let stdout_final: String;
     let stderr_final: String;
@@ -190,4 +199,28 @@ mod tests {
             panic!("Expected JSON output");
         }
     }
+
+    #[tokio::test]
+    async fn test_dotenv_reaches_command() {
+        let mut os = Os::new().await.unwrap();
+        os.fs
+            .write("/.env", "Q_DOTENV_TEST_VAR=\"from dotenv\"\nHOME=/overridden\n")
+            .await
+            .unwrap();
+        assert_eq!(os.load_dotenv(std::path::Path::new("/"), false).await.unwrap(), 1);
+
+        let out = serde_json::from_value::<ExecuteCommand>(serde_json::json!({
+            "command": "echo $Q_DOTENV_TEST_VAR",
+        }))
+        .unwrap()
+        .invoke(&os, &mut std::io::sink())
+        .await
+        .unwrap();
+        let OutputKind::Json(json) = out.output else {
+            panic!("Expected JSON output");
+        };
+        assert_eq!(json.get("stdout").unwrap(), "from dotenv");
+        // Variables that are already set are kept
+        assert!(!os.session_env.contains_key("HOME"));
+    }
 }
@@ -16,6 +16,10 @@ use super::{
     format_output,
 };
 use crate::os::Os;
+use crate::util::process_registry::{
+    EXECUTE_JOB_ENV,
+    ProcessRegistry,
+};
 
 /// Run a command on Windows using cmd.exe.
 /// # Arguments
@@ -32,17 +36,22 @@ pub async fn run_command<W: Write>(
 ) -> Result<CommandResult> {
     // Set up environment variables with user agent metadata for CloudTrail tracking
     let env_vars = env_vars_with_user_agent(os);
+    let job_id = uuid::Uuid::new_v4().simple().to_string();
 
     // We need to maintain a handle on stderr and stdout, but pipe it to the terminal as well
     let mut child = tokio::process::Command::new("cmd")
         .arg("/C")
         .arg(command)
         .envs(env_vars)
+        .env(EXECUTE_JOB_ENV, &job_id)
         .stdin(Stdio::inherit())
         .stdout(Stdio::piped())
         .stderr(Stdio::piped())
         .spawn()
         .wrap_err_with(|| format!("Unable to spawn command '{}'", command))?;
+    let _tracked = child
+        .id()
+        .map(|pid| ProcessRegistry::global().track_job(pid, job_id, command));
 
     let stdout_final: String;
     let stderr_final: String;
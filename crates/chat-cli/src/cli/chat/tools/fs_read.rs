@@ -1,6 +1,7 @@
 use std::collections::VecDeque;
 use std::fs::Metadata;
 use std::io::Write;
+use std::path::Path;
 
 use crossterm::queue;
 use crossterm::style::{
@@ -11,7 +12,10 @@ use eyre::{
     Result,
     bail,
 };
-use globset::GlobSetBuilder;
+use globset::{
+    GlobSet,
+    GlobSetBuilder,
+};
 use serde::{
     Deserialize,
     Serialize,
@@ -44,6 +48,7 @@ use crate::cli::chat::{
     CONTINUATION_LINE,
     sanitize_unicode_tags,
 };
+use crate::database::settings::Setting;
 use crate::os::Os;
 use crate::util::directories;
 use crate::util::pattern_matching::matches_any_pattern;
@@ -118,6 +123,7 @@ impl FsRead {
         }
 
         let is_in_allowlist = matches_any_pattern(&agent.allowed_tools, "fs_read");
+        let follow_symlinks = follow_symlinks(os);
         match agent.tools_settings.get("fs_read") {
             Some(settings) => {
                 let Settings {
@@ -174,11 +180,20 @@ impl FsRead {
                                 FsReadOperation::Line(FsLine { path, .. })
                                 | FsReadOperation::Directory(FsDirectory { path, .. })
                                 | FsReadOperation::Search(FsSearch { path, .. }) => {
+                                    // A symlink that is followed must not lead to a denied path either
+                                    let target = follow_symlinks
+                                        .then(|| os.fs.canonicalize_sync(sanitize_path_tool_arg(os, path)).ok())
+                                        .flatten();
                                     let Ok(path) = directories::canonicalizes_path(os, path) else {
                                         ask = true;
                                         continue;
                                     };
-                                    let denied_match_set = deny_set.matches(path.as_ref() as &str);
+                                    let mut denied_match_set = deny_set.matches(path.as_ref() as &str);
+                                    if let Some(target) = target {
+                                        denied_match_set.extend(deny_set.matches(target));
+                                        denied_match_set.sort_unstable();
+                                        denied_match_set.dedup();
+                                    }
                                     if !denied_match_set.is_empty() {
                                         let deny_res = PermissionEvalResult::Deny({
                                             denied_match_set
@@ -265,10 +280,10 @@ impl FsRead {
         }
     }
 
-    pub async fn invoke(&self, os: &Os, updates: &mut impl Write) -> Result<InvokeOutput> {
+    pub async fn invoke(&self, os: &Os, updates: &mut impl Write, agent: Option<&Agent>) -> Result<InvokeOutput> {
         if self.operations.len() == 1 {
             // Single operation - return result directly
-            self.operations[0].invoke(os, updates).await
+            self.operations[0].invoke(os, updates, agent).await
         } else {
             // Multiple operations - combine results
             let mut combined_results = Vec::new();
@@ -278,7 +293,7 @@ impl FsRead {
             let mut failed_ops = 0usize;
 
             for (i, op) in self.operations.iter().enumerate() {
-                match op.invoke(os, updates).await {
+                match op.invoke(os, updates, agent).await {
                     Ok(result) => {
                         success_ops += 1;
 
@@ -375,10 +390,10 @@ impl FsReadOperation {
         }
     }
 
-    pub async fn invoke(&self, os: &Os, updates: &mut impl Write) -> Result<InvokeOutput> {
+    pub async fn invoke(&self, os: &Os, updates: &mut impl Write, agent: Option<&Agent>) -> Result<InvokeOutput> {
         match self {
             FsReadOperation::Line(fs_line) => fs_line.invoke(os, updates).await,
-            FsReadOperation::Directory(fs_directory) => fs_directory.invoke(os, updates).await,
+            FsReadOperation::Directory(fs_directory) => fs_directory.invoke(os, updates, agent).await,
             FsReadOperation::Search(fs_search) => fs_search.invoke(os, updates).await,
             FsReadOperation::Image(fs_image) => fs_image.invoke(updates).await,
         }
@@ -400,7 +415,8 @@ impl FsImage {
                 if !is_supported_image_type(&processed_path) {
                     bail!("'{}' is not a supported image type", &processed_path);
                 }
-                let is_file = os.fs.symlink_metadata(&processed_path).await?.is_file();
+                check_symlinks(os, Path::new(&processed_path)).await?;
+                let is_file = os.fs.metadata(&processed_path).await?.is_file();
                 if !is_file {
                     bail!("'{}' is not a file", &processed_path);
                 }
@@ -439,10 +455,15 @@ pub struct FsLine {
     pub path: String,
     pub start_line: Option<i32>,
     pub end_line: Option<i32>,
+    /// Continues a paged read from the `next_cursor` returned by the previous page.
+    pub cursor: Option<usize>,
+    /// Reads the file in pages of at most this many lines.
+    pub page_size: Option<usize>,
 }
 
 impl FsLine {
     const DEFAULT_END_LINE: i32 = -1;
+    const DEFAULT_PAGE_SIZE: usize = 500;
     const DEFAULT_START_LINE: i32 = 1;
 
     pub async fn validate(&mut self, os: &Os) -> Result<()> {
@@ -450,7 +471,8 @@ impl FsLine {
         if !path.exists() {
             bail!("'{}' does not exist", self.path);
         }
-        let is_file = os.fs.symlink_metadata(&path).await?.is_file();
+        check_symlinks(os, &path).await?;
+        let is_file = os.fs.metadata(&path).await?.is_file();
         if !is_file {
             bail!("'{}' is not a file", self.path);
         }
@@ -521,6 +543,10 @@ impl FsLine {
             );
         }
 
+        if self.cursor.is_some() || self.page_size.is_some() {
+            return self.invoke_page(&file_content, start, end, &path, updates);
+        }
+
         // The range should be inclusive on both ends.
         let file_contents = file_content
             .lines()
@@ -553,6 +579,66 @@ time. You tried to read {byte_count} bytes. Try executing with fewer lines speci
         })
     }
 
+    /// Reads a single page of the inclusive line range `start..=end`, beginning at the cursor if
+    /// set. Pages are also cut short so they never exceed [MAX_TOOL_RESPONSE_SIZE].
+    fn invoke_page(
+        &self,
+        file_content: &str,
+        start: usize,
+        end: usize,
+        path: &std::path::Path,
+        updates: &mut impl Write,
+    ) -> Result<InvokeOutput> {
+        let cursor = self.cursor.unwrap_or(start);
+        if cursor < start || cursor > end {
+            bail!(
+                "cursor {} is outside of the requested lines ({}, {})",
+                cursor,
+                start,
+                end
+            );
+        }
+        let page_size = self.page_size.unwrap_or(Self::DEFAULT_PAGE_SIZE).max(1);
+
+        let mut page = Vec::new();
+        let mut byte_count = 0;
+        for line in file_content
+            .lines()
+            .skip(cursor)
+            .take((end - cursor + 1).min(page_size))
+        {
+            if !page.is_empty() && byte_count + line.len() + 1 > MAX_TOOL_RESPONSE_SIZE {
+                break;
+            }
+            byte_count += line.len() + 1;
+            page.push(line);
+        }
+        let next = cursor + page.len();
+        let content = page.join("\n");
+
+        super::queue_function_result(
+            &format!(
+                "Successfully read {} bytes from {} (lines {} to {})",
+                content.len(),
+                path.display(),
+                cursor + 1,
+                next
+            ),
+            updates,
+            false,
+            false,
+        )?;
+
+        Ok(InvokeOutput {
+            output: OutputKind::Json(serde_json::json!({
+                "content": content,
+                "start_line": cursor + 1,
+                "end_line": next,
+                "next_cursor": (next <= end).then_some(next),
+            })),
+        })
+    }
+
     fn start_line(&self) -> i32 {
         self.start_line.unwrap_or(Self::DEFAULT_START_LINE)
     }
@@ -581,7 +667,8 @@ impl FsSearch {
         if !path.exists() {
             bail!("File not found: {}", relative_path);
         }
-        if !os.fs.symlink_metadata(path).await?.is_file() {
+        check_symlinks(os, &path).await?;
+        if !os.fs.metadata(path).await?.is_file() {
             bail!("Path is not a file: {}", relative_path);
         }
         if self.pattern.is_empty() {
@@ -680,7 +767,8 @@ impl FsDirectory {
         if !path.exists() {
             bail!("Directory not found: {}", relative_path);
         }
-        if !os.fs.symlink_metadata(path).await?.is_dir() {
+        check_symlinks(os, &path).await?;
+        if !os.fs.metadata(path).await?.is_dir() {
             bail!("Path is not a directory: {}", relative_path);
         }
         Ok(())
@@ -702,9 +790,12 @@ impl FsDirectory {
         )?)
     }
 
-    pub async fn invoke(&self, os: &Os, updates: &mut impl Write) -> Result<InvokeOutput> {
+    /// Symlinks are only followed into targets outside of the `agent`'s denied paths.
+    pub async fn invoke(&self, os: &Os, updates: &mut impl Write, agent: Option<&Agent>) -> Result<InvokeOutput> {
         let path = sanitize_path_tool_arg(os, &self.path);
         let max_depth = self.depth();
+        let follow_symlinks = follow_symlinks(os);
+        let denied_paths = denied_paths(os, agent);
         debug!(?path, max_depth, "Reading directory at path with depth");
         let mut result = Vec::new();
         let mut dir_queue = VecDeque::new();
@@ -717,7 +808,7 @@ impl FsDirectory {
 
             #[cfg(windows)]
             while let Some(ent) = read_dir.next_entry().await? {
-                let md = ent.metadata().await?;
+                let (md, note) = entry_metadata(os, &ent, follow_symlinks, denied_paths.as_ref()).await?;
 
                 let modified_timestamp = md.modified()?.duration_since(std::time::UNIX_EPOCH)?.as_secs();
                 let datetime = time::OffsetDateTime::from_unix_timestamp(modified_timestamp as i64).unwrap();
@@ -728,11 +819,12 @@ impl FsDirectory {
                     .unwrap();
 
                 result.push(format!(
-                    "{} {} {} {}",
+                    "{} {} {} {}{}",
                     format_ftype(&md),
                     String::from_utf8_lossy(ent.file_name().as_encoded_bytes()),
                     formatted_date,
-                    ent.path().to_string_lossy()
+                    ent.path().to_string_lossy(),
+                    note.unwrap_or_default()
                 ));
 
                 if md.is_dir() && md.is_dir() {
@@ -747,7 +839,7 @@ impl FsDirectory {
                     PermissionsExt,
                 };
 
-                let md = ent.metadata().await?;
+                let (md, note) = entry_metadata(os, &ent, follow_symlinks, denied_paths.as_ref()).await?;
                 let formatted_mode = format_mode(md.permissions().mode()).into_iter().collect::<String>();
 
                 let modified_timestamp = md.modified()?.duration_since(std::time::UNIX_EPOCH)?.as_secs();
@@ -761,7 +853,7 @@ impl FsDirectory {
                 // Mostly copying "The Long Format" from `man ls`.
                 // TODO: query user/group database to convert uid/gid to names?
                 result.push(format!(
-                    "{}{} {} {} {} {} {} {}",
+                    "{}{} {} {} {} {} {} {}{}",
                     format_ftype(&md),
                     formatted_mode,
                     md.nlink(),
@@ -769,7 +861,8 @@ impl FsDirectory {
                     md.gid(),
                     md.size(),
                     formatted_date,
-                    ent.path().to_string_lossy()
+                    ent.path().to_string_lossy(),
+                    note.unwrap_or_default()
                 ));
                 if md.is_dir() {
                     dir_queue.push_back((ent.path(), depth + 1));
@@ -807,6 +900,86 @@ impl FsDirectory {
     }
 }
 
+/// Appended to the directory listing entries of symlinks that were not followed
+const SKIPPED_SYMLINK_NOTE: &str = " (symlink not followed, see tools.fs.followSymlinks)";
+
+/// Appended to the directory listing entries of symlinks that lead to a denied path
+const DENIED_SYMLINK_NOTE: &str = " (symlink to a denied path, not followed)";
+
+fn follow_symlinks(os: &Os) -> bool {
+    os.database
+        .settings
+        .get_bool(Setting::ToolsFsFollowSymlinks)
+        .unwrap_or(false)
+}
+
+/// Fails if `path` is a symlink, or lies beneath a symlink within the current directory, unless
+/// `tools.fs.followSymlinks` is enabled.
+async fn check_symlinks(os: &Os, path: &Path) -> Result<()> {
+    if follow_symlinks(os) {
+        return Ok(());
+    }
+    let cwd = os.fs.chroot_path(os.env.current_dir()?);
+    let path = cwd.join(path);
+    for ancestor in path.ancestors() {
+        if ancestor != path && (ancestor == cwd || !ancestor.starts_with(&cwd)) {
+            break;
+        }
+        if os.fs.symlink_metadata(ancestor).await.is_ok_and(|md| md.is_symlink()) {
+            let subject = if ancestor == path {
+                format!("'{}' is a symlink", path.display())
+            } else {
+                format!("'{}' is beneath the symlink '{}'", path.display(), ancestor.display())
+            };
+            bail!(
+                "{subject}, which is not followed. Enable following symlinks with: q settings {} true",
+                Setting::ToolsFsFollowSymlinks.as_ref()
+            );
+        }
+    }
+    Ok(())
+}
+
+/// The agent's `deniedPaths` for fs_read, if it has any that are valid.
+fn denied_paths(os: &Os, agent: Option<&Agent>) -> Option<GlobSet> {
+    let denied_paths = agent?.tools_settings.get("fs_read")?.get("deniedPaths")?.as_array()?;
+    let mut builder = GlobSetBuilder::new();
+    for path in denied_paths.iter().filter_map(|path| path.as_str()) {
+        let Ok(path) = directories::canonicalizes_path(os, path) else {
+            continue;
+        };
+        if let Err(e) = directories::add_gitignore_globs(&mut builder, path.as_str()) {
+            warn!("Failed to create glob from path given: {path}: {e}. Ignoring.");
+        }
+    }
+    builder.build().ok()
+}
+
+/// Metadata of a directory entry, following it if it is a symlink and `follow_symlinks` is set,
+/// unless it leads to one of the `denied_paths`. Also returns the note to list a symlink that was
+/// not followed with.
+async fn entry_metadata(
+    os: &Os,
+    ent: &tokio::fs::DirEntry,
+    follow_symlinks: bool,
+    denied_paths: Option<&GlobSet>,
+) -> Result<(Metadata, Option<&'static str>)> {
+    let md = ent.metadata().await?;
+    if !md.is_symlink() {
+        return Ok((md, None));
+    }
+    if !follow_symlinks {
+        return Ok((md, Some(SKIPPED_SYMLINK_NOTE)));
+    }
+    if let (Some(denied_paths), Ok(target)) = (denied_paths, os.fs.canonicalize(ent.path()).await) {
+        if denied_paths.is_match(target) {
+            return Ok((md, Some(DENIED_SYMLINK_NOTE)));
+        }
+    }
+    // A broken link is listed as the link itself
+    Ok((os.fs.metadata(ent.path()).await.unwrap_or(md), None))
+}
+
 /// Converts negative 1-based indices to positive 0-based indices.
 fn convert_negative_index(line_count: usize, i: i32) -> usize {
     if i <= 0 {
@@ -924,6 +1097,61 @@ mod tests {
         .unwrap();
     }
 
+    #[tokio::test]
+    async fn test_fs_read_line_pages() {
+        let os = setup_test_directory().await;
+        let lines = TEST_FILE_CONTENTS.lines().collect::<Vec<_>>();
+        let mut stdout = std::io::stdout();
+
+        let mut cursor = None::<usize>;
+        let mut pages = Vec::new();
+        loop {
+            let v = serde_json::json!({
+                "operations": [{
+                    "path": TEST_FILE_PATH,
+                    "mode": "Line",
+                    "page_size": 2,
+                    "cursor": cursor,
+                }]
+            });
+            let output = serde_json::from_value::<FsRead>(v)
+                .unwrap()
+                .invoke(&os, &mut stdout, None)
+                .await
+                .unwrap();
+            let OutputKind::Json(page) = output.output else {
+                panic!("expected json output");
+            };
+            pages.push(page["content"].as_str().unwrap().to_string());
+            match page["next_cursor"].as_u64() {
+                Some(next) => cursor = Some(next as usize),
+                None => {
+                    assert!(page["next_cursor"].is_null());
+                    break;
+                },
+            }
+        }
+
+        assert_eq!(pages.len(), lines.len().div_ceil(2));
+        assert_eq!(pages[0], lines[..2].join("\n"));
+        assert_eq!(pages.join("\n"), lines.join("\n"));
+
+        // Paging respects the requested line range
+        let v = serde_json::json!({
+            "operations": [{ "path": TEST_FILE_PATH, "mode": "Line", "start_line": 2, "end_line": 2, "page_size": 5 }]
+        });
+        let output = serde_json::from_value::<FsRead>(v)
+            .unwrap()
+            .invoke(&os, &mut stdout, None)
+            .await
+            .unwrap();
+        let OutputKind::Json(page) = output.output else {
+            panic!("expected json output");
+        };
+        assert_eq!(page["content"], lines[1]);
+        assert!(page["next_cursor"].is_null());
+    }
+
     #[tokio::test]
     async fn test_fs_read_line_invoke() {
         let os = setup_test_directory().await;
@@ -941,7 +1169,7 @@ mod tests {
                 });
                 let output = serde_json::from_value::<FsRead>(v)
                     .unwrap()
-                    .invoke(&os, &mut stdout)
+                    .invoke(&os, &mut stdout, None)
                     .await
                     .unwrap();
 
@@ -975,7 +1203,7 @@ mod tests {
         assert!(
             serde_json::from_value::<FsRead>(v)
                 .unwrap()
-                .invoke(&os, &mut stdout)
+                .invoke(&os, &mut stdout, None)
                 .await
                 .is_err()
         );
@@ -1008,7 +1236,7 @@ mod tests {
         }]});
         let output = serde_json::from_value::<FsRead>(v)
             .unwrap()
-            .invoke(&os, &mut stdout)
+            .invoke(&os, &mut stdout, None)
             .await
             .unwrap();
 
@@ -1027,7 +1255,7 @@ mod tests {
         });
         let output = serde_json::from_value::<FsRead>(v)
             .unwrap()
-            .invoke(&os, &mut stdout)
+            .invoke(&os, &mut stdout, None)
             .await
             .unwrap();
 
@@ -1053,7 +1281,7 @@ mod tests {
                 let v = serde_json::json!($value);
                 let output = serde_json::from_value::<FsRead>(v)
                     .unwrap()
-                    .invoke(&os, &mut stdout)
+                    .invoke(&os, &mut stdout, None)
                     .await
                     .unwrap();
 
@@ -1100,7 +1328,7 @@ mod tests {
         });
         let output = serde_json::from_value::<FsRead>(v)
             .unwrap()
-            .invoke(&os, &mut stdout)
+            .invoke(&os, &mut stdout, None)
             .await
             .unwrap();
 
@@ -1132,7 +1360,7 @@ mod tests {
         });
         let output = serde_json::from_value::<FsRead>(v)
             .unwrap()
-            .invoke(&os, &mut stdout)
+            .invoke(&os, &mut stdout, None)
             .await
             .unwrap();
 
@@ -1169,7 +1397,7 @@ mod tests {
         });
         let output = serde_json::from_value::<FsRead>(v)
             .unwrap()
-            .invoke(&os, &mut stdout)
+            .invoke(&os, &mut stdout, None)
             .await
             .unwrap();
 
@@ -1193,7 +1421,7 @@ mod tests {
         });
         let output = serde_json::from_value::<FsRead>(v)
             .unwrap()
-            .invoke(&os, &mut stdout)
+            .invoke(&os, &mut stdout, None)
             .await
             .unwrap();
 
@@ -1230,7 +1458,7 @@ mod tests {
         });
         let output = serde_json::from_value::<FsRead>(v)
             .unwrap()
-            .invoke(&os, &mut stdout)
+            .invoke(&os, &mut stdout, None)
             .await
             .unwrap();
 
@@ -1270,7 +1498,7 @@ mod tests {
         });
         let output = serde_json::from_value::<FsRead>(v)
             .unwrap()
-            .invoke(&os, &mut stdout)
+            .invoke(&os, &mut stdout, None)
             .await
             .unwrap();
 
@@ -1300,7 +1528,7 @@ mod tests {
         });
         let output = serde_json::from_value::<FsRead>(v)
             .unwrap()
-            .invoke(&os, &mut stdout)
+            .invoke(&os, &mut stdout, None)
             .await
             .unwrap();
 
@@ -1319,7 +1547,7 @@ mod tests {
         });
         let output = serde_json::from_value::<FsRead>(v)
             .unwrap()
-            .invoke(&os, &mut stdout)
+            .invoke(&os, &mut stdout, None)
             .await
             .unwrap();
 
@@ -1351,7 +1579,7 @@ mod tests {
 
         let output = serde_json::from_value::<FsRead>(v)
             .unwrap()
-            .invoke(&os, &mut stdout)
+            .invoke(&os, &mut stdout, None)
             .await
             .unwrap();
         // All text operations should return combined text
@@ -1447,4 +1675,69 @@ mod tests {
                 && deny_list.iter().filter(|p| *p == DENIED_PATH_OR_FILE).collect::<Vec<_>>().len() == 2
         ));
     }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_fs_read_symlinks() {
+        let mut os = setup_test_directory().await;
+        let mut stdout = std::io::stdout();
+        os.fs.create_dir_all("/data/private").await.unwrap();
+        os.fs.write("/data/private/secret.txt", "secret").await.unwrap();
+        os.fs.symlink("/data/private", "/outside").await.unwrap();
+
+        let read_secret = || {
+            serde_json::from_value::<FsRead>(serde_json::json!({
+                "operations": [{ "mode": "Line", "path": "/outside/secret.txt" }]
+            }))
+            .unwrap()
+        };
+        let list_root = || {
+            serde_json::from_value::<FsRead>(serde_json::json!({
+                "operations": [{ "mode": "Directory", "path": "/", "depth": 1 }]
+            }))
+            .unwrap()
+        };
+        let listing = |output: InvokeOutput| match output.output {
+            OutputKind::Text(text) => text,
+            _ => panic!("expected text output"),
+        };
+
+        // Not followed by default
+        let err = read_secret().validate(&os).await.unwrap_err();
+        assert!(err.to_string().contains("is beneath the symlink"), "{err}");
+        assert!(err.to_string().contains("tools.fs.followSymlinks"), "{err}");
+        let text = listing(list_root().invoke(&os, &mut stdout, None).await.unwrap());
+        let line = text.lines().find(|l| l.ends_with(SKIPPED_SYMLINK_NOTE)).unwrap();
+        assert!(line.contains("/outside"));
+        assert!(!text.contains("secret.txt"));
+
+        // Followed once enabled
+        os.database
+            .settings
+            .set(Setting::ToolsFsFollowSymlinks, true)
+            .await
+            .unwrap();
+        let mut fs_read = read_secret();
+        fs_read.validate(&os).await.unwrap();
+        match fs_read.invoke(&os, &mut stdout, None).await.unwrap().output {
+            OutputKind::Text(text) => assert_eq!(text, "secret"),
+            _ => panic!("expected text output"),
+        }
+        let text = listing(list_root().invoke(&os, &mut stdout, None).await.unwrap());
+        assert!(!text.contains(SKIPPED_SYMLINK_NOTE));
+        assert!(text.contains("secret.txt"));
+
+        // Not followed into a path the agent denies
+        let agent = Agent {
+            tools_settings: HashMap::from([(
+                ToolSettingTarget("fs_read".to_string()),
+                serde_json::json!({ "deniedPaths": ["/data/private"] }),
+            )]),
+            ..Default::default()
+        };
+        let text = listing(list_root().invoke(&os, &mut stdout, Some(&agent)).await.unwrap());
+        let line = text.lines().find(|l| l.ends_with(DENIED_SYMLINK_NOTE)).unwrap();
+        assert!(line.contains("/outside"));
+        assert!(!text.contains("secret.txt"));
+    }
 }
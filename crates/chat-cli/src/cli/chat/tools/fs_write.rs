@@ -43,6 +43,7 @@ use crate::cli::agent::{
     PermissionEvalResult,
 };
 use crate::cli::chat::line_tracker::FileLineTracker;
+use crate::database::settings::Setting;
 use crate::os::Os;
 use crate::util::directories;
 use crate::util::pattern_matching::matches_any_pattern;
@@ -50,6 +51,10 @@ use crate::util::pattern_matching::matches_any_pattern;
 static SYNTAX_SET: LazyLock<SyntaxSet> = LazyLock::new(SyntaxSet::load_defaults_newlines);
 static THEME_SET: LazyLock<ThemeSet> = LazyLock::new(ThemeSet::load_defaults);
 
+/// Changes to text larger than this are summarized rather than diffed, unless
+/// `tools.fsWrite.maxDiffBytes` is set.
+const DEFAULT_MAX_DIFF_BYTES: usize = 1024 * 1024;
+
 #[derive(Debug, Clone, Deserialize)]
 #[serde(tag = "command")]
 pub enum FsWrite {
@@ -61,6 +66,8 @@ pub enum FsWrite {
         file_text: Option<String>,
         new_str: Option<String>,
         summary: Option<String>,
+        #[serde(default)]
+        diff_algorithm: DiffAlgorithm,
     },
     #[serde(rename = "str_replace")]
     StrReplace {
@@ -68,6 +75,8 @@ pub enum FsWrite {
         old_str: String,
         new_str: String,
         summary: Option<String>,
+        #[serde(default)]
+        diff_algorithm: DiffAlgorithm,
     },
     #[serde(rename = "insert")]
     Insert {
@@ -75,15 +84,32 @@ pub enum FsWrite {
         insert_line: usize,
         new_str: String,
         summary: Option<String>,
+        #[serde(default)]
+        diff_algorithm: DiffAlgorithm,
     },
     #[serde(rename = "append")]
     Append {
         path: String,
         new_str: String,
         summary: Option<String>,
+        #[serde(default)]
+        diff_algorithm: DiffAlgorithm,
     },
 }
 
+/// The granularity at which changes are shown when describing an [FsWrite].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DiffAlgorithm {
+    /// Whole lines are marked as removed or added, with syntax highlighting. Best suited to code.
+    #[default]
+    Line,
+    /// Changed words are marked inline. Best suited to prose.
+    Word,
+    /// Changed characters are marked inline.
+    Char,
+}
+
 impl FsWrite {
     pub fn path(&self, os: &Os) -> PathBuf {
         sanitize_path_tool_arg(os, match self {
@@ -268,6 +294,10 @@ impl FsWrite {
                 let lines_added = new_content.lines().count();
                 (lines_added, 0)
             },
+            FsWrite::StrReplace { old_str, new_str, .. } if exceeds_max_diff_bytes(os, old_str, new_str) => {
+                let summary = DiffSummary::new(old_str, new_str, 1);
+                (summary.lines_added, summary.lines_removed)
+            },
             FsWrite::StrReplace { old_str, new_str, .. } => {
                 // Use actual diff analysis for accurate line counting
                 let diff = similar::TextDiff::from_lines(old_str, new_str);
@@ -307,13 +337,11 @@ impl FsWrite {
                 let path = sanitize_path_tool_arg(os, path);
                 let relative_path = format_path(cwd, &path);
                 let prev = if os.fs.exists(&path) {
-                    let file = os.fs.read_to_string_sync(&path)?;
-                    stylize_output_if_able(os, &path, &file)
+                    os.fs.read_to_string_sync(&path)?
                 } else {
                     Default::default()
                 };
-                let new = stylize_output_if_able(os, &relative_path, &file_text);
-                print_diff(output, &prev, &new, 1)?;
+                self.queue_diff(os, output, &relative_path, &prev, &file_text, 1)?;
 
                 // Display summary as purpose if available after the diff
                 super::display_purpose(self.get_summary(), output)?;
@@ -339,10 +367,7 @@ impl FsWrite {
                     .unwrap_or_default();
                 let old = [prefix, insert_line_content, suffix].join("");
                 let new = [prefix, insert_line_content, new_str, suffix].join("");
-
-                let old = stylize_output_if_able(os, &relative_path, &old);
-                let new = stylize_output_if_able(os, &relative_path, &new);
-                print_diff(output, &old, &new, start_line)?;
+                self.queue_diff(os, output, &relative_path, &old, &new, start_line)?;
 
                 // Display summary as purpose if available after the diff
                 super::display_purpose(self.get_summary(), output)?;
@@ -359,9 +384,7 @@ impl FsWrite {
                     Some((start_line, end_line)) => (start_line, end_line),
                     _ => (0, 0),
                 };
-                let old_str = stylize_output_if_able(os, &relative_path, old_str);
-                let new_str = stylize_output_if_able(os, &relative_path, new_str);
-                print_diff(output, &old_str, &new_str, start_line)?;
+                self.queue_diff(os, output, &relative_path, old_str, new_str, start_line)?;
 
                 // Display summary as purpose if available after the diff
                 super::display_purpose(self.get_summary(), output)?;
@@ -372,8 +395,7 @@ impl FsWrite {
                 let path = sanitize_path_tool_arg(os, path);
                 let relative_path = format_path(cwd, &path);
                 let start_line = os.fs.read_to_string_sync(&path)?.lines().count() + 1;
-                let file = stylize_output_if_able(os, &relative_path, new_str);
-                print_diff(output, &Default::default(), &file, start_line)?;
+                self.queue_diff(os, output, &relative_path, "", new_str, start_line)?;
 
                 // Display summary as purpose if available after the diff
                 super::display_purpose(self.get_summary(), output)?;
@@ -450,6 +472,39 @@ impl FsWrite {
         }
     }
 
+    /// Returns the diff algorithm from any variant of the FsWrite enum
+    fn diff_algorithm(&self) -> DiffAlgorithm {
+        match self {
+            FsWrite::Create { diff_algorithm, .. }
+            | FsWrite::StrReplace { diff_algorithm, .. }
+            | FsWrite::Insert { diff_algorithm, .. }
+            | FsWrite::Append { diff_algorithm, .. } => *diff_algorithm,
+        }
+    }
+
+    /// Prints the change from `old` to `new` using the requested [DiffAlgorithm].
+    fn queue_diff(
+        &self,
+        os: &Os,
+        output: &mut impl Write,
+        path: &str,
+        old: &str,
+        new: &str,
+        start_line: usize,
+    ) -> Result<()> {
+        if exceeds_max_diff_bytes(os, old, new) {
+            return print_diff_summary(output, &DiffSummary::new(old, new, start_line));
+        }
+        match self.diff_algorithm() {
+            DiffAlgorithm::Line => {
+                let old = stylize_output_if_able(os, path, old);
+                let new = stylize_output_if_able(os, path, new);
+                print_diff(output, &old, &new, start_line)
+            },
+            algorithm => print_inline_diff(output, old, new, algorithm),
+        }
+    }
+
     /// Returns the summary from any variant of the FsWrite enum
     fn get_summary(&self) -> Option<&String> {
         match self {
@@ -461,6 +516,31 @@ impl FsWrite {
     }
 
     pub fn eval_perm(&self, os: &Os, agent: &Agent) -> PermissionEvalResult {
+        match self.eval_agent_perm(os, agent) {
+            PermissionEvalResult::Allow if self.overwrites_existing_file(os) && Self::confirm_overwrite(os) => {
+                PermissionEvalResult::Ask
+            },
+            result => result,
+        }
+    }
+
+    /// Whether this is a `create` that would replace the contents of a file that already exists.
+    fn overwrites_existing_file(&self, os: &Os) -> bool {
+        match self {
+            Self::Create { path, .. } => os.fs.exists(sanitize_path_tool_arg(os, path)),
+            Self::Insert { .. } | Self::Append { .. } | Self::StrReplace { .. } => false,
+        }
+    }
+
+    fn confirm_overwrite(os: &Os) -> bool {
+        os.database
+            .settings
+            .get_or_default(Setting::ToolsFsWriteConfirmOverwrite)
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false)
+    }
+
+    fn eval_agent_perm(&self, os: &Os, agent: &Agent) -> PermissionEvalResult {
         #[derive(Debug, Deserialize)]
         #[serde(rename_all = "camelCase")]
         struct Settings {
@@ -630,6 +710,71 @@ fn get_lines_with_context(
     )
 }
 
+/// Whether the change from `old` to `new` is too large to diff, per `tools.fsWrite.maxDiffBytes`.
+fn exceeds_max_diff_bytes(os: &Os, old: &str, new: &str) -> bool {
+    let max_diff_bytes = os
+        .database
+        .settings
+        .get_int_or(Setting::ToolsFsWriteMaxDiffBytes, DEFAULT_MAX_DIFF_BYTES);
+    old.len().max(new.len()) > max_diff_bytes
+}
+
+/// A summary of the change between two texts, cheap enough to compute for large files.
+///
+/// Only the lines in common at the start and end are matched up, so every line between the first
+/// and last change counts as changed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct DiffSummary {
+    lines_added: usize,
+    lines_removed: usize,
+    /// 1-indexed line number of the first change, if there is one.
+    first_changed_line: Option<usize>,
+}
+
+impl DiffSummary {
+    /// - `start_line` - 1-indexed line number that `old` and `new` start at.
+    fn new(old: &str, new: &str, start_line: usize) -> Self {
+        let old_lines = old.lines().collect::<Vec<_>>();
+        let new_lines = new.lines().collect::<Vec<_>>();
+        let prefix = old_lines.iter().zip(&new_lines).take_while(|(a, b)| a == b).count();
+        let suffix = old_lines[prefix..]
+            .iter()
+            .rev()
+            .zip(new_lines[prefix..].iter().rev())
+            .take_while(|(a, b)| a == b)
+            .count();
+        let lines_added = new_lines.len() - prefix - suffix;
+        let lines_removed = old_lines.len() - prefix - suffix;
+        Self {
+            lines_added,
+            lines_removed,
+            first_changed_line: (lines_added + lines_removed > 0).then_some(start_line + prefix),
+        }
+    }
+}
+
+/// Prints a [DiffSummary] in place of a diff.
+fn print_diff_summary(output: &mut impl Write, summary: &DiffSummary) -> Result<()> {
+    queue!(
+        output,
+        style::SetForegroundColor(style::Color::DarkGrey),
+        style::Print("Too large to diff (see tools.fsWrite.maxDiffBytes): "),
+        style::SetForegroundColor(style::Color::Green),
+        style::Print(format!("+{}", summary.lines_added)),
+        style::SetForegroundColor(style::Color::DarkGrey),
+        style::Print(" / "),
+        style::SetForegroundColor(style::Color::Red),
+        style::Print(format!("-{}", summary.lines_removed)),
+        style::SetForegroundColor(style::Color::DarkGrey),
+        style::Print(" lines"),
+    )?;
+    if let Some(line) = summary.first_changed_line {
+        queue!(output, style::Print(format!(", first change at line {line}")))?;
+    }
+    queue!(output, style::ResetColor, style::Print("\n"))?;
+    Ok(())
+}
+
 /// Prints a git-diff style comparison between `old_str` and `new_str`.
 /// - `start_line` - 1-indexed line number that `old_str` and `new_str` start at.
 fn print_diff(
@@ -738,6 +883,50 @@ fn print_diff(
     Ok(())
 }
 
+/// Prints `new` with the words or characters changed from `old` marked inline.
+fn print_inline_diff(output: &mut impl Write, old: &str, new: &str, algorithm: DiffAlgorithm) -> Result<()> {
+    for (tag, text) in diff_segments(old, new, algorithm) {
+        match tag {
+            similar::ChangeTag::Equal => queue!(output, style::Print(text))?,
+            similar::ChangeTag::Delete => queue!(
+                output,
+                style::SetForegroundColor(style::Color::Red),
+                style::SetAttribute(style::Attribute::CrossedOut),
+                style::Print(text),
+                style::SetAttribute(style::Attribute::Reset),
+                style::ResetColor,
+            )?,
+            similar::ChangeTag::Insert => queue!(
+                output,
+                style::SetForegroundColor(style::Color::Green),
+                style::Print(text),
+                style::ResetColor,
+            )?,
+        }
+    }
+    queue!(output, style::Print("\n"))?;
+
+    Ok(())
+}
+
+/// Splits the difference between `old` and `new` into runs of equal, deleted, and inserted text at
+/// the granularity of `algorithm`.
+fn diff_segments(old: &str, new: &str, algorithm: DiffAlgorithm) -> Vec<(similar::ChangeTag, String)> {
+    let diff = match algorithm {
+        DiffAlgorithm::Line => similar::TextDiff::from_lines(old, new),
+        DiffAlgorithm::Word => similar::TextDiff::from_words(old, new),
+        DiffAlgorithm::Char => similar::TextDiff::from_chars(old, new),
+    };
+    let mut segments = Vec::<(similar::ChangeTag, String)>::new();
+    for change in diff.iter_all_changes() {
+        match segments.last_mut() {
+            Some((tag, text)) if *tag == change.tag() => text.push_str(change.value()),
+            _ => segments.push((change.tag(), change.value().to_string())),
+        }
+    }
+    segments
+}
+
 /// Returns a 1-indexed line number range of the start and end of `needle` inside `file`.
 fn line_number_at(file: impl AsRef<str>, needle: impl AsRef<str>) -> Option<(usize, usize)> {
     let file = file.as_ref();
@@ -1573,4 +1762,100 @@ mod tests {
             "after_lines should match the actual line count in the file"
         );
     }
+
+    #[tokio::test]
+    async fn test_large_write_is_summarized() {
+        let mut os = setup_test_directory().await;
+        let old = (1..=20).map(|i| format!("line {i}\n")).collect::<String>();
+        os.fs.write("/large.txt", &old).await.unwrap();
+        let new = old.replace("line 5\n", "line five\nline 5.5\n");
+        let create = serde_json::from_value::<FsWrite>(serde_json::json!({
+            "path": "/large.txt",
+            "command": "create",
+            "file_text": new,
+        }))
+        .unwrap();
+
+        // Under the threshold the full diff is shown
+        let mut output = Vec::new();
+        create.queue_description(&os, &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.contains("line five"));
+        assert!(!output.contains("Too large to diff"));
+
+        os.database
+            .settings
+            .set(Setting::ToolsFsWriteMaxDiffBytes, 64)
+            .await
+            .unwrap();
+        let mut output = Vec::new();
+        create.queue_description(&os, &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert!(!output.contains("line five"));
+        assert!(output.contains("Too large to diff"));
+        assert!(output.contains("+2"));
+        assert!(output.contains("-1"));
+        assert!(output.contains("first change at line 5"));
+
+        assert_eq!(DiffSummary::new(&old, &new, 1), DiffSummary {
+            lines_added: 2,
+            lines_removed: 1,
+            first_changed_line: Some(5),
+        });
+        assert_eq!(DiffSummary::new(&old, &old, 1).first_changed_line, None);
+    }
+
+    #[test]
+    fn test_diff_segments_word_granularity() {
+        let old = "The quick brown fox jumps over the lazy dog.\n";
+        let new = "The quick red fox jumps over the sleepy dog.\n";
+
+        // Line based diffs replace the whole sentence
+        let line = diff_segments(old, new, DiffAlgorithm::Line);
+        assert_eq!(line, vec![
+            (similar::ChangeTag::Delete, old.to_string()),
+            (similar::ChangeTag::Insert, new.to_string()),
+        ]);
+
+        // Word based diffs only mark the changed words
+        let word = diff_segments(old, new, DiffAlgorithm::Word);
+        assert_eq!(word, vec![
+            (similar::ChangeTag::Equal, "The quick ".to_string()),
+            (similar::ChangeTag::Delete, "brown".to_string()),
+            (similar::ChangeTag::Insert, "red".to_string()),
+            (similar::ChangeTag::Equal, " fox jumps over the ".to_string()),
+            (similar::ChangeTag::Delete, "lazy".to_string()),
+            (similar::ChangeTag::Insert, "sleepy".to_string()),
+            (similar::ChangeTag::Equal, " dog.\n".to_string()),
+        ]);
+
+        let char = diff_segments("colour", "color", DiffAlgorithm::Char);
+        assert_eq!(char, vec![
+            (similar::ChangeTag::Equal, "colo".to_string()),
+            (similar::ChangeTag::Delete, "u".to_string()),
+            (similar::ChangeTag::Equal, "r".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn test_fs_write_deserialize_diff_algorithm() {
+        let fw = serde_json::from_value::<FsWrite>(serde_json::json!({
+            "path": "/my-file",
+            "command": "str_replace",
+            "old_str": "prev string",
+            "new_str": "new string",
+        }))
+        .unwrap();
+        assert_eq!(fw.diff_algorithm(), DiffAlgorithm::Line);
+
+        let fw = serde_json::from_value::<FsWrite>(serde_json::json!({
+            "path": "/my-file",
+            "command": "str_replace",
+            "old_str": "prev string",
+            "new_str": "new string",
+            "diff_algorithm": "word",
+        }))
+        .unwrap();
+        assert_eq!(fw.diff_algorithm(), DiffAlgorithm::Word);
+    }
 }
@@ -67,8 +67,24 @@ pub struct KnowledgeClear {
 pub struct KnowledgeSearch {
     pub query: String,
     pub context_id: Option<String>,
+    #[serde(default)]
+    pub mode: SearchMode,
+}
+
+/// How [KnowledgeSearch] results are returned to the model
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchMode {
+    /// Matching text for the model to synthesize an answer from
+    #[default]
+    Synthesized,
+    /// The top matching chunks verbatim, each with the source it was indexed from
+    Quote,
 }
 
+/// Maximum number of chunks returned by a [SearchMode::Quote] search
+const MAX_QUOTES: usize = 5;
+
 #[derive(Debug, Clone, Deserialize)]
 pub struct KnowledgeUpdate {
     #[serde(default)]
@@ -295,6 +311,10 @@ impl Knowledge {
                 } else {
                     queue!(updates, style::Print(" across all contexts"),)?;
                 }
+
+                if search.mode == SearchMode::Quote {
+                    queue!(updates, style::Print(" (direct quotes only)"),)?;
+                }
             },
             Knowledge::Show => {
                 queue!(updates, style::Print("Showing all knowledge base entries"),)?;
@@ -430,6 +450,11 @@ impl Knowledge {
             Knowledge::Search(search) => {
                 let results = store.search(&search.query, search.context_id.as_deref()).await;
                 match results {
+                    Ok(results) if search.mode == SearchMode::Quote => {
+                        return Ok(InvokeOutput {
+                            output: OutputKind::Json(Self::quote_results(&results)),
+                        });
+                    },
                     Ok(results) => {
                         if results.is_empty() {
                             format!("No matching entries found for query: \"{}\"", search.query)
@@ -504,6 +529,28 @@ impl Knowledge {
         }
     }
 
+    /// The top matching chunks verbatim, with the path they were indexed from when known, for
+    /// [SearchMode::Quote]
+    fn quote_results(results: &[semantic_search_client::SearchResult]) -> serde_json::Value {
+        let quotes = results
+            .iter()
+            .filter_map(|result| {
+                let text = result.text()?;
+                Some(serde_json::json!({
+                    "text": text,
+                    "source": result.point.payload.get("path"),
+                    "distance": result.distance,
+                }))
+            })
+            .take(MAX_QUOTES)
+            .collect::<Vec<_>>();
+
+        serde_json::json!({
+            "instructions": "Quote these passages verbatim and cite their source. Do not paraphrase them.",
+            "quotes": quotes,
+        })
+    }
+
     /// Format status data for display (UI rendering responsibility)
     fn format_status_display(status: &semantic_search_client::SystemStatus) -> String {
         let mut status_lines = Vec::new();
@@ -575,3 +622,78 @@ impl Knowledge {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use semantic_search_client::{
+        DataPoint,
+        SearchResult,
+    };
+
+    use super::*;
+
+    fn result(text: &str, path: Option<&str>, distance: f32) -> SearchResult {
+        let mut payload = HashMap::from([("text".to_string(), serde_json::json!(text))]);
+        if let Some(path) = path {
+            payload.insert("path".to_string(), serde_json::json!(path));
+        }
+        SearchResult::new(
+            DataPoint {
+                id: 0,
+                payload,
+                vector: vec![],
+            },
+            distance,
+        )
+    }
+
+    #[test]
+    fn test_search_mode_deserialization() {
+        let search = serde_json::from_value::<Knowledge>(serde_json::json!({
+            "command": "search",
+            "query": "retry policy",
+        }))
+        .unwrap();
+        assert!(matches!(
+            search,
+            Knowledge::Search(KnowledgeSearch {
+                mode: SearchMode::Synthesized,
+                ..
+            })
+        ));
+
+        let search = serde_json::from_value::<Knowledge>(serde_json::json!({
+            "command": "search",
+            "query": "retry policy",
+            "mode": "quote",
+        }))
+        .unwrap();
+        assert!(matches!(
+            search,
+            Knowledge::Search(KnowledgeSearch {
+                mode: SearchMode::Quote,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_quote_results_are_verbatim() {
+        let chunk = "  Retries use exponential backoff:\n\n```rust\nlet delay = base * 2^attempt;\n```\n";
+        let mut results = vec![
+            result(chunk, Some("/docs/retries.md"), 0.1),
+            result("Added as text", None, 0.2),
+        ];
+        results.extend((0..MAX_QUOTES).map(|i| result(&format!("filler {i}"), Some("/docs/other.md"), 0.5)));
+
+        let output = Knowledge::quote_results(&results);
+        let quotes = output["quotes"].as_array().unwrap();
+        assert_eq!(quotes.len(), MAX_QUOTES);
+        assert_eq!(quotes[0]["text"], chunk);
+        assert_eq!(quotes[0]["source"], "/docs/retries.md");
+        assert_eq!(quotes[1]["text"], "Added as text");
+        assert!(quotes[1]["source"].is_null());
+    }
+}
@@ -6,6 +6,9 @@ pub mod fs_write;
 pub mod gh_issue;
 pub mod introspect;
 pub mod knowledge;
+pub mod processes;
+pub mod run_tests;
+pub mod scratchpad;
 pub mod thinking;
 pub mod todo;
 pub mod use_aws;
@@ -30,11 +33,24 @@ use crossterm::style::{
 use custom_tool::CustomTool;
 use execute::ExecuteCommand;
 use eyre::Result;
-use fs_read::FsRead;
+use fs_read::{
+    FsDirectory,
+    FsLine,
+    FsRead,
+    FsReadOperation,
+    FsSearch,
+};
 use fs_write::FsWrite;
 use gh_issue::GhIssue;
 use introspect::Introspect;
 use knowledge::Knowledge;
+use processes::Processes;
+use run_tests::RunTests;
+use scratchpad::{
+    Scratchpad,
+    ScratchpadRead,
+    ScratchpadWrite,
+};
 use serde::{
     Deserialize,
     Serialize,
@@ -60,7 +76,7 @@ use crate::cli::chat::line_tracker::FileLineTracker;
 use crate::os::Os;
 
 pub const DEFAULT_APPROVE: [&str; 1] = ["fs_read"];
-pub const NATIVE_TOOLS: [&str; 9] = [
+pub const NATIVE_TOOLS: [&str; 13] = [
     "fs_read",
     "fs_write",
     #[cfg(windows)]
@@ -72,6 +88,10 @@ pub const NATIVE_TOOLS: [&str; 9] = [
     "knowledge",
     "thinking",
     "todo_list",
+    "run_tests",
+    "processes",
+    "scratchpad_read",
+    "scratchpad_write",
     "commands", // NEW: Add commands to tool names
 ];
 
@@ -90,6 +110,10 @@ pub enum Tool {
     Commands(Commands), // NEW: Add Commands variant
     Thinking(Thinking),
     Todo(TodoList),
+    RunTests(RunTests),
+    Processes(Processes),
+    ScratchpadRead(ScratchpadRead),
+    ScratchpadWrite(ScratchpadWrite),
 }
 
 impl Tool {
@@ -110,10 +134,31 @@ impl Tool {
             Tool::Commands(_) => "commands", // NEW: Add commands name
             Tool::Thinking(_) => "thinking (prerelease)",
             Tool::Todo(_) => "todo_list",
+            Tool::RunTests(_) => "run_tests",
+            Tool::Processes(_) => "processes",
+            Tool::ScratchpadRead(_) => "scratchpad_read",
+            Tool::ScratchpadWrite(_) => "scratchpad_write",
         }
         .to_owned()
     }
 
+    /// What the tool operates on, such as a file path or a command, if it has a single target.
+    pub fn display_target(&self, os: &Os) -> Option<String> {
+        match self {
+            Tool::FsRead(fs_read) => match fs_read.operations.as_slice() {
+                [
+                    FsReadOperation::Line(FsLine { path, .. })
+                    | FsReadOperation::Search(FsSearch { path, .. })
+                    | FsReadOperation::Directory(FsDirectory { path, .. }),
+                ] => Some(path.clone()),
+                _ => None,
+            },
+            Tool::FsWrite(fs_write) => Some(fs_write.path(os).display().to_string()),
+            Tool::ExecuteCommand(execute_command) => Some(execute_command.command.clone()),
+            _ => None,
+        }
+    }
+
     /// Whether or not the tool should prompt the user to accept before [Self::invoke] is called.
     pub fn requires_acceptance(&self, os: &Os, agent: &Agent) -> PermissionEvalResult {
         match self {
@@ -128,6 +173,9 @@ impl Tool {
             Tool::Todo(_) => PermissionEvalResult::Allow,
             Tool::Knowledge(knowledge) => knowledge.eval_perm(os, agent),
             Tool::Commands(_) => PermissionEvalResult::Ask,
+            Tool::RunTests(run_tests) => run_tests.eval_perm(os, agent),
+            Tool::Processes(processes) => processes.eval_perm(agent),
+            Tool::ScratchpadRead(_) | Tool::ScratchpadWrite(_) => PermissionEvalResult::Allow,
         }
     }
 
@@ -138,9 +186,10 @@ impl Tool {
         stdout: &mut impl Write,
         line_tracker: &mut HashMap<String, FileLineTracker>,
         agent: Option<&crate::cli::agent::Agent>,
+        scratchpad: &mut Scratchpad,
     ) -> Result<InvokeOutput> {
         match self {
-            Tool::FsRead(fs_read) => fs_read.invoke(os, stdout).await,
+            Tool::FsRead(fs_read) => fs_read.invoke(os, stdout, agent).await,
             Tool::FsWrite(fs_write) => fs_write.invoke(os, stdout, line_tracker).await,
             Tool::ExecuteCommand(execute_command) => execute_command.invoke(os, stdout).await,
             Tool::UseAws(use_aws) => use_aws.invoke(os, stdout).await,
@@ -151,6 +200,10 @@ impl Tool {
             Tool::Knowledge(knowledge) => knowledge.invoke(os, stdout, agent).await,
             Tool::Thinking(think) => think.invoke(stdout).await,
             Tool::Todo(todo) => todo.invoke(os, stdout).await,
+            Tool::RunTests(run_tests) => run_tests.invoke(os, stdout).await,
+            Tool::Processes(processes) => processes.invoke().await,
+            Tool::ScratchpadRead(read) => read.invoke(scratchpad).await,
+            Tool::ScratchpadWrite(write) => write.invoke(scratchpad).await,
         }
     }
 
@@ -169,6 +222,10 @@ impl Tool {
             // queue_description
             Tool::Thinking(thinking) => thinking.queue_description(output),
             Tool::Todo(_) => Ok(()),
+            Tool::RunTests(run_tests) => run_tests.queue_description(os, output),
+            Tool::Processes(processes) => processes.queue_description(output),
+            Tool::ScratchpadRead(_) => Ok(()),
+            Tool::ScratchpadWrite(write) => write.queue_description(output),
         }
     }
 
@@ -186,6 +243,9 @@ impl Tool {
             Tool::Commands(commands) => commands.validate(os).await, // NEW: Add commands validate
             Tool::Thinking(think) => think.validate(os).await,
             Tool::Todo(todo) => todo.validate(os).await,
+            Tool::RunTests(run_tests) => run_tests.validate(os).await,
+            Tool::Processes(_) => Ok(()),
+            Tool::ScratchpadRead(_) | Tool::ScratchpadWrite(_) => Ok(()),
         }
     }
 
@@ -209,6 +269,9 @@ pub struct ToolSpec {
     pub input_schema: InputSchema,
     #[serde(skip_serializing, default = "tool_origin")]
     pub tool_origin: ToolOrigin,
+    /// Example inputs shown to the user in `/tools`, if the tool provides any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub examples: Option<Vec<serde_json::Value>>,
 }
 
 #[derive(Debug, Clone, Eq, PartialEq)]
@@ -462,6 +525,7 @@ pub fn queue_function_result(result: &str, updates: &mut impl Write, is_error: b
 /// Helper function to set up environment variables with user agent metadata for CloudTrail tracking
 pub fn env_vars_with_user_agent(os: &Os) -> std::collections::HashMap<String, String> {
     let mut env_vars: std::collections::HashMap<String, String> = std::env::vars().collect();
+    env_vars.extend(os.session_env.clone());
 
     // Set up additional metadata for the AWS CLI user agent
     let user_agent_metadata_value = format!(
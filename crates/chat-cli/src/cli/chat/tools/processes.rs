@@ -0,0 +1,138 @@
+use std::io::Write;
+
+use crossterm::queue;
+use crossterm::style::{
+    self,
+    Color,
+};
+use eyre::Result;
+use serde::Deserialize;
+
+use super::{
+    InvokeOutput,
+    OutputKind,
+};
+use crate::cli::agent::{
+    Agent,
+    PermissionEvalResult,
+};
+use crate::util::pattern_matching::matches_any_pattern;
+use crate::util::process_registry::ProcessRegistry;
+
+/// Lists the child processes the CLI is tracking (MCP servers and execute jobs), and terminates
+/// them.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "command", rename_all = "lowercase")]
+pub enum Processes {
+    List,
+    Terminate { pid: u32 },
+}
+
+impl Processes {
+    pub fn eval_perm(&self, agent: &Agent) -> PermissionEvalResult {
+        match self {
+            Self::List => PermissionEvalResult::Allow,
+            Self::Terminate { .. } if matches_any_pattern(&agent.allowed_tools, "processes") => {
+                PermissionEvalResult::Allow
+            },
+            Self::Terminate { .. } => PermissionEvalResult::Ask,
+        }
+    }
+
+    pub fn queue_description(&self, output: &mut impl Write) -> Result<()> {
+        let Self::Terminate { pid } = self else {
+            return Ok(());
+        };
+        let target = match ProcessRegistry::global().get(*pid) {
+            Some(process) => format!("{} {} (pid {pid})", process.kind, process.name),
+            None => format!("pid {pid}"),
+        };
+        queue!(
+            output,
+            style::Print("Terminating "),
+            style::SetForegroundColor(Color::Green),
+            style::Print(target),
+            style::ResetColor,
+            style::Print("\n"),
+        )?;
+        Ok(())
+    }
+
+    pub async fn invoke(&self) -> Result<InvokeOutput> {
+        self.invoke_with(ProcessRegistry::global())
+    }
+
+    fn invoke_with(&self, registry: &ProcessRegistry) -> Result<InvokeOutput> {
+        let output = match self {
+            Self::List => {
+                let processes = registry
+                    .list()
+                    .into_iter()
+                    .map(|(process, status)| {
+                        serde_json::json!({
+                            "pid": process.pid,
+                            "kind": process.kind,
+                            "name": process.name,
+                            "status": status,
+                        })
+                    })
+                    .collect::<Vec<_>>();
+                OutputKind::Json(serde_json::json!({ "processes": processes }))
+            },
+            Self::Terminate { pid } => {
+                let process = registry.terminate(*pid)?;
+                OutputKind::Text(format!("Terminated {} {} (pid {pid})", process.kind, process.name))
+            },
+        };
+        Ok(InvokeOutput { output })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::process_registry::ProcessKind;
+
+    #[cfg(unix)]
+    #[test]
+    fn test_list_and_terminate() {
+        let registry = ProcessRegistry::default();
+        let spawn = || std::process::Command::new("sleep").arg("30").spawn().unwrap();
+        let mut server = spawn();
+        let mut job = spawn();
+        registry.track(server.id(), ProcessKind::McpServer, "slow-server");
+        registry.track(job.id(), ProcessKind::Execute, "sleep 30");
+
+        let list = serde_json::from_value::<Processes>(serde_json::json!({ "command": "list" })).unwrap();
+        let OutputKind::Json(listed) = list.invoke_with(&registry).unwrap().output else {
+            panic!("expected json output");
+        };
+        assert_eq!(
+            listed["processes"][0],
+            serde_json::json!({
+                "pid": server.id().min(job.id()),
+                "kind": if server.id() < job.id() { "mcp_server" } else { "execute" },
+                "name": if server.id() < job.id() { "slow-server" } else { "sleep 30" },
+                "status": "running",
+            })
+        );
+        assert_eq!(listed["processes"].as_array().unwrap().len(), 2);
+
+        let terminate = Processes::Terminate { pid: server.id() };
+        let output = terminate.invoke_with(&registry).unwrap();
+        assert_eq!(
+            output.as_str(),
+            format!("Terminated MCP server slow-server (pid {})", server.id())
+        );
+        assert!(!server.wait().unwrap().success());
+
+        let OutputKind::Json(listed) = list.invoke_with(&registry).unwrap().output else {
+            panic!("expected json output");
+        };
+        assert_eq!(listed["processes"][0]["name"], "sleep 30");
+
+        Processes::Terminate { pid: job.id() }.invoke_with(&registry).unwrap();
+        assert!(!job.wait().unwrap().success());
+        assert!(Processes::Terminate { pid: job.id() }.invoke_with(&registry).is_err());
+    }
+}
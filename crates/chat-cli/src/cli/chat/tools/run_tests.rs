@@ -0,0 +1,481 @@
+use std::io::Write;
+use std::path::{
+    Path,
+    PathBuf,
+};
+use std::process::Stdio;
+use std::sync::LazyLock;
+
+use crossterm::queue;
+use crossterm::style::{
+    self,
+    Color,
+};
+use eyre::{
+    Result,
+    bail,
+};
+use regex::Regex;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use super::{
+    InvokeOutput,
+    MAX_TOOL_RESPONSE_SIZE,
+    OutputKind,
+};
+use crate::cli::agent::{
+    Agent,
+    PermissionEvalResult,
+};
+use crate::cli::chat::util::truncate_safe;
+use crate::database::settings::Setting;
+use crate::os::Os;
+use crate::util::pattern_matching::matches_any_pattern;
+
+/// Maximum size in bytes of a single failure message returned to the model.
+const MAX_FAILURE_MESSAGE_SIZE: usize = 4_000;
+/// Maximum size in bytes of the raw output tail included when no failures could be parsed.
+const MAX_OUTPUT_TAIL_SIZE: usize = 8_000;
+
+/// Runs the project's test suite and returns a structured summary of the results instead of the
+/// raw test output.
+///
+/// This is a beta feature that can be enabled/disabled via settings:
+/// `q settings chat.enableRunTests true`
+#[derive(Debug, Clone, Deserialize)]
+pub struct RunTests {
+    /// Directory of the project to test, defaults to the current working directory
+    pub path: Option<String>,
+    /// Only run tests whose name matches this filter
+    pub filter: Option<String>,
+}
+
+/// A test runner that [RunTests] knows how to invoke and parse.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TestRunner {
+    Cargo,
+    Npm,
+    Pytest,
+}
+
+impl TestRunner {
+    /// Detects the runner for the project in `dir` from its manifest files.
+    fn detect(os: &Os, dir: &Path) -> Option<Self> {
+        if os.fs.exists(dir.join("Cargo.toml")) {
+            Some(Self::Cargo)
+        } else if os.fs.exists(dir.join("package.json")) {
+            Some(Self::Npm)
+        } else if ["pyproject.toml", "pytest.ini", "setup.py", "setup.cfg", "tox.ini"]
+            .iter()
+            .any(|marker| os.fs.exists(dir.join(marker)))
+        {
+            Some(Self::Pytest)
+        } else {
+            None
+        }
+    }
+
+    fn command(self, filter: Option<&str>) -> (&'static str, Vec<String>) {
+        let mut args: Vec<String> = match self {
+            Self::Cargo => vec!["test".into(), "--no-fail-fast".into(), "--color".into(), "never".into()],
+            Self::Npm => vec!["test".into(), "--".into()],
+            Self::Pytest => vec!["-rfE".into(), "--color=no".into()],
+        };
+        if let Some(filter) = filter {
+            if self == Self::Pytest {
+                args.push("-k".into());
+            }
+            args.push(filter.to_string());
+        }
+        let program = match self {
+            Self::Cargo => "cargo",
+            // npm is installed as a batch script on Windows, which is only found with its extension
+            Self::Npm if cfg!(windows) => "npm.cmd",
+            Self::Npm => "npm",
+            Self::Pytest => "pytest",
+        };
+        (program, args)
+    }
+
+    fn parse(self, output: &str) -> TestSummary {
+        match self {
+            Self::Cargo => parse_cargo(output),
+            Self::Npm => parse_npm(output),
+            Self::Pytest => parse_pytest(output),
+        }
+    }
+}
+
+/// A single failing test.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TestFailure {
+    pub name: String,
+    pub message: String,
+}
+
+/// The structured result of a test run returned to the model.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct TestSummary {
+    pub passed: usize,
+    pub failed: usize,
+    pub ignored: usize,
+    pub failures: Vec<TestFailure>,
+    /// Number of failures left out of [Self::failures] to stay within the size cap.
+    #[serde(skip_serializing_if = "is_zero")]
+    pub omitted_failures: usize,
+    /// The end of the raw output, included when the run failed without any parsed test failures
+    /// (e.g. a compilation error).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output_tail: Option<String>,
+}
+
+fn is_zero(n: &usize) -> bool {
+    *n == 0
+}
+
+impl TestSummary {
+    /// Truncates failure messages and drops failures until the summary fits in `max_size` bytes.
+    fn cap_size(&mut self, max_size: usize) {
+        for failure in &mut self.failures {
+            let len = truncate_safe(&failure.message, MAX_FAILURE_MESSAGE_SIZE).len();
+            failure.message.truncate(len);
+        }
+        while serde_json::to_string(self).map_or(0, |s| s.len()) > max_size && self.failures.pop().is_some() {
+            self.omitted_failures += 1;
+        }
+    }
+}
+
+impl RunTests {
+    /// Checks if the run_tests tool is enabled in settings
+    pub fn is_enabled(os: &Os) -> bool {
+        os.database.settings.get_bool(Setting::EnabledRunTests).unwrap_or(false)
+    }
+
+    pub fn eval_perm(&self, os: &Os, agent: &Agent) -> PermissionEvalResult {
+        _ = self;
+        _ = os;
+
+        if matches_any_pattern(&agent.allowed_tools, "run_tests") {
+            PermissionEvalResult::Allow
+        } else {
+            PermissionEvalResult::Ask
+        }
+    }
+
+    /// The project directory, as seen by the user.
+    fn project_dir(&self, os: &Os) -> Result<PathBuf> {
+        let cwd = os.env.current_dir()?;
+        Ok(match self.path.as_deref() {
+            Some(path) => match path.strip_prefix("~") {
+                Some(rest) => os.env.home().unwrap_or_default().join(rest.trim_start_matches('/')),
+                None => cwd.join(path),
+            },
+            None => cwd,
+        })
+    }
+
+    pub async fn validate(&mut self, os: &Os) -> Result<()> {
+        let dir = self.project_dir(os)?;
+        if !os.fs.exists(&dir) {
+            bail!("'{}' does not exist", dir.display());
+        }
+        if TestRunner::detect(os, &dir).is_none() {
+            bail!(
+                "No supported test runner found in '{}'. Expected a Cargo.toml, package.json or pytest configuration",
+                dir.display()
+            );
+        }
+        Ok(())
+    }
+
+    pub fn queue_description(&self, os: &Os, output: &mut impl Write) -> Result<()> {
+        let dir = self.project_dir(os)?;
+        let Some(runner) = TestRunner::detect(os, &dir) else {
+            return Ok(());
+        };
+        let (program, args) = runner.command(self.filter.as_deref());
+        queue!(
+            output,
+            style::Print("I will run the following test command: "),
+            style::SetForegroundColor(Color::Green),
+            style::Print(format!("{program} {}", args.join(" "))),
+            style::ResetColor,
+            style::Print(format!(" in {}\n", dir.display())),
+        )?;
+        Ok(())
+    }
+
+    pub async fn invoke(&self, os: &Os, updates: impl Write) -> Result<InvokeOutput> {
+        let dir = self.project_dir(os)?;
+        let Some(runner) = TestRunner::detect(os, &dir) else {
+            bail!("No supported test runner found in '{}'", dir.display());
+        };
+        let (program, args) = runner.command(self.filter.as_deref());
+        Self::run(os, &dir, runner, program, &args, updates).await
+    }
+
+    /// Runs `program` in `dir` and summarizes its output as the output of `runner`.
+    async fn run(
+        os: &Os,
+        dir: &Path,
+        runner: TestRunner,
+        program: &str,
+        args: &[String],
+        mut updates: impl Write,
+    ) -> Result<InvokeOutput> {
+        let output = tokio::process::Command::new(program)
+            .args(args)
+            .current_dir(os.fs.chroot_path(dir))
+            .stdin(Stdio::null())
+            .output()
+            .await
+            .map_err(|err| eyre::eyre!("Failed to run {program}: {err}"))?;
+        let combined = format!(
+            "{}\n{}",
+            String::from_utf8_lossy(&output.stdout),
+            String::from_utf8_lossy(&output.stderr)
+        );
+
+        let mut summary = runner.parse(&combined);
+        if !output.status.success() && summary.failures.is_empty() {
+            let start = combined.len().saturating_sub(MAX_OUTPUT_TAIL_SIZE);
+            let start = (start..combined.len())
+                .find(|&i| combined.is_char_boundary(i))
+                .unwrap_or(combined.len());
+            summary.output_tail = Some(combined[start..].to_string());
+        }
+        summary.cap_size(MAX_TOOL_RESPONSE_SIZE / 2);
+
+        queue!(
+            updates,
+            style::Print(format!(
+                "{} passed, {} failed, {} ignored\n",
+                summary.passed, summary.failed, summary.ignored
+            )),
+        )?;
+
+        Ok(InvokeOutput {
+            output: OutputKind::Json(serde_json::json!({
+                "runner": runner,
+                "command": format!("{program} {}", args.join(" ")),
+                "success": output.status.success(),
+                "summary": summary,
+            })),
+        })
+    }
+}
+
+fn parse_cargo(output: &str) -> TestSummary {
+    static RESULT_LINE: LazyLock<Regex> =
+        LazyLock::new(|| Regex::new(r"^test (\S+)(?: - should panic)? \.\.\. (ok|FAILED|ignored)").unwrap());
+
+    let mut summary = TestSummary::default();
+    let mut current: Option<TestFailure> = None;
+    for line in output.lines() {
+        if let Some(caps) = RESULT_LINE.captures(line) {
+            match &caps[2] {
+                "ok" => summary.passed += 1,
+                "FAILED" => summary.failed += 1,
+                _ => summary.ignored += 1,
+            }
+            continue;
+        }
+
+        // Failure details are printed as "---- name stdout ----" followed by the captured output.
+        if let Some(name) = line.strip_prefix("---- ").and_then(|l| l.strip_suffix(" stdout ----")) {
+            summary.failures.extend(current.take());
+            current = Some(TestFailure {
+                name: name.to_string(),
+                message: String::new(),
+            });
+        } else if line == "failures:" || line.starts_with("test result:") {
+            summary.failures.extend(current.take());
+        } else if let Some(failure) = current.as_mut() {
+            failure.message.push_str(line);
+            failure.message.push('\n');
+        }
+    }
+    summary.failures.extend(current);
+    for failure in &mut summary.failures {
+        failure.message = failure.message.trim().to_string();
+    }
+    summary
+}
+
+fn parse_pytest(output: &str) -> TestSummary {
+    let mut summary = counts_from_totals(output);
+    for line in output.lines() {
+        if let Some(rest) = line.strip_prefix("FAILED ").or_else(|| line.strip_prefix("ERROR ")) {
+            let (name, message) = rest.split_once(" - ").unwrap_or((rest, ""));
+            summary.failures.push(TestFailure {
+                name: name.trim().to_string(),
+                message: message.trim().to_string(),
+            });
+        }
+    }
+    summary
+}
+
+fn parse_npm(output: &str) -> TestSummary {
+    let mut summary = counts_from_totals(output);
+    // Jest marks each failing test with a bullet followed by its full name.
+    for line in output.lines() {
+        if let Some(name) = line.trim_start().strip_prefix("● ") {
+            if !name.starts_with("Console") {
+                summary.failures.push(TestFailure {
+                    name: name.trim().to_string(),
+                    message: String::new(),
+                });
+            }
+        }
+    }
+    summary
+}
+
+/// Extracts pass/fail/skip counts from totals lines such as pytest's `1 failed, 2 passed in 0.1s`,
+/// jest's `Tests: 1 failed, 2 passed, 3 total` or mocha's `2 passing`.
+fn counts_from_totals(output: &str) -> TestSummary {
+    static COUNT: LazyLock<Regex> = LazyLock::new(|| {
+        Regex::new(r"(\d+) (passed|passing|failed|failing|errors?|skipped|pending|xfailed|todo)\b").unwrap()
+    });
+
+    let mut summary = TestSummary::default();
+    for caps in COUNT.captures_iter(output) {
+        let count: usize = caps[1].parse().unwrap_or_default();
+        match &caps[2] {
+            "passed" | "passing" => summary.passed = count,
+            "failed" | "failing" => summary.failed = count,
+            "error" | "errors" => summary.failed += count,
+            _ => summary.ignored = count,
+        }
+    }
+    summary
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_pytest() {
+        let output = "\
+.F.s
+=========================== short test summary info ============================
+FAILED tests/test_math.py::test_add - AssertionError: assert 3 == 4
+1 failed, 2 passed, 1 skipped in 0.05s
+";
+        let summary = parse_pytest(output);
+        assert_eq!(summary.passed, 2);
+        assert_eq!(summary.failed, 1);
+        assert_eq!(summary.ignored, 1);
+        assert_eq!(summary.failures, vec![TestFailure {
+            name: "tests/test_math.py::test_add".to_string(),
+            message: "AssertionError: assert 3 == 4".to_string(),
+        }]);
+    }
+
+    #[test]
+    fn test_cap_size() {
+        let mut summary = TestSummary {
+            failed: 50,
+            failures: (0..50)
+                .map(|i| TestFailure {
+                    name: format!("test_{i}"),
+                    message: "x".repeat(MAX_FAILURE_MESSAGE_SIZE * 2),
+                })
+                .collect(),
+            ..Default::default()
+        };
+        summary.cap_size(20_000);
+        assert!(serde_json::to_string(&summary).unwrap().len() <= 20_000);
+        assert!(
+            summary
+                .failures
+                .iter()
+                .all(|f| f.message.len() == MAX_FAILURE_MESSAGE_SIZE)
+        );
+        assert_eq!(summary.failures.len() + summary.omitted_failures, 50);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_run_cargo_tests() {
+        let os = Os::new().await.unwrap();
+        os.fs.create_dir_all("/project").await.unwrap();
+        os.fs.write("/project/Cargo.toml", "[package]\n").await.unwrap();
+        // Stands in for `cargo test`, printing what a run with one passing and one failing test prints
+        os.fs
+            .write(
+                "/project/cargo_output.txt",
+                "\
+running 2 tests
+test tests::passes ... ok
+test tests::fails ... FAILED
+
+failures:
+
+---- tests::fails stdout ----
+assertion `left == right` failed: math is broken
+
+failures:
+    tests::fails
+
+test result: FAILED. 1 passed; 1 failed; 0 ignored; 0 measured; 0 filtered out
+",
+            )
+            .await
+            .unwrap();
+
+        let mut tool = RunTests {
+            path: Some("/project".to_string()),
+            filter: None,
+        };
+        tool.validate(&os).await.unwrap();
+        assert_eq!(TestRunner::detect(&os, Path::new("/project")), Some(TestRunner::Cargo));
+        let args = ["-c".to_string(), "cat cargo_output.txt; exit 101".to_string()];
+        let output = RunTests::run(
+            &os,
+            Path::new("/project"),
+            TestRunner::Cargo,
+            "sh",
+            &args,
+            std::io::sink(),
+        )
+        .await
+        .unwrap();
+        let OutputKind::Json(json) = output.output else {
+            panic!("expected json output");
+        };
+
+        assert_eq!(json["runner"], "cargo");
+        assert_eq!(json["success"], false);
+        assert_eq!(json["summary"]["passed"], 1);
+        assert_eq!(json["summary"]["failed"], 1);
+        let failures = json["summary"]["failures"].as_array().unwrap();
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0]["name"], "tests::fails");
+        assert!(failures[0]["message"].as_str().unwrap().contains("math is broken"));
+    }
+
+    #[test]
+    fn test_npm_program() {
+        let (program, _) = TestRunner::Npm.command(None);
+        assert_eq!(program, if cfg!(windows) { "npm.cmd" } else { "npm" });
+    }
+
+    #[tokio::test]
+    async fn test_validate_without_runner() {
+        let os = Os::new().await.unwrap();
+        os.fs.create_dir_all("/empty").await.unwrap();
+        let mut tool = RunTests {
+            path: Some("/empty".to_string()),
+            filter: None,
+        };
+        assert!(tool.validate(&os).await.is_err());
+    }
+}
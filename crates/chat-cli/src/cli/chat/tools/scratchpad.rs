@@ -0,0 +1,171 @@
+use std::io::Write;
+
+use crossterm::queue;
+use crossterm::style::{
+    self,
+    Color,
+};
+use eyre::{
+    Result,
+    bail,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use super::{
+    InvokeOutput,
+    OutputKind,
+};
+use crate::database::settings::Setting;
+use crate::os::Os;
+
+/// Largest scratchpad the model can write, in bytes.
+pub const MAX_SCRATCHPAD_LEN: usize = 16 * 1024;
+
+/// Working notes the model keeps for itself over a conversation. They are saved with the
+/// conversation but never shown in the transcript; the model reads them back with
+/// `scratchpad_read`.
+///
+/// This is a beta feature that can be enabled/disabled via settings:
+/// `q settings chat.enableScratchpad true`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Scratchpad(String);
+
+impl Scratchpad {
+    /// Checks if the scratchpad tools are enabled in settings
+    pub fn is_enabled(os: &Os) -> bool {
+        os.database
+            .settings
+            .get_bool(Setting::EnabledScratchpad)
+            .unwrap_or(false)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Replaces the notes with `content`, or appends it on a new line when `append` is set. Fails
+    /// without changing anything if the result would exceed [MAX_SCRATCHPAD_LEN].
+    pub fn write(&mut self, content: &str, append: bool) -> Result<()> {
+        let separator = if append && !self.0.is_empty() { "\n" } else { "" };
+        let len = if append {
+            self.0.len() + separator.len() + content.len()
+        } else {
+            content.len()
+        };
+        if len > MAX_SCRATCHPAD_LEN {
+            bail!(
+                "The scratchpad would be {len} bytes, over the limit of {MAX_SCRATCHPAD_LEN}. Rewrite it with only the notes that are still needed."
+            );
+        }
+
+        if !append {
+            self.0.clear();
+        }
+        self.0.push_str(separator);
+        self.0.push_str(content);
+        Ok(())
+    }
+}
+
+/// Returns the current scratchpad contents.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ScratchpadRead {}
+
+impl ScratchpadRead {
+    pub async fn invoke(&self, scratchpad: &Scratchpad) -> Result<InvokeOutput> {
+        let text = match scratchpad.is_empty() {
+            true => "The scratchpad is empty.".to_string(),
+            false => scratchpad.as_str().to_string(),
+        };
+        Ok(InvokeOutput {
+            output: OutputKind::Text(text),
+        })
+    }
+}
+
+/// Replaces or appends to the scratchpad.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ScratchpadWrite {
+    pub content: String,
+    #[serde(default)]
+    pub append: bool,
+}
+
+impl ScratchpadWrite {
+    pub fn queue_description(&self, output: &mut impl Write) -> Result<()> {
+        queue!(
+            output,
+            style::SetForegroundColor(Color::DarkGrey),
+            style::Print(match self.append {
+                true => "Appending to the scratchpad\n",
+                false => "Updating the scratchpad\n",
+            }),
+            style::SetForegroundColor(Color::Reset),
+        )?;
+        Ok(())
+    }
+
+    pub async fn invoke(&self, scratchpad: &mut Scratchpad) -> Result<InvokeOutput> {
+        scratchpad.write(&self.content, self.append)?;
+        Ok(InvokeOutput {
+            output: OutputKind::Text(format!(
+                "Saved. The scratchpad is {} of {MAX_SCRATCHPAD_LEN} bytes.",
+                scratchpad.as_str().len()
+            )),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(output: InvokeOutput) -> String {
+        match output.output {
+            OutputKind::Text(text) => text,
+            other => panic!("expected text output, got {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_scratchpad_write_and_read() {
+        let mut scratchpad = Scratchpad::default();
+        let read = ScratchpadRead {};
+        assert_eq!(
+            text(read.invoke(&scratchpad).await.unwrap()),
+            "The scratchpad is empty."
+        );
+
+        let write = |content: &str, append| ScratchpadWrite {
+            content: content.to_string(),
+            append,
+        };
+        write("step 1 done", false).invoke(&mut scratchpad).await.unwrap();
+        write("step 2: check the parser", true)
+            .invoke(&mut scratchpad)
+            .await
+            .unwrap();
+        assert_eq!(
+            text(read.invoke(&scratchpad).await.unwrap()),
+            "step 1 done\nstep 2: check the parser"
+        );
+
+        write("fresh notes", false).invoke(&mut scratchpad).await.unwrap();
+        assert_eq!(scratchpad.as_str(), "fresh notes");
+
+        // Writes past the cap fail and leave the notes untouched
+        let too_long = "x".repeat(MAX_SCRATCHPAD_LEN);
+        assert!(write(&too_long, true).invoke(&mut scratchpad).await.is_err());
+        assert_eq!(scratchpad.as_str(), "fresh notes");
+        write(&too_long, false).invoke(&mut scratchpad).await.unwrap();
+        assert_eq!(scratchpad.as_str().len(), MAX_SCRATCHPAD_LEN);
+    }
+}
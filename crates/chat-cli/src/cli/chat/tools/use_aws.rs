@@ -28,6 +28,7 @@ use crate::cli::agent::{
     Agent,
     PermissionEvalResult,
 };
+use crate::database::settings::Setting;
 use crate::os::Os;
 use crate::util::pattern_matching::matches_any_pattern;
 
@@ -174,7 +175,25 @@ impl UseAws {
         }
     }
 
-    pub fn eval_perm(&self, _os: &Os, agent: &Agent) -> PermissionEvalResult {
+    /// The `service:operation` globs in the `setting` list that match this call. Operation names
+    /// are compared ignoring case, dashes and underscores, so `ec2:Describe*` matches
+    /// `describe-instances`.
+    fn matching_operation_patterns(&self, os: &Os, setting: Setting) -> Vec<String> {
+        let normalize = |s: &str| s.to_lowercase().replace(['-', '_'], "");
+        let operation = normalize(&format!("{}:{}", self.service_name, self.operation_name));
+        os.database
+            .settings
+            .get(setting)
+            .and_then(|v| v.as_array())
+            .into_iter()
+            .flatten()
+            .filter_map(|pattern| pattern.as_str())
+            .filter(|pattern| matches_any_pattern(&[normalize(pattern)].into(), &operation))
+            .map(str::to_string)
+            .collect()
+    }
+
+    pub fn eval_perm(&self, os: &Os, agent: &Agent) -> PermissionEvalResult {
         #[derive(Debug, Deserialize)]
         #[serde(rename_all = "camelCase")]
         struct Settings {
@@ -186,8 +205,17 @@ impl UseAws {
             auto_allow_readonly: bool,
         }
 
+        // Denied operations are refused even for trusted tools and allowed services
+        let denied_operations = self.matching_operation_patterns(os, Setting::ToolsUseAwsDeniedOperations);
+        if !denied_operations.is_empty() {
+            return PermissionEvalResult::Deny(denied_operations);
+        }
+        let is_allowed_operation = !self
+            .matching_operation_patterns(os, Setting::ToolsUseAwsAllowedOperations)
+            .is_empty();
+
         let Self { service_name, .. } = self;
-        let is_in_allowlist = matches_any_pattern(&agent.allowed_tools, "use_aws");
+        let is_in_allowlist = matches_any_pattern(&agent.allowed_tools, "use_aws") || is_allowed_operation;
         match agent.tools_settings.get("use_aws") {
             Some(settings) => {
                 let settings = match serde_json::from_value::<Settings>(settings.clone()) {
@@ -505,4 +533,83 @@ mod tests {
         // Should deny even read-only operations on denied services
         assert!(matches!(res, PermissionEvalResult::Deny(ref services) if services.contains(&"s3".to_string())));
     }
+
+    #[tokio::test]
+    async fn test_eval_perm_allowed_operations() {
+        let mut os = Os::new().await.unwrap();
+        os.database
+            .settings
+            .set(
+                Setting::ToolsUseAwsAllowedOperations,
+                serde_json::json!(["ec2:Describe*", "s3:put-object"]),
+            )
+            .await
+            .unwrap();
+        let agent = Agent::default();
+
+        // Allowlisted operations skip confirmation, whether or not they are read-only
+        let describe = use_aws! {{
+            "service_name": "ec2",
+            "operation_name": "describe-instances",
+            "region": "us-west-2"
+        }};
+        assert!(matches!(describe.eval_perm(&os, &agent), PermissionEvalResult::Allow));
+        let put = use_aws! {{
+            "service_name": "s3",
+            "operation_name": "put-object",
+            "region": "us-west-2"
+        }};
+        assert!(matches!(put.eval_perm(&os, &agent), PermissionEvalResult::Allow));
+
+        let delete = use_aws! {{
+            "service_name": "s3",
+            "operation_name": "delete-object",
+            "region": "us-west-2"
+        }};
+        assert!(matches!(delete.eval_perm(&os, &agent), PermissionEvalResult::Ask));
+    }
+
+    #[tokio::test]
+    async fn test_eval_perm_denied_operations() {
+        let mut os = Os::new().await.unwrap();
+        os.database
+            .settings
+            .set(Setting::ToolsUseAwsDeniedOperations, serde_json::json!(["s3:delete-*"]))
+            .await
+            .unwrap();
+        os.database
+            .settings
+            .set(Setting::ToolsUseAwsAllowedOperations, serde_json::json!(["s3:*"]))
+            .await
+            .unwrap();
+        let mut agent = Agent {
+            tools_settings: {
+                let mut map = HashMap::<ToolSettingTarget, serde_json::Value>::new();
+                map.insert(
+                    ToolSettingTarget("use_aws".to_string()),
+                    serde_json::json!({ "allowedServices": ["s3"] }),
+                );
+                map
+            },
+            ..Default::default()
+        };
+        agent.allowed_tools.insert("use_aws".to_string());
+
+        // Denied even though the tool is trusted, the service allowed and the operation allowlisted
+        let delete = use_aws! {{
+            "service_name": "s3",
+            "operation_name": "DeleteObject",
+            "region": "us-west-2"
+        }};
+        assert!(
+            matches!(delete.eval_perm(&os, &agent), PermissionEvalResult::Deny(ref rules) if rules == &["s3:delete-*"])
+        );
+
+        let list = use_aws! {{
+            "service_name": "s3",
+            "operation_name": "list-objects",
+            "region": "us-west-2"
+        }};
+        assert!(matches!(list.eval_perm(&os, &agent), PermissionEvalResult::Allow));
+    }
 }
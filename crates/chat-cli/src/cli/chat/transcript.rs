@@ -0,0 +1,186 @@
+use std::io::{
+    self,
+    Write,
+};
+use std::path::Path;
+use std::sync::{
+    Arc,
+    Mutex,
+};
+
+use tracing::warn;
+
+use crate::database::settings::Setting;
+use crate::os::Os;
+
+/// A file the rendered output of a session is copied into, escape codes included, so that the
+/// session can be reviewed as it appeared with `cat`. This is separate from the conversation
+/// history that is sent to the model.
+#[derive(Clone)]
+pub struct Transcript(Arc<Mutex<Box<dyn Write + Send>>>);
+
+impl Transcript {
+    /// Opens the transcript of a conversation in the directory set with
+    /// `session.recordTranscriptPath`, if recording is enabled.
+    pub fn open(os: &Os, conversation_id: &str) -> Option<Self> {
+        let dir = os.database.settings.get_string(Setting::SessionRecordTranscriptPath)?;
+        let path = Path::new(&dir).join(format!("{conversation_id}.log"));
+        let file = os.fs.create_dir_all_sync(&dir).and_then(|_| os.fs.append_sync(&path));
+        match file {
+            Ok(file) => Some(Self::from_writer(file)),
+            Err(err) => {
+                warn!(?err, "failed to open transcript {}", path.display());
+                None
+            },
+        }
+    }
+
+    pub fn from_writer(writer: impl Write + Send + 'static) -> Self {
+        Self(Arc::new(Mutex::new(Box::new(writer))))
+    }
+
+    /// Records text that is not written through a [Recorded] stream, such as the line a user typed
+    pub fn record(&self, buf: &[u8]) {
+        if let Ok(mut writer) = self.0.lock() {
+            if let Err(err) = writer.write_all(buf) {
+                warn!(?err, "failed to write to transcript");
+            }
+        }
+    }
+}
+
+/// An output stream that copies everything written to it into the session's [Transcript]
+pub struct Recorded<W> {
+    inner: W,
+    transcript: Option<Transcript>,
+}
+
+impl<W: Write> Recorded<W> {
+    pub fn new(inner: W, transcript: Option<Transcript>) -> Self {
+        Self { inner, transcript }
+    }
+}
+
+impl<W: Write> Write for Recorded<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        if let Some(transcript) = &self.transcript {
+            transcript.record(&buf[..written]);
+        }
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if let Some(transcript) = &self.transcript {
+            if let Ok(mut writer) = transcript.0.lock() {
+                if let Err(err) = writer.flush() {
+                    warn!(?err, "failed to flush transcript");
+                }
+            }
+        }
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crossterm::style::{
+        self,
+        Color,
+    };
+    use crossterm::{
+        execute,
+        queue,
+    };
+
+    use super::*;
+
+    /// Collects what is written to it
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// Fails every write and flush, like a transcript on a disk that has filled up
+    struct Broken;
+
+    impl Write for Broken {
+        fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+            Err(io::Error::other("disk full"))
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Err(io::Error::other("disk full"))
+        }
+    }
+
+    #[test]
+    fn test_broken_transcript_does_not_fail_output() {
+        let mut stdout = Recorded::new(Vec::new(), Some(Transcript::from_writer(Broken)));
+        execute!(stdout, style::Print("still shown")).unwrap();
+        stdout.flush().unwrap();
+        assert_eq!(stdout.inner, b"still shown");
+    }
+
+    #[tokio::test]
+    async fn test_open_appends_to_transcript() {
+        let mut os = Os::new().await.unwrap();
+        assert!(Transcript::open(&os, "abc").is_none());
+
+        os.database
+            .settings
+            .set(Setting::SessionRecordTranscriptPath, "/transcripts")
+            .await
+            .unwrap();
+        Transcript::open(&os, "abc").unwrap().record(b"first\n");
+        Transcript::open(&os, "abc").unwrap().record(b"second\n");
+
+        assert_eq!(
+            os.fs.read_to_string("/transcripts/abc.log").await.unwrap(),
+            "first\nsecond\n"
+        );
+    }
+
+    #[test]
+    fn test_records_rendered_output() {
+        let file = Buffer::default();
+        let transcript = Transcript::from_writer(file.clone());
+        let mut stdout = Recorded::new(Vec::new(), Some(transcript.clone()));
+        let mut stderr = Recorded::new(Vec::new(), Some(transcript.clone()));
+
+        for (input, response) in [("hello", "Hi there!"), ("bye", "Goodbye!")] {
+            transcript.record(format!("> {input}\n").as_bytes());
+            queue!(
+                stdout,
+                style::SetForegroundColor(Color::Magenta),
+                style::Print(response),
+                style::SetForegroundColor(Color::Reset),
+                style::Print("\n"),
+            )
+            .unwrap();
+            execute!(
+                stderr,
+                style::SetForegroundColor(Color::DarkGrey),
+                style::Print("▸ Credits: 0.01\n")
+            )
+            .unwrap();
+        }
+
+        // The terminal streams get their output as before
+        assert!(String::from_utf8(stdout.inner.clone()).unwrap().contains("Hi there!"));
+
+        let recorded = String::from_utf8(file.0.lock().unwrap().clone()).unwrap();
+        assert_eq!(
+            recorded,
+            "> hello\n\x1b[38;5;13mHi there!\x1b[39m\n\x1b[38;5;8m▸ Credits: 0.01\n> bye\n\x1b[38;5;13mGoodbye!\x1b[39m\n\x1b[38;5;8m▸ Credits: 0.01\n"
+        );
+    }
+}
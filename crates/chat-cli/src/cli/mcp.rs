@@ -68,6 +68,8 @@ pub enum McpSubcommand {
     Import(ImportArgs),
     /// Get the status of a configured server
     Status(StatusArgs),
+    /// Approve a server that is disabled because it is new, see `mcp.newServerPolicy`
+    Approve(ApproveArgs),
 }
 
 impl McpSubcommand {
@@ -78,6 +80,7 @@ impl McpSubcommand {
             Self::List(args) => args.execute(os, output).await?,
             Self::Import(args) => args.execute(os, output).await?,
             Self::Status(args) => args.execute(os, output).await?,
+            Self::Approve(args) => args.execute(os, output).await?,
         }
 
         output.flush()?;
@@ -151,6 +154,8 @@ impl AddArgs {
                 mcp_servers.insert(self.name.clone(), tool);
                 let json = agent.to_str_pretty()?;
                 os.fs.write(config_path, json).await?;
+                // Adding a server is approval enough for mcp.newServerPolicy
+                os.database.add_known_mcp_servers([self.name.clone()])?;
                 writeln!(output, "✓ Added MCP server '{}' to agent {}\n", self.name, agent_name)?;
             },
             None => {
@@ -183,6 +188,7 @@ impl AddArgs {
 
                 mcp_servers.mcp_servers.insert(self.name.clone(), tool);
                 mcp_servers.save_to_file(os, &legacy_mcp_config_path).await?;
+                os.database.add_known_mcp_servers([self.name.clone()])?;
                 writeln!(
                     output,
                     "✓ Added MCP server '{}' to global config in {}\n",
@@ -381,6 +387,34 @@ impl ImportArgs {
     }
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, Args)]
+pub struct ApproveArgs {
+    #[arg(long)]
+    pub name: String,
+}
+
+impl ApproveArgs {
+    pub async fn execute(self, os: &mut Os, output: &mut impl Write) -> Result<()> {
+        let configs = get_mcp_server_configs(os).await?;
+        let configured = configs.values().flatten().any(|(_, cfg_opt, _)| {
+            cfg_opt
+                .as_ref()
+                .is_some_and(|cfg| cfg.mcp_servers.contains_key(&self.name))
+        });
+        if !configured {
+            bail!("No MCP server named '{}' found in any agent\n", self.name);
+        }
+
+        os.database.add_known_mcp_servers([self.name.clone()])?;
+        writeln!(
+            output,
+            "\n✓ Approved MCP server '{}'. It loads according to its configuration from the next session.\n",
+            self.name
+        )?;
+        Ok(())
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Args)]
 pub struct StatusArgs {
     #[arg(long)]
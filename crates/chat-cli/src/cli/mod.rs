@@ -1,4 +1,4 @@
-mod agent;
+pub(crate) mod agent;
 pub mod chat;
 mod debug;
 mod diagnostics;
@@ -1,3 +1,4 @@
+use std::path::PathBuf;
 use std::process::ExitCode;
 
 use anstream::println;
@@ -32,6 +33,16 @@ pub enum SettingsSubcommands {
         #[arg(long, short, hide = true)]
         state: bool,
     },
+    /// Reset a setting to its default value
+    Unset {
+        /// key
+        key: String,
+    },
+    /// Back up the settings and state database to a file, e.g. before upgrading
+    Backup {
+        /// Path of the backup file
+        path: PathBuf,
+    },
 }
 
 #[derive(Clone, Debug, Args, PartialEq, Eq)]
@@ -55,7 +66,20 @@ pub struct SettingsArgs {
 
 impl SettingsArgs {
     pub async fn execute(&self, os: &mut Os) -> Result<ExitCode> {
-        match self.cmd {
+        match &self.cmd {
+            Some(SettingsSubcommands::Unset { key }) => {
+                let key = Setting::try_from(key.as_str())?;
+                match os.database.settings.unset(key).await? {
+                    Some(default) => println!("Reset {key} to its default: {default}"),
+                    None => println!("Unset {key}"),
+                }
+                Ok(ExitCode::SUCCESS)
+            },
+            Some(SettingsSubcommands::Backup { path }) => {
+                os.database.backup_to(path)?;
+                println!("Backed up the database to {}", path.display());
+                Ok(ExitCode::SUCCESS)
+            },
             Some(SettingsSubcommands::Open) => {
                 let file = directories::settings_path().context("Could not get settings path")?;
                 if let Ok(editor) = os.env.get("EDITOR") {
@@ -66,8 +90,15 @@ impl SettingsArgs {
                 }
             },
             Some(SettingsSubcommands::All { format, state }) => {
+                let (format, state) = (*format, *state);
                 let settings = match state {
-                    true => os.database.get_all_entries()?,
+                    true => {
+                        let dump = os.database.state_dump()?;
+                        if dump.is_empty() && format == OutputFormat::Plain {
+                            println!("No state entries");
+                        }
+                        dump.entries
+                    },
                     false => os.database.settings.map().clone(),
                 };
 
@@ -92,7 +123,7 @@ impl SettingsArgs {
 
                 let key = Setting::try_from(key.as_str())?;
                 match (&self.value, self.delete) {
-                    (None, false) => match os.database.settings.get(key) {
+                    (None, false) => match os.database.settings.get_or_default(key) {
                         Some(value) => {
                             match self.format {
                                 OutputFormat::Plain => match value.as_str() {
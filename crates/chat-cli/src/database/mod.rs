@@ -1,15 +1,37 @@
 pub mod settings;
 
+use std::collections::{
+    BTreeSet,
+    HashSet,
+};
+use std::io::{
+    Read,
+    Write,
+};
 use std::ops::Deref;
 use std::path::Path;
 use std::str::FromStr;
-use std::sync::PoisonError;
+use std::sync::{
+    Arc,
+    Mutex,
+    PoisonError,
+};
 
 use aws_sdk_cognitoidentity::primitives::DateTimeFormat;
 use aws_sdk_cognitoidentity::types::Credentials;
+use chrono::{
+    DateTime,
+    Utc,
+};
+use flate2::Compression;
+use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
 use r2d2::Pool;
 use r2d2_sqlite::SqliteConnectionManager;
-use rusqlite::types::FromSql;
+use rusqlite::types::{
+    FromSql,
+    Value as SqlValue,
+};
 use rusqlite::{
     Connection,
     Error,
@@ -25,12 +47,16 @@ use serde_json::{
     Map,
     Value,
 };
-use settings::Settings;
+use settings::{
+    Setting,
+    Settings,
+};
 use thiserror::Error;
 use tracing::{
     error,
     info,
     trace,
+    warn,
 };
 use uuid::Uuid;
 
@@ -62,6 +88,39 @@ const IDC_REGION_KEY: &str = "auth.idc.region";
 const CUSTOMIZATION_STATE_KEY: &str = "api.selectedCustomization";
 const PROFILE_MIGRATION_KEY: &str = "profile.Migrated";
 const HEARTBEAT_DATE_KEY: &str = "telemetry.lastHeartbeatDate";
+const KNOWN_MCP_SERVERS_KEY: &str = "mcp.knownServers";
+const FAVORITE_CONVERSATIONS_KEY: &str = "conversations.favorites";
+
+/// Tables the client cannot work without, along with the migrations (by version) that create them
+/// in their current shape. See [has_migration] for how a table could go missing.
+const CRITICAL_TABLES: &[(&str, &[usize])] = &[("state", &[4, 6]), ("auth_kv", &[5]), ("conversations", &[7, 8, 11])];
+
+/// Number of times [Database::with_retry] retries an operation that failed with a busy or locked
+/// database before giving up.
+const MAX_BUSY_RETRIES: u32 = 5;
+/// Delay before the first retry, doubled on every subsequent attempt.
+const BUSY_RETRY_BASE_DELAY: std::time::Duration = std::time::Duration::from_millis(20);
+
+/// Waits out a retry delay from synchronous code. On a multi-threaded runtime the worker thread's
+/// other tasks are moved to another thread first, so that they keep running in the meantime.
+fn backoff_sleep(delay: std::time::Duration) {
+    match tokio::runtime::Handle::try_current() {
+        Ok(handle) if handle.runtime_flavor() == tokio::runtime::RuntimeFlavor::MultiThread => {
+            tokio::task::block_in_place(|| std::thread::sleep(delay));
+        },
+        _ => std::thread::sleep(delay),
+    }
+}
+
+/// Replaces the full-text index entry for the conversation stored under `key`.
+fn index_conversation(conn: &rusqlite::Connection, key: &str, state: &ConversationState) -> Result<(), DatabaseError> {
+    conn.execute("DELETE FROM conversations_fts WHERE key = ?1", params![key])?;
+    conn.execute("INSERT INTO conversations_fts (key, content) VALUES (?1, ?2)", params![
+        key,
+        state.message_text()
+    ])?;
+    Ok(())
+}
 
 const MIGRATIONS: &[Migration] = migrations![
     "000_migration_table",
@@ -71,7 +130,11 @@ const MIGRATIONS: &[Migration] = migrations![
     "004_state_table",
     "005_auth_table",
     "006_make_state_blob",
-    "007_conversations_table"
+    "007_conversations_table",
+    "008_conversations_last_accessed",
+    "009_conversations_fts",
+    "010_normalize_idc_state",
+    "011_conversations_version"
 ];
 
 #[derive(Debug, serde::Deserialize, serde::Serialize)]
@@ -97,10 +160,24 @@ impl From<amzn_codewhisperer_client::types::Profile> for AuthProfile {
     }
 }
 
-#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, serde::Serialize, serde::Deserialize)]
+#[derive(Clone, Eq, PartialOrd, Ord, serde::Serialize, serde::Deserialize)]
 #[serde(transparent)]
 pub struct Secret(pub String);
 
+impl Secret {
+    /// Compares two secrets in constant time with respect to their contents.
+    pub fn ct_eq(&self, other: &Secret) -> bool {
+        use subtle::ConstantTimeEq;
+        self.0.as_bytes().ct_eq(other.0.as_bytes()).into()
+    }
+}
+
+impl PartialEq for Secret {
+    fn eq(&self, other: &Self) -> bool {
+        self.ct_eq(other)
+    }
+}
+
 impl std::fmt::Debug for Secret {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("Secret").finish()
@@ -145,6 +222,33 @@ pub enum DatabaseError {
     StrFromUtf8(#[from] std::str::Utf8Error),
     #[error("`{}` is not a valid setting", .0)]
     InvalidSetting(String),
+    #[error(transparent)]
+    Conflict(#[from] ConflictError),
+}
+
+/// A conversation was saved by another session since it was loaded, see
+/// [Database::set_conversation_by_path]. The caller can reload the stored conversation, or base it
+/// on the `current` version with [ConversationState::set_db_version] and save again to overwrite
+/// it.
+#[derive(Debug, Clone, Error)]
+#[error("conversation at {path} was saved by another session (based on version {base}, now at version {current})")]
+pub struct ConflictError {
+    pub path: String,
+    /// The version the rejected save was based on
+    pub base: i64,
+    /// The version currently stored
+    pub current: i64,
+}
+
+impl DatabaseError {
+    /// Whether the error is a transient SQLite busy or locked failure that may succeed on retry.
+    pub fn is_busy(&self) -> bool {
+        matches!(
+            self,
+            DatabaseError::Rusqlite(Error::SqliteFailure(err, _))
+                if matches!(err.code, rusqlite::ErrorCode::DatabaseBusy | rusqlite::ErrorCode::DatabaseLocked)
+        )
+    }
 }
 
 impl<T> From<PoisonError<T>> for DatabaseError {
@@ -179,9 +283,71 @@ struct Migration {
     sql: &'static str,
 }
 
+/// Cumulative sizes of the conversations compressed by a [Database] when saving them.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CompressionStats {
+    /// Total size of conversations before compression, in bytes.
+    pub original_bytes: u64,
+    /// Total size of the same conversations after compression, in bytes.
+    pub compressed_bytes: u64,
+}
+
+impl CompressionStats {
+    /// The ratio of original to compressed size, if anything has been compressed.
+    pub fn ratio(&self) -> Option<f64> {
+        (self.compressed_bytes > 0).then(|| self.original_bytes as f64 / self.compressed_bytes as f64)
+    }
+
+    fn record(&mut self, original_bytes: usize, compressed_bytes: usize) {
+        self.original_bytes += original_bytes as u64;
+        self.compressed_bytes += compressed_bytes as u64;
+    }
+}
+
+/// Maximum number of characters kept in [ConversationMeta::preview].
+const CONVERSATION_PREVIEW_LEN: usize = 80;
+
+/// Summary of a stored conversation, as returned by [Database::list_recent_conversations].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ConversationMeta {
+    /// The path the conversation is stored under.
+    pub path: String,
+    /// When the conversation was last saved, if known.
+    pub last_accessed: Option<DateTime<Utc>>,
+    /// The start of the first user message, collapsed onto a single line.
+    pub preview: Option<String>,
+    /// Whether the conversation is marked as a favorite with [Database::set_favorite].
+    pub favorite: bool,
+}
+
+fn conversation_preview(prompt: &str) -> String {
+    let line = prompt.split_whitespace().collect::<Vec<_>>().join(" ");
+    match line.char_indices().nth(CONVERSATION_PREVIEW_LEN) {
+        Some((idx, _)) => format!("{}...", &line[..idx]),
+        None => line,
+    }
+}
+
+/// Snapshot of the persistent application state, as returned by [Database::state_dump].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct StateDump {
+    /// Every state entry, sorted by key.
+    pub entries: Map<String, Value>,
+    /// Number of entries. Zero means the state table is empty, as opposed to a failed read, which
+    /// is reported as an error instead.
+    pub count: usize,
+}
+
+impl StateDump {
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct Database {
     pool: Pool<SqliteConnectionManager>,
+    compression_stats: Arc<Mutex<CompressionStats>>,
     pub settings: Settings,
 }
 
@@ -191,6 +357,7 @@ impl Database {
             true => {
                 return Self {
                     pool: Pool::builder().build(SqliteConnectionManager::memory()).unwrap(),
+                    compression_stats: Default::default(),
                     settings: Settings::new().await?,
                 }
                 .migrate();
@@ -221,12 +388,27 @@ impl Database {
             }
         }
 
-        Ok(Self {
+        let database = Self {
             pool,
+            compression_stats: Default::default(),
             settings: Settings::new().await?,
         }
         .migrate()
-        .map_err(|e| DbOpenError(e.to_string()))?)
+        .map_err(|e| DbOpenError(e.to_string()))?;
+
+        if let Some(max) = database
+            .settings
+            .get_int(Setting::ChatMaxConversations)
+            .and_then(|max| usize::try_from(max).ok())
+        {
+            match database.enforce_conversation_cap(max) {
+                Ok(0) => {},
+                Ok(evicted) => info!(evicted, max, "evicted least recently accessed conversations"),
+                Err(err) => warn!(?err, "failed to enforce the conversation cap"),
+            }
+        }
+
+        Ok(database)
     }
 
     /// Get all entries for dumping the persistent application state.
@@ -234,6 +416,16 @@ impl Database {
         self.all_entries(Table::State)
     }
 
+    /// Get all entries of the persistent application state sorted by key, for stable display.
+    pub fn state_dump(&self) -> Result<StateDump, DatabaseError> {
+        let mut entries = self.get_all_entries()?;
+        entries.sort_keys();
+        Ok(StateDump {
+            count: entries.len(),
+            entries,
+        })
+    }
+
     /// Get cognito credentials used by toolkit telemetry.
     pub fn get_credentials_entry(&mut self) -> Result<Option<CredentialsJson>, DatabaseError> {
         self.get_json_entry::<CredentialsJson>(Table::State, CREDENTIALS_KEY)
@@ -297,6 +489,26 @@ impl Database {
         Ok(())
     }
 
+    /// Names of the MCP servers that have been seen or approved before, which are exempt from
+    /// `mcp.newServerPolicy`.
+    pub fn get_known_mcp_servers(&self) -> Result<HashSet<String>, DatabaseError> {
+        Ok(self
+            .get_json_entry::<HashSet<String>>(Table::State, KNOWN_MCP_SERVERS_KEY)?
+            .unwrap_or_default())
+    }
+
+    /// Record MCP servers as seen or approved.
+    pub fn add_known_mcp_servers(&self, names: impl IntoIterator<Item = String>) -> Result<(), DatabaseError> {
+        let mut known = self.get_known_mcp_servers()?;
+        known.extend(names);
+        self.set_json_entry(
+            Table::State,
+            KNOWN_MCP_SERVERS_KEY,
+            known.into_iter().collect::<BTreeSet<_>>(),
+        )?;
+        Ok(())
+    }
+
     /// Set the client ID used for telemetry requests.
     pub fn set_client_id(&mut self, client_id: Uuid) -> Result<usize, DatabaseError> {
         self.set_json_entry(Table::State, CLIENT_ID_KEY, client_id.to_string())
@@ -304,24 +516,22 @@ impl Database {
 
     /// Get the start URL used for IdC login.
     pub fn get_start_url(&self) -> Result<Option<String>, DatabaseError> {
-        self.get_json_entry::<String>(Table::State, START_URL_KEY)
+        self.get_legacy_string_entry(Table::State, START_URL_KEY)
     }
 
     /// Set the start URL used for IdC login.
     pub fn set_start_url(&mut self, start_url: String) -> Result<usize, DatabaseError> {
-        self.set_json_entry(Table::State, START_URL_KEY, start_url)
+        self.set_entry(Table::State, START_URL_KEY, start_url)
     }
 
     /// Get the region used for IdC login.
     pub fn get_idc_region(&self) -> Result<Option<String>, DatabaseError> {
-        // Annoyingly, this is encoded as a JSON string on older clients
-        self.get_json_entry::<String>(Table::State, IDC_REGION_KEY)
+        self.get_legacy_string_entry(Table::State, IDC_REGION_KEY)
     }
 
     /// Set the region used for IdC login.
     pub fn set_idc_region(&mut self, region: String) -> Result<usize, DatabaseError> {
-        // Annoyingly, this is encoded as a JSON string on older clients
-        self.set_json_entry(Table::State, IDC_REGION_KEY, region)
+        self.set_entry(Table::State, IDC_REGION_KEY, region)
     }
 
     /// Get if user has already completed a migration
@@ -370,32 +580,201 @@ impl Database {
     // }
 
     /// Get a chat conversation given a path to the conversation.
-    pub fn get_conversation_by_path(
-        &mut self,
-        path: impl AsRef<Path>,
-    ) -> Result<Option<ConversationState>, DatabaseError> {
+    pub fn get_conversation_by_path(&self, path: impl AsRef<Path>) -> Result<Option<ConversationState>, DatabaseError> {
         // We would need to encode this to support non utf8 paths.
         let path = match path.as_ref().to_str() {
             Some(path) => path,
             None => return Ok(None),
         };
 
-        self.get_json_entry(Table::Conversations, path)
+        let conn = self.pool.get()?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT value, version FROM {} WHERE key = ?1",
+            Table::Conversations
+        ))?;
+        let (value, version) = match stmt.query_row(params![path], |row| {
+            Ok((row.get::<_, SqlValue>(0)?, row.get::<_, i64>(1)?))
+        }) {
+            Ok(row) => row,
+            Err(Error::QueryReturnedNoRows) => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+
+        let mut state = self.decode_conversation(value)?;
+        state.set_db_version(Some((path.to_string(), version)));
+        Ok(Some(state))
     }
 
-    /// Set a chat conversation given a path to the conversation.
+    /// Set a chat conversation given a path to the conversation, returning the version it was
+    /// saved as.
+    ///
+    /// Saves are rejected with a [ConflictError] when the conversation was loaded from or last
+    /// saved under `path` as a version that is no longer the stored one, so that concurrent
+    /// sessions don't silently overwrite each other. A conversation without a version for `path`
+    /// always overwrites.
     pub fn set_conversation_by_path(
         &mut self,
         path: impl AsRef<Path>,
         state: &ConversationState,
-    ) -> Result<usize, DatabaseError> {
+    ) -> Result<i64, DatabaseError> {
         // We would need to encode this to support non utf8 paths.
         let path = match path.as_ref().to_str() {
             Some(path) => path,
             None => return Ok(0),
         };
 
-        self.set_json_entry(Table::Conversations, path, state)
+        self.set_conversation_entry(path, state, chrono::Utc::now().timestamp_millis())
+    }
+
+    /// Get the most recently accessed chat conversation whose path is `prefix` or lies beneath it,
+    /// returning the path it was stored under alongside the conversation. The conversation carries
+    /// its stored version, so that saving it again detects saves by other sessions.
+    pub fn most_recent_conversation(
+        &self,
+        prefix: impl AsRef<Path>,
+    ) -> Result<Option<(String, ConversationState)>, DatabaseError> {
+        // We would need to encode this to support non utf8 paths.
+        let prefix = match prefix.as_ref().to_str() {
+            Some(prefix) => prefix.trim_end_matches(std::path::MAIN_SEPARATOR),
+            None => return Ok(None),
+        };
+        let child_prefix = format!("{prefix}{}", std::path::MAIN_SEPARATOR);
+
+        // Conversations saved before last_accessed existed have a NULL timestamp and sort last.
+        let conn = self.pool.get()?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT key, value, version FROM {} WHERE key = ?1 OR substr(key, 1, length(?2)) = ?2 \
+             ORDER BY COALESCE(last_accessed, 0) DESC LIMIT 1",
+            Table::Conversations
+        ))?;
+        let (key, value, version) = match stmt.query_row(params![prefix, child_prefix], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, SqlValue>(1)?,
+                row.get::<_, i64>(2)?,
+            ))
+        }) {
+            Ok(row) => row,
+            Err(Error::QueryReturnedNoRows) => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+
+        let mut state = self.decode_conversation(value)?;
+        state.set_db_version(Some((key.clone(), version)));
+        Ok(Some((key, state)))
+    }
+
+    /// Marks the conversation stored under `path` as accessed now, e.g. when it is resumed, without
+    /// changing its version.
+    pub fn touch_conversation(&self, path: &str) -> Result<(), DatabaseError> {
+        self.pool.get()?.execute(
+            &format!("UPDATE {} SET last_accessed = ?2 WHERE key = ?1", Table::Conversations),
+            params![path, chrono::Utc::now().timestamp_millis()],
+        )?;
+        Ok(())
+    }
+
+    /// Full-text search over the messages of stored conversations, best matches first. `query` uses
+    /// SQLite FTS5 syntax, e.g. `"exact phrase"` or `deploy AND staging`. Returns the path of each
+    /// matching conversation with a snippet of the matched text.
+    pub fn search_conversations(&self, query: &str) -> Result<Vec<(String, String)>, DatabaseError> {
+        let conn = self.pool.get()?;
+        let mut stmt = conn.prepare(
+            "SELECT key, snippet(conversations_fts, 1, '[', ']', '...', 12) FROM conversations_fts \
+             WHERE conversations_fts MATCH ?1 ORDER BY rank",
+        )?;
+        let rows = stmt.query_map(params![query], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        Ok(rows.collect::<Result<Vec<_>, _>>()?)
+    }
+
+    /// Mark the conversation stored under `path` as a favorite, or unmark it, so that it is listed
+    /// above the others.
+    pub fn set_favorite(&self, path: &str, favorite: bool) -> Result<(), DatabaseError> {
+        let mut favorites = self.list_favorites()?.into_iter().collect::<BTreeSet<_>>();
+        if favorite {
+            favorites.insert(path.to_string());
+        } else {
+            favorites.remove(path);
+        }
+        self.set_json_entry(Table::State, FAVORITE_CONVERSATIONS_KEY, favorites)?;
+        Ok(())
+    }
+
+    /// Paths of the conversations marked as favorites, sorted.
+    pub fn list_favorites(&self) -> Result<Vec<String>, DatabaseError> {
+        Ok(self
+            .get_json_entry::<Vec<String>>(Table::State, FAVORITE_CONVERSATIONS_KEY)?
+            .unwrap_or_default())
+    }
+
+    /// List stored conversations newest first, optionally only those accessed at or after `since`.
+    /// Favorites are listed before all other conversations.
+    ///
+    /// Conversations saved before access times were recorded are only listed when `since` is
+    /// `None`.
+    pub fn list_recent_conversations(
+        &self,
+        limit: usize,
+        since: Option<DateTime<Utc>>,
+    ) -> Result<Vec<ConversationMeta>, DatabaseError> {
+        let favorites = serde_json::to_string(&self.list_favorites()?)?;
+        let conn = self.pool.get()?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT key, value, last_accessed, key IN (SELECT value FROM json_each(?3)) AS favorite FROM {} \
+             WHERE ?1 IS NULL OR last_accessed >= ?1 \
+             ORDER BY favorite DESC, COALESCE(last_accessed, 0) DESC LIMIT ?2",
+            Table::Conversations
+        ))?;
+        let rows = stmt
+            .query_map(
+                params![since.map(|since| since.timestamp_millis()), limit as i64, favorites],
+                |row| {
+                    Ok((
+                        row.get::<_, String>(0)?,
+                        row.get::<_, SqlValue>(1)?,
+                        row.get::<_, Option<i64>>(2)?,
+                        row.get::<_, bool>(3)?,
+                    ))
+                },
+            )?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // A conversation that can't be read shouldn't hide the others.
+        Ok(rows
+            .into_iter()
+            .filter_map(|(path, value, last_accessed, favorite)| {
+                let conversation = match self.decode_conversation(value) {
+                    Ok(conversation) => conversation,
+                    Err(err) => {
+                        warn!(?err, path, "skipping unreadable conversation");
+                        return None;
+                    },
+                };
+                Some(ConversationMeta {
+                    path,
+                    last_accessed: last_accessed.and_then(DateTime::from_timestamp_millis),
+                    preview: conversation.first_user_prompt().map(conversation_preview),
+                    favorite,
+                })
+            })
+            .collect())
+    }
+
+    /// The `limit` biggest stored conversations, largest first, with their size in bytes as stored
+    /// (i.e. after compression, if it is enabled).
+    pub fn largest_conversations(&self, limit: usize) -> Result<Vec<(String, i64)>, DatabaseError> {
+        let conn = self.pool.get()?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT key, length(CAST(value AS BLOB)) AS size FROM {} ORDER BY size DESC, key LIMIT ?1",
+            Table::Conversations
+        ))?;
+        let rows = stmt.query_map([limit as i64], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        Ok(rows.collect::<Result<Vec<_>, _>>()?)
+    }
+
+    /// Cumulative compression statistics for conversations saved since this database was opened.
+    pub fn compression_stats(&self) -> CompressionStats {
+        self.compression_stats.lock().map(|stats| *stats).unwrap_or_default()
     }
 
     pub async fn get_secret(&self, key: &str) -> Result<Option<Secret>, DatabaseError> {
@@ -414,6 +793,60 @@ impl Database {
         self.delete_entry(Table::Auth, key)
     }
 
+    /// Run `f` against a pooled connection, retrying with backoff while SQLite reports the
+    /// database as busy or locked. Any other error is returned immediately.
+    ///
+    /// This is called from async code, so the backoff hands the runtime's worker thread over to
+    /// other tasks while it waits, see [backoff_sleep].
+    pub fn with_retry<F, T>(&self, mut f: F) -> Result<T, DatabaseError>
+    where
+        F: FnMut(&Connection) -> Result<T, DatabaseError>,
+    {
+        let conn = self.pool.get()?;
+        let mut delay = BUSY_RETRY_BASE_DELAY;
+        let mut attempt = 0;
+        loop {
+            match f(&conn) {
+                Err(err) if attempt < MAX_BUSY_RETRIES && err.is_busy() => {
+                    attempt += 1;
+                    trace!(attempt, ?err, "database busy, retrying");
+                    backoff_sleep(delay);
+                    delay *= 2;
+                },
+                result => return result,
+            }
+        }
+    }
+
+    /// Copy the live database to `dest` using SQLite's online backup API, which produces a
+    /// consistent snapshot even while other connections are open. The copy is only readable by the
+    /// current user.
+    pub fn backup_to(&self, dest: impl AsRef<Path>) -> Result<(), DatabaseError> {
+        let dest = dest.as_ref();
+
+        // Create the file with restricted permissions up front so the copy is never readable by
+        // others, even while it is being written
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::{
+                OpenOptionsExt,
+                PermissionsExt,
+            };
+            std::fs::OpenOptions::new()
+                .write(true)
+                .create(true)
+                .truncate(true)
+                .mode(0o600)
+                .open(dest)?;
+            std::fs::set_permissions(dest, std::fs::Permissions::from_mode(0o600))?;
+        }
+
+        let conn = self.pool.get()?;
+        conn.backup(rusqlite::DatabaseName::Main, dest, None)?;
+        info!(?dest, "Backed up database");
+        Ok(())
+    }
+
     // Private functions. Do not expose.
 
     fn migrate(self) -> Result<Self, DatabaseError> {
@@ -421,6 +854,7 @@ impl Database {
         let transaction = conn.transaction()?;
 
         let max_version = max_migration_version(&transaction);
+        let mut created_search_index = false;
 
         for (version, migration) in MIGRATIONS.iter().enumerate() {
             if has_migration(&transaction, version, max_version)? {
@@ -429,6 +863,7 @@ impl Database {
 
             // execute the migration
             transaction.execute_batch(migration.sql)?;
+            created_search_index |= migration.name == "009_conversations_fts";
 
             info!(%version, name =% migration.name, "Applying migration");
 
@@ -439,27 +874,159 @@ impl Database {
             )?;
         }
 
+        repair_critical_tables(&transaction)?;
+
+        // The search index starts out empty, so index the conversations saved before it existed.
+        if created_search_index {
+            self.backfill_search_index(&transaction)?;
+        }
+
         // commit the transaction
         transaction.commit()?;
 
         Ok(self)
     }
 
-    fn get_entry<T: FromSql>(&self, table: Table, key: impl AsRef<str>) -> Result<Option<T>, DatabaseError> {
-        let conn = self.pool.get()?;
-        let mut stmt = conn.prepare(&format!("SELECT value FROM {table} WHERE key = ?1"))?;
-        match stmt.query_row([key.as_ref()], |row| row.get(0)) {
-            Ok(data) => Ok(Some(data)),
-            Err(Error::QueryReturnedNoRows) => Ok(None),
-            Err(err) => Err(err.into()),
+    /// Adds every stored conversation to the full-text index. Conversations that can't be decoded
+    /// are skipped rather than failing the migration.
+    fn backfill_search_index(&self, conn: &rusqlite::Connection) -> Result<(), DatabaseError> {
+        let mut stmt = conn.prepare(&format!("SELECT key, value FROM {}", Table::Conversations))?;
+        let rows = stmt
+            .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, SqlValue>(1)?)))?
+            .collect::<Result<Vec<_>, _>>()?;
+        for (key, value) in rows {
+            match self.decode_conversation(value) {
+                Ok(state) => index_conversation(conn, &key, &state)?,
+                Err(err) => warn!(?err, path = key, "skipping unreadable conversation in search index"),
+            }
         }
+        Ok(())
+    }
+
+    /// Adds a conversation that is saved outside of the database, e.g. to a project file, to the
+    /// index used by [Self::search_conversations], under `key`.
+    pub fn index_conversation(&self, key: &str, state: &ConversationState) -> Result<(), DatabaseError> {
+        let mut conn = self.pool.get()?;
+        let transaction = conn.transaction()?;
+        index_conversation(&transaction, key, state)?;
+        transaction.commit()?;
+        Ok(())
+    }
+
+    /// Deletes the least recently accessed conversations so that at most `max` remain, returning
+    /// how many were evicted. Conversations saved before access times were recorded count as the
+    /// oldest. Favorites count towards `max` but are never evicted.
+    pub fn enforce_conversation_cap(&self, max: usize) -> Result<usize, DatabaseError> {
+        let favorites = serde_json::to_string(&self.list_favorites()?)?;
+        let mut conn = self.pool.get()?;
+        let transaction = conn.transaction()?;
+        let evicted = {
+            let mut stmt = transaction.prepare(&format!(
+                "SELECT key FROM (SELECT key, key IN (SELECT value FROM json_each(?2)) AS favorite FROM {} \
+                 ORDER BY favorite DESC, COALESCE(last_accessed, 0) DESC LIMIT -1 OFFSET ?1) WHERE NOT favorite",
+                Table::Conversations
+            ))?;
+            let keys = stmt.query_map(params![max as i64, favorites], |row| row.get::<_, String>(0))?;
+            keys.collect::<Result<Vec<_>, _>>()?
+        };
+        for key in &evicted {
+            transaction.execute(
+                &format!("DELETE FROM {} WHERE key = ?1", Table::Conversations),
+                params![key],
+            )?;
+            transaction.execute("DELETE FROM conversations_fts WHERE key = ?1", params![key])?;
+        }
+        transaction.commit()?;
+        Ok(evicted.len())
+    }
+
+    fn set_conversation_entry(
+        &self,
+        path: &str,
+        state: &ConversationState,
+        last_accessed: i64,
+    ) -> Result<i64, DatabaseError> {
+        let json = serde_json::to_string(state)?;
+        // Compressed conversations are stored as blobs, uncompressed ones as JSON text.
+        let value = match self.settings.get_bool(Setting::ChatCompressConversations) {
+            Some(true) => {
+                let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+                encoder.write_all(json.as_bytes())?;
+                let compressed = encoder.finish()?;
+                self.compression_stats.lock()?.record(json.len(), compressed.len());
+                trace!(ratio = ?self.compression_stats().ratio(), "stored compressed conversation");
+                SqlValue::Blob(compressed)
+            },
+            _ => SqlValue::Text(json),
+        };
+
+        let mut conn = self.pool.get()?;
+        // Take the write lock up front so that no other session saves between the version check
+        // and the write.
+        let transaction = conn.transaction_with_behavior(rusqlite::TransactionBehavior::Immediate)?;
+        let current = match transaction.query_row(
+            &format!("SELECT version FROM {} WHERE key = ?1", Table::Conversations),
+            params![path],
+            |row| row.get::<_, i64>(0),
+        ) {
+            Ok(version) => version,
+            Err(Error::QueryReturnedNoRows) => 0,
+            Err(err) => return Err(err.into()),
+        };
+        if let Some(base) = state.db_version(path) {
+            if base != current {
+                return Err(ConflictError {
+                    path: path.to_string(),
+                    base,
+                    current,
+                }
+                .into());
+            }
+        }
+        let version = current + 1;
+        transaction.execute(
+            &format!(
+                "INSERT OR REPLACE INTO {} (key, value, last_accessed, version) VALUES (?1, ?2, ?3, ?4)",
+                Table::Conversations
+            ),
+            params![path, value, last_accessed, version],
+        )?;
+        // Keep the full-text index in step with the stored conversation.
+        index_conversation(&transaction, path, state)?;
+        transaction.commit()?;
+        Ok(version)
+    }
+
+    fn decode_conversation(&self, value: SqlValue) -> Result<ConversationState, DatabaseError> {
+        match value {
+            SqlValue::Blob(compressed) => {
+                let mut json = String::new();
+                ZlibDecoder::new(compressed.as_slice()).read_to_string(&mut json)?;
+                Ok(serde_json::from_str(&json)?)
+            },
+            SqlValue::Text(json) => Ok(serde_json::from_str(&json)?),
+            other => Err(rusqlite::Error::InvalidColumnType(1, "value".into(), other.data_type()).into()),
+        }
+    }
+
+    fn get_entry<T: FromSql>(&self, table: Table, key: impl AsRef<str>) -> Result<Option<T>, DatabaseError> {
+        self.with_retry(|conn| {
+            let mut stmt = conn.prepare(&format!("SELECT value FROM {table} WHERE key = ?1"))?;
+            match stmt.query_row([key.as_ref()], |row| row.get(0)) {
+                Ok(data) => Ok(Some(data)),
+                Err(Error::QueryReturnedNoRows) => Ok(None),
+                Err(err) => Err(err.into()),
+            }
+        })
     }
 
     fn set_entry(&self, table: Table, key: impl AsRef<str>, value: impl ToSql) -> Result<usize, DatabaseError> {
-        Ok(self.pool.get()?.execute(
-            &format!("INSERT OR REPLACE INTO {table} (key, value) VALUES (?1, ?2)"),
-            params![key.as_ref(), value],
-        )?)
+        self.with_retry(|conn| {
+            Ok(conn.execute(
+                &format!("INSERT OR REPLACE INTO {table} (key, value) VALUES (?1, ?2)"),
+                params![key.as_ref(), value],
+            )?)
+        })
     }
 
     fn get_json_entry<T: DeserializeOwned>(
@@ -482,6 +1049,15 @@ impl Database {
         self.set_entry(table, key, serde_json::to_string(&value)?)
     }
 
+    /// Reads a string entry that older clients stored encoded as a JSON string. Migration
+    /// `010_normalize_idc_state` rewrites those to plain strings, but a value written by an older
+    /// client after the migration ran is still decoded here.
+    fn get_legacy_string_entry(&self, table: Table, key: impl AsRef<str>) -> Result<Option<String>, DatabaseError> {
+        Ok(self
+            .get_entry::<String>(table, key)?
+            .map(|value| serde_json::from_str::<String>(&value).unwrap_or(value)))
+    }
+
     fn delete_entry(&self, table: Table, key: impl AsRef<str>) -> Result<(), DatabaseError> {
         self.pool
             .get()?
@@ -513,6 +1089,29 @@ fn max_migration_version<C: Deref<Target = Connection>>(conn: &C) -> Option<i64>
     stmt.query_row([], |row| row.get(0)).ok()
 }
 
+/// Recreates any critical table that is missing despite the migrations table recording the
+/// migration that creates it.
+fn repair_critical_tables<C: Deref<Target = Connection>>(conn: &C) -> Result<(), DatabaseError> {
+    for (table, versions) in CRITICAL_TABLES {
+        let exists: bool = conn.query_row(
+            "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?1)",
+            [table],
+            |row| row.get(0),
+        )?;
+        if exists {
+            continue;
+        }
+
+        for &version in *versions {
+            let migration = &MIGRATIONS[version];
+            warn!(%table, %version, name =% migration.name, "Table is missing, re-applying migration");
+            conn.execute_batch(migration.sql)?;
+        }
+    }
+
+    Ok(())
+}
+
 fn has_migration<C: Deref<Target = Connection>>(
     conn: &C,
     version: usize,
@@ -554,8 +1153,28 @@ fn has_migration<C: Deref<Target = Connection>>(
 }
 
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
+    use std::collections::HashMap;
+
     use super::*;
+    use crate::cli::agent::Agents;
+    use crate::cli::chat::message::AssistantMessage;
+    use crate::cli::chat::tool_manager::ToolManager;
+    use crate::os::Os;
+
+    /// An empty conversation with the given id, for tests that store conversations
+    pub(crate) async fn test_conversation(os: &Os, id: &str) -> ConversationState {
+        ConversationState::new(
+            id,
+            Agents::default(),
+            HashMap::new(),
+            ToolManager::default(),
+            None,
+            os,
+            false,
+        )
+        .await
+    }
 
     fn all_errors() -> Vec<DatabaseError> {
         vec![
@@ -577,6 +1196,109 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn test_state_dump() {
+        let db = Database::new().await.unwrap();
+        let dump = db.state_dump().unwrap();
+        assert!(dump.is_empty());
+        assert_eq!(dump, StateDump::default());
+
+        for key in ["zeta", "alpha", "mu"] {
+            db.set_entry(Table::State, key, key).unwrap();
+        }
+        let dump = db.state_dump().unwrap();
+        assert_eq!(dump.count, 3);
+        assert_eq!(dump.entries.keys().collect::<Vec<_>>(), ["alpha", "mu", "zeta"]);
+    }
+
+    #[tokio::test]
+    async fn test_repairs_missing_critical_table() {
+        let db = Database::new().await.unwrap();
+        let migration_count = |db: &Database| -> i64 {
+            db.pool
+                .get()
+                .unwrap()
+                .query_row("SELECT COUNT(*) FROM migrations", [], |row| row.get(0))
+                .unwrap()
+        };
+        let count = migration_count(&db);
+
+        // Simulate a database affected by the migration off-by-one: the migration is recorded but
+        // the table does not exist
+        db.pool.get().unwrap().execute_batch("DROP TABLE auth_kv;").unwrap();
+        assert!(db.get_secret("key").await.is_err());
+
+        let db = db.migrate().unwrap();
+        db.set_secret("key", "value").await.unwrap();
+        assert_eq!(db.get_secret("key").await.unwrap().unwrap().0, "value");
+        assert_eq!(migration_count(&db), count);
+    }
+
+    #[tokio::test]
+    async fn test_normalize_legacy_idc_state() {
+        let db = Database::new().await.unwrap();
+        let raw = |db: &Database, key| db.get_entry::<String>(Table::State, key).unwrap();
+
+        // Seed the values the way older clients wrote them, and forget the migration (and the
+        // ones after it) ran
+        db.set_json_entry(Table::State, IDC_REGION_KEY, "us-west-2").unwrap();
+        db.set_json_entry(Table::State, START_URL_KEY, "https://example.awsapps.com/start")
+            .unwrap();
+        db.pool
+            .get()
+            .unwrap()
+            .execute_batch("DELETE FROM migrations WHERE version >= 10; ALTER TABLE conversations DROP COLUMN version;")
+            .unwrap();
+        assert_eq!(raw(&db, IDC_REGION_KEY).as_deref(), Some("\"us-west-2\""));
+        assert_eq!(db.get_idc_region().unwrap().as_deref(), Some("us-west-2"));
+
+        let mut db = db.migrate().unwrap();
+        assert_eq!(raw(&db, IDC_REGION_KEY).as_deref(), Some("us-west-2"));
+        assert_eq!(
+            raw(&db, START_URL_KEY).as_deref(),
+            Some("https://example.awsapps.com/start")
+        );
+        assert_eq!(db.get_idc_region().unwrap().as_deref(), Some("us-west-2"));
+        assert_eq!(
+            db.get_start_url().unwrap().as_deref(),
+            Some("https://example.awsapps.com/start")
+        );
+
+        // New values are written in the canonical form
+        db.set_idc_region("eu-central-1".to_string()).unwrap();
+        assert_eq!(raw(&db, IDC_REGION_KEY).as_deref(), Some("eu-central-1"));
+        assert_eq!(db.get_idc_region().unwrap().as_deref(), Some("eu-central-1"));
+    }
+
+    #[tokio::test]
+    async fn test_backup_to() {
+        let db = Database::new().await.unwrap();
+        db.set_entry(Table::State, "backup-key", "backup-value").unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        let dest = dir.path().join("backup.sqlite3");
+        db.backup_to(&dest).unwrap();
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&dest).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        let backup = Database {
+            pool: Pool::builder().build(SqliteConnectionManager::file(&dest)).unwrap(),
+            compression_stats: Default::default(),
+            settings: Settings::default(),
+        };
+        assert_eq!(
+            backup.get_entry::<String>(Table::State, "backup-key").unwrap(),
+            Some("backup-value".to_string())
+        );
+        let max_migration = max_migration_version(&&*backup.pool.get().unwrap());
+        assert_eq!(max_migration, Some(MIGRATIONS.len() as i64 - 1));
+    }
+
     #[tokio::test]
     async fn test_migrate() {
         let db = Database::new().await.unwrap();
@@ -630,6 +1352,384 @@ mod tests {
         assert!(db.get_entry::<bool>(Table::State, "bool").unwrap().is_some());
     }
 
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_with_retry_on_busy() {
+        let db = Database::new().await.unwrap();
+
+        let mut attempts = 0;
+        let result = db.with_retry(|conn| {
+            attempts += 1;
+            if attempts == 1 {
+                return Err(Error::SqliteFailure(rusqlite::ffi::Error::new(rusqlite::ffi::SQLITE_BUSY), None).into());
+            }
+            Ok(conn.query_row("SELECT 1", [], |row| row.get::<_, i64>(0))?)
+        });
+        assert_eq!(result.unwrap(), 1);
+        assert_eq!(attempts, 2);
+
+        // Non-transient errors are not retried
+        let mut attempts = 0;
+        let result = db.with_retry(|conn| {
+            attempts += 1;
+            Ok(conn.query_row("SELECT * FROM missing_table", [], |row| row.get::<_, i64>(0))?)
+        });
+        assert!(result.is_err());
+        assert_eq!(attempts, 1);
+    }
+
+    #[tokio::test]
+    async fn test_compression_stats() {
+        let os = Os::new().await.unwrap();
+        let mut db = Database::new().await.unwrap();
+        let mut conversation = test_conversation(&os, "conv").await;
+        conversation
+            .transcript
+            .extend(std::iter::repeat_n("repetitive text".to_string(), 100));
+
+        db.set_conversation_entry("/uncompressed", &conversation, 0).unwrap();
+        assert_eq!(db.compression_stats(), CompressionStats::default());
+        assert!(db.compression_stats().ratio().is_none());
+
+        db.settings.set(Setting::ChatCompressConversations, true).await.unwrap();
+        db.set_conversation_entry("/compressed", &conversation, 1).unwrap();
+        let written = db.compression_stats();
+        assert!(written.ratio().unwrap() > 1.0);
+
+        // Reading back decompresses without counting towards the stats
+        let (path, state) = db.most_recent_conversation("/compressed").unwrap().unwrap();
+        assert_eq!(path, "/compressed");
+        assert_eq!(state.transcript.len(), 100);
+        db.list_recent_conversations(10, None).unwrap();
+        assert_eq!(db.compression_stats(), written);
+
+        // Uncompressed conversations remain readable
+        let (_, state) = db.most_recent_conversation("/uncompressed").unwrap().unwrap();
+        assert_eq!(state.transcript.len(), 100);
+    }
+
+    #[tokio::test]
+    async fn test_stale_conversation_save_is_rejected() {
+        let os = Os::new().await.unwrap();
+        let mut db = Database::new().await.unwrap();
+        let conversation = test_conversation(&os, "conv").await;
+        assert_eq!(db.set_conversation_by_path("/project", &conversation).unwrap(), 1);
+
+        // Two sessions load the same conversation
+        let (_, mut first) = db.most_recent_conversation("/project").unwrap().unwrap();
+        let (_, mut second) = db.most_recent_conversation("/project").unwrap().unwrap();
+        assert_eq!(first.db_version("/project"), Some(1));
+        assert_eq!(first.db_version("/elsewhere"), None);
+
+        first.transcript.push_back("first".to_string());
+        let version = db.set_conversation_by_path("/project", &first).unwrap();
+        assert_eq!(version, 2);
+        first.set_db_version(Some(("/project".to_string(), version)));
+
+        // The second save is based on a version that is no longer stored
+        second.transcript.push_back("second".to_string());
+        let err = db.set_conversation_by_path("/project", &second).unwrap_err();
+        assert!(matches!(
+            err,
+            DatabaseError::Conflict(ConflictError {
+                base: 1,
+                current: 2,
+                ..
+            })
+        ));
+        let (_, stored) = db.most_recent_conversation("/project").unwrap().unwrap();
+        assert_eq!(stored.transcript.back().map(String::as_str), Some("first"));
+
+        // The first session keeps saving on top of its own version
+        assert_eq!(db.set_conversation_by_path("/project", &first).unwrap(), 3);
+
+        // The second session can choose to overwrite
+        second.set_db_version(None);
+        assert_eq!(db.set_conversation_by_path("/project", &second).unwrap(), 4);
+        let (_, stored) = db.most_recent_conversation("/project").unwrap().unwrap();
+        assert_eq!(stored.transcript.back().map(String::as_str), Some("second"));
+    }
+
+    #[tokio::test]
+    async fn test_search_conversations() {
+        let mut os = Os::new().await.unwrap();
+        let db = Database::new().await.unwrap();
+        let mut conversation = test_conversation(&os, "conv").await;
+        conversation
+            .set_next_user_message("how do I rotate the staging database credentials".to_string())
+            .await;
+        conversation.push_assistant_message(
+            &mut os,
+            AssistantMessage::new_response(None, "Use the secrets manager rotation lambda.".to_string()),
+            None,
+        );
+
+        db.set_conversation_entry("/project", &conversation, 1_000).unwrap();
+        let results = db.search_conversations("rotation").unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].0, "/project");
+        assert!(results[0].1.contains("[rotation]"), "{}", results[0].1);
+
+        assert_eq!(
+            db.search_conversations("\"staging database\"").unwrap()[0].0,
+            "/project"
+        );
+        assert!(db.search_conversations("kubernetes").unwrap().is_empty());
+
+        // Saving again replaces the indexed text rather than duplicating it
+        db.set_conversation_entry("/project", &conversation, 2_000).unwrap();
+        assert_eq!(db.search_conversations("rotation").unwrap().len(), 1);
+
+        // Conversations saved outside the database can be indexed too
+        db.index_conversation("/repo/.amazonq/conversations/conv.json", &conversation)
+            .unwrap();
+        assert_eq!(db.search_conversations("rotation").unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_search_index_backfilled_by_migration() {
+        let os = Os::new().await.unwrap();
+        let db = Database::new().await.unwrap();
+        let mut conversation = test_conversation(&os, "conv").await;
+        conversation
+            .set_next_user_message("where are the terraform modules".to_string())
+            .await;
+        conversation.add_assistant_message(AssistantMessage::new_response(None, "In infra/.".to_string()), None);
+        db.set_conversation_entry("/project", &conversation, 1_000).unwrap();
+        db.pool
+            .get()
+            .unwrap()
+            .execute(
+                &format!(
+                    "INSERT INTO {} (key, value) VALUES ('/corrupt', 'not json')",
+                    Table::Conversations
+                ),
+                [],
+            )
+            .unwrap();
+
+        // Simulate a database from before the index existed
+        db.pool
+            .get()
+            .unwrap()
+            .execute_batch(
+                "DROP TABLE conversations_fts; DELETE FROM migrations WHERE version >= 9; \
+                 ALTER TABLE conversations DROP COLUMN version;",
+            )
+            .unwrap();
+
+        let db = db.migrate().unwrap();
+        let results = db.search_conversations("terraform").unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].0, "/project");
+    }
+
+    #[tokio::test]
+    async fn test_list_recent_conversations() {
+        let mut os = Os::new().await.unwrap();
+        let db = Database::new().await.unwrap();
+        let mut conversation = test_conversation(&os, "conv").await;
+        conversation
+            .set_next_user_message("first   question\nabout the code".to_string())
+            .await;
+        conversation.push_assistant_message(
+            &mut os,
+            AssistantMessage::new_response(None, "answer".to_string()),
+            None,
+        );
+
+        db.set_conversation_entry("/a", &conversation, 1_000).unwrap();
+        db.set_conversation_entry("/b", &conversation, 3_000).unwrap();
+        db.set_conversation_entry("/c", &conversation, 2_000).unwrap();
+
+        let all = db.list_recent_conversations(10, None).unwrap();
+        assert_eq!(all.iter().map(|c| c.path.as_str()).collect::<Vec<_>>(), [
+            "/b", "/c", "/a"
+        ]);
+        assert_eq!(all[0].last_accessed, DateTime::from_timestamp_millis(3_000));
+        assert_eq!(all[0].preview.as_deref(), Some("first question about the code"));
+
+        let limited = db.list_recent_conversations(2, None).unwrap();
+        assert_eq!(limited.iter().map(|c| c.path.as_str()).collect::<Vec<_>>(), [
+            "/b", "/c"
+        ]);
+
+        let since = db
+            .list_recent_conversations(10, DateTime::from_timestamp_millis(2_000))
+            .unwrap();
+        assert_eq!(since.iter().map(|c| c.path.as_str()).collect::<Vec<_>>(), ["/b", "/c"]);
+        assert!(all.iter().all(|c| !c.favorite));
+
+        // Favorites are listed first, newest first among themselves
+        db.set_favorite("/a", true).unwrap();
+        db.set_favorite("/c", true).unwrap();
+        assert_eq!(db.list_favorites().unwrap(), ["/a", "/c"]);
+        let favorited = db.list_recent_conversations(10, None).unwrap();
+        assert_eq!(
+            favorited
+                .iter()
+                .map(|c| (c.path.as_str(), c.favorite))
+                .collect::<Vec<_>>(),
+            [("/c", true), ("/a", true), ("/b", false)]
+        );
+        let limited = db.list_recent_conversations(1, None).unwrap();
+        assert_eq!(limited[0].path, "/c");
+
+        db.set_favorite("/c", false).unwrap();
+        assert_eq!(db.list_favorites().unwrap(), ["/a"]);
+        let unfavorited = db.list_recent_conversations(10, None).unwrap();
+        assert_eq!(unfavorited.iter().map(|c| c.path.as_str()).collect::<Vec<_>>(), [
+            "/a", "/b", "/c"
+        ]);
+
+        // Unreadable conversations are skipped rather than failing the listing
+        db.pool
+            .get()
+            .unwrap()
+            .execute(
+                &format!(
+                    "INSERT INTO {} (key, value, last_accessed) VALUES ('/corrupt', 'not json', 9000)",
+                    Table::Conversations
+                ),
+                [],
+            )
+            .unwrap();
+        let listed = db.list_recent_conversations(10, None).unwrap();
+        assert_eq!(listed.iter().map(|c| c.path.as_str()).collect::<Vec<_>>(), [
+            "/a", "/b", "/c"
+        ]);
+    }
+
+    #[tokio::test]
+    async fn test_enforce_conversation_cap() {
+        let mut os = Os::new().await.unwrap();
+        let db = Database::new().await.unwrap();
+        let mut conversation = test_conversation(&os, "conv").await;
+        conversation
+            .set_next_user_message("capped conversation".to_string())
+            .await;
+        conversation.push_assistant_message(&mut os, AssistantMessage::new_response(None, "ok".to_string()), None);
+
+        for (path, last_accessed) in [
+            ("/a", 4_000),
+            ("/b", 1_000),
+            ("/c", 5_000),
+            ("/d", 2_000),
+            ("/e", 3_000),
+        ] {
+            db.set_conversation_entry(path, &conversation, last_accessed).unwrap();
+        }
+
+        assert_eq!(db.enforce_conversation_cap(3).unwrap(), 2);
+        let remaining = db.list_recent_conversations(10, None).unwrap();
+        assert_eq!(remaining.iter().map(|c| c.path.as_str()).collect::<Vec<_>>(), [
+            "/c", "/a", "/e"
+        ]);
+        // Evicted conversations are dropped from the search index as well
+        assert_eq!(db.search_conversations("capped").unwrap().len(), 3);
+
+        assert_eq!(db.enforce_conversation_cap(3).unwrap(), 0);
+        assert_eq!(db.enforce_conversation_cap(0).unwrap(), 3);
+        assert!(db.list_recent_conversations(10, None).unwrap().is_empty());
+
+        // Favorites are kept regardless of when they were last accessed
+        for (path, last_accessed) in [("/a", 3_000), ("/b", 1_000), ("/c", 2_000)] {
+            db.set_conversation_entry(path, &conversation, last_accessed).unwrap();
+        }
+        db.set_favorite("/b", true).unwrap();
+        assert_eq!(db.enforce_conversation_cap(2).unwrap(), 1);
+        let remaining = db.list_recent_conversations(10, None).unwrap();
+        assert_eq!(remaining.iter().map(|c| c.path.as_str()).collect::<Vec<_>>(), [
+            "/b", "/a"
+        ]);
+        assert_eq!(db.enforce_conversation_cap(0).unwrap(), 1);
+        assert_eq!(db.list_favorites().unwrap(), ["/b"]);
+    }
+
+    #[tokio::test]
+    async fn test_largest_conversations() {
+        let mut os = Os::new().await.unwrap();
+        let db = Database::new().await.unwrap();
+        for (path, words) in [("/small", 1), ("/large", 500), ("/medium", 50)] {
+            let mut conversation = test_conversation(&os, "conv").await;
+            conversation.set_next_user_message("question".to_string()).await;
+            conversation.push_assistant_message(
+                &mut os,
+                AssistantMessage::new_response(None, "word ".repeat(words)),
+                None,
+            );
+            db.set_conversation_entry(path, &conversation, 1_000).unwrap();
+        }
+
+        let largest = db.largest_conversations(10).unwrap();
+        assert_eq!(largest.iter().map(|(path, _)| path.as_str()).collect::<Vec<_>>(), [
+            "/large", "/medium", "/small"
+        ]);
+        assert!(largest.windows(2).all(|w| w[0].1 > w[1].1));
+        assert!(largest[0].1 > "word ".repeat(500).len() as i64);
+
+        assert_eq!(db.largest_conversations(1).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_conversation_preview() {
+        assert_eq!(conversation_preview("  hello\n\tworld "), "hello world");
+        let long = "x".repeat(CONVERSATION_PREVIEW_LEN + 10);
+        assert_eq!(
+            conversation_preview(&long),
+            format!("{}...", "x".repeat(CONVERSATION_PREVIEW_LEN))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_most_recent_conversation() {
+        let os = Os::new().await.unwrap();
+        let db = Database::new().await.unwrap();
+
+        assert!(db.most_recent_conversation("/project").unwrap().is_none());
+
+        db.set_conversation_entry("/project", &test_conversation(&os, "old").await, 100)
+            .unwrap();
+        db.set_conversation_entry("/project/sub", &test_conversation(&os, "newest").await, 300)
+            .unwrap();
+        db.set_conversation_entry("/project/other", &test_conversation(&os, "middle").await, 200)
+            .unwrap();
+        // Sibling directory sharing the string prefix must not match.
+        db.set_conversation_entry("/projects", &test_conversation(&os, "sibling").await, 400)
+            .unwrap();
+
+        let (path, state) = db.most_recent_conversation("/project").unwrap().unwrap();
+        assert_eq!(path, "/project/sub");
+        assert_eq!(state.conversation_id(), "newest");
+
+        let (path, state) = db.most_recent_conversation("/project/other/").unwrap().unwrap();
+        assert_eq!(path, "/project/other");
+        assert_eq!(state.conversation_id(), "middle");
+
+        assert!(db.most_recent_conversation("/elsewhere").unwrap().is_none());
+
+        let state = db.get_conversation_by_path("/project").unwrap().unwrap();
+        assert_eq!(state.conversation_id(), "old");
+        assert_eq!(state.db_version("/project"), Some(1));
+        assert!(db.get_conversation_by_path("/project/missing").unwrap().is_none());
+
+        // Resuming a conversation makes it the most recently accessed one.
+        db.touch_conversation("/project/other").unwrap();
+        let (path, _) = db.most_recent_conversation("/project").unwrap().unwrap();
+        assert_eq!(path, "/project/other");
+    }
+
+    #[test]
+    fn test_secret_ct_eq() {
+        let secret = Secret::from("token-1234");
+        assert!(secret.ct_eq(&Secret::from("token-1234")));
+        assert!(!secret.ct_eq(&Secret::from("token-1235")));
+        assert!(!secret.ct_eq(&Secret::from("token-12345")));
+        assert!(!secret.ct_eq(&Secret::from("")));
+
+        assert_eq!(secret, Secret::from("token-1234"));
+        assert_ne!(secret, Secret::from("token-4321"));
+    }
+
     #[tokio::test]
     #[ignore = "not on ci"]
     async fn test_set_password() {
@@ -27,6 +27,30 @@ pub enum Setting {
     EnabledThinking,
     #[strum(message = "Enable custom commands functionality (boolean)")]
     EnabledCommands, // NEW: Add custom commands setting
+    #[strum(message = "Run custom command bash snippets with a minimal environment in a temporary directory (boolean)")]
+    CommandsBashRestricted,
+    #[strum(message = "Run bash snippets embedded in custom commands (boolean)")]
+    CommandsBashEnabled,
+    #[strum(message = "Environment variables passed to restricted custom command bash snippets (array)")]
+    CommandsBashAllowedEnv,
+    #[strum(message = "PATH for restricted custom command bash snippets, none if unset (string)")]
+    CommandsBashPath,
+    #[strum(message = "Open the platform default editor when EDITOR is not installed (boolean)")]
+    CommandsEditorFallback,
+    #[strum(message = "Additional read-only directories to load custom commands from (array)")]
+    CommandsExtraDirs,
+    #[strum(message = "Maximum combined size in bytes of files inlined by @references in commands (number)")]
+    CommandsFileReferencesMaxBytes,
+    #[strum(message = "Levels of @references inside referenced files to inline, 0 for none (number)")]
+    CommandsFileReferencesMaxDepth,
+    #[strum(
+        message = "Resolve @references in commands against the working directory when not found next to the command (boolean)"
+    )]
+    CommandsFileReferencesCwdFallback,
+    #[strum(message = "Project root for workspace config, detected from the working directory if unset (string)")]
+    ProjectRoot,
+    #[strum(message = "Where conversations are saved: db, project or both (string)")]
+    ConversationsStorage,
     #[strum(message = "Enable knowledge base functionality (boolean)")]
     EnabledKnowledge,
     #[strum(message = "Default file patterns to include in knowledge base (array)")]
@@ -57,6 +81,8 @@ pub enum Setting {
     ChatEditMode,
     #[strum(message = "Enable desktop notifications (boolean)")]
     ChatEnableNotifications,
+    #[strum(message = "Save and exit chat after this many milliseconds without input (number)")]
+    ChatIdleTimeoutMs,
     #[strum(message = "CodeWhisperer service endpoint URL (string)")]
     ApiCodeWhispererService,
     #[strum(message = "Q service endpoint URL (string)")]
@@ -65,24 +91,78 @@ pub enum Setting {
     McpInitTimeout,
     #[strum(message = "Non-interactive MCP timeout (number)")]
     McpNoInteractiveTimeout,
+    #[strum(message = "Maximum number of MCP tools loaded across all servers (number)")]
+    McpMaxTools,
+    #[strum(message = "Prefix MCP tool descriptions with the name of their server (boolean)")]
+    McpPrefixDescriptions,
     #[strum(message = "Track previously loaded MCP servers (boolean)")]
     McpLoadedBefore,
+    #[strum(message = "Strategy used to sanitize MCP tool names: strip or underscore (string)")]
+    McpToolNameSanitization,
+    #[strum(message = "Show stderr output from MCP servers while they load (boolean)")]
+    McpShowServerStderr,
+    #[strum(message = "Times to retry fetching an MCP prompt after a transient error (number)")]
+    McpPromptRetries,
+    #[strum(message = "Separator shown between server and tool names of MCP tools (string)")]
+    McpToolNameSeparator,
+    #[strum(message = "Whether MCP servers not seen before start enabled or disabled until approved (string)")]
+    McpNewServerPolicy,
+    #[strum(message = "Include the placeholder dummy tool in the tool schema (boolean)")]
+    ToolsIncludeDummy,
+    #[strum(message = "Confirm before fs_write overwrites an existing file, even when trusted (boolean)")]
+    ToolsFsWriteConfirmOverwrite,
+    #[strum(message = "Whether fs_read follows symlinks, including in directory listings (boolean)")]
+    ToolsFsFollowSymlinks,
+    #[strum(message = "Largest file fs_write shows a full diff for, larger ones get a summary (number)")]
+    ToolsFsWriteMaxDiffBytes,
+    #[strum(message = "AWS operations use_aws runs without confirmation, as service:operation globs (array)")]
+    ToolsUseAwsAllowedOperations,
+    #[strum(message = "AWS operations use_aws always refuses, as service:operation globs (array)")]
+    ToolsUseAwsDeniedOperations,
     #[strum(message = "Default AI model for conversations (string)")]
     ChatDefaultModel,
     #[strum(message = "Disable markdown formatting in chat (boolean)")]
     ChatDisableMarkdownRendering,
+    #[strum(message = "Mask secrets such as API keys in model output before display and storage (boolean)")]
+    PrivacyMaskOutputSecrets,
     #[strum(message = "Default agent configuration (string)")]
     ChatDefaultAgent,
     #[strum(message = "Disable automatic conversation summarization (boolean)")]
     ChatDisableAutoCompaction,
     #[strum(message = "Show conversation history hints (boolean)")]
     ChatEnableHistoryHints,
+    #[strum(message = "Glob patterns of files to load into context at chat start (array)")]
+    ChatAutoContext,
+    #[strum(message = "Store saved conversations compressed (boolean)")]
+    ChatCompressConversations,
+    #[strum(message = "Maximum number of saved conversations, evicting the least recently used (number)")]
+    ChatMaxConversations,
     #[strum(message = "Enable the todo list feature (boolean)")]
     EnabledTodoList,
+    #[strum(message = "Enable the run_tests tool for structured test results (boolean)")]
+    EnabledRunTests,
+    #[strum(message = "Enable the scratchpad tools for the model's working notes (boolean)")]
+    EnabledScratchpad,
     #[strum(message = "Enable themes functionality (boolean)")]
     EnabledThemes,
     #[strum(message = "Current active theme name (string)")]
     CurrentTheme,
+    #[strum(message = "Allow installing themes from a URL (boolean)")]
+    EnabledThemeDownloads,
+    #[strum(message = "Locale used to format numbers, e.g. de-DE, or \"system\" for the system locale (string)")]
+    UiLocale,
+    #[strum(message = "Number of decimal places shown for durations (number)")]
+    UiDurationPrecision,
+    #[strum(message = "Collapse long tool output to a summary line, shown again with /expand (boolean)")]
+    UiCollapseToolResults,
+    #[strum(message = "Use emoji in status messages (boolean)")]
+    UiEmoji,
+    #[strum(message = "Load the project .env file for executed commands and MCP servers (boolean)")]
+    SessionLoadDotenv,
+    #[strum(message = "Let the project .env file override variables that are already set (boolean)")]
+    SessionDotenvOverride,
+    #[strum(message = "Directory the rendered output of each session is recorded to, with colors (string)")]
+    SessionRecordTranscriptPath,
 }
 
 impl AsRef<str> for Setting {
@@ -93,6 +173,17 @@ impl AsRef<str> for Setting {
             Self::ShareCodeWhispererContent => "codeWhisperer.shareCodeWhispererContentWithAWS",
             Self::EnabledThinking => "chat.enableThinking",
             Self::EnabledCommands => "chat.enableCommands", // NEW: Add commands setting key
+            Self::CommandsBashRestricted => "commands.bash.restricted",
+            Self::CommandsBashEnabled => "commands.bash.enabled",
+            Self::CommandsBashAllowedEnv => "commands.bash.allowedEnv",
+            Self::CommandsBashPath => "commands.bash.path",
+            Self::CommandsEditorFallback => "commands.editorFallback",
+            Self::CommandsExtraDirs => "commands.extraDirs",
+            Self::CommandsFileReferencesMaxBytes => "commands.fileReferences.maxBytes",
+            Self::CommandsFileReferencesMaxDepth => "commands.fileReferences.maxDepth",
+            Self::CommandsFileReferencesCwdFallback => "commands.fileReferences.cwdFallback",
+            Self::ProjectRoot => "project.root",
+            Self::ConversationsStorage => "conversations.storage",
             Self::EnabledKnowledge => "chat.enableKnowledge",
             Self::KnowledgeDefaultIncludePatterns => "knowledge.defaultIncludePatterns",
             Self::KnowledgeDefaultExcludePatterns => "knowledge.defaultExcludePatterns",
@@ -107,20 +198,66 @@ impl AsRef<str> for Setting {
             Self::ChatGreetingEnabled => "chat.greeting.enabled",
             Self::ApiTimeout => "api.timeout",
             Self::ChatEditMode => "chat.editMode",
+            Self::ChatIdleTimeoutMs => "chat.idleTimeoutMs",
             Self::ChatEnableNotifications => "chat.enableNotifications",
             Self::ApiCodeWhispererService => "api.codewhisperer.service",
             Self::ApiQService => "api.q.service",
             Self::McpInitTimeout => "mcp.initTimeout",
             Self::McpNoInteractiveTimeout => "mcp.noInteractiveTimeout",
+            Self::McpMaxTools => "mcp.maxTools",
+            Self::McpPrefixDescriptions => "mcp.prefixDescriptions",
             Self::McpLoadedBefore => "mcp.loadedBefore",
+            Self::McpToolNameSanitization => "mcp.toolNameSanitization",
+            Self::McpShowServerStderr => "mcp.showServerStderr",
+            Self::McpPromptRetries => "mcp.promptRetries",
+            Self::McpToolNameSeparator => "mcp.toolNameSeparator",
+            Self::McpNewServerPolicy => "mcp.newServerPolicy",
+            Self::ToolsIncludeDummy => "tools.includeDummy",
+            Self::ToolsFsWriteConfirmOverwrite => "tools.fsWrite.confirmOverwrite",
+            Self::ToolsFsFollowSymlinks => "tools.fs.followSymlinks",
+            Self::ToolsFsWriteMaxDiffBytes => "tools.fsWrite.maxDiffBytes",
+            Self::ToolsUseAwsAllowedOperations => "tools.useAws.allowedOperations",
+            Self::ToolsUseAwsDeniedOperations => "tools.useAws.deniedOperations",
             Self::ChatDefaultModel => "chat.defaultModel",
             Self::ChatDisableMarkdownRendering => "chat.disableMarkdownRendering",
+            Self::PrivacyMaskOutputSecrets => "privacy.maskOutputSecrets",
             Self::ChatDefaultAgent => "chat.defaultAgent",
             Self::ChatDisableAutoCompaction => "chat.disableAutoCompaction",
             Self::ChatEnableHistoryHints => "chat.enableHistoryHints",
+            Self::ChatAutoContext => "chat.autoContext",
+            Self::ChatCompressConversations => "chat.compressConversations",
+            Self::ChatMaxConversations => "chat.maxConversations",
             Self::EnabledTodoList => "chat.enableTodoList",
+            Self::EnabledRunTests => "chat.enableRunTests",
+            Self::EnabledScratchpad => "chat.enableScratchpad",
             Self::EnabledThemes => "chat.enableThemes",
             Self::CurrentTheme => "chat.currentTheme",
+            Self::EnabledThemeDownloads => "chat.enableThemeDownloads",
+            Self::UiLocale => "ui.locale",
+            Self::UiDurationPrecision => "ui.durationPrecision",
+            Self::UiCollapseToolResults => "ui.collapseToolResults",
+            Self::UiEmoji => "ui.emoji",
+            Self::SessionLoadDotenv => "session.loadDotenv",
+            Self::SessionDotenvOverride => "session.dotenvOverride",
+            Self::SessionRecordTranscriptPath => "session.recordTranscriptPath",
+        }
+    }
+}
+
+impl Setting {
+    /// The value a setting takes when nothing is stored for it, if it has one. Settings without a
+    /// default are treated as unset by their callers.
+    pub fn default_value(&self) -> Option<Value> {
+        match self {
+            Self::TelemetryEnabled
+            | Self::ShareCodeWhispererContent
+            | Self::ChatGreetingEnabled
+            | Self::ToolsIncludeDummy
+            | Self::CommandsBashEnabled
+            | Self::UiEmoji => Some(Value::Bool(true)),
+            Self::McpInitTimeout => Some(Value::from(5000)),
+            Self::McpNoInteractiveTimeout => Some(Value::from(30_000)),
+            _ => None,
         }
     }
 }
@@ -141,6 +278,17 @@ impl TryFrom<&str> for Setting {
             "codeWhisperer.shareCodeWhispererContentWithAWS" => Ok(Self::ShareCodeWhispererContent),
             "chat.enableThinking" => Ok(Self::EnabledThinking),
             "chat.enableCommands" => Ok(Self::EnabledCommands), // NEW: Add commands setting parsing
+            "commands.bash.restricted" => Ok(Self::CommandsBashRestricted),
+            "commands.bash.enabled" => Ok(Self::CommandsBashEnabled),
+            "commands.bash.allowedEnv" => Ok(Self::CommandsBashAllowedEnv),
+            "commands.bash.path" => Ok(Self::CommandsBashPath),
+            "commands.editorFallback" => Ok(Self::CommandsEditorFallback),
+            "commands.extraDirs" => Ok(Self::CommandsExtraDirs),
+            "commands.fileReferences.maxBytes" => Ok(Self::CommandsFileReferencesMaxBytes),
+            "commands.fileReferences.maxDepth" => Ok(Self::CommandsFileReferencesMaxDepth),
+            "commands.fileReferences.cwdFallback" => Ok(Self::CommandsFileReferencesCwdFallback),
+            "project.root" => Ok(Self::ProjectRoot),
+            "conversations.storage" => Ok(Self::ConversationsStorage),
             "chat.enableKnowledge" => Ok(Self::EnabledKnowledge),
             "knowledge.defaultIncludePatterns" => Ok(Self::KnowledgeDefaultIncludePatterns),
             "knowledge.defaultExcludePatterns" => Ok(Self::KnowledgeDefaultExcludePatterns),
@@ -155,18 +303,46 @@ impl TryFrom<&str> for Setting {
             "chat.greeting.enabled" => Ok(Self::ChatGreetingEnabled),
             "api.timeout" => Ok(Self::ApiTimeout),
             "chat.editMode" => Ok(Self::ChatEditMode),
+            "chat.idleTimeoutMs" => Ok(Self::ChatIdleTimeoutMs),
             "chat.enableNotifications" => Ok(Self::ChatEnableNotifications),
             "api.codewhisperer.service" => Ok(Self::ApiCodeWhispererService),
             "api.q.service" => Ok(Self::ApiQService),
             "mcp.initTimeout" => Ok(Self::McpInitTimeout),
             "mcp.noInteractiveTimeout" => Ok(Self::McpNoInteractiveTimeout),
+            "mcp.maxTools" => Ok(Self::McpMaxTools),
+            "mcp.prefixDescriptions" => Ok(Self::McpPrefixDescriptions),
             "mcp.loadedBefore" => Ok(Self::McpLoadedBefore),
+            "mcp.toolNameSanitization" => Ok(Self::McpToolNameSanitization),
+            "mcp.showServerStderr" => Ok(Self::McpShowServerStderr),
+            "mcp.promptRetries" => Ok(Self::McpPromptRetries),
+            "mcp.toolNameSeparator" => Ok(Self::McpToolNameSeparator),
+            "mcp.newServerPolicy" => Ok(Self::McpNewServerPolicy),
+            "tools.includeDummy" => Ok(Self::ToolsIncludeDummy),
+            "tools.fsWrite.confirmOverwrite" => Ok(Self::ToolsFsWriteConfirmOverwrite),
+            "tools.fs.followSymlinks" => Ok(Self::ToolsFsFollowSymlinks),
+            "tools.fsWrite.maxDiffBytes" => Ok(Self::ToolsFsWriteMaxDiffBytes),
+            "tools.useAws.allowedOperations" => Ok(Self::ToolsUseAwsAllowedOperations),
+            "tools.useAws.deniedOperations" => Ok(Self::ToolsUseAwsDeniedOperations),
             "chat.defaultModel" => Ok(Self::ChatDefaultModel),
             "chat.disableMarkdownRendering" => Ok(Self::ChatDisableMarkdownRendering),
+            "privacy.maskOutputSecrets" => Ok(Self::PrivacyMaskOutputSecrets),
             "chat.defaultAgent" => Ok(Self::ChatDefaultAgent),
             "chat.disableAutoCompaction" => Ok(Self::ChatDisableAutoCompaction),
             "chat.enableHistoryHints" => Ok(Self::ChatEnableHistoryHints),
+            "chat.autoContext" => Ok(Self::ChatAutoContext),
+            "chat.compressConversations" => Ok(Self::ChatCompressConversations),
+            "chat.maxConversations" => Ok(Self::ChatMaxConversations),
             "chat.enableTodoList" => Ok(Self::EnabledTodoList),
+            "chat.enableRunTests" => Ok(Self::EnabledRunTests),
+            "chat.enableScratchpad" => Ok(Self::EnabledScratchpad),
+            "chat.enableThemeDownloads" => Ok(Self::EnabledThemeDownloads),
+            "ui.locale" => Ok(Self::UiLocale),
+            "ui.durationPrecision" => Ok(Self::UiDurationPrecision),
+            "ui.collapseToolResults" => Ok(Self::UiCollapseToolResults),
+            "ui.emoji" => Ok(Self::UiEmoji),
+            "session.loadDotenv" => Ok(Self::SessionLoadDotenv),
+            "session.dotenvOverride" => Ok(Self::SessionDotenvOverride),
+            "session.recordTranscriptPath" => Ok(Self::SessionRecordTranscriptPath),
             _ => Err(DatabaseError::InvalidSetting(value.to_string())),
         }
     }
@@ -213,6 +389,11 @@ impl Settings {
         self.0.get(key.as_ref())
     }
 
+    /// The stored value of a setting, falling back to [Setting::default_value] when none is stored.
+    pub fn get_or_default(&self, key: Setting) -> Option<Value> {
+        self.get(key).cloned().or_else(|| key.default_value())
+    }
+
     pub async fn set(&mut self, key: Setting, value: impl Into<serde_json::Value>) -> Result<(), DatabaseError> {
         self.0.insert(key.to_string(), value.into());
         self.save_to_file().await
@@ -224,6 +405,17 @@ impl Settings {
         Ok(key)
     }
 
+    /// Resets a setting to its default by deleting the stored value, returning the default now in
+    /// effect.
+    ///
+    /// Unlike [Self::remove], which reports what was stored, this reports what the setting now
+    /// resolves to. An absent key is not the same as a key set to its default: only the former
+    /// follows future changes to the default.
+    pub async fn unset(&mut self, key: Setting) -> Result<Option<Value>, DatabaseError> {
+        self.remove(key).await?;
+        Ok(key.default_value())
+    }
+
     pub fn get_bool(&self, key: Setting) -> Option<bool> {
         self.get(key).and_then(|value| value.as_bool())
     }
@@ -341,4 +533,38 @@ mod test {
         assert_eq!(settings.get(Setting::McpLoadedBefore), None);
         assert_eq!(settings.get(Setting::ChatDisableMarkdownRendering), None);
     }
+
+    #[tokio::test]
+    async fn test_unset_restores_default() {
+        let mut settings = Settings::default();
+        assert_eq!(
+            settings.get_or_default(Setting::TelemetryEnabled),
+            Some(Value::Bool(true))
+        );
+
+        settings.set(Setting::TelemetryEnabled, false).await.unwrap();
+        assert_eq!(
+            settings.get_or_default(Setting::TelemetryEnabled),
+            Some(Value::Bool(false))
+        );
+
+        let default = settings.unset(Setting::TelemetryEnabled).await.unwrap();
+        assert_eq!(default, Some(Value::Bool(true)));
+        assert_eq!(settings.get(Setting::TelemetryEnabled), None);
+        assert_eq!(
+            settings.get_or_default(Setting::TelemetryEnabled),
+            Some(Value::Bool(true))
+        );
+
+        settings.set(Setting::McpInitTimeout, 100).await.unwrap();
+        assert_eq!(
+            settings.unset(Setting::McpInitTimeout).await.unwrap(),
+            Some(Value::from(5000))
+        );
+
+        // Settings without a default are simply absent after unsetting
+        settings.set(Setting::ChatDefaultModel, "model 1").await.unwrap();
+        assert_eq!(settings.unset(Setting::ChatDefaultModel).await.unwrap(), None);
+        assert_eq!(settings.get_or_default(Setting::ChatDefaultModel), None);
+    }
 }
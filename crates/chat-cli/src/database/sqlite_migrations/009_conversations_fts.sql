@@ -0,0 +1 @@
+CREATE VIRTUAL TABLE conversations_fts USING fts5(key UNINDEXED, content);
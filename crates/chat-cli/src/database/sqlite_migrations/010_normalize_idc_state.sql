@@ -0,0 +1,6 @@
+UPDATE state
+SET value = json_extract(value, '$')
+WHERE key IN ('auth.idc.start-url', 'auth.idc.region')
+    AND typeof(value) = 'text'
+    AND json_valid(value)
+    AND json_type(value) = 'text';
@@ -37,7 +37,11 @@ use rmcp::{
     ServiceError,
     ServiceExt,
 };
-use tokio::io::AsyncReadExt as _;
+use tokio::io::{
+    AsyncBufReadExt as _,
+    AsyncReadExt as _,
+    BufReader,
+};
 use tokio::process::{
     ChildStderr,
     Command,
@@ -63,6 +67,10 @@ use crate::cli::chat::tools::custom_tool::{
 };
 use crate::os::Os;
 use crate::util::directories::DirectoryError;
+use crate::util::process_registry::{
+    ProcessKind,
+    ProcessRegistry,
+};
 
 /// Fetches all pages of specified resources from a server
 macro_rules! paginated_fetch {
@@ -132,6 +140,51 @@ fn process_env_vars(env_vars: &mut HashMap<String, String>, env: &crate::os::Env
     }
 }
 
+/// Maximum number of stderr lines forwarded to the consumer per server. Everything is still logged.
+pub const MAX_FORWARDED_STDERR_LINES: usize = 10;
+
+/// Lines longer than this are split when read from stderr.
+const MAX_STDERR_LINE_BYTES: u64 = 4096;
+
+/// Logs everything a server prints to its stderr, forwarding the first
+/// [MAX_FORWARDED_STDERR_LINES] non-empty lines through the messenger.
+fn forward_stderr(server_name: String, stderr: ChildStderr, messenger: Box<dyn Messenger>) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut reader = BufReader::new(stderr);
+        let mut buf = Vec::new();
+        let mut forwarded = 0;
+        loop {
+            buf.clear();
+            match (&mut reader)
+                .take(MAX_STDERR_LINE_BYTES)
+                .read_until(b'\n', &mut buf)
+                .await
+            {
+                Ok(0) => {
+                    tracing::info!(target: "mcp", "{server_name} stderr listening process exited due to EOF");
+                    break;
+                },
+                Ok(_) => {
+                    let line = String::from_utf8_lossy(&buf);
+                    let line = line.trim_end();
+                    tracing::info!(target: "mcp", "{server_name} logged to its stderr: {line}");
+                    if forwarded < MAX_FORWARDED_STDERR_LINES && !line.is_empty() {
+                        forwarded += 1;
+                        if messenger.send_stderr_line(line.to_string()).await.is_err() {
+                            // Nobody is listening anymore, so there's no point in trying again
+                            forwarded = MAX_FORWARDED_STDERR_LINES;
+                        }
+                    }
+                },
+                Err(e) => {
+                    tracing::info!(target: "mcp", "{server_name} stderr listening process exited due to error: {e}");
+                    break;
+                },
+            }
+        }
+    })
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum McpClientError {
     #[error(transparent)]
@@ -319,15 +372,20 @@ impl McpClientService {
 
             let result: Result<_, McpClientError> = async {
                 let messenger_dup = messenger_clone.duplicate();
-                let (service, stderr, auth_client) = match self.get_transport(&os_clone, &*messenger_dup).await? {
+                let (service, auth_client) = match self.get_transport(&os_clone, &*messenger_dup).await? {
                     Transport::Stdio((child_process, stderr)) => {
+                        // Start reading stderr before the handshake so that startup diagnostics
+                        // are available while the server is still loading
+                        if let Some(stderr) = stderr {
+                            forward_stderr(server_name.clone(), stderr, messenger_clone.duplicate());
+                        }
                         let service = self
                             .into_dyn()
                             .serve::<TokioChildProcess, _, _>(child_process)
                             .await
                             .map_err(Box::new)?;
 
-                        (service, stderr, None)
+                        (service, None)
                     },
                     Transport::Http(http_transport) => {
                         match http_transport {
@@ -388,23 +446,23 @@ impl McpClientService {
                                     Err(e) => return Err(e.into()),
                                 };
 
-                                (service, None, Some(auth_dg))
+                                (service, Some(auth_dg))
                             },
                             HttpTransport::WithoutAuth(transport) => {
                                 let service = self.into_dyn().serve(transport).await.map_err(Box::new)?;
 
-                                (service, None, None)
+                                (service, None)
                             },
                         }
                     },
                 };
 
-                Ok((service, stderr, auth_client))
+                Ok((service, auth_client))
             }
             .await;
 
-            let (service, child_stderr, auth_dropguard) = match result {
-                Ok((service, stderr, auth_dg)) => (service, stderr, auth_dg),
+            let (service, auth_dropguard) = match result {
+                Ok((service, auth_dg)) => (service, auth_dg),
                 Err(e) => {
                     let msg = e.to_string();
                     let error_data = ErrorData {
@@ -422,28 +480,6 @@ impl McpClientService {
                 },
             };
 
-            if let Some(mut stderr) = child_stderr {
-                let server_name_clone = server_name.clone();
-                tokio::spawn(async move {
-                    let mut buf = [0u8; 1024];
-                    loop {
-                        match stderr.read(&mut buf).await {
-                            Ok(0) => {
-                                tracing::info!(target: "mcp", "{server_name_clone} stderr listening process exited due to EOF");
-                                break;
-                            },
-                            Ok(size) => {
-                                tracing::info!(target: "mcp", "{server_name_clone} logged to its stderr: {}", String::from_utf8_lossy(&buf[0..size]));
-                            },
-                            Err(e) => {
-                                tracing::info!(target: "mcp", "{server_name_clone} stderr listening process exited due to error: {e}");
-                                break; // Error reading
-                            },
-                        }
-                    }
-                });
-            }
-
             let service_clone = service.clone();
             tokio::spawn(async move {
                 let result: Result<(), Box<dyn std::error::Error + Send + Sync>> = async {
@@ -507,11 +543,13 @@ impl McpClientService {
         match transport_type {
             TransportType::Stdio => {
                 let command = Command::new(command_as_str).configure(|cmd| {
+                    // The server's configured env takes precedence over the session's variables
+                    cmd.envs(std::env::vars()).envs(&os.session_env);
                     if let Some(envs) = config_envs {
                         process_env_vars(envs, &os.env);
                         cmd.envs(envs);
                     }
-                    cmd.envs(std::env::vars()).args(args);
+                    cmd.args(args);
 
                     #[cfg(not(windows))]
                     cmd.process_group(0);
@@ -519,6 +557,9 @@ impl McpClientService {
 
                 let (tokio_child_process, child_stderr) =
                     TokioChildProcess::builder(command).stderr(Stdio::piped()).spawn()?;
+                if let Some(pid) = tokio_child_process.id() {
+                    ProcessRegistry::global().track(pid, ProcessKind::McpServer, self.server_name.clone());
+                }
 
                 Ok(Transport::Stdio((tokio_child_process, child_stderr)))
             },
@@ -666,6 +707,31 @@ impl std::fmt::Debug for InitializedMcpClient {
 }
 
 impl InitializedMcpClient {
+    /// Re-fetches the tools of a ready server and delivers them through `messenger`, as is done
+    /// when the server announces that its tool list has changed. A pending client delivers its
+    /// tools once it finishes initializing, so there is nothing to do for it.
+    pub fn refetch_tools(&self, server_name: String, messenger: ServerMessenger) {
+        let InitializedMcpClient::Ready(running_service) = self else {
+            return;
+        };
+        let peer = match &running_service.inner_service {
+            InnerService::Original(service) => service.peer().clone(),
+            InnerService::Peer(peer) => peer.clone(),
+        };
+        tokio::spawn(async move {
+            paginated_fetch! {
+                final_result_type: ListToolsResult,
+                content_type: rmcp::model::Tool,
+                service_method: list_tools,
+                result_field: tools,
+                messenger_method: send_tools_list_result,
+                service: peer,
+                messenger: messenger,
+                server_name: server_name
+            };
+        });
+    }
+
     pub async fn get_running_service(&mut self) -> Result<&RunningService, McpClientError> {
         match self {
             InitializedMcpClient::Pending(handle) if handle.is_finished() => {
@@ -687,6 +753,41 @@ impl InitializedMcpClient {
 mod tests {
     use super::*;
 
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_forward_stderr() {
+        use crate::cli::chat::server_messenger::{
+            ServerMessengerBuilder,
+            UpdateEventMessage,
+        };
+
+        let mut child = Command::new("sh")
+            .arg("-c")
+            .arg("echo 'downloading model...' >&2; echo >&2; for i in $(seq 1 20); do echo \"step $i\" >&2; done")
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let (mut rx, builder) = ServerMessengerBuilder::new(64);
+        let messenger = builder.build_with_name("slow".to_string());
+
+        forward_stderr("slow".to_string(), child.stderr.take().unwrap(), Box::new(messenger))
+            .await
+            .unwrap();
+        child.wait().await.unwrap();
+        drop(builder);
+
+        let mut lines = Vec::new();
+        while let Some(msg) = rx.recv().await {
+            if let UpdateEventMessage::StderrLine { server_name, line } = msg {
+                assert_eq!(server_name, "slow");
+                lines.push(line);
+            }
+        }
+        assert_eq!(lines.len(), MAX_FORWARDED_STDERR_LINES);
+        assert_eq!(lines[0], "downloading model...");
+        assert_eq!(lines[1], "step 1");
+    }
+
     #[tokio::test]
     async fn test_substitute_env_vars() {
         // Set a test environment variable
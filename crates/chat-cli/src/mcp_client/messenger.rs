@@ -57,6 +57,10 @@ pub trait Messenger: std::fmt::Debug + Send + Sync + 'static {
     /// This function is used to deliver OAuth links that users need to visit for authentication
     async fn send_oauth_link(&self, link: String) -> MessengerResult;
 
+    /// Sends a line the server printed to its stderr to the consumer
+    /// This function is used to surface diagnostics from servers that are still loading
+    async fn send_stderr_line(&self, line: String) -> MessengerResult;
+
     /// Signals to the orchestrator that a server has started initializing
     async fn send_init_msg(&self) -> MessengerResult;
 
@@ -115,6 +119,10 @@ impl Messenger for NullMessenger {
         Ok(())
     }
 
+    async fn send_stderr_line(&self, _line: String) -> MessengerResult {
+        Ok(())
+    }
+
     async fn send_init_msg(&self) -> MessengerResult {
         Ok(())
     }
@@ -132,6 +132,15 @@ impl Env {
         }
     }
 
+    /// Sets the working directory of a fake environment.
+    #[cfg(test)]
+    pub fn set_current_dir_for_test(&self, path: impl Into<PathBuf>) {
+        match &self.0 {
+            inner::Inner::Real => panic!("cannot set the working directory of the real environment"),
+            inner::Inner::Fake(fake) => fake.lock().unwrap().cwd = path.into(),
+        }
+    }
+
     pub fn current_exe(&self) -> Result<PathBuf, io::Error> {
         use inner::Inner;
         match &self.0 {
@@ -86,6 +86,14 @@ fn append(base: impl AsRef<Path>, path: impl AsRef<Path>) -> PathBuf {
     }
 }
 
+/// Maps a canonical path beneath the canonical `root` of a chroot back to the path inside of it.
+fn unchroot(root: &Path, path: PathBuf) -> PathBuf {
+    match path.strip_prefix(root) {
+        Ok(inner) => Path::new(std::path::MAIN_SEPARATOR_STR).join(inner),
+        Err(_) => path,
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum Fs {
     Real,
@@ -212,6 +220,71 @@ impl Fs {
         }
     }
 
+    /// Directories are implicit in a fake [Fs], so this always succeeds for one.
+    pub fn create_dir_all_sync(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        match self {
+            Self::Real => std::fs::create_dir_all(path),
+            Self::Chroot(root) => std::fs::create_dir_all(append(root.path(), path)),
+            Self::Fake(_) => Ok(()),
+        }
+    }
+
+    pub fn write_sync(&self, path: impl AsRef<Path>, contents: impl AsRef<[u8]>) -> io::Result<()> {
+        match self {
+            Self::Real => std::fs::write(path, contents),
+            Self::Chroot(root) => std::fs::write(append(root.path(), path), contents),
+            Self::Fake(map) => {
+                let Ok(mut lock) = map.lock() else {
+                    return Err(io::Error::other("poisoned lock"));
+                };
+                lock.insert(path.as_ref().to_owned(), contents.as_ref().to_owned());
+                Ok(())
+            },
+        }
+    }
+
+    /// Opens a file for appending, creating it if it does not exist.
+    pub fn append_sync(&self, path: impl AsRef<Path>) -> io::Result<std::fs::File> {
+        let mut options = std::fs::OpenOptions::new();
+        options.create(true).append(true);
+        match self {
+            Self::Real => options.open(path),
+            Self::Chroot(root) => options.open(append(root.path(), path)),
+            Self::Fake(_) => Err(io::Error::other("unimplemented")),
+        }
+    }
+
+    /// Returns the paths of the entries in the directory `path`, in no particular order. For a
+    /// chroot [Fs] the paths are outside of the chroot, like `path`.
+    pub fn read_dir_sync(&self, path: impl AsRef<Path>) -> io::Result<Vec<PathBuf>> {
+        let path = path.as_ref();
+        let entries = |dir: &Path| {
+            std::fs::read_dir(dir)?
+                .map(|entry| Ok(path.join(entry?.file_name())))
+                .collect::<io::Result<Vec<_>>>()
+        };
+        match self {
+            Self::Real => entries(path),
+            Self::Chroot(root) => entries(&append(root.path(), path)),
+            Self::Fake(map) => {
+                let Ok(lock) = map.lock() else {
+                    return Err(io::Error::other("poisoned lock"));
+                };
+                let mut children = lock
+                    .keys()
+                    .filter_map(|file| file.strip_prefix(path).ok()?.components().next())
+                    .map(|child| path.join(child))
+                    .collect::<Vec<_>>();
+                if children.is_empty() {
+                    return Err(io::Error::new(io::ErrorKind::NotFound, "not found"));
+                }
+                children.sort();
+                children.dedup();
+                Ok(children)
+            },
+        }
+    }
+
     /// Creates a future that will open a file for writing and write the entire
     /// contents of `contents` to it.
     ///
@@ -305,7 +378,22 @@ impl Fs {
         match self {
             Self::Real => path.as_ref().exists(),
             Self::Chroot(root) => append(root.path(), path).exists(),
-            Self::Fake(_) => panic!("unimplemented"),
+            Self::Fake(map) => {
+                let path = path.as_ref();
+                map.lock()
+                    .is_ok_and(|lock| lock.keys().any(|file| file.starts_with(path)))
+            },
+        }
+    }
+
+    /// Returns `true` if the path points at an existing file.
+    ///
+    /// This is a proxy to [std::path::Path::is_file].
+    pub fn is_file(&self, path: impl AsRef<Path>) -> bool {
+        match self {
+            Self::Real => path.as_ref().is_file(),
+            Self::Chroot(root) => append(root.path(), path).is_file(),
+            Self::Fake(map) => map.lock().is_ok_and(|lock| lock.contains_key(path.as_ref())),
         }
     }
 
@@ -381,6 +469,26 @@ impl Fs {
         }
     }
 
+    /// Query the metadata about a file, following symlinks.
+    ///
+    /// This is a proxy to [`tokio::fs::metadata`]
+    pub async fn metadata(&self, path: impl AsRef<Path>) -> io::Result<std::fs::Metadata> {
+        match self {
+            Self::Real => fs::metadata(path).await,
+            Self::Chroot(root) => fs::metadata(append(root.path(), path)).await,
+            Self::Fake(_) => Err(io::Error::other("unimplemented")),
+        }
+    }
+
+    /// See [Fs::metadata].
+    pub fn metadata_sync(&self, path: impl AsRef<Path>) -> io::Result<std::fs::Metadata> {
+        match self {
+            Self::Real => std::fs::metadata(path),
+            Self::Chroot(root) => std::fs::metadata(append(root.path(), path)),
+            Self::Fake(_) => panic!("unimplemented"),
+        }
+    }
+
     /// Reads a symbolic link, returning the file that the link points to.
     ///
     /// This is a proxy to [`tokio::fs::read_link`].
@@ -406,11 +514,27 @@ impl Fs {
     /// Returns the canonical, absolute form of a path with all intermediate
     /// components normalized and symbolic links resolved.
     ///
-    /// This is a proxy to [`tokio::fs::canonicalize`].
+    /// This is a proxy to [`tokio::fs::canonicalize`]. For a chroot [Fs] the returned path is
+    /// inside of the chroot, like `path`.
     pub async fn canonicalize(&self, path: impl AsRef<Path>) -> Result<PathBuf, io::Error> {
         match self {
             Self::Real => fs::canonicalize(path).await,
-            Self::Chroot(root) => fs::canonicalize(append(root.path(), path)).await,
+            Self::Chroot(root) => Ok(unchroot(
+                &fs::canonicalize(root.path()).await?,
+                fs::canonicalize(append(root.path(), path)).await?,
+            )),
+            Self::Fake(_) => panic!("unimplemented"),
+        }
+    }
+
+    /// See [Fs::canonicalize].
+    pub fn canonicalize_sync(&self, path: impl AsRef<Path>) -> Result<PathBuf, io::Error> {
+        match self {
+            Self::Real => std::fs::canonicalize(path),
+            Self::Chroot(root) => Ok(unchroot(
+                &std::fs::canonicalize(root.path())?,
+                std::fs::canonicalize(append(root.path(), path))?,
+            )),
             Self::Fake(_) => panic!("unimplemented"),
         }
     }
@@ -466,6 +590,18 @@ mod tests {
         fs.write(dir.join("write"), b"write").await.unwrap();
         assert_eq!(fs.read(dir.join("write")).await.unwrap(), b"write");
         assert_eq!(fs.read_to_string(dir.join("write")).await.unwrap(), "write");
+
+        fs.create_dir_all_sync(dir.join("sync")).unwrap();
+        fs.write_sync(dir.join("sync/a"), "a").unwrap();
+        fs.write_sync(dir.join("sync/nested/b"), "b").unwrap();
+        assert!(fs.exists(dir.join("sync")));
+        assert!(fs.is_file(dir.join("sync/a")));
+        assert!(!fs.is_file(dir.join("sync/nested")));
+        assert_eq!(fs.read_dir_sync(dir.join("sync")).unwrap(), vec![
+            dir.join("sync/a"),
+            dir.join("sync/nested")
+        ]);
+        assert!(fs.read_dir_sync(dir.join("missing")).is_err());
     }
 
     #[tokio::test]
@@ -588,6 +724,8 @@ mod tests {
         );
         assert_eq!(fs.read_to_string("/fake_symlink_sync").await.unwrap(), "contents");
         assert_eq!(fs.read_to_string_sync("/fake_symlink").unwrap(), "contents");
+        assert_eq!(fs.canonicalize("/fake_symlink").await.unwrap(), PathBuf::from("/fake"));
+        assert_eq!(fs.canonicalize_sync("/fake_symlink").unwrap(), PathBuf::from("/fake"));
 
         // Checking symlink exist
         assert!(fs.symlink_exists("/fake_symlink").await);
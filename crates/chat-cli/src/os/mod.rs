@@ -5,6 +5,9 @@ mod env;
 mod fs;
 mod sysinfo;
 
+use std::collections::HashMap;
+use std::path::Path;
+
 pub use env::Env;
 use eyre::Result;
 pub use fs::Fs;
@@ -38,6 +41,10 @@ pub struct Os {
     pub database: Database,
     pub client: ApiClient,
     pub telemetry: TelemetryThread,
+    /// Variables scoped to the chat session, such as those loaded from a project `.env` file. They
+    /// are passed to executed commands and MCP servers without changing the environment of this
+    /// process.
+    pub session_env: HashMap<String, String>,
 }
 
 impl Os {
@@ -55,8 +62,36 @@ impl Os {
             database,
             client,
             telemetry,
+            session_env: HashMap::new(),
         })
     }
+
+    /// Loads the `.env` file in `dir`, if there is one, into [Self::session_env] and returns how
+    /// many variables were loaded. Variables already set in the environment are skipped unless
+    /// `override_existing` is set.
+    pub async fn load_dotenv(&mut self, dir: &Path, override_existing: bool) -> Result<usize> {
+        let path = dir.join(".env");
+        if !self.fs.exists(&path) {
+            return Ok(0);
+        }
+
+        let contents = self.fs.read_to_string(&path).await?;
+        let mut loaded = 0;
+        for item in dotenvy::from_read_iter(contents.as_bytes()) {
+            let (key, value) = item?;
+            if override_existing || self.env.get(&key).is_err() {
+                self.session_env.insert(key, value);
+                loaded += 1;
+            }
+        }
+        Ok(loaded)
+    }
+
+    /// Loads the `.env` file at the project root, see [crate::util::directories::project_root].
+    pub async fn load_project_dotenv(&mut self, override_existing: bool) -> Result<usize> {
+        let root = crate::util::directories::project_root(self)?;
+        self.load_dotenv(&root, override_existing).await
+    }
 }
 
 #[cfg(test)]
@@ -83,4 +118,16 @@ mod tests {
 
         assert_eq!(os.env.get("hello").unwrap(), "world");
     }
+
+    #[tokio::test]
+    async fn test_load_project_dotenv_from_subdirectory() {
+        let mut os = Os::new().await.unwrap();
+        os.fs.create_dir_all("/project/.git").await.unwrap();
+        os.fs.create_dir_all("/project/src/nested").await.unwrap();
+        os.fs.write("/project/.env", "Q_PROJECT_VAR=root\n").await.unwrap();
+        os.env.set_current_dir_for_test("/project/src/nested");
+
+        assert_eq!(os.load_project_dotenv(false).await.unwrap(), 1);
+        assert_eq!(os.session_env.get("Q_PROJECT_VAR").map(String::as_str), Some("root"));
+    }
 }
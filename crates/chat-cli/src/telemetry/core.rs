@@ -326,6 +326,7 @@ impl Event {
                 turn_duration,
                 aws_service_name,
                 aws_operation_name,
+                correlation_id,
             } => Some(
                 CodewhispererterminalToolUseSuggested {
                     create_time: self.created_time,
@@ -358,6 +359,7 @@ impl Event {
                     codewhispererterminal_client_application: self.client_application.map(Into::into),
                     codewhispererterminal_aws_service_name: aws_service_name.map(Into::into),
                     codewhispererterminal_aws_operation_name: aws_operation_name.map(Into::into),
+                    codewhispererterminal_correlation_id: correlation_id.map(Into::into),
                 }
                 .into_metric_datum(),
             ),
@@ -653,6 +655,7 @@ pub enum EventType {
         turn_duration: Option<Duration>,
         aws_service_name: Option<String>,
         aws_operation_name: Option<String>,
+        correlation_id: Option<String>,
     },
     AgentContribution {
         conversation_id: String,
@@ -722,6 +725,8 @@ pub struct ToolUseEventBuilder {
     pub turn_duration: Option<Duration>,
     pub aws_service_name: Option<String>,
     pub aws_operation_name: Option<String>,
+    /// Shared by the tool uses suggested in the same turn
+    pub correlation_id: Option<String>,
 }
 
 impl ToolUseEventBuilder {
@@ -746,6 +751,7 @@ impl ToolUseEventBuilder {
             turn_duration: None,
             aws_service_name: None,
             aws_operation_name: None,
+            correlation_id: None,
         }
     }
 
@@ -763,6 +769,11 @@ impl ToolUseEventBuilder {
         self.tool_name.replace(name);
         self
     }
+
+    pub fn correlation_id(mut self, id: String) -> Self {
+        self.correlation_id.replace(id);
+        self
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
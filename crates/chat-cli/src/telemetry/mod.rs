@@ -370,6 +370,7 @@ impl TelemetryThread {
             turn_duration: event.turn_duration,
             aws_service_name: event.aws_service_name,
             aws_operation_name: event.aws_operation_name,
+            correlation_id: event.correlation_id,
         });
         set_event_metadata(database, &mut telemetry_event).await;
 
@@ -1,3 +1,4 @@
+use std::path::PathBuf;
 use std::process::Stdio;
 use std::time::Duration;
 
@@ -10,15 +11,61 @@ use tokio::io::{
 use tokio::process::Command as TokioCommand;
 use tokio::time::timeout;
 
+use crate::database::settings::{
+    Setting,
+    Settings,
+};
 use crate::util::command_frontmatter::CommandFrontmatter;
 use crate::util::command_types::CommandError;
 
+/// Environment variables passed through to restricted snippets in addition to any configured in
+/// `commands.bash.allowedEnv`
+const DEFAULT_RESTRICTED_ENV: &[&str] = &["LANG", "LC_ALL", "TERM"];
+
+/// Restricted execution for bash snippets, enabled with `commands.bash.restricted`. Snippets run
+/// in an empty temporary directory with only an allowlist of environment variables.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RestrictedShell {
+    /// Environment variables passed through from the current environment
+    pub allowed_env: Vec<String>,
+    /// The PATH given to snippets, configured with `commands.bash.path`. The current PATH is never
+    /// inherited.
+    pub path: Option<String>,
+}
+
+impl RestrictedShell {
+    /// The restricted shell configured in settings, if `commands.bash.restricted` is enabled
+    pub fn from_settings(settings: &Settings) -> Option<Self> {
+        if !settings.get_bool(Setting::CommandsBashRestricted).unwrap_or(false) {
+            return None;
+        }
+
+        let mut allowed_env = DEFAULT_RESTRICTED_ENV
+            .iter()
+            .map(|v| (*v).to_string())
+            .collect::<Vec<_>>();
+        if let Some(configured) = settings.get(Setting::CommandsBashAllowedEnv).and_then(|v| v.as_array()) {
+            allowed_env.extend(configured.iter().filter_map(|v| v.as_str()).map(str::to_string));
+        }
+
+        Some(Self {
+            allowed_env,
+            path: settings.get_string(Setting::CommandsBashPath),
+        })
+    }
+}
+
 /// Preprocessor for bash commands in custom command content
 pub struct BashPreprocessor {
     /// Maximum output size for bash commands
     max_output_size: usize,
     /// Timeout for bash command execution
     timeout_duration: Duration,
+    /// When set, snippets run in a restricted environment
+    restricted: Option<RestrictedShell>,
+    /// When false, configured with `commands.bash.enabled`, snippets are never executed and are
+    /// left in the content as written
+    enabled: bool,
 }
 
 impl Default for BashPreprocessor {
@@ -26,6 +73,8 @@ impl Default for BashPreprocessor {
         Self {
             max_output_size: 4096,                     // 4KB max output per command
             timeout_duration: Duration::from_secs(30), // 30 second timeout
+            restricted: None,
+            enabled: true,
         }
     }
 }
@@ -36,15 +85,33 @@ impl BashPreprocessor {
         Self {
             max_output_size,
             timeout_duration: Duration::from_secs(timeout_seconds),
+            restricted: None,
+            enabled: true,
         }
     }
 
+    /// Run snippets in the given restricted environment, or unrestricted when `None`
+    pub fn with_restricted(mut self, restricted: Option<RestrictedShell>) -> Self {
+        self.restricted = restricted;
+        self
+    }
+
+    /// Enable or disable execution of snippets entirely
+    pub fn with_enabled(mut self, enabled: bool) -> Self {
+        self.enabled = enabled;
+        self
+    }
+
     /// Process bash commands in content, replacing !`command` with command output
     pub fn process_bash_commands(
         &self,
         content: &str,
         frontmatter: Option<&CommandFrontmatter>,
     ) -> Result<String, CommandError> {
+        if !self.enabled {
+            return Ok(content.to_string());
+        }
+
         // Parse bash commands from content
         let bash_commands = Self::parse_bash_commands(content)?;
 
@@ -182,11 +249,34 @@ impl BashPreprocessor {
     async fn execute_bash_command(&self, command: &str) -> Result<String, CommandError> {
         let shell = std::env::var("AMAZON_Q_CHAT_SHELL").unwrap_or_else(|_| "bash".to_string());
 
-        let mut child = TokioCommand::new(shell)
-            .arg("-c")
-            .arg(command)
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
+        // The shell is looked up on the current PATH, which restricted snippets don't get
+        let mut cmd = TokioCommand::new(match &self.restricted {
+            Some(_) => resolve_program(&shell),
+            None => shell.into(),
+        });
+        cmd.arg("-c").arg(command).stdout(Stdio::piped()).stderr(Stdio::piped());
+
+        // Held until the command finishes so the working directory outlives it
+        let _restricted_cwd = match &self.restricted {
+            Some(restricted) => {
+                let cwd = tempfile::tempdir()
+                    .map_err(|e| CommandError::Other(format!("Failed to create restricted directory: {}", e)))?;
+                // A null stdin keeps bash from treating the cleared environment as a remote shell
+                // and sourcing the user's bashrc
+                cmd.env_clear().current_dir(cwd.path()).stdin(Stdio::null());
+                for var in &restricted.allowed_env {
+                    if let Some(value) = std::env::var_os(var) {
+                        cmd.env(var, value);
+                    }
+                }
+                // Without a PATH bash would fall back to its built in default
+                cmd.env("PATH", restricted.path.as_deref().unwrap_or_default());
+                Some(cwd)
+            },
+            None => None,
+        };
+
+        let mut child = cmd
             .spawn()
             .map_err(|e| CommandError::Other(format!("Failed to spawn command '{}': {}", command, e)))?;
 
@@ -297,6 +387,22 @@ struct BashCommand {
     line_number: usize,
 }
 
+/// The path of `program` found on the current PATH, or `program` itself if it is a path or is not
+/// found.
+fn resolve_program(program: &str) -> PathBuf {
+    if !program.contains(std::path::MAIN_SEPARATOR) {
+        if let Some(paths) = std::env::var_os("PATH") {
+            if let Some(found) = std::env::split_paths(&paths)
+                .map(|dir| dir.join(program))
+                .find(|candidate| candidate.is_file())
+            {
+                return found;
+            }
+        }
+    }
+    PathBuf::from(program)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -433,4 +539,96 @@ Some other content.
 
         assert!(BashPreprocessor::validate_bash_permissions(&non_git_commands, Some(&frontmatter)).is_err());
     }
+
+    #[test]
+    fn test_restricted_shell_env() {
+        // SAFETY: the variables are unique to this test
+        unsafe {
+            std::env::set_var("Q_TEST_RESTRICTED_SECRET", "hunter2");
+            std::env::set_var("Q_TEST_RESTRICTED_ALLOWED", "visible");
+        }
+        let content = "secret=[!`echo $Q_TEST_RESTRICTED_SECRET`] allowed=[!`echo $Q_TEST_RESTRICTED_ALLOWED`]";
+
+        let unrestricted = BashPreprocessor::default()
+            .process_bash_commands(content, None)
+            .unwrap();
+        assert_eq!(unrestricted, "secret=[hunter2] allowed=[visible]");
+
+        let restricted = BashPreprocessor::default()
+            .with_restricted(Some(RestrictedShell {
+                allowed_env: vec!["Q_TEST_RESTRICTED_ALLOWED".to_string()],
+                path: None,
+            }))
+            .process_bash_commands(content, None)
+            .unwrap();
+        assert_eq!(restricted, "secret=[(no output)] allowed=[visible]");
+
+        // Snippets run in an empty temporary directory
+        let cwd = std::env::current_dir().unwrap();
+        let pwd = BashPreprocessor::default()
+            .with_restricted(Some(RestrictedShell::default()))
+            .process_bash_commands("!`pwd`", None)
+            .unwrap();
+        assert_ne!(std::path::Path::new(&pwd), cwd);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_restricted_shell_path() {
+        // `ls` is on bash's built in default PATH, so it only runs when the PATH allows it
+        let denied = BashPreprocessor::default()
+            .with_restricted(Some(RestrictedShell::default()))
+            .process_bash_commands("!`ls /`", None)
+            .unwrap();
+        assert!(denied.contains("command not found"), "{denied}");
+
+        let allowed = BashPreprocessor::default()
+            .with_restricted(Some(RestrictedShell {
+                path: Some("/usr/bin:/bin".to_string()),
+                ..Default::default()
+            }))
+            .process_bash_commands("!`ls /`", None)
+            .unwrap();
+        assert!(!allowed.contains("command not found"), "{allowed}");
+    }
+
+    #[test]
+    fn test_disabled_leaves_snippets_untouched() {
+        let dir = tempfile::tempdir().unwrap();
+        let marker = dir.path().join("ran");
+        let content = format!("before !`touch {}` after", marker.display());
+        let frontmatter = CommandFrontmatter {
+            allowed_tools: vec!["execute_bash".to_string()],
+            ..Default::default()
+        };
+
+        let output = BashPreprocessor::default()
+            .with_enabled(false)
+            .process_bash_commands(&content, Some(&frontmatter))
+            .unwrap();
+        assert_eq!(output, content);
+        assert!(!marker.exists());
+
+        BashPreprocessor::default()
+            .process_bash_commands(&content, Some(&frontmatter))
+            .unwrap();
+        assert!(marker.exists());
+    }
+
+    #[tokio::test]
+    async fn test_restricted_shell_from_settings() {
+        let mut settings = Settings::default();
+        assert_eq!(RestrictedShell::from_settings(&settings), None);
+
+        settings.set(Setting::CommandsBashRestricted, true).await.unwrap();
+        settings
+            .set(Setting::CommandsBashAllowedEnv, serde_json::json!(["HOME"]))
+            .await
+            .unwrap();
+        settings.set(Setting::CommandsBashPath, "/usr/bin:/bin").await.unwrap();
+        let restricted = RestrictedShell::from_settings(&settings).unwrap();
+        assert!(restricted.allowed_env.contains(&"HOME".to_string()));
+        assert!(restricted.allowed_env.contains(&"LANG".to_string()));
+        assert_eq!(restricted.path.as_deref(), Some("/usr/bin:/bin"));
+    }
 }
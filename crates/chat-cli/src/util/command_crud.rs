@@ -117,7 +117,7 @@ impl CommandCrud {
             .map_err(|e| CommandError::FileError(format!("Failed to create commands directory: {}", e)))?;
 
         // Determine file path
-        let file_path = target_dir.join(format!("{}.md", options.name));
+        let file_path = target_dir.join(CustomCommand::new_file_name(&options.name)?);
 
         // Check if file exists
         if file_path.exists() && !options.force {
@@ -1,14 +1,28 @@
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{
+    Path,
+    PathBuf,
+};
 
 use crate::database::settings::Setting;
-use crate::os::Os;
-use crate::util::bash_preprocessor::BashPreprocessor;
+use crate::os::{
+    Fs,
+    Os,
+};
+use crate::util::bash_preprocessor::{
+    BashPreprocessor,
+    RestrictedShell,
+};
 use crate::util::command_types::{
+    AddedCommand,
     CommandError,
     CommandScope,
     CustomCommand,
 };
+use crate::util::directories::{
+    canonicalizes_path,
+    project_root,
+};
 
 #[cfg(test)]
 pub mod test_utils;
@@ -16,25 +30,77 @@ pub mod test_utils;
 #[cfg(test)]
 mod integration_tests;
 
+/// Default for `commands.fileReferences.maxBytes`
+const DEFAULT_MAX_FILE_REFERENCE_BYTES: usize = 100 * 1024;
+
+/// How `@path` file references in command content are inlined
+struct FileReferenceOptions {
+    /// Combined size of all inlined files
+    max_bytes: usize,
+    /// Levels of references inside referenced files to inline, 0 to leave them as-is
+    max_depth: usize,
+    /// The working directory, when relative references not found next to the referencing file
+    /// should be resolved against it
+    cwd_fallback: Option<PathBuf>,
+}
+
+/// Resolves `.` and `..` components of `path` without touching the filesystem
+fn normalize_path(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            std::path::Component::CurDir => {},
+            std::path::Component::ParentDir if normalized.file_name().is_some() => {
+                normalized.pop();
+            },
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+/// The result of [CommandManager::list_commands_with_failures]
+#[derive(Debug, Default)]
+pub struct CommandListing {
+    /// Commands that loaded successfully, sorted by name
+    pub commands: Vec<CustomCommand>,
+    /// Command files that could not be loaded, with the reason
+    pub failures: Vec<(PathBuf, CommandError)>,
+}
+
+/// The result of [CommandManager::import_from_dir]
+#[derive(Debug, Default)]
+pub struct ImportReport {
+    /// Commands that were imported, sorted by name
+    pub imported: Vec<AddedCommand>,
+    /// Command files that were not imported, with the reason
+    pub failures: Vec<(PathBuf, CommandError)>,
+}
+
 /// Manages custom commands for the Amazon Q CLI
 pub struct CommandManager {
     /// Path to project commands directory (.amazonq/commands/)
     project_commands_dir: PathBuf,
     /// Path to user commands directory (~/.amazonq/commands/)
     user_commands_dir: PathBuf,
+    /// Shared, read-only commands directories from `commands.extraDirs`, searched in order after
+    /// the project and user directories
+    extra_commands_dirs: Vec<PathBuf>,
     /// Cache of loaded commands
     cache: HashMap<String, CustomCommand>,
     /// Bash command preprocessor
     bash_preprocessor: BashPreprocessor,
+    /// Whether to open the platform default editor when `EDITOR` is not installed
+    editor_fallback: bool,
+    /// Filesystem the command files are read from and written to
+    fs: Fs,
 }
 
 impl CommandManager {
     /// Create a new command manager
     pub fn new(os: &Os) -> Result<Self, CommandError> {
-        let project_commands_dir = os
-            .env
-            .current_dir()
-            .map_err(CommandError::Io)?
+        let project_commands_dir = project_root(os)
+            .map_err(|e| CommandError::Other(format!("Could not determine project root: {e}")))?
             .join(".amazonq")
             .join("commands");
 
@@ -45,11 +111,38 @@ impl CommandManager {
             .join(".amazonq")
             .join("commands");
 
+        let extra_commands_dirs = os
+            .database
+            .settings
+            .get(Setting::CommandsExtraDirs)
+            .and_then(|v| v.as_array())
+            .map(|dirs| {
+                dirs.iter()
+                    .filter_map(|dir| dir.as_str())
+                    .map(|dir| PathBuf::from(canonicalizes_path(os, dir).unwrap_or_else(|_| dir.to_string())))
+                    .collect()
+            })
+            .unwrap_or_default();
+
         Ok(Self {
             project_commands_dir,
             user_commands_dir,
+            extra_commands_dirs,
             cache: HashMap::new(),
-            bash_preprocessor: BashPreprocessor::default(),
+            bash_preprocessor: BashPreprocessor::default()
+                .with_restricted(RestrictedShell::from_settings(&os.database.settings))
+                .with_enabled(
+                    os.database
+                        .settings
+                        .get_bool(Setting::CommandsBashEnabled)
+                        .unwrap_or(true),
+                ),
+            editor_fallback: os
+                .database
+                .settings
+                .get_bool(Setting::CommandsEditorFallback)
+                .unwrap_or(false),
+            fs: os.fs.clone(),
         })
     }
 
@@ -58,18 +151,19 @@ impl CommandManager {
         os.database.settings.get_bool(Setting::EnabledCommands).unwrap_or(false)
     }
 
-    /// Add a new command
-    pub fn add_command(&mut self, name: &str, _os: &Os) -> Result<String, CommandError> {
+    /// Add a new command, returning where it was created. See [AddedCommand::format] for the
+    /// message to show the user.
+    pub fn add_command(&mut self, name: &str, os: &Os) -> Result<AddedCommand, CommandError> {
         // Validate command name
         CustomCommand::validate_name(name)?;
 
         // Create commands directory if it doesn't exist
-        std::fs::create_dir_all(&self.project_commands_dir)?;
+        self.fs.create_dir_all_sync(&self.project_commands_dir)?;
 
-        let file_path = self.project_commands_dir.join(format!("{}.md", name));
+        let file_path = self.project_commands_dir.join(CustomCommand::new_file_name(name)?);
 
         // Check if command already exists
-        if file_path.exists() {
+        if self.fs.exists(&file_path) {
             return Err(CommandError::AlreadyExists(name.to_string()));
         }
 
@@ -77,21 +171,23 @@ impl CommandManager {
         let template = Self::create_command_template(name);
 
         // Write template to file
-        std::fs::write(&file_path, template)?;
+        self.fs.write_sync(&file_path, template)?;
 
         // Open editor (skip in test mode for performance)
-        if !cfg!(test) && std::env::var("EDITOR").unwrap_or_default() != "true" {
-            Self::open_editor(&file_path)?;
+        let mut notice = None;
+        if !cfg!(test) && os.env.get("EDITOR").unwrap_or_default() != "true" {
+            notice = self.open_editor(&file_path, os)?;
         }
 
         // Load the command into cache
-        let command = CustomCommand::from_file(file_path)?;
+        let command = self.read_command(file_path.clone())?;
         self.cache.insert(name.to_string(), command);
 
-        Ok(format!(
-            "✅ Command '{}' created successfully!\n   Use '/project:{}' to execute it.\n\n💡 Tip: Use '/commands show {}' to see command details.",
-            name, name, name
-        ))
+        Ok(AddedCommand {
+            name: name.to_string(),
+            path: file_path,
+            notice,
+        })
     }
 
     /// Get a command by name
@@ -100,12 +196,12 @@ impl CommandManager {
         if !self.cache.contains_key(name) {
             // Try to load from file
             let file_path = self.project_commands_dir.join(format!("{}.md", name));
-            if file_path.exists() {
-                let command = CustomCommand::from_file(file_path)?;
-                self.cache.insert(name.to_string(), command);
+            let command = if self.fs.exists(&file_path) {
+                self.read_command(file_path)?
             } else {
-                return Err(CommandError::NotFound(name.to_string()));
-            }
+                self.load_shared_command(name)?
+            };
+            self.cache.insert(name.to_string(), command);
         }
 
         // This should never fail since we just inserted the key above
@@ -130,6 +226,7 @@ impl CommandManager {
         let command = self.get_command(name)?;
         let mut content = command.content.clone();
         let frontmatter = command.frontmatter.clone();
+        let command_dir = command.file_path.parent().unwrap_or(Path::new(".")).to_path_buf();
 
         // Process argument substitution
         if let Some(args) = args {
@@ -139,7 +236,7 @@ impl CommandManager {
         }
 
         // Process file references
-        content = Self::process_file_references(content, os)?;
+        content = Self::process_file_references(content, &command_dir, os)?;
 
         // Process bash commands (NEW)
         content = self
@@ -162,6 +259,7 @@ impl CommandManager {
         let command = self.get_user_command(name)?;
         let mut content = command.content.clone();
         let frontmatter = command.frontmatter.clone();
+        let command_dir = command.file_path.parent().unwrap_or(Path::new(".")).to_path_buf();
 
         // Process argument substitution
         if let Some(args) = args {
@@ -171,7 +269,7 @@ impl CommandManager {
         }
 
         // Process file references
-        content = Self::process_file_references(content, os)?;
+        content = Self::process_file_references(content, &command_dir, os)?;
 
         // Process bash commands (NEW)
         content = self
@@ -203,33 +301,148 @@ impl CommandManager {
         // Handle both namespace/name format and simple name format
         let file_path = self.user_commands_dir.join(format!("{}.md", name));
 
-        if !file_path.exists() {
-            return Err(CommandError::NotFound(name.to_string()));
+        if !self.fs.exists(&file_path) {
+            return self.load_shared_command(name);
         }
 
-        CustomCommand::from_file(file_path)
+        self.read_command(file_path)
     }
 
-    /// Process file references in command content
-    fn process_file_references(content: String, _os: &Os) -> Result<String, CommandError> {
+    /// Load a command from the first shared directory that contains it
+    fn load_shared_command(&self, name: &str) -> Result<CustomCommand, CommandError> {
+        let file_path = self
+            .extra_commands_dirs
+            .iter()
+            .map(|dir| dir.join(format!("{}.md", name)))
+            .find(|path| self.fs.exists(path))
+            .ok_or_else(|| CommandError::NotFound(name.to_string()))?;
+
+        let mut command = self.read_command(file_path)?;
+        command.scope = CommandScope::Shared;
+        Ok(command)
+    }
+
+    /// Process file references in the content of the command in `command_dir`.
+    ///
+    /// Relative references are resolved against the directory of the referencing file, and may
+    /// not climb out of it. With `commands.fileReferences.cwdFallback` they are also looked up
+    /// in the working directory when not found there.
+    ///
+    /// The combined size of inlined files is capped by `commands.fileReferences.maxBytes`.
+    /// References inside referenced files are only inlined up to
+    /// `commands.fileReferences.maxDepth` levels.
+    fn process_file_references(content: String, command_dir: &Path, os: &Os) -> Result<String, CommandError> {
+        let settings = &os.database.settings;
+        let cwd_fallback = settings
+            .get_bool(Setting::CommandsFileReferencesCwdFallback)
+            .unwrap_or(false);
+        let options = FileReferenceOptions {
+            max_bytes: settings.get_int_or(
+                Setting::CommandsFileReferencesMaxBytes,
+                DEFAULT_MAX_FILE_REFERENCE_BYTES,
+            ),
+            max_depth: settings.get_int_or(Setting::CommandsFileReferencesMaxDepth, 0),
+            cwd_fallback: match cwd_fallback {
+                true => Some(os.env.current_dir()?),
+                false => None,
+            },
+        };
+
+        Self::inline_file_references(&os.fs, &content, command_dir, &options, &mut 0, &mut Vec::new())
+    }
+
+    /// Resolves the `@reference` in a file in `base_dir`, refusing relative references that climb
+    /// out of it.
+    fn resolve_file_reference(base_dir: &Path, reference: &str) -> Result<PathBuf, CommandError> {
+        let path = base_dir.join(reference);
+        if Path::new(reference).is_relative() && !normalize_path(&path).starts_with(normalize_path(base_dir)) {
+            return Err(CommandError::SecurityViolation(format!(
+                "File reference @{reference} points outside of {}",
+                base_dir.display()
+            )));
+        }
+        Ok(path)
+    }
+
+    /// Inlines the `@path` references in `content`. `stack` holds the files being inlined,
+    /// outermost first, and `inlined_bytes` the combined size of the files inlined so far.
+    fn inline_file_references(
+        fs: &Fs,
+        content: &str,
+        base_dir: &Path,
+        options: &FileReferenceOptions,
+        inlined_bytes: &mut usize,
+        stack: &mut Vec<PathBuf>,
+    ) -> Result<String, CommandError> {
         use regex::Regex;
 
         let file_ref_regex =
             Regex::new(r"@([^\s]+)").map_err(|e| CommandError::Other(format!("Regex error: {}", e)))?;
-        let mut result = content.clone();
-
-        for captures in file_ref_regex.captures_iter(&content) {
-            let file_path = &captures[1];
-            let full_match = &captures[0];
-
-            // Try to read the file
-            if let Ok(file_content) = std::fs::read_to_string(file_path) {
-                result = result.replace(full_match, &file_content);
+        let mut result = String::with_capacity(content.len());
+        let mut last_end = 0;
+
+        for captures in file_ref_regex.captures_iter(content) {
+            let full_match = captures.get(0).expect("capture group 0 always matches");
+            result.push_str(&content[last_end..full_match.start()]);
+            last_end = full_match.end();
+
+            let mut file_path = Self::resolve_file_reference(base_dir, &captures[1])?;
+            if !fs.exists(&file_path) {
+                if let Some(cwd) = &options.cwd_fallback {
+                    file_path = Self::resolve_file_reference(cwd, &captures[1])?;
+                }
             }
+
             // Leave the reference as-is if file can't be read
             // This allows for graceful degradation
+            let Ok(file_content) = fs.read_to_string_sync(&file_path) else {
+                result.push_str(full_match.as_str());
+                continue;
+            };
+
+            let canonical = normalize_path(&file_path);
+            let chain = |last: &Path| {
+                stack
+                    .iter()
+                    .map(|path| path.display().to_string())
+                    .chain(std::iter::once(last.display().to_string()))
+                    .collect::<Vec<_>>()
+                    .join(" -> ")
+            };
+            if stack.contains(&canonical) {
+                return Err(CommandError::FileError(format!(
+                    "File references form a cycle: {}",
+                    chain(&canonical)
+                )));
+            }
+            if stack.len() > options.max_depth {
+                return Err(CommandError::FileError(format!(
+                    "File references are nested more than {} levels deep: {}",
+                    options.max_depth,
+                    chain(&canonical)
+                )));
+            }
+            *inlined_bytes += file_content.len();
+            if *inlined_bytes > options.max_bytes {
+                return Err(CommandError::FileError(format!(
+                    "Referenced files exceed the limit of {} bytes at {}. Raise it with: q settings commands.fileReferences.maxBytes <bytes>",
+                    options.max_bytes,
+                    file_path.display()
+                )));
+            }
+
+            if options.max_depth == 0 {
+                result.push_str(&file_content);
+                continue;
+            }
+            stack.push(canonical);
+            let file_dir = file_path.parent().unwrap_or(base_dir);
+            let inlined = Self::inline_file_references(fs, &file_content, file_dir, options, inlined_bytes, stack)?;
+            stack.pop();
+            result.push_str(&inlined);
         }
 
+        result.push_str(&content[last_end..]);
         Ok(result)
     }
 
@@ -259,50 +472,166 @@ impl CommandManager {
         Ok(())
     }
 
-    /// List all available commands with full details
+    /// List all available commands with full details, skipping files that fail to load
     pub fn list_commands_detailed(&mut self, scope: Option<&CommandScope>) -> Result<Vec<CustomCommand>, CommandError> {
-        let mut commands = Vec::new();
+        Ok(self.list_commands_with_failures(scope)?.commands)
+    }
+
+    /// List all available commands with full details, along with the command files that failed
+    /// to load and why
+    pub fn list_commands_with_failures(
+        &mut self,
+        scope: Option<&CommandScope>,
+    ) -> Result<CommandListing, CommandError> {
+        let mut listing = CommandListing::default();
 
         // Load project commands if requested or no scope specified
-        if (scope.is_none() || scope == Some(&CommandScope::Project)) && self.project_commands_dir.exists() {
-            for entry in std::fs::read_dir(&self.project_commands_dir)? {
-                let entry = entry?;
-                let path = entry.path();
-
-                if path.is_file() && path.extension().and_then(|s| s.to_str()) == Some("md") {
-                    if let Ok(mut command) = CustomCommand::from_file(path) {
-                        command.scope = CommandScope::Project;
-                        commands.push(command);
-                    }
+        if scope.is_none() || scope == Some(&CommandScope::Project) {
+            self.load_commands_dir(&self.project_commands_dir, CommandScope::Project, &mut listing)?;
+        }
+
+        // Load user/global commands if requested or no scope specified
+        if scope.is_none() || scope == Some(&CommandScope::Global) {
+            self.load_commands_dir(&self.user_commands_dir, CommandScope::Global, &mut listing)?;
+        }
+
+        // Shared commands are shadowed by project and user commands of the same name
+        if scope.is_none() || scope == Some(&CommandScope::Shared) {
+            let mut shared = CommandListing::default();
+            for dir in &self.extra_commands_dirs {
+                // An unreadable shared directory should not hide the rest of the commands
+                if let Err(err) = self.load_commands_dir(dir, CommandScope::Shared, &mut shared) {
+                    shared.failures.push((dir.clone(), err));
                 }
             }
+            let mut seen = listing
+                .commands
+                .iter()
+                .map(|command| command.name.clone())
+                .collect::<std::collections::HashSet<_>>();
+            listing.commands.extend(
+                shared
+                    .commands
+                    .into_iter()
+                    .filter(|command| seen.insert(command.name.clone())),
+            );
+            listing.failures.extend(shared.failures);
         }
 
-        // Load user/global commands if requested or no scope specified
-        if (scope.is_none() || scope == Some(&CommandScope::Global)) && self.user_commands_dir.exists() {
-            for entry in std::fs::read_dir(&self.user_commands_dir)? {
-                let entry = entry?;
-                let path = entry.path();
-
-                if path.is_file() && path.extension().and_then(|s| s.to_str()) == Some("md") {
-                    if let Ok(mut command) = CustomCommand::from_file(path) {
-                        command.scope = CommandScope::Global;
-                        commands.push(command);
-                    }
+        listing.commands.sort_by(|a, b| a.name.cmp(&b.name));
+        listing.failures.sort_by(|(a, _), (b, _)| a.cmp(b));
+        Ok(listing)
+    }
+
+    fn load_commands_dir(
+        &self,
+        dir: &Path,
+        scope: CommandScope,
+        listing: &mut CommandListing,
+    ) -> Result<(), CommandError> {
+        if !self.fs.exists(dir) {
+            return Ok(());
+        }
+
+        for path in self.fs.read_dir_sync(dir)? {
+            if self.fs.is_file(&path) && path.extension().and_then(|s| s.to_str()) == Some("md") {
+                match self.read_command(path.clone()) {
+                    Ok(mut command) => {
+                        command.scope = scope.clone();
+                        listing.commands.push(command);
+                    },
+                    Err(err) => listing.failures.push((path, err)),
                 }
             }
         }
 
-        commands.sort_by(|a, b| a.name.cmp(&b.name));
-        Ok(commands)
+        Ok(())
+    }
+
+    /// Copy every `.md` command file directly in `src` into the commands directory of `scope`.
+    /// Files with an invalid name, frontmatter or content are skipped, as are commands that already
+    /// exist in `scope` unless `force` is set. Each skipped file is reported with the reason.
+    pub fn import_from_dir(
+        &mut self,
+        src: &Path,
+        scope: &CommandScope,
+        force: bool,
+    ) -> Result<ImportReport, CommandError> {
+        let target_dir = match scope {
+            CommandScope::Project => self.project_commands_dir.clone(),
+            CommandScope::Global => self.user_commands_dir.clone(),
+            CommandScope::Shared => {
+                return Err(CommandError::Other(
+                    "Shared commands are read-only and cannot be imported into".to_string(),
+                ));
+            },
+        };
+        if !self.fs.exists(src) {
+            return Err(CommandError::NotFound(src.display().to_string()));
+        }
+
+        let mut report = ImportReport::default();
+        for path in self.fs.read_dir_sync(src)? {
+            if !self.fs.is_file(&path) || path.extension().and_then(|s| s.to_str()) != Some("md") {
+                continue;
+            }
+            match self.import_file(&path, &target_dir, force) {
+                Ok(added) => report.imported.push(added),
+                Err(err) => report.failures.push((path, err)),
+            }
+        }
+
+        report.imported.sort_by(|a, b| a.name.cmp(&b.name));
+        report.failures.sort_by(|(a, _), (b, _)| a.cmp(b));
+        Ok(report)
+    }
+
+    /// Validate the command file at `path` and copy it into `target_dir`
+    fn import_file(&mut self, path: &Path, target_dir: &Path, force: bool) -> Result<AddedCommand, CommandError> {
+        let content = self.fs.read_to_string_sync(path)?;
+        let command = CustomCommand::from_content(path.to_path_buf(), &content)?;
+        CustomCommand::validate_name(&command.name)?;
+        Self::validate_command_security(&command.content)?;
+
+        let file_path = target_dir.join(CustomCommand::new_file_name(&command.name)?);
+        if !force && self.fs.exists(&file_path) {
+            return Err(CommandError::AlreadyExists(command.name));
+        }
+        self.fs.create_dir_all_sync(target_dir)?;
+        self.fs.write_sync(&file_path, content)?;
+        self.cache.remove(&command.name);
+
+        Ok(AddedCommand {
+            name: command.name,
+            path: file_path,
+            notice: None,
+        })
     }
 
     /// Clear the command cache
-    #[allow(dead_code)]
     pub fn clear_cache(&mut self) {
         self.cache.clear();
     }
 
+    /// Number of commands currently held in the cache
+    pub fn cache_len(&self) -> usize {
+        self.cache.len()
+    }
+
+    /// Names of the commands currently held in the cache, sorted
+    pub fn cached_names(&self) -> Vec<String> {
+        let mut names = self.cache.keys().cloned().collect::<Vec<_>>();
+        names.sort();
+        names
+    }
+
+    /// Drop all cached commands and reload the listing from disk, picking up any files that were
+    /// edited outside of the chat session
+    pub fn refresh(&mut self) -> Result<CommandListing, CommandError> {
+        self.clear_cache();
+        self.list_commands_with_failures(None)
+    }
+
     /// Test accessor methods for integration tests
     #[cfg(test)]
     pub fn get_project_commands_dir(&self) -> &std::path::PathBuf {
@@ -325,25 +654,31 @@ impl CommandManager {
         Self {
             project_commands_dir,
             user_commands_dir,
+            extra_commands_dirs: Vec::new(),
             cache: HashMap::new(),
             bash_preprocessor: BashPreprocessor::default(),
+            editor_fallback: false,
+            fs: Fs::Real,
         }
     }
 
+    /// Read the command file at `file_path`
+    fn read_command(&self, file_path: PathBuf) -> Result<CustomCommand, CommandError> {
+        let content = self.fs.read_to_string_sync(&file_path)?;
+        CustomCommand::from_content(file_path, &content)
+    }
+
     /// List all available commands
     #[allow(dead_code)]
     pub fn list_commands(&mut self) -> Result<Vec<String>, CommandError> {
         let mut commands = Vec::new();
 
-        if !self.project_commands_dir.exists() {
+        if !self.fs.exists(&self.project_commands_dir) {
             return Ok(commands);
         }
 
-        for entry in std::fs::read_dir(&self.project_commands_dir)? {
-            let entry = entry?;
-            let path = entry.path();
-
-            if path.is_file() && path.extension().and_then(|s| s.to_str()) == Some("md") {
+        for path in self.fs.read_dir_sync(&self.project_commands_dir)? {
+            if self.fs.is_file(&path) && path.extension().and_then(|s| s.to_str()) == Some("md") {
                 if let Some(name) = path.file_stem().and_then(|s| s.to_str()) {
                     commands.push(name.to_string());
                 }
@@ -389,44 +724,69 @@ impl CommandManager {
         )
     }
 
-    /// Open editor for command file
-    pub fn open_editor(file_path: &PathBuf) -> Result<(), CommandError> {
+    /// Open editor for command file. Returns a notice for the user if a fallback editor was opened
+    /// instead of the configured one.
+    pub fn open_editor(&self, file_path: &Path, os: &Os) -> Result<Option<String>, CommandError> {
         // Get editor from environment, fallback to sensible defaults
-        let editor = std::env::var("EDITOR")
-            .or_else(|_| std::env::var("VISUAL"))
-            .unwrap_or_else(|_| {
-                // Platform-specific defaults
-                if cfg!(target_os = "windows") {
-                    "notepad".to_string()
-                } else if cfg!(target_os = "macos") {
-                    "open".to_string()
-                } else {
-                    "nano".to_string()
-                }
-            });
+        let editor = os
+            .env
+            .get("EDITOR")
+            .or_else(|_| os.env.get("VISUAL"))
+            .unwrap_or_else(|_| default_editor().to_string());
 
-        let mut cmd = std::process::Command::new(&editor);
+        run_editor(&editor, file_path, self.editor_fallback.then(default_editor))
+    }
+}
 
-        // Special handling for macOS 'open' command
-        if editor == "open" {
-            cmd.arg("-t"); // Open in text editor
-        }
+/// The platform specific editor used when neither `EDITOR` nor `VISUAL` is set
+fn default_editor() -> &'static str {
+    if cfg!(target_os = "windows") {
+        "notepad"
+    } else if cfg!(target_os = "macos") {
+        "open"
+    } else {
+        "nano"
+    }
+}
 
-        cmd.arg(file_path);
+/// Runs `editor` on `file_path`, retrying with `fallback` if `editor` is not installed. Returns a
+/// notice for the user when the fallback was opened.
+fn run_editor(editor: &str, file_path: &Path, fallback: Option<&str>) -> Result<Option<String>, CommandError> {
+    match (spawn_editor(editor, file_path), fallback) {
+        (Err(CommandError::EditorNotFound(_)), Some(fallback)) if fallback != editor => {
+            spawn_editor(fallback, file_path)?;
+            Ok(Some(format!(
+                "Warning: editor '{}' was not found, opened '{}' instead. Set EDITOR to an installed editor to avoid this warning.",
+                editor, fallback
+            )))
+        },
+        (result, _) => result.map(|_| None),
+    }
+}
 
-        let status = cmd
-            .status()
-            .map_err(|e| CommandError::EditorError(format!("Failed to start editor '{}': {}", editor, e)))?;
+fn spawn_editor(editor: &str, file_path: &Path) -> Result<(), CommandError> {
+    let mut cmd = std::process::Command::new(editor);
 
-        if !status.success() {
-            return Err(CommandError::EditorError(format!(
-                "Editor '{}' exited with error",
-                editor
-            )));
-        }
+    // Special handling for macOS 'open' command
+    if editor == "open" {
+        cmd.arg("-t"); // Open in text editor
+    }
 
-        Ok(())
+    cmd.arg(file_path);
+
+    let status = cmd.status().map_err(|e| match e.kind() {
+        std::io::ErrorKind::NotFound => CommandError::EditorNotFound(editor.to_string()),
+        _ => CommandError::EditorError(format!("Failed to start editor '{}': {}", editor, e)),
+    })?;
+
+    if !status.success() {
+        return Err(CommandError::EditorError(format!(
+            "Editor '{}' exited with error",
+            editor
+        )));
     }
+
+    Ok(())
 }
 
 #[cfg(test)]
@@ -441,6 +801,301 @@ mod tests {
         assert!(CustomCommand::validate_name("help").is_err());
     }
 
+    #[tokio::test]
+    async fn test_project_root_detection() {
+        let mut os = Os::new().await.unwrap();
+        os.fs.create_dir_all("/repo/.amazonq/commands").await.unwrap();
+        os.fs.create_dir_all("/repo/src/nested").await.unwrap();
+        os.env.set_current_dir_for_test("/repo/src/nested");
+
+        let manager = CommandManager::new(&os).unwrap();
+        assert_eq!(manager.get_project_commands_dir(), Path::new("/repo/.amazonq/commands"));
+        assert_eq!(
+            crate::cli::chat::tool_manager::workspace_mcp_config_path(&os).unwrap(),
+            Path::new("/repo/.amazonq/mcp.json")
+        );
+        assert_eq!(
+            crate::util::directories::chat_local_agent_dir(&os).unwrap(),
+            Path::new("/repo/.amazonq/cli-agents")
+        );
+
+        // A `.git` directory marks the root too, and the closest marker wins
+        os.fs.create_dir_all("/repo/src/.git").await.unwrap();
+        let manager = CommandManager::new(&os).unwrap();
+        assert_eq!(
+            manager.get_project_commands_dir(),
+            Path::new("/repo/src/.amazonq/commands")
+        );
+
+        // The setting overrides detection
+        os.database.settings.set(Setting::ProjectRoot, "/other").await.unwrap();
+        let manager = CommandManager::new(&os).unwrap();
+        assert_eq!(
+            manager.get_project_commands_dir(),
+            Path::new("/other/.amazonq/commands")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_project_root_ignores_home() {
+        let os = Os::new().await.unwrap();
+        let home = os.env.home().unwrap();
+        os.fs.create_dir_all(home.join(".amazonq/commands")).await.unwrap();
+        os.fs.create_dir_all(home.join("scratch")).await.unwrap();
+
+        // Global config in the home directory doesn't make it the project root
+        os.env.set_current_dir_for_test(home.join("scratch"));
+        assert_eq!(
+            crate::util::directories::project_root(&os).unwrap(),
+            home.join("scratch")
+        );
+        os.env.set_current_dir_for_test(&home);
+        assert_eq!(crate::util::directories::project_root(&os).unwrap(), home);
+    }
+
+    #[tokio::test]
+    async fn test_file_references_within_budget() {
+        let os = Os::new().await.unwrap();
+        os.fs.create_dir_all("/refs").await.unwrap();
+        os.fs
+            .write("/refs/notes.md", "Deploy on Fridays @nested.md")
+            .await
+            .unwrap();
+        os.fs.write("/refs/nested.md", "never").await.unwrap();
+
+        let content = "Read @/refs/notes.md and @missing.md".to_string();
+        let processed = CommandManager::process_file_references(content, Path::new("/"), &os).unwrap();
+        // References inside referenced files are left as-is unless a depth is configured
+        assert_eq!(processed, "Read Deploy on Fridays @nested.md and @missing.md");
+    }
+
+    #[tokio::test]
+    async fn test_file_references_over_budget() {
+        let mut os = Os::new().await.unwrap();
+        os.database
+            .settings
+            .set(Setting::CommandsFileReferencesMaxBytes, 10)
+            .await
+            .unwrap();
+        os.fs.create_dir_all("/refs").await.unwrap();
+        os.fs.write("/refs/first.md", "123456").await.unwrap();
+        os.fs.write("/refs/second.md", "789012").await.unwrap();
+
+        let one = "@/refs/first.md".to_string();
+        assert_eq!(
+            CommandManager::process_file_references(one, Path::new("/"), &os).unwrap(),
+            "123456"
+        );
+
+        let both = "@/refs/first.md @/refs/second.md".to_string();
+        let err = CommandManager::process_file_references(both, Path::new("/"), &os).unwrap_err();
+        assert!(matches!(&err, CommandError::FileError(msg) if msg.contains("limit of 10 bytes")));
+        assert!(err.to_string().contains("/refs/second.md"));
+    }
+
+    #[tokio::test]
+    async fn test_recursive_file_references() {
+        let mut os = Os::new().await.unwrap();
+        os.database
+            .settings
+            .set(Setting::CommandsFileReferencesMaxDepth, 2)
+            .await
+            .unwrap();
+        os.fs.create_dir_all("/refs").await.unwrap();
+        os.fs.write("/refs/a.md", "A @b.md").await.unwrap();
+        os.fs.write("/refs/b.md", "B @c.md").await.unwrap();
+        os.fs.write("/refs/c.md", "C").await.unwrap();
+
+        // Nested references are resolved relative to the referencing file
+        let content = "@/refs/a.md".to_string();
+        assert_eq!(
+            CommandManager::process_file_references(content.clone(), Path::new("/"), &os).unwrap(),
+            "A B C"
+        );
+
+        // One level too deep
+        os.fs.write("/refs/c.md", "C @d.md").await.unwrap();
+        os.fs.write("/refs/d.md", "D").await.unwrap();
+        let err = CommandManager::process_file_references(content.clone(), Path::new("/"), &os).unwrap_err();
+        assert!(err.to_string().contains("nested more than 2 levels deep"));
+
+        // A cycle is reported instead of recursing until the depth limit
+        os.fs.write("/refs/b.md", "B @a.md").await.unwrap();
+        let err = CommandManager::process_file_references(content, Path::new("/"), &os).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "File error: File references form a cycle: /refs/a.md -> /refs/b.md -> /refs/a.md"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_file_references_relative_to_command() {
+        let mut os = Os::new().await.unwrap();
+        let commands_dir = PathBuf::from("/project/.amazonq/commands");
+        os.fs.create_dir_all(&commands_dir).await.unwrap();
+        os.fs.create_dir_all("/elsewhere").await.unwrap();
+        os.fs
+            .write(commands_dir.join("config.json"), "{\"stage\": \"prod\"}")
+            .await
+            .unwrap();
+        os.fs.write("/elsewhere/local.md", "from cwd").await.unwrap();
+        os.fs.write("/project/secret.md", "secret").await.unwrap();
+        os.env.set_current_dir_for_test("/elsewhere");
+
+        let mut manager = CommandManager::new_for_test(commands_dir.clone(), PathBuf::from("/home/.amazonq/commands"));
+        manager.fs = os.fs.clone();
+        let write_command = |content: &str| {
+            futures::executor::block_on(os.fs.write(commands_dir.join("deploy.md"), content)).unwrap();
+        };
+
+        // The sibling file resolves even though the cwd is elsewhere
+        write_command("Deploy with @config.json and @local.md");
+        let content = manager.execute_command_with_args("deploy", None, &os).unwrap();
+        assert_eq!(content, "Deploy with {\"stage\": \"prod\"} and @local.md");
+
+        // References may not climb out of the command's directory
+        write_command("Read @../../secret.md");
+        manager.cache.clear();
+        let err = manager.execute_command_with_args("deploy", None, &os).unwrap_err();
+        assert!(matches!(err, CommandError::SecurityViolation(msg) if msg.contains("@../../secret.md")));
+
+        // The cwd is only used when configured
+        write_command("Deploy with @config.json and @local.md");
+        manager.cache.clear();
+        os.database
+            .settings
+            .set(Setting::CommandsFileReferencesCwdFallback, true)
+            .await
+            .unwrap();
+        let content = manager.execute_command_with_args("deploy", None, &os).unwrap();
+        assert_eq!(content, "Deploy with {\"stage\": \"prod\"} and from cwd");
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_fs() {
+        let os = Os::new().await.unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let project = dir.path().join("project/.amazonq/commands");
+        let mut manager = CommandManager::new_for_test(project.clone(), dir.path().join("home/.amazonq/commands"));
+        manager.fs = Fs::from_slice(&[]);
+
+        manager.add_command("deploy", &os).unwrap();
+        manager.cache.clear();
+        let command = manager.get_command("deploy").unwrap();
+        assert_eq!(command.name, "deploy");
+        assert_eq!(manager.list_commands().unwrap(), vec!["deploy".to_string()]);
+
+        // Nothing was written to disk
+        assert!(!project.exists());
+        assert!(!dir.path().join("home").exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_missing_editor() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_path = dir.path().join("command.md");
+        let missing = "q-test-editor-that-does-not-exist";
+
+        let err = run_editor(missing, &file_path, None).unwrap_err();
+        assert!(matches!(&err, CommandError::EditorNotFound(editor) if editor == missing));
+        assert!(err.to_string().contains("Set EDITOR"));
+
+        // With the fallback enabled, the fallback editor is opened instead
+        let notice = run_editor(missing, &file_path, Some("true")).unwrap().unwrap();
+        assert!(notice.contains(missing));
+
+        // A missing fallback is reported as missing too
+        let err = run_editor(missing, &file_path, Some(missing)).unwrap_err();
+        assert!(matches!(err, CommandError::EditorNotFound(_)));
+    }
+
+    #[tokio::test]
+    async fn test_cache_contents_and_refresh() {
+        let os = Os::new().await.unwrap();
+        let project_dir = PathBuf::from("/project");
+        os.fs.create_dir_all(&project_dir).await.unwrap();
+        os.fs
+            .write(project_dir.join("review.md"), "Review the staged changes")
+            .await
+            .unwrap();
+        os.fs.write(project_dir.join("deploy.md"), "Deploy it").await.unwrap();
+
+        let mut manager = CommandManager::new_for_test(project_dir.clone(), PathBuf::from("/user"));
+        manager.fs = os.fs.clone();
+        assert_eq!(manager.cache_len(), 0);
+        manager.get_command("review").unwrap();
+        manager.get_command("review").unwrap();
+        assert_eq!(manager.cache_len(), 1);
+        manager.get_command("deploy").unwrap();
+        assert_eq!(manager.cached_names(), ["deploy", "review"]);
+
+        // Edits made on disk are only picked up after a refresh
+        os.fs
+            .write(project_dir.join("review.md"), "Review everything")
+            .await
+            .unwrap();
+        assert_eq!(
+            manager.get_command("review").unwrap().content,
+            "Review the staged changes"
+        );
+
+        let listing = manager.refresh().unwrap();
+        assert_eq!(listing.commands.len(), 2);
+        assert_eq!(manager.cache_len(), 0);
+        assert!(manager.cached_names().is_empty());
+        assert_eq!(manager.get_command("review").unwrap().content, "Review everything");
+    }
+
+    #[tokio::test]
+    async fn test_extra_dirs_are_listed_and_read_only() {
+        let os = Os::new().await.unwrap();
+        let project_dir = PathBuf::from("/project");
+        let user_dir = PathBuf::from("/user");
+        let shared_dir = PathBuf::from("/shared");
+        os.fs.create_dir_all(&user_dir).await.unwrap();
+        os.fs.create_dir_all(&shared_dir).await.unwrap();
+        os.fs
+            .write(shared_dir.join("lint.md"), "Lint the codebase")
+            .await
+            .unwrap();
+        os.fs
+            .write(shared_dir.join("standup.md"), "Shared standup")
+            .await
+            .unwrap();
+        os.fs.write(user_dir.join("standup.md"), "My standup").await.unwrap();
+
+        let mut manager = CommandManager::new_for_test(project_dir.clone(), user_dir);
+        manager.fs = os.fs.clone();
+        manager.extra_commands_dirs = vec![PathBuf::from("/missing"), shared_dir.clone()];
+
+        // Listed, with user commands taking precedence over shared ones of the same name
+        let listing = manager.list_commands_with_failures(None).unwrap();
+        assert!(listing.failures.is_empty());
+        let listed = listing
+            .commands
+            .iter()
+            .map(|c| (c.name.as_str(), c.scope.clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(listed, [
+            ("lint", CommandScope::Shared),
+            ("standup", CommandScope::Global)
+        ]);
+
+        // Resolvable, but read-only
+        let command = manager.get_command("lint").unwrap();
+        assert_eq!(command.content, "Lint the codebase");
+        assert!(command.is_read_only());
+        assert_eq!(manager.get_user_command("lint").unwrap().scope, CommandScope::Shared);
+        assert_eq!(manager.get_user_command("standup").unwrap().content, "My standup");
+
+        // New commands are never created in a shared directory
+        manager.add_command("format", &os).unwrap();
+        assert!(os.fs.exists(project_dir.join("format.md")));
+        assert!(!os.fs.exists(shared_dir.join("format.md")));
+        assert!(!os.fs.exists("/missing"));
+    }
+
     // Phase 2 functionality tests
     #[test]
     fn test_argument_substitution() {
@@ -460,8 +1115,11 @@ mod tests {
         let _manager = CommandManager {
             project_commands_dir: PathBuf::new(),
             user_commands_dir: PathBuf::new(),
+            extra_commands_dirs: Vec::new(),
             cache: HashMap::new(),
             bash_preprocessor: BashPreprocessor::default(),
+            editor_fallback: false,
+            fs: Fs::Real,
         };
 
         // Test dangerous patterns
@@ -554,8 +1212,11 @@ mod tests {
         let mut manager = CommandManager {
             project_commands_dir: commands_dir.clone(),
             user_commands_dir: temp_dir.path().join(".amazonq").join("user_commands"),
+            extra_commands_dirs: Vec::new(),
             cache: HashMap::new(),
             bash_preprocessor: BashPreprocessor::default(),
+            editor_fallback: false,
+            fs: Fs::Real,
         };
 
         // Test adding a command
@@ -575,6 +1236,9 @@ mod tests {
 
         // Check that the operation succeeded
         assert!(result.is_ok(), "add_command should succeed: {:?}", result);
+        let added = result.unwrap();
+        assert_eq!(added.name, command_name);
+        assert_eq!(added.path, commands_dir.join("test-command.md"));
 
         // Check that the file was created
         let expected_file_path = commands_dir.join(format!("{}.md", command_name));
@@ -603,7 +1267,7 @@ mod tests {
         assert!(manager.cache.contains_key(command_name), "Command should be cached");
 
         // Check that the success message is correct
-        let success_message = result.unwrap();
+        let success_message = added.format(false, true);
         assert!(success_message.contains("✅ Command 'test-command' created successfully!"));
         assert!(success_message.contains("Use '/project:test-command' to execute it"));
     }
@@ -624,8 +1288,11 @@ mod tests {
         let mut manager = CommandManager {
             project_commands_dir: commands_dir.clone(),
             user_commands_dir: temp_dir.path().join(".amazonq").join("user_commands"),
+            extra_commands_dirs: Vec::new(),
             cache: HashMap::new(),
             bash_preprocessor: BashPreprocessor::default(),
+            editor_fallback: false,
+            fs: Fs::Real,
         };
 
         let command_name = "duplicate-command";
@@ -663,8 +1330,11 @@ mod tests {
         let mut manager = CommandManager {
             project_commands_dir: commands_dir,
             user_commands_dir: temp_dir.path().join(".amazonq").join("user_commands"),
+            extra_commands_dirs: Vec::new(),
             cache: HashMap::new(),
             bash_preprocessor: BashPreprocessor::default(),
+            editor_fallback: false,
+            fs: Fs::Real,
         };
 
         // Test with invalid command names
@@ -679,4 +1349,106 @@ mod tests {
             );
         }
     }
+
+    #[tokio::test]
+    async fn test_list_commands_with_failures() {
+        let os = Os::new().await.unwrap();
+        let project_dir = PathBuf::from("/project");
+        let user_dir = PathBuf::from("/user");
+        os.fs.create_dir_all(&project_dir).await.unwrap();
+        os.fs.create_dir_all(&user_dir).await.unwrap();
+        os.fs
+            .write(project_dir.join("review.md"), "Review the staged changes")
+            .await
+            .unwrap();
+        os.fs
+            .write(
+                project_dir.join("deploy.md"),
+                "---\ndescription: [unclosed\n---\nDeploy it",
+            )
+            .await
+            .unwrap();
+        os.fs
+            .write(user_dir.join("standup.md"), "---\ndescription: Standup\n---\nSummarize")
+            .await
+            .unwrap();
+        os.fs
+            .write(user_dir.join("slow.md"), "---\ntimeout_seconds: 0\n---\nWait")
+            .await
+            .unwrap();
+
+        let mut manager = CommandManager::new_for_test(project_dir.clone(), user_dir.clone());
+        manager.fs = os.fs.clone();
+        let listing = manager.list_commands_with_failures(None).unwrap();
+
+        let names = listing.commands.iter().map(|c| c.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, ["review", "standup"]);
+        let failures = listing
+            .failures
+            .iter()
+            .map(|(path, _)| path.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(failures, [project_dir.join("deploy.md"), user_dir.join("slow.md")]);
+        assert!(matches!(listing.failures[0].1, CommandError::InvalidFormat(_)));
+
+        // The plain listing still returns just the loaded commands
+        assert_eq!(manager.list_commands_detailed(None).unwrap().len(), 2);
+        let project_only = manager
+            .list_commands_with_failures(Some(&CommandScope::Project))
+            .unwrap();
+        assert_eq!(project_only.commands.len(), 1);
+        assert_eq!(project_only.failures.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_import_from_dir() {
+        let os = Os::new().await.unwrap();
+        let src = PathBuf::from("/src");
+        let project_dir = PathBuf::from("/project");
+        os.fs.create_dir_all(&src).await.unwrap();
+        os.fs.create_dir_all(&project_dir).await.unwrap();
+        for (name, content) in [
+            ("review.md", "Review the staged changes"),
+            ("standup.md", "---\ndescription: Standup\n---\nSummarize"),
+            ("deploy.md", "---\ndescription: [unclosed\n---\nDeploy it"),
+            ("help.md", "Reserved name"),
+            ("notes.txt", "Not a command"),
+        ] {
+            os.fs.write(src.join(name), content).await.unwrap();
+        }
+        os.fs
+            .write(project_dir.join("standup.md"), "Existing standup")
+            .await
+            .unwrap();
+
+        let mut manager = CommandManager::new_for_test(project_dir.clone(), PathBuf::from("/user"));
+        manager.fs = os.fs.clone();
+        let report = manager.import_from_dir(&src, &CommandScope::Project, false).unwrap();
+
+        let imported = report.imported.iter().map(|c| c.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(imported, ["review"]);
+        assert_eq!(report.imported[0].path, project_dir.join("review.md"));
+        let failures = report.failures.iter().map(|(path, _)| path.clone()).collect::<Vec<_>>();
+        assert_eq!(failures, [
+            src.join("deploy.md"),
+            src.join("help.md"),
+            src.join("standup.md")
+        ]);
+        assert!(matches!(report.failures[0].1, CommandError::InvalidFormat(_)));
+        assert!(matches!(report.failures[1].1, CommandError::InvalidName(_)));
+        assert!(matches!(report.failures[2].1, CommandError::AlreadyExists(_)));
+        assert_eq!(
+            os.fs.read_to_string(project_dir.join("standup.md")).await.unwrap(),
+            "Existing standup"
+        );
+
+        // Forcing overwrites existing commands
+        let report = manager.import_from_dir(&src, &CommandScope::Project, true).unwrap();
+        let imported = report.imported.iter().map(|c| c.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(imported, ["review", "standup"]);
+        assert_eq!(report.failures.len(), 2);
+        assert_eq!(manager.get_command("standup").unwrap().content.trim(), "Summarize");
+
+        assert!(manager.import_from_dir(&src, &CommandScope::Shared, false).is_err());
+    }
 }
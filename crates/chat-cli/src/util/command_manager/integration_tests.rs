@@ -183,8 +183,11 @@ async fn test_file_system_integration() {
     let mut nested_manager = CommandManager {
         project_commands_dir: nested_dir.clone(),
         user_commands_dir: test_fs.user_commands_dir.clone(),
+        extra_commands_dirs: Vec::new(),
         cache: std::collections::HashMap::new(),
         bash_preprocessor: crate::util::bash_preprocessor::BashPreprocessor::default(),
+        editor_fallback: false,
+        fs: crate::os::Fs::Real,
     };
 
     unsafe {
@@ -217,8 +220,11 @@ async fn test_file_system_integration() {
     let mut readonly_manager = CommandManager {
         project_commands_dir: readonly_dir.clone(),
         user_commands_dir: test_fs.user_commands_dir.clone(),
+        extra_commands_dirs: Vec::new(),
         cache: std::collections::HashMap::new(),
         bash_preprocessor: crate::util::bash_preprocessor::BashPreprocessor::default(),
+        editor_fallback: false,
+        fs: crate::os::Fs::Real,
     };
 
     unsafe {
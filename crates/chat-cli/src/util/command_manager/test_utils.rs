@@ -10,6 +10,7 @@ pub struct TestFileSystem {
     pub temp_dir: TempDir,
     pub project_commands_dir: PathBuf,
     pub user_commands_dir: PathBuf,
+    pub shared_commands_dir: PathBuf,
 }
 
 impl TestFileSystem {
@@ -17,15 +18,18 @@ impl TestFileSystem {
         let temp_dir = TempDir::new()?;
         let project_commands_dir = temp_dir.path().join(".amazonq").join("commands");
         let user_commands_dir = temp_dir.path().join("home").join(".amazonq").join("commands");
+        let shared_commands_dir = temp_dir.path().join("shared").join("commands");
 
         // Create directories
         std::fs::create_dir_all(&project_commands_dir)?;
         std::fs::create_dir_all(&user_commands_dir)?;
+        std::fs::create_dir_all(&shared_commands_dir)?;
 
         Ok(Self {
             temp_dir,
             project_commands_dir,
             user_commands_dir,
+            shared_commands_dir,
         })
     }
 
@@ -38,6 +42,7 @@ impl TestFileSystem {
         let dir = match scope {
             CommandScope::Project => &self.project_commands_dir,
             CommandScope::Global => &self.user_commands_dir,
+            CommandScope::Shared => &self.shared_commands_dir,
         };
 
         let file_path = dir.join(format!("{}.md", name));
@@ -46,7 +51,10 @@ impl TestFileSystem {
     }
 
     pub fn create_manager(&self) -> CommandManager {
-        CommandManager::new_for_test(self.project_commands_dir.clone(), self.user_commands_dir.clone())
+        let mut manager =
+            CommandManager::new_for_test(self.project_commands_dir.clone(), self.user_commands_dir.clone());
+        manager.extra_commands_dirs = vec![self.shared_commands_dir.clone()];
+        manager
     }
 
     pub fn simulate_permission_error(&self, path: &PathBuf) -> Result<(), std::io::Error> {
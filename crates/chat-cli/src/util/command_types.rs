@@ -11,6 +11,7 @@ use serde::{
 };
 
 use crate::util::command_frontmatter::CommandFrontmatter;
+use crate::util::sanitize_filename;
 
 /// Scope of a command (project-specific or global)
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
@@ -19,6 +20,9 @@ pub enum CommandScope {
     Project,
     /// Command is available globally across all projects
     Global,
+    /// Command is loaded from a shared directory configured in `commands.extraDirs`, and is
+    /// read-only
+    Shared,
 }
 
 /// A custom command definition
@@ -45,9 +49,13 @@ impl CustomCommand {
     #[allow(dead_code)]
     pub fn from_file(file_path: PathBuf) -> Result<Self, CommandError> {
         let content = std::fs::read_to_string(&file_path)?;
+        Self::from_content(file_path, &content)
+    }
 
+    /// Create a command from the `content` of the file at `file_path`
+    pub fn from_content(file_path: PathBuf, content: &str) -> Result<Self, CommandError> {
         // Parse YAML frontmatter if present
-        let (frontmatter, markdown_content) = CommandFrontmatter::parse_from_content(&content)?;
+        let (frontmatter, markdown_content) = CommandFrontmatter::parse_from_content(content)?;
 
         // Validate frontmatter
         frontmatter.validate()?;
@@ -81,6 +89,11 @@ impl CustomCommand {
         }
     }
 
+    /// Whether the command comes from a shared directory and must not be modified
+    pub fn is_read_only(&self) -> bool {
+        self.scope == CommandScope::Shared
+    }
+
     /// Get allowed tools from frontmatter
     pub fn allowed_tools(&self) -> &[String] {
         &self.frontmatter.allowed_tools
@@ -96,6 +109,37 @@ impl CustomCommand {
         self.frontmatter.timeout_seconds
     }
 
+    /// One-line description for listings and pickers: the frontmatter `description`, or the first
+    /// non-heading line of the content when absent
+    pub fn description(&self) -> Option<&str> {
+        self.frontmatter
+            .description
+            .as_deref()
+            .map(str::trim)
+            .filter(|d| !d.is_empty())
+            .or_else(|| {
+                self.content
+                    .lines()
+                    .map(str::trim)
+                    .find(|line| !line.is_empty() && !line.starts_with('#'))
+            })
+    }
+
+    /// File name for a new command called `name`, relative to a commands directory. Each
+    /// `namespace/` segment is kept as a directory. Fails if a segment is not a valid file name on
+    /// every platform, see [sanitize_filename], rather than saving the command under another name
+    /// that could collide with an existing command.
+    pub fn new_file_name(name: &str) -> Result<String, CommandError> {
+        match name.split('/').find(|segment| sanitize_filename(segment) != *segment) {
+            Some(segment) => Err(CommandError::InvalidName(format!(
+                "'{}' is not a valid file name on every platform, try '{}'",
+                segment,
+                sanitize_filename(segment)
+            ))),
+            None => Ok(format!("{}.md", name)),
+        }
+    }
+
     /// Validate command name
     pub fn validate_name(name: &str) -> Result<(), CommandError> {
         if name.is_empty() {
@@ -136,6 +180,40 @@ impl CustomCommand {
     }
 }
 
+/// A command created by
+/// [CommandManager::add_command](crate::util::command_manager::CommandManager::add_command)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AddedCommand {
+    pub name: String,
+    /// The file the command was written to
+    pub path: PathBuf,
+    /// A message for the user about creating the command, such as a fallback editor being opened
+    pub notice: Option<String>,
+}
+
+impl AddedCommand {
+    /// The message shown once the command is created, with or without emoji. When `quiet`, this
+    /// is only the path of the command file, for use in scripts.
+    pub fn format(&self, quiet: bool, emoji: bool) -> String {
+        if quiet {
+            return self.path.display().to_string();
+        }
+        let (done, tip) = match emoji {
+            true => ("✅ ", "💡 "),
+            false => ("", ""),
+        };
+        let name = &self.name;
+        let notice = self
+            .notice
+            .as_ref()
+            .map(|notice| format!("{notice}\n"))
+            .unwrap_or_default();
+        format!(
+            "{notice}{done}Command '{name}' created successfully!\n   Use '/project:{name}' to execute it.\n\n{tip}Tip: Use '/commands show {name}' to see command details."
+        )
+    }
+}
+
 /// Errors that can occur during command operations
 #[allow(dead_code)]
 #[derive(Debug, thiserror::Error)]
@@ -161,6 +239,11 @@ pub enum CommandError {
     #[error("Editor error: {0}")]
     EditorError(String),
 
+    #[error(
+        "Editor '{0}' was not found. Set EDITOR to an installed editor (e.g. export EDITOR=vim), or enable: q settings commands.editorFallback true"
+    )]
+    EditorNotFound(String),
+
     #[error("Security violation: {0}")]
     SecurityViolation(String),
 
@@ -272,4 +355,66 @@ This command has frontmatter configuration."#;
         assert!(CustomCommand::validate_name("invalid@name").is_err());
         assert!(CustomCommand::validate_name(&"a".repeat(51)).is_err());
     }
+
+    #[test]
+    fn test_new_file_name() {
+        assert_eq!(CustomCommand::new_file_name("deploy").unwrap(), "deploy.md");
+        assert_eq!(CustomCommand::new_file_name("team/deploy").unwrap(), "team/deploy.md");
+
+        // Names that would have to be changed are refused rather than saved under another name
+        let err = CustomCommand::new_file_name("con").unwrap_err();
+        assert!(err.to_string().contains("'con_'"), "{err}");
+        assert!(CustomCommand::new_file_name("team/a:b").is_err());
+    }
+
+    #[test]
+    fn test_description() {
+        let dir = tempdir().unwrap();
+
+        let explicit = dir.path().join("explicit.md");
+        fs::write(
+            &explicit,
+            "---\ndescription: Review the staged diff\n---\n# Review\n\nLook over the changes.",
+        )
+        .unwrap();
+        let command = CustomCommand::from_file(explicit).unwrap();
+        assert_eq!(command.description(), Some("Review the staged diff"));
+
+        let fallback = dir.path().join("fallback.md");
+        fs::write(
+            &fallback,
+            "# Review\n\n## Steps\n\nLook over the changes.\nThen summarize.",
+        )
+        .unwrap();
+        let command = CustomCommand::from_file(fallback).unwrap();
+        assert_eq!(command.description(), Some("Look over the changes."));
+
+        let headings_only = dir.path().join("headings.md");
+        fs::write(&headings_only, "# Review\n").unwrap();
+        let command = CustomCommand::from_file(headings_only).unwrap();
+        assert_eq!(command.description(), None);
+    }
+
+    #[test]
+    fn test_added_command_format() {
+        let added = AddedCommand {
+            name: "deploy".to_string(),
+            path: PathBuf::from("/repo/.amazonq/commands/deploy.md"),
+            notice: None,
+        };
+
+        // Quiet output is only the path, for scripts
+        assert_eq!(added.format(true, true), "/repo/.amazonq/commands/deploy.md");
+
+        assert_eq!(
+            added.format(false, true),
+            "✅ Command 'deploy' created successfully!\n   Use '/project:deploy' to execute it.\n\n💡 Tip: Use '/commands show deploy' to see command details."
+        );
+        let plain = added.format(false, false);
+        assert_eq!(
+            plain,
+            "Command 'deploy' created successfully!\n   Use '/project:deploy' to execute it.\n\nTip: Use '/commands show deploy' to see command details."
+        );
+        assert!(plain.is_ascii());
+    }
 }
@@ -69,7 +69,10 @@ pub mod env_var {
         Q_BUNDLE_METADATA_PATH = "Q_BUNDLE_METADATA_PATH",
 
         /// Identifier for the client application or service using the chat-cli
-        Q_CLI_CLIENT_APPLICATION = "Q_CLI_CLIENT_APPLICATION"
+        Q_CLI_CLIENT_APPLICATION = "Q_CLI_CLIENT_APPLICATION",
+
+        /// Path the tool schema sent to the model is written to whenever it changes
+        Q_DUMP_TOOL_SCHEMA = "Q_DUMP_TOOL_SCHEMA"
     }
 }
 
@@ -11,6 +11,7 @@ use globset::{
 use thiserror::Error;
 
 use crate::cli::DEFAULT_AGENT_NAME;
+use crate::database::settings::Setting;
 use crate::os::Os;
 
 #[derive(Debug, Error)]
@@ -46,6 +47,9 @@ const WORKSPACE_AGENT_DIR_RELATIVE: &str = ".amazonq/cli-agents";
 const GLOBAL_AGENT_DIR_RELATIVE_TO_HOME: &str = ".aws/amazonq/cli-agents";
 const CLI_BASH_HISTORY_PATH: &str = ".aws/amazonq/.cli_bash_history";
 
+/// Entries that mark a directory as the root of a project
+const PROJECT_ROOT_MARKERS: &[&str] = &[".amazonq", ".git"];
+
 /// The directory of the users home
 ///
 /// - Linux: /home/Alice
@@ -181,10 +185,29 @@ pub fn chat_cli_bash_history_path(os: &Os) -> Result<PathBuf> {
     Ok(home_dir(os)?.join(CLI_BASH_HISTORY_PATH))
 }
 
+/// The root of the project q is running in, where the workspace `.amazonq` config lives.
+///
+/// This is the `project.root` setting if set. Otherwise it is the closest directory, starting
+/// from the working directory, that contains `.amazonq` or `.git`. The home directory is only
+/// used when it is the working directory, since `~/.amazonq` holds the global config. Falls back
+/// to the working directory when no marker is found.
+pub fn project_root(os: &Os) -> Result<PathBuf> {
+    let cwd = os.env.current_dir()?;
+    if let Some(root) = os.database.settings.get_string(Setting::ProjectRoot) {
+        return Ok(cwd.join(canonicalizes_path(os, &root)?));
+    }
+
+    let home = os.env.home();
+    let root = cwd.ancestors().enumerate().find(|(depth, dir)| {
+        (*depth == 0 || home.as_deref() != Some(*dir))
+            && PROJECT_ROOT_MARKERS.iter().any(|marker| os.fs.exists(dir.join(marker)))
+    });
+    Ok(root.map_or(cwd.clone(), |(_, dir)| dir.to_path_buf()))
+}
+
 /// Legacy workspace MCP server config path
 pub fn chat_legacy_workspace_mcp_config(os: &Os) -> Result<PathBuf> {
-    let cwd = os.env.current_dir()?;
-    Ok(cwd.join(".amazonq").join("mcp.json"))
+    Ok(project_root(os)?.join(".amazonq").join("mcp.json"))
 }
 
 /// The directory to the directory containing global agents
@@ -194,8 +217,7 @@ pub fn chat_global_agent_path(os: &Os) -> Result<PathBuf> {
 
 /// The directory to the directory containing config for the `/context` feature in `q chat`.
 pub fn chat_local_agent_dir(os: &Os) -> Result<PathBuf> {
-    let cwd = os.env.current_dir()?;
-    Ok(cwd.join(WORKSPACE_AGENT_DIR_RELATIVE))
+    Ok(project_root(os)?.join(WORKSPACE_AGENT_DIR_RELATIVE))
 }
 
 /// Canonicalizes path given by expanding the path given
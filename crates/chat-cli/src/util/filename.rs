@@ -0,0 +1,63 @@
+/// Names Windows reserves for devices, which cannot be used as a file stem in any directory
+const RESERVED_STEMS: &[&str] = &[
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8", "COM9", "LPT1", "LPT2",
+    "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Makes `name` safe to use as a file stem on every platform we support.
+///
+/// Characters that are illegal on some filesystem (`<>:"/\|?*` and control characters) become
+/// `_`, as do trailing dots and spaces, which Windows strips. Reserved device names such as `CON`
+/// get a trailing `_`. An empty name stays empty, so callers still need to reject it. Callers
+/// should only sanitize names of files they create, and tell the user when a name changes.
+pub fn sanitize_filename(name: &str) -> String {
+    let mut sanitized = name
+        .chars()
+        .map(|c| match c {
+            '<' | '>' | ':' | '"' | '/' | '\\' | '|' | '?' | '*' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect::<String>();
+
+    let trimmed_len = sanitized.trim_end_matches(['.', ' ']).len();
+    let trailing = sanitized.len() - trimmed_len;
+    sanitized.truncate(trimmed_len);
+    sanitized.extend(std::iter::repeat_n('_', trailing));
+
+    let stem = sanitized.split('.').next().unwrap_or_default();
+    if RESERVED_STEMS
+        .iter()
+        .any(|reserved| reserved.eq_ignore_ascii_case(stem))
+    {
+        sanitized.insert(stem.len(), '_');
+    }
+
+    sanitized
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sanitize_filename() {
+        // Names that are valid everywhere are unchanged
+        for name in ["review", "deploy-prod", "my_theme", "v1.2", "console", "über"] {
+            assert_eq!(sanitize_filename(name), name);
+        }
+
+        // Characters Windows does not allow
+        assert_eq!(sanitize_filename("deploy:prod"), "deploy_prod");
+        assert_eq!(sanitize_filename(r#"a<b>c"d/e\f|g?h*i"#), "a_b_c_d_e_f_g_h_i");
+        assert_eq!(sanitize_filename("tab\there"), "tab_here");
+        assert_eq!(sanitize_filename("trailing. "), "trailing__");
+
+        // Reserved device names, in any case and with an extension
+        assert_eq!(sanitize_filename("CON"), "CON_");
+        assert_eq!(sanitize_filename("nul.theme"), "nul_.theme");
+        assert_eq!(sanitize_filename("com1"), "com1_");
+
+        assert_eq!(sanitize_filename(""), "");
+    }
+}
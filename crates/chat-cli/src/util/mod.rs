@@ -5,9 +5,12 @@ pub mod command_manager; // NEW: Add command manager module
 pub mod command_types; // NEW: Add command types module
 pub mod consts;
 pub mod directories;
+pub mod filename;
 pub mod knowledge_store;
 pub mod open;
 pub mod pattern_matching;
+pub mod process_registry;
+pub mod retry;
 pub mod spinner;
 pub mod system_info;
 #[cfg(test)]
@@ -31,6 +34,7 @@ use eyre::{
     Result,
     bail,
 };
+pub use filename::sanitize_filename;
 use thiserror::Error;
 use tracing::warn;
 
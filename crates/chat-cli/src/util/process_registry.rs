@@ -0,0 +1,310 @@
+use std::collections::BTreeMap;
+use std::sync::{
+    LazyLock,
+    Mutex,
+};
+
+use eyre::{
+    Result,
+    bail,
+};
+use serde::Serialize;
+use sysinfo::{
+    Pid,
+    ProcessRefreshKind,
+    ProcessesToUpdate,
+    Signal,
+    System,
+    UpdateKind,
+};
+
+/// Set in the environment of execute jobs to a per-job id, so that background processes a job
+/// leaves running can be found after it exits
+pub const EXECUTE_JOB_ENV: &str = "Q_EXECUTE_JOB";
+
+/// What a tracked child process was spawned for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProcessKind {
+    /// A stdio MCP server
+    McpServer,
+    /// A command run by the execute tool
+    Execute,
+}
+
+impl std::fmt::Display for ProcessKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::McpServer => write!(f, "MCP server"),
+            Self::Execute => write!(f, "command"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProcessStatus {
+    Running,
+    Exited,
+}
+
+/// A child process spawned by the CLI
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TrackedProcess {
+    pub pid: u32,
+    pub kind: ProcessKind,
+    /// The MCP server name or the command that was run
+    pub name: String,
+    /// When the process started, in seconds since the epoch, to tell it apart from a later process
+    /// that reused its pid
+    #[serde(skip)]
+    start_time: Option<u64>,
+}
+
+/// Child processes spawned by the CLI, so that users can find and terminate runaway ones with
+/// the `processes` tool.
+#[derive(Debug, Default)]
+pub struct ProcessRegistry {
+    processes: Mutex<BTreeMap<u32, TrackedProcess>>,
+}
+
+impl ProcessRegistry {
+    /// The registry of the running CLI
+    pub fn global() -> &'static Self {
+        static REGISTRY: LazyLock<ProcessRegistry> = LazyLock::new(ProcessRegistry::default);
+        &REGISTRY
+    }
+
+    /// Starts tracking the process `pid`. Tracking an MCP server replaces the process of an earlier
+    /// start of the same server.
+    pub fn track(&self, pid: u32, kind: ProcessKind, name: impl Into<String>) {
+        let system = refreshed_system([pid]);
+        let process = TrackedProcess {
+            pid,
+            kind,
+            name: name.into(),
+            start_time: system.process(Pid::from_u32(pid)).map(|p| p.start_time()),
+        };
+        let mut processes = self.processes.lock().unwrap();
+        if kind == ProcessKind::McpServer {
+            processes.retain(|_, p| p.kind != ProcessKind::McpServer || p.name != process.name);
+        }
+        processes.insert(pid, process);
+    }
+
+    /// Tracks the execute job `pid` until the returned guard is dropped, and from then on the
+    /// background processes it left running. `job_id` must be set as [EXECUTE_JOB_ENV] in the
+    /// job's environment.
+    pub fn track_job(&'static self, pid: u32, job_id: impl Into<String>, command: impl Into<String>) -> JobGuard {
+        let command = command.into();
+        self.track(pid, ProcessKind::Execute, command.clone());
+        JobGuard {
+            registry: self,
+            pid,
+            job_id: job_id.into(),
+            command,
+        }
+    }
+
+    /// Tracks the processes started since `started` that have `job_id` in their environment, other
+    /// than the job `job_pid` itself.
+    fn track_left_running(&self, job_pid: u32, started: u64, job_id: &str, command: &str) {
+        // Only processes started during the job can belong to it, which saves reading the
+        // environment of every other process
+        let mut system = System::new();
+        system.refresh_processes_specifics(ProcessesToUpdate::All, true, ProcessRefreshKind::nothing());
+        let candidates = system
+            .processes()
+            .iter()
+            .filter(|(pid, process)| pid.as_u32() != job_pid && process.start_time() >= started)
+            .map(|(pid, _)| *pid)
+            .collect::<Vec<_>>();
+        system.refresh_processes_specifics(
+            ProcessesToUpdate::Some(&candidates),
+            true,
+            ProcessRefreshKind::nothing().with_environ(UpdateKind::Always),
+        );
+
+        let marker = format!("{EXECUTE_JOB_ENV}={job_id}");
+        for pid in candidates {
+            let left_running = system
+                .process(pid)
+                .is_some_and(|process| process.environ().iter().any(|var| var.as_os_str() == marker.as_str()));
+            if left_running {
+                self.track(pid.as_u32(), ProcessKind::Execute, command);
+            }
+        }
+    }
+
+    pub fn untrack(&self, pid: u32) {
+        self.processes.lock().unwrap().remove(&pid);
+    }
+
+    pub fn get(&self, pid: u32) -> Option<TrackedProcess> {
+        self.processes.lock().unwrap().get(&pid).cloned()
+    }
+
+    /// The tracked processes ordered by pid, with whether each is still running
+    pub fn list(&self) -> Vec<(TrackedProcess, ProcessStatus)> {
+        let processes = self.processes.lock().unwrap().values().cloned().collect::<Vec<_>>();
+        let system = refreshed_system(processes.iter().map(|p| p.pid));
+        processes
+            .into_iter()
+            .map(|process| {
+                let status = status(&system, &process);
+                (process, status)
+            })
+            .collect()
+    }
+
+    /// Terminates the tracked process `pid` and stops tracking it. Processes the CLI did not spawn
+    /// are never touched.
+    pub fn terminate(&self, pid: u32) -> Result<TrackedProcess> {
+        let Some(process) = self.get(pid) else {
+            bail!("No process with pid {pid} was started by q");
+        };
+
+        let system = refreshed_system([pid]);
+        if status(&system, &process) == ProcessStatus::Running {
+            // MCP servers are spawned in their own process group, so terminate the whole group to
+            // also stop anything the server spawned
+            #[cfg(unix)]
+            if process.kind == ProcessKind::McpServer {
+                use nix::sys::signal::{
+                    Signal as NixSignal,
+                    killpg,
+                };
+                use nix::unistd::Pid as NixPid;
+
+                if killpg(NixPid::from_raw(pid as i32), NixSignal::SIGTERM).is_ok() {
+                    self.untrack(pid);
+                    return Ok(process);
+                }
+            }
+
+            let Some(target) = system.process(Pid::from_u32(pid)) else {
+                bail!("Process {pid} exited before it could be terminated");
+            };
+            let terminated = target.kill_with(Signal::Term).unwrap_or_else(|| target.kill());
+            if !terminated {
+                bail!("Failed to terminate process {pid}");
+            }
+        }
+
+        self.untrack(pid);
+        Ok(process)
+    }
+}
+
+/// Stops tracking an execute job when dropped, tracking what it left running instead, see
+/// [ProcessRegistry::track_job]
+#[must_use]
+pub struct JobGuard {
+    registry: &'static ProcessRegistry,
+    pid: u32,
+    job_id: String,
+    command: String,
+}
+
+impl Drop for JobGuard {
+    fn drop(&mut self) {
+        let started = self.registry.get(self.pid).and_then(|p| p.start_time);
+        self.registry.untrack(self.pid);
+        let Some(started) = started else {
+            return;
+        };
+
+        // Scanning the processes is slow, so it is kept off of the async runtime's threads
+        let (registry, pid) = (self.registry, self.pid);
+        let (job_id, command) = (std::mem::take(&mut self.job_id), std::mem::take(&mut self.command));
+        let scan = move || registry.track_left_running(pid, started, &job_id, &command);
+        match tokio::runtime::Handle::try_current() {
+            Ok(handle) => drop(handle.spawn_blocking(scan)),
+            Err(_) => scan(),
+        }
+    }
+}
+
+fn refreshed_system(pids: impl IntoIterator<Item = u32>) -> System {
+    let pids = pids.into_iter().map(Pid::from_u32).collect::<Vec<_>>();
+    let mut system = System::new();
+    system.refresh_processes(ProcessesToUpdate::Some(&pids), true);
+    system
+}
+
+/// Whether `process` is still running. A process with its pid that started at a different time
+/// reused the pid after it exited.
+fn status(system: &System, process: &TrackedProcess) -> ProcessStatus {
+    match system.process(Pid::from_u32(process.pid)) {
+        Some(p)
+            if process
+                .start_time
+                .is_some_and(|start_time| start_time != p.start_time()) =>
+        {
+            ProcessStatus::Exited
+        },
+        Some(p)
+            if matches!(
+                p.status(),
+                sysinfo::ProcessStatus::Zombie | sysinfo::ProcessStatus::Dead
+            ) =>
+        {
+            ProcessStatus::Exited
+        },
+        Some(_) => ProcessStatus::Running,
+        None => ProcessStatus::Exited,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn test_reused_pid_is_not_terminated() {
+        let registry = ProcessRegistry::default();
+        let mut process = std::process::Command::new("sleep").arg("30").spawn().unwrap();
+        registry.track(process.id(), ProcessKind::McpServer, "slow-server");
+
+        // Another process with the same pid, as if the server exited and its pid was reused
+        registry
+            .processes
+            .lock()
+            .unwrap()
+            .get_mut(&process.id())
+            .unwrap()
+            .start_time = Some(0);
+        assert_eq!(registry.list()[0].1, ProcessStatus::Exited);
+        registry.terminate(process.id()).unwrap();
+        assert!(process.try_wait().unwrap().is_none());
+
+        process.kill().unwrap();
+        process.wait().unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_job_tracks_background_processes() {
+        let registry: &'static ProcessRegistry = Box::leak(Box::default());
+        let mut job = std::process::Command::new("sh")
+            .args(["-c", "sleep 30 > /dev/null 2>&1 & echo $!"])
+            .env(EXECUTE_JOB_ENV, "job-1")
+            .stdout(std::process::Stdio::piped())
+            .spawn()
+            .unwrap();
+        let guard = registry.track_job(job.id(), "job-1", "sleep 30 &");
+        let output = job.wait_with_output().unwrap();
+        let background = String::from_utf8(output.stdout).unwrap().trim().parse::<u32>().unwrap();
+        drop(guard);
+
+        let listed = registry.list();
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].0.pid, background);
+        assert_eq!(listed[0].0.name, "sleep 30 &");
+        assert_eq!(listed[0].1, ProcessStatus::Running);
+
+        registry.terminate(background).unwrap();
+        assert!(registry.list().is_empty());
+    }
+}
@@ -0,0 +1,97 @@
+use std::future::Future;
+use std::time::Duration;
+
+use tracing::debug;
+
+/// Runs `f` until it succeeds, fails with an error that `is_retryable` rejects, or `max_retries`
+/// retries have been made. The delay before the first retry is `base_delay`, doubling after each
+/// attempt.
+pub async fn retry_with_backoff<T, E, F, Fut>(
+    max_retries: u32,
+    base_delay: Duration,
+    is_retryable: impl Fn(&E) -> bool,
+    mut f: F,
+) -> Result<T, E>
+where
+    E: std::fmt::Debug,
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+{
+    let mut delay = base_delay;
+    let mut attempt = 0;
+    loop {
+        match f().await {
+            Err(err) if attempt < max_retries && is_retryable(&err) => {
+                attempt += 1;
+                debug!(attempt, ?err, "retrying after {delay:?}");
+                tokio::time::sleep(delay).await;
+                delay *= 2;
+            },
+            result => return result,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use super::*;
+
+    const DELAY: Duration = Duration::from_millis(100);
+
+    #[tokio::test(start_paused = true)]
+    async fn test_retry_with_backoff() {
+        // Transient errors are retried until the operation succeeds
+        let attempts = Cell::new(0);
+        let start = tokio::time::Instant::now();
+        let result = retry_with_backoff(
+            3,
+            DELAY,
+            |_: &&str| true,
+            || {
+                attempts.set(attempts.get() + 1);
+                async {
+                    if attempts.get() < 3 {
+                        Err("transient")
+                    } else {
+                        Ok(attempts.get())
+                    }
+                }
+            },
+        )
+        .await;
+        assert_eq!(result, Ok(3));
+        assert_eq!(start.elapsed(), DELAY + DELAY * 2);
+
+        // Retries are bounded
+        attempts.set(0);
+        let result = retry_with_backoff(
+            2,
+            DELAY,
+            |_: &&str| true,
+            || {
+                attempts.set(attempts.get() + 1);
+                async { Err::<(), _>("transient") }
+            },
+        )
+        .await;
+        assert_eq!(result, Err("transient"));
+        assert_eq!(attempts.get(), 3);
+
+        // Other errors are returned immediately
+        attempts.set(0);
+        let result = retry_with_backoff(
+            2,
+            DELAY,
+            |err: &&str| *err == "transient",
+            || {
+                attempts.set(attempts.get() + 1);
+                async { Err::<(), _>("fatal") }
+            },
+        )
+        .await;
+        assert_eq!(result, Err("fatal"));
+        assert_eq!(attempts.get(), 1);
+    }
+}
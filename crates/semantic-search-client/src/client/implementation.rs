@@ -22,6 +22,10 @@ use crate::error::{
     Result,
     SemanticSearchError,
 };
+use crate::processing::checkpoint::{
+    IndexCheckpoint,
+    embed_files_with_checkpoint,
+};
 use crate::processing::process_file_with_config;
 use crate::types::{
     ContextId,
@@ -371,11 +375,18 @@ impl SemanticSearchClient {
             )));
         }
 
-        // Process files
-        let items = Self::process_directory_files(dir_path, file_count, &progress_callback, &self.config)?;
+        // Embed files, resuming from the checkpoint of an earlier run that failed partway
+        let checkpoint_path = IndexCheckpoint::path_for(&self.base_dir, dir_path);
+        let mut checkpoint = IndexCheckpoint::load(&checkpoint_path)?;
+        let data_points = self.embed_directory_files(dir_path, file_count, &mut checkpoint, &progress_callback)?;
 
-        // Create and populate semantic context
-        let semantic_context = self.create_semantic_context(&context_dir, &items, &progress_callback)?;
+        // Notify progress: Building index
+        if let Some(ref callback) = progress_callback {
+            callback(ProgressStatus::BuildingIndex);
+        }
+
+        let mut semantic_context = SemanticContext::new(context_dir.join("data.json"))?;
+        semantic_context.add_data_points(data_points)?;
 
         // Save and store context
         self.save_and_store_context(
@@ -386,10 +397,46 @@ impl SemanticSearchClient {
             Some(dir_path.to_string_lossy().to_string()),
             semantic_context,
         )?;
+        checkpoint.remove()?;
 
         Ok(id)
     }
 
+    /// Resume indexing a directory that failed partway, only embedding the files that were not
+    /// embedded by the earlier run or have changed since
+    ///
+    /// # Arguments
+    ///
+    /// * `dir_path` - Path to the directory
+    /// * `name` - Name for the context
+    /// * `description` - Description of the context
+    /// * `persistent` - Whether to make this context persistent
+    /// * `progress_callback` - Optional callback for progress updates
+    ///
+    /// # Returns
+    ///
+    /// The ID of the created context
+    pub fn resume_index<F>(
+        &mut self,
+        dir_path: impl AsRef<Path>,
+        name: &str,
+        description: &str,
+        persistent: bool,
+        progress_callback: Option<F>,
+    ) -> Result<ContextId>
+    where
+        F: Fn(ProgressStatus) + Send + 'static,
+    {
+        let dir_path = dir_path.as_ref();
+        if !IndexCheckpoint::exists(&IndexCheckpoint::path_for(&self.base_dir, dir_path)) {
+            return Err(SemanticSearchError::InvalidArgument(format!(
+                "There is no interrupted indexing of {} to resume",
+                dir_path.display()
+            )));
+        }
+        self.add_context_from_directory(dir_path, name, description, persistent, progress_callback)
+    }
+
     /// Create a context directory
     fn create_context_directory(&self, id: &str, persistent: bool) -> Result<PathBuf> {
         utils::create_context_directory(&self.base_dir, id, persistent)
@@ -403,13 +450,14 @@ impl SemanticSearchClient {
         utils::count_files_in_directory(dir_path, progress_callback)
     }
 
-    /// Process files in a directory
-    fn process_directory_files<F>(
+    /// Embed the files in a directory, recording each in `checkpoint` as it is done
+    fn embed_directory_files<F>(
+        &self,
         dir_path: &Path,
         file_count: usize,
+        checkpoint: &mut IndexCheckpoint,
         progress_callback: &Option<F>,
-        config: &crate::config::SemanticSearchConfig,
-    ) -> Result<Vec<Value>>
+    ) -> Result<Vec<DataPoint>>
     where
         F: Fn(ProgressStatus) + Send + 'static,
     {
@@ -418,42 +466,39 @@ impl SemanticSearchClient {
             callback(ProgressStatus::StartingIndexing(file_count));
         }
 
-        // Process all files in the directory with progress updates
-        let mut processed_files = 0;
-        let mut items = Vec::new();
-
-        for entry in walkdir::WalkDir::new(dir_path)
+        let files = walkdir::WalkDir::new(dir_path)
             .follow_links(true)
+            .sort_by_file_name()
             .into_iter()
             .filter_map(|e| e.ok())
             .filter(|e| e.file_type().is_file())
-        {
-            let path = entry.path();
-
             // Skip hidden files
-            if path
-                .file_name()
-                .and_then(|n| n.to_str())
-                .is_some_and(|s| s.starts_with('.'))
-            {
-                continue;
-            }
-
-            // Process the file
-            match process_file_with_config(path, Some(config.chunk_size), Some(config.chunk_overlap)) {
-                Ok(mut file_items) => items.append(&mut file_items),
-                Err(_) => continue, // Skip files that fail to process
-            }
-
-            processed_files += 1;
-
+            .filter(|e| {
+                !e.path()
+                    .file_name()
+                    .and_then(|n| n.to_str())
+                    .is_some_and(|s| s.starts_with('.'))
+            })
+            .map(|e| e.into_path())
+            .collect::<Vec<_>>();
+
+        embed_files_with_checkpoint(&files, checkpoint, |i, path| {
             // Update progress
             if let Some(callback) = progress_callback {
-                callback(ProgressStatus::Indexing(processed_files, file_count));
+                callback(ProgressStatus::Indexing(i + 1, file_count));
             }
-        }
 
-        Ok(items)
+            // Skip files that fail to process
+            let Ok(items) =
+                process_file_with_config(path, Some(self.config.chunk_size), Some(self.config.chunk_overlap))
+            else {
+                return Ok(Vec::new());
+            };
+            items
+                .iter()
+                .map(|item| self.create_data_point_from_item(item, 0))
+                .collect()
+        })
     }
 
     /// Create a semantic context from items
@@ -0,0 +1,225 @@
+use std::collections::HashMap;
+use std::fs::{
+    self,
+    File,
+    OpenOptions,
+};
+use std::io::{
+    BufRead,
+    BufReader,
+    Write,
+};
+use std::path::{
+    Path,
+    PathBuf,
+};
+
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use sha2::{
+    Digest,
+    Sha256,
+};
+
+use crate::error::Result;
+use crate::types::DataPoint;
+
+/// A file that has been embedded, as recorded in the checkpoint
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CheckpointEntry {
+    path: PathBuf,
+    hash: String,
+    data_points: Vec<DataPoint>,
+}
+
+/// Records which files of a directory have been embedded, so that indexing which fails partway
+/// can resume instead of starting over.
+///
+/// Each embedded file is appended to the checkpoint file as one JSON line, along with a hash of its
+/// content. A file is only skipped on resume if its content still matches that hash.
+#[derive(Debug)]
+pub struct IndexCheckpoint {
+    path: PathBuf,
+    files: HashMap<PathBuf, CheckpointEntry>,
+}
+
+impl IndexCheckpoint {
+    /// Path of the checkpoint for indexing `source_dir`, kept under `base_dir`
+    pub fn path_for(base_dir: &Path, source_dir: &Path) -> PathBuf {
+        let hash = format!("{:x}", Sha256::digest(source_dir.to_string_lossy().as_bytes()));
+        base_dir.join("checkpoints").join(format!("{}.jsonl", &hash[..16]))
+    }
+
+    /// Load the checkpoint at `path`, or start an empty one if there is none. A line left
+    /// incomplete by a crash while it was written is ignored.
+    pub fn load(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let mut files = HashMap::new();
+        if path.exists() {
+            for line in BufReader::new(File::open(&path)?).lines() {
+                match serde_json::from_str::<CheckpointEntry>(&line?) {
+                    Ok(entry) => {
+                        files.insert(entry.path.clone(), entry);
+                    },
+                    Err(e) => tracing::warn!("Ignoring invalid line in checkpoint {}: {}", path.display(), e),
+                }
+            }
+        }
+        Ok(Self { path, files })
+    }
+
+    /// Whether a checkpoint was left behind at `path`
+    pub fn exists(path: &Path) -> bool {
+        path.exists()
+    }
+
+    /// Number of files recorded in the checkpoint
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    /// Whether no files are recorded in the checkpoint
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    /// The data points embedded for `file`, if it was recorded with the content hash `hash`
+    pub fn get(&self, file: &Path, hash: &str) -> Option<&[DataPoint]> {
+        self.files
+            .get(file)
+            .filter(|entry| entry.hash == hash)
+            .map(|entry| entry.data_points.as_slice())
+    }
+
+    /// Record that `file` has been embedded, writing it to disk before returning
+    pub fn record(&mut self, file: &Path, hash: String, data_points: Vec<DataPoint>) -> Result<()> {
+        let entry = CheckpointEntry {
+            path: file.to_path_buf(),
+            hash,
+            data_points,
+        };
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut line = serde_json::to_string(&entry)?;
+        line.push('\n');
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?
+            .write_all(line.as_bytes())?;
+        self.files.insert(entry.path.clone(), entry);
+        Ok(())
+    }
+
+    /// Delete the checkpoint once indexing has completed
+    pub fn remove(self) -> Result<()> {
+        if self.path.exists() {
+            fs::remove_file(&self.path)?;
+        }
+        Ok(())
+    }
+}
+
+/// Hash of a file's content, used to tell whether a file recorded in a checkpoint has changed
+pub fn hash_file(path: &Path) -> Result<String> {
+    Ok(format!("{:x}", Sha256::digest(fs::read(path)?)))
+}
+
+/// Embed `files` in order, skipping those already recorded in `checkpoint` with unchanged content.
+/// Each file embedded by `embed_file` is recorded before moving on to the next, so an error leaves
+/// the checkpoint ready to resume from.
+///
+/// Returns the data points of all the files, renumbered in order.
+pub fn embed_files_with_checkpoint<F>(
+    files: &[PathBuf],
+    checkpoint: &mut IndexCheckpoint,
+    mut embed_file: F,
+) -> Result<Vec<DataPoint>>
+where
+    F: FnMut(usize, &Path) -> Result<Vec<DataPoint>>,
+{
+    let mut data_points = Vec::new();
+    for (i, file) in files.iter().enumerate() {
+        let hash = hash_file(file)?;
+        let file_points = match checkpoint.get(file, &hash) {
+            Some(points) => points.to_vec(),
+            None => {
+                let points = embed_file(i, file)?;
+                checkpoint.record(file, hash, points.clone())?;
+                points
+            },
+        };
+        data_points.extend(file_points);
+    }
+
+    for (id, point) in data_points.iter_mut().enumerate() {
+        point.id = id;
+    }
+    Ok(data_points)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::SemanticSearchError;
+
+    fn point(text: &str) -> DataPoint {
+        DataPoint {
+            id: 0,
+            payload: HashMap::from([("text".to_string(), serde_json::json!(text))]),
+            vector: vec![text.len() as f32],
+        }
+    }
+
+    #[test]
+    fn test_resume_after_failure() {
+        let dir = tempfile::tempdir().unwrap();
+        let files = (0..5)
+            .map(|i| {
+                let path = dir.path().join(format!("file{i}.txt"));
+                fs::write(&path, format!("content {i}")).unwrap();
+                path
+            })
+            .collect::<Vec<_>>();
+        let checkpoint_path = IndexCheckpoint::path_for(&dir.path().join("base"), dir.path());
+
+        // The first run fails after embedding two files
+        let mut processed = Vec::new();
+        let mut checkpoint = IndexCheckpoint::load(&checkpoint_path).unwrap();
+        let result = embed_files_with_checkpoint(&files, &mut checkpoint, |i, file| {
+            if i == 2 {
+                return Err(SemanticSearchError::EmbeddingError("out of memory".to_string()));
+            }
+            processed.push(file.to_path_buf());
+            Ok(vec![point(&fs::read_to_string(file).unwrap())])
+        });
+        assert!(result.is_err());
+        assert_eq!(processed, files[..2]);
+        assert!(IndexCheckpoint::exists(&checkpoint_path));
+
+        // Re-running only embeds the remaining files, and changed files are embedded again
+        fs::write(&files[0], "changed").unwrap();
+        let mut processed = Vec::new();
+        let mut checkpoint = IndexCheckpoint::load(&checkpoint_path).unwrap();
+        assert_eq!(checkpoint.len(), 2);
+        let data_points = embed_files_with_checkpoint(&files, &mut checkpoint, |_, file| {
+            processed.push(file.to_path_buf());
+            Ok(vec![point(&fs::read_to_string(file).unwrap())])
+        })
+        .unwrap();
+        assert_eq!(processed, [&files[..1], &files[2..]].concat());
+
+        let texts = data_points
+            .iter()
+            .map(|p| p.payload["text"].as_str().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(texts, ["changed", "content 1", "content 2", "content 3", "content 4"]);
+        assert!(data_points.iter().enumerate().all(|(i, p)| p.id == i));
+
+        checkpoint.remove().unwrap();
+        assert!(!IndexCheckpoint::exists(&checkpoint_path));
+    }
+}
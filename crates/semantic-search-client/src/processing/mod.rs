@@ -1,3 +1,5 @@
+/// Checkpoints that let indexing a directory resume after a failure
+pub mod checkpoint;
 /// File processing utilities for handling different file types and extracting content
 pub mod file_processor;
 /// Text chunking utilities for breaking down text into manageable pieces for embedding
@@ -1,6 +1,7 @@
 pub mod git;
 pub mod manager;
 pub mod renderer;
+pub mod template;
 
 pub use git::{
     GitInfo,
@@ -8,3 +9,7 @@ pub use git::{
 };
 pub use manager::ThemeManager;
 pub use renderer::ThemeRenderer;
+pub use template::{
+    PromptTemplate,
+    TemplateNode,
+};
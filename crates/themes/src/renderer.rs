@@ -5,6 +5,10 @@ use std::path::{
 };
 
 use crate::git::GitInfo;
+use crate::template::{
+    PromptTemplate,
+    TemplateNode,
+};
 
 pub struct ThemeRenderer {
     git_info: GitInfo,
@@ -95,209 +99,116 @@ impl ThemeRenderer {
     }
 
     pub fn render_prompt(&self, template: &str) -> String {
-        let mut result = template.to_string();
-
-        // Process conditional formatting first
-        result = self.process_conditional_formatting(&result);
-
-        // Replace git variables
-        result = self.replace_git_variables(&result);
-
-        // Replace agent and usage variables
-        result = self.replace_agent_variables(&result);
-
-        // Replace color variables
-        result = self.replace_color_variables(&result);
-
-        result
+        self.render_template(&PromptTemplate::parse(template))
     }
 
-    fn process_conditional_formatting(&self, template: &str) -> String {
+    /// Render a parsed template. Of the unbraced `$NAME` references only `$TOKEN_USAGE` is
+    /// substituted, and unknown variables are output as written.
+    pub fn render_template(&self, template: &PromptTemplate) -> String {
         let mut result = String::new();
-        let mut chars = template.chars().peekable();
-
-        while let Some(ch) = chars.next() {
-            if ch == '$' && chars.peek() == Some(&'{') {
-                chars.next(); // consume '{'
-
-                // Find the variable name (up to ':' or '}')
-                let mut var_name = String::new();
-                let mut found_colon = false;
-
-                while let Some(&next_ch) = chars.peek() {
-                    if next_ch == ':' {
-                        chars.next(); // consume ':'
-                        found_colon = true;
-                        break;
-                    } else if next_ch == '}' {
-                        break;
-                    } else {
-                        var_name.push(chars.next().unwrap());
-                    }
-                }
-
-                if found_colon && chars.peek() == Some(&'+') {
-                    chars.next(); // consume '+'
-
-                    // Find the conditional text with proper brace matching
-                    let mut conditional_text = String::new();
-                    let mut brace_count = 1; // We're inside the outer braces
-
-                    for next_ch in chars.by_ref() {
-                        if next_ch == '{' {
-                            brace_count += 1;
-                            conditional_text.push(next_ch);
-                        } else if next_ch == '}' {
-                            brace_count -= 1;
-                            if brace_count == 0 {
-                                // Found the matching closing brace
-                                break;
-                            } else {
-                                conditional_text.push(next_ch);
-                            }
-                        } else {
-                            conditional_text.push(next_ch);
-                        }
-                    }
-
-                    // Check if the variable has a value
-                    let has_value = match var_name.as_str() {
-                        "GIT_BRANCH" => self.git_info.branch.is_some(),
-                        "GIT_CLEAN" => self.git_info.status.as_ref().is_some_and(|s| s.clean),
-                        "GIT_STAGED" => self.git_info.status.as_ref().is_some_and(|s| s.staged),
-                        "GIT_MODIFIED" => self.git_info.status.as_ref().is_some_and(|s| s.modified),
-                        "GIT_UNTRACKED" => self.git_info.status.as_ref().is_some_and(|s| s.untracked),
-                        "GIT_AHEAD" => self.git_info.status.as_ref().is_some_and(|s| s.ahead > 0),
-                        "GIT_BEHIND" => self.git_info.status.as_ref().is_some_and(|s| s.behind > 0),
-                        _ => false,
-                    };
-
-                    if has_value {
-                        // Process the conditional text recursively for nested variables
-                        let processed_text = self.replace_color_variables(&conditional_text);
-                        let processed_text = self.replace_git_variables(&processed_text);
-                        result.push_str(&processed_text);
-                    }
-                    // If empty or doesn't exist, add nothing
-                } else {
-                    // Not a conditional, put back what we consumed
-                    result.push('$');
-                    result.push('{');
-                    result.push_str(&var_name);
-                    if found_colon {
-                        result.push(':');
-                    }
-                    // Continue processing normally
-                }
-            } else {
-                result.push(ch);
-            }
-        }
-
+        self.render_nodes(&template.nodes, &mut result);
         result
     }
 
-    fn replace_git_variables(&self, template: &str) -> String {
-        let mut result = template.to_string();
-
-        // Replace PWD (current directory with ~ substitution)
-        let pwd = if let Some(home) = std::env::var_os("HOME") {
-            let home_path = std::path::Path::new(&home);
-            if let Ok(relative) = self.current_dir.strip_prefix(home_path) {
-                format!("~/{}", relative.display())
-            } else {
-                self.current_dir.to_string_lossy().to_string()
-            }
-        } else {
-            self.current_dir.to_string_lossy().to_string()
-        };
-        result = result.replace("${PWD}", &pwd);
-
-        // Replace git branch
-        if let Some(branch) = &self.git_info.branch {
-            result = result.replace("${GIT_BRANCH}", branch);
-        } else {
-            result = result.replace("${GIT_BRANCH}", "");
-        }
-
-        // Replace git status indicators
-        if let Some(status) = &self.git_info.status {
-            result = result.replace("${GIT_CLEAN}", if status.clean { "✓" } else { "" });
-            result = result.replace("${GIT_STAGED}", if status.staged { "●" } else { "" });
-            result = result.replace("${GIT_MODIFIED}", if status.modified { "✚" } else { "" });
-            result = result.replace("${GIT_UNTRACKED}", if status.untracked { "?" } else { "" });
-            result = result.replace(
-                "${GIT_AHEAD}",
-                &if status.ahead > 0 {
-                    format!("↑{}", status.ahead)
-                } else {
-                    String::new()
+    fn render_nodes(&self, nodes: &[TemplateNode], result: &mut String) {
+        for node in nodes {
+            match node {
+                TemplateNode::Literal(text) => result.push_str(text),
+                TemplateNode::Variable { name, braced: true } => match self.variable(name) {
+                    Some(value) => result.push_str(&value),
+                    None => result.push_str(&format!("${{{name}}}")),
                 },
-            );
-            result = result.replace(
-                "${GIT_BEHIND}",
-                &if status.behind > 0 {
-                    format!("↓{}", status.behind)
-                } else {
-                    String::new()
+                TemplateNode::Variable { name, braced: false } => match name.strip_prefix("TOKEN_USAGE") {
+                    Some(rest) => {
+                        result.push_str(&token_usage());
+                        result.push_str(rest);
+                    },
+                    None => result.push_str(&format!("${name}")),
                 },
-            );
-        } else {
-            // No git status available
-            result = result.replace("${GIT_CLEAN}", "");
-            result = result.replace("${GIT_STAGED}", "");
-            result = result.replace("${GIT_MODIFIED}", "");
-            result = result.replace("${GIT_UNTRACKED}", "");
-            result = result.replace("${GIT_AHEAD}", "");
-            result = result.replace("${GIT_BEHIND}", "");
+                TemplateNode::Conditional { name, body, .. } => {
+                    if self.has_value(name) {
+                        self.render_nodes(body, result);
+                    }
+                },
+            }
         }
-
-        result
     }
 
-    fn replace_color_variables(&self, template: &str) -> String {
-        template
-            .replace("${RED}", RED)
-            .replace("${GREEN}", GREEN)
-            .replace("${YELLOW}", YELLOW)
-            .replace("${BLUE}", BLUE)
-            .replace("${MAGENTA}", MAGENTA)
-            .replace("${CYAN}", CYAN)
-            .replace("${RESET}", RESET)
-            .replace("${BOLD}", BOLD)
-    }
-
-    fn replace_agent_variables(&self, template: &str) -> String {
-        let mut result = template.to_string();
-
-        result = self.replace_agent_variable(&result);
-        result = self.replace_model_variable(&result);
-        result = self.replace_token_usage_variable(&result);
-
-        result
+    /// Whether the variable tested by a `${NAME:+text}` conditional has a value
+    fn has_value(&self, name: &str) -> bool {
+        let status = self.git_info.status.as_ref();
+        match name {
+            "GIT_BRANCH" => self.git_info.branch.is_some(),
+            "GIT_CLEAN" => status.is_some_and(|s| s.clean),
+            "GIT_STAGED" => status.is_some_and(|s| s.staged),
+            "GIT_MODIFIED" => status.is_some_and(|s| s.modified),
+            "GIT_UNTRACKED" => status.is_some_and(|s| s.untracked),
+            "GIT_AHEAD" => status.is_some_and(|s| s.ahead > 0),
+            "GIT_BEHIND" => status.is_some_and(|s| s.behind > 0),
+            _ => false,
+        }
     }
 
-    fn replace_agent_variable(&self, template: &str) -> String {
-        let agent = std::env::var("Q_AGENT").unwrap_or_else(|_| "default".to_string());
-        template.replace("${AGENT}", &agent)
+    /// The value of a `${NAME}` reference, or [None] if the variable is unknown
+    fn variable(&self, name: &str) -> Option<String> {
+        let status = self.git_info.status.as_ref();
+        let flag = |set: bool, symbol: &str| if set { symbol.to_string() } else { String::new() };
+        let value = match name {
+            "PWD" => self.pwd(),
+            "GIT_BRANCH" => self.git_info.branch.clone().unwrap_or_default(),
+            "GIT_CLEAN" => flag(status.is_some_and(|s| s.clean), "✓"),
+            "GIT_STAGED" => flag(status.is_some_and(|s| s.staged), "●"),
+            "GIT_MODIFIED" => flag(status.is_some_and(|s| s.modified), "✚"),
+            "GIT_UNTRACKED" => flag(status.is_some_and(|s| s.untracked), "?"),
+            "GIT_AHEAD" => match status {
+                Some(s) if s.ahead > 0 => format!("↑{}", s.ahead),
+                _ => String::new(),
+            },
+            "GIT_BEHIND" => match status {
+                Some(s) if s.behind > 0 => format!("↓{}", s.behind),
+                _ => String::new(),
+            },
+            "AGENT" => std::env::var("Q_AGENT").unwrap_or_else(|_| "default".to_string()),
+            "MODEL" => std::env::var("Q_MODEL").unwrap_or_else(|_| "unknown".to_string()),
+            "TOKEN_USAGE" => token_usage(),
+            "RED" => RED.to_string(),
+            "GREEN" => GREEN.to_string(),
+            "YELLOW" => YELLOW.to_string(),
+            "BLUE" => BLUE.to_string(),
+            "MAGENTA" => MAGENTA.to_string(),
+            "CYAN" => CYAN.to_string(),
+            "RESET" => RESET.to_string(),
+            "BOLD" => BOLD.to_string(),
+            _ => return None,
+        };
+        Some(value)
     }
 
-    fn replace_model_variable(&self, template: &str) -> String {
-        let model = std::env::var("Q_MODEL").unwrap_or_else(|_| "unknown".to_string());
-        template.replace("${MODEL}", &model)
+    /// The current directory, relative to the home directory when inside it
+    fn pwd(&self) -> String {
+        if let Some(home) = std::env::var_os("HOME") {
+            let home_path = std::path::Path::new(&home);
+            if let Ok(relative) = self.current_dir.strip_prefix(home_path) {
+                return format!("~/{}", relative.display());
+            }
+        }
+        self.current_dir.to_string_lossy().to_string()
     }
+}
 
-    fn replace_token_usage_variable(&self, template: &str) -> String {
-        let token_usage = std::env::var("Q_TOKEN_USAGE").unwrap_or_else(|_| "(25.50%)".to_string());
-        template
-            .replace("${TOKEN_USAGE}", &token_usage)
-            .replace("$TOKEN_USAGE", &token_usage)
-    }
+fn token_usage() -> String {
+    std::env::var("Q_TOKEN_USAGE").unwrap_or_else(|_| "(25.50%)".to_string())
 }
 
 #[cfg(test)]
 mod tests {
+    use std::sync::Mutex;
+
     use super::*;
+    use crate::git::GitStatus;
+
+    /// Serializes the tests that set the environment variables read while rendering
+    static ENV_LOCK: Mutex<()> = Mutex::new(());
 
     #[test]
     fn test_render_basic_template() {
@@ -315,6 +226,7 @@ mod tests {
 
     #[test]
     fn test_model_variable_substitution() {
+        let _lock = ENV_LOCK.lock().unwrap();
         let renderer = ThemeRenderer::new();
 
         // Test with environment variable set
@@ -330,6 +242,7 @@ mod tests {
 
     #[test]
     fn test_token_usage_variable_substitution() {
+        let _lock = ENV_LOCK.lock().unwrap();
         let renderer = ThemeRenderer::new();
 
         // Test with environment variable set
@@ -359,4 +272,68 @@ mod tests {
 
         let _ = std::fs::remove_dir_all(&temp_dir);
     }
+
+    fn renderer(branch: Option<&str>, status: Option<GitStatus>) -> ThemeRenderer {
+        ThemeRenderer {
+            git_info: GitInfo {
+                is_repo: true,
+                branch: branch.map(str::to_string),
+                status,
+            },
+            current_dir: PathBuf::from("/tmp/project"),
+        }
+    }
+
+    #[test]
+    fn test_render_templates() {
+        let _lock = ENV_LOCK.lock().unwrap();
+        let no_git = renderer(None, None);
+        let git = renderer(
+            Some("main"),
+            Some(GitStatus {
+                clean: false,
+                staged: true,
+                modified: true,
+                untracked: false,
+                ahead: 2,
+                behind: 0,
+            }),
+        );
+
+        // (template, rendered outside of a repository, rendered on a dirty `main` branch)
+        let cases = [
+            ("> ", "> ", "> "),
+            (
+                "\x1b[44m\x1b[37m ${AGENT} \x1b[0m\x1b[45m\x1b[34m\u{e0b0}\x1b[37m $TOKEN_USAGE \x1b[0m${GIT_BRANCH:+\x1b[43m\x1b[35m\u{e0b0}\x1b[30m ${GIT_BRANCH} \x1b[0m\x1b[33m\u{e0b0}}\x1b[0m ",
+                "\x1b[44m\x1b[37m default \x1b[0m\x1b[45m\x1b[34m\u{e0b0}\x1b[37m (25.50%) \x1b[0m\x1b[0m ",
+                "\x1b[44m\x1b[37m default \x1b[0m\x1b[45m\x1b[34m\u{e0b0}\x1b[37m (25.50%) \x1b[0m\x1b[43m\x1b[35m\u{e0b0}\x1b[30m main \x1b[0m\x1b[33m\u{e0b0}\x1b[0m ",
+            ),
+            (
+                "${BOLD}${MAGENTA}➜ ${MODEL}:$TOKEN_USAGE${RESET} ${GREEN}${BOLD}${PWD}${GIT_BRANCH:+:(${YELLOW}${GIT_BRANCH}${RESET}${GREEN}${BOLD})${RESET} }> ",
+                "\x1b[1m\x1b[35m➜ unknown:(25.50%)\x1b[0m \x1b[32m\x1b[1m/tmp/project> ",
+                "\x1b[1m\x1b[35m➜ unknown:(25.50%)\x1b[0m \x1b[32m\x1b[1m/tmp/project:(\x1b[33mmain\x1b[0m\x1b[32m\x1b[1m)\x1b[0m > ",
+            ),
+            (
+                "${GIT_CLEAN}${GIT_STAGED}${GIT_MODIFIED}${GIT_UNTRACKED}${GIT_AHEAD}${GIT_BEHIND}",
+                "",
+                "●✚↑2",
+            ),
+            (
+                "${GIT_CLEAN:+c}${GIT_STAGED:+s}${GIT_MODIFIED:+m}${GIT_UNTRACKED:+u}${GIT_AHEAD:+a${GIT_AHEAD}}${GIT_BEHIND:+b}",
+                "",
+                "sma↑2",
+            ),
+            (
+                "${UNKNOWN} $UNKNOWN $$ $1 ${} ${A:-x} ${A B} ${${RED}} ${GIT_BRANCH:+{x}} ${GIT_BRANCH:+${GIT_CLEAN:+x}} $TOKEN_USAGEx",
+                "${UNKNOWN} $UNKNOWN $$ $1 ${} ${A:-x} ${A B} ${\x1b[31m}   (25.50%)x",
+                "${UNKNOWN} $UNKNOWN $$ $1 ${} ${A:-x} ${A B} ${\x1b[31m} {x} ${GIT_CLEAN:+x} (25.50%)x",
+            ),
+            ("${GIT_BRANCH:+unclosed ${CYAN}", "", "unclosed \x1b[36m"),
+        ];
+
+        for (template, expected_no_git, expected_git) in cases {
+            assert_eq!(no_git.render_prompt(template), expected_no_git, "{template:?}");
+            assert_eq!(git.render_prompt(template), expected_git, "{template:?}");
+        }
+    }
 }
@@ -0,0 +1,223 @@
+use std::fmt;
+
+/// A piece of a [PromptTemplate]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TemplateNode {
+    /// Text that is output as is
+    Literal(String),
+    /// A variable reference, written `${NAME}` or, when not `braced`, `$NAME`
+    Variable { name: String, braced: bool },
+    /// `${NAME:+body}`, which outputs `body` only when `NAME` has a value. `closed` is false when
+    /// the template ends before the closing brace.
+    Conditional {
+        name: String,
+        body: Vec<TemplateNode>,
+        closed: bool,
+    },
+}
+
+/// A prompt template parsed into literals, variables and conditionals.
+///
+/// Parsing never fails: text that is not a well formed reference is kept as a literal, so
+/// displaying a template gives back exactly the text it was parsed from.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PromptTemplate {
+    pub nodes: Vec<TemplateNode>,
+}
+
+impl PromptTemplate {
+    pub fn parse(template: &str) -> Self {
+        Self {
+            nodes: parse_nodes(template, true),
+        }
+    }
+}
+
+impl fmt::Display for PromptTemplate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_nodes(&self.nodes, f)
+    }
+}
+
+fn write_nodes(nodes: &[TemplateNode], f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    for node in nodes {
+        match node {
+            TemplateNode::Literal(text) => f.write_str(text)?,
+            TemplateNode::Variable { name, braced: true } => write!(f, "${{{name}}}")?,
+            TemplateNode::Variable { name, braced: false } => write!(f, "${name}")?,
+            TemplateNode::Conditional { name, body, closed } => {
+                write!(f, "${{{name}:+")?;
+                write_nodes(body, f)?;
+                if *closed {
+                    f.write_str("}")?;
+                }
+            },
+        }
+    }
+    Ok(())
+}
+
+/// Parses `template` into nodes. Conditionals are not recognized inside the body of another
+/// conditional, where they are kept as literal text.
+fn parse_nodes(template: &str, conditionals: bool) -> Vec<TemplateNode> {
+    let mut nodes = Vec::new();
+    let mut literal = String::new();
+    let mut rest = template;
+
+    while let Some(idx) = rest.find('$') {
+        literal.push_str(&rest[..idx]);
+        let (node, remaining) = parse_reference(&rest[idx..], conditionals);
+        match node {
+            Some(node) => {
+                if !literal.is_empty() {
+                    nodes.push(TemplateNode::Literal(std::mem::take(&mut literal)));
+                }
+                nodes.push(node);
+            },
+            None => literal.push_str(&rest[idx..rest.len() - remaining.len()]),
+        }
+        rest = remaining;
+    }
+
+    literal.push_str(rest);
+    if !literal.is_empty() {
+        nodes.push(TemplateNode::Literal(literal));
+    }
+    nodes
+}
+
+/// Parses the reference at the start of `input`, which begins with `$`. Returns the node, or
+/// [None] if the text consumed is a literal, along with the remaining input.
+fn parse_reference(input: &str, conditionals: bool) -> (Option<TemplateNode>, &str) {
+    let after_dollar = &input[1..];
+
+    let Some(braced) = after_dollar.strip_prefix('{') else {
+        let len = after_dollar
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+            .unwrap_or(after_dollar.len());
+        if len == 0 || after_dollar.starts_with(|c: char| c.is_ascii_digit()) {
+            return (None, after_dollar);
+        }
+        return (
+            Some(TemplateNode::Variable {
+                name: after_dollar[..len].to_string(),
+                braced: false,
+            }),
+            &after_dollar[len..],
+        );
+    };
+
+    let name_len = braced.find([':', '}']).unwrap_or(braced.len());
+    let name = &braced[..name_len];
+    let rest = &braced[name_len..];
+
+    if let Some(body) = rest.strip_prefix(":+").filter(|_| conditionals) {
+        // The body runs up to the matching closing brace, or the end of the template
+        let mut depth = 0;
+        let end = body.char_indices().find(|&(_, c)| {
+            match c {
+                '{' => depth += 1,
+                '}' if depth == 0 => return true,
+                '}' => depth -= 1,
+                _ => {},
+            }
+            false
+        });
+        let (body, remaining, closed) = match end {
+            Some((idx, _)) => (&body[..idx], &body[idx + 1..], true),
+            None => (body, "", false),
+        };
+        return (
+            Some(TemplateNode::Conditional {
+                name: name.to_string(),
+                body: parse_nodes(body, false),
+                closed,
+            }),
+            remaining,
+        );
+    }
+
+    if rest.starts_with('}') && is_identifier(name) {
+        return (
+            Some(TemplateNode::Variable {
+                name: name.to_string(),
+                braced: true,
+            }),
+            &rest[1..],
+        );
+    }
+
+    // Only the `${` is literal, what follows may still contain references
+    (None, braced)
+}
+
+fn is_identifier(name: &str) -> bool {
+    name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn literal(text: &str) -> TemplateNode {
+        TemplateNode::Literal(text.to_string())
+    }
+
+    fn variable(name: &str, braced: bool) -> TemplateNode {
+        TemplateNode::Variable {
+            name: name.to_string(),
+            braced,
+        }
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            PromptTemplate::parse("${GREEN}$TOKEN_USAGE ${GIT_BRANCH:+(${GIT_BRANCH}) }> ").nodes,
+            vec![
+                variable("GREEN", true),
+                variable("TOKEN_USAGE", false),
+                literal(" "),
+                TemplateNode::Conditional {
+                    name: "GIT_BRANCH".to_string(),
+                    body: vec![literal("("), variable("GIT_BRANCH", true), literal(") ")],
+                    closed: true,
+                },
+                literal("> "),
+            ]
+        );
+
+        // Malformed references are literal text
+        assert_eq!(PromptTemplate::parse("$ $1 ${A:-x} ${A B}").nodes, vec![literal(
+            "$ $1 ${A:-x} ${A B}"
+        )]);
+
+        // Conditionals do not nest, and may be left open
+        assert_eq!(PromptTemplate::parse("${A:+${B:+x}}").nodes, vec![
+            TemplateNode::Conditional {
+                name: "A".to_string(),
+                body: vec![literal("${B:+x}")],
+                closed: true,
+            }
+        ]);
+        assert_eq!(PromptTemplate::parse("${A:+open").nodes, vec![
+            TemplateNode::Conditional {
+                name: "A".to_string(),
+                body: vec![literal("open")],
+                closed: false,
+            }
+        ]);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for template in [
+            "",
+            "> ",
+            "${BOLD}${MAGENTA}➜ ${MODEL}:$TOKEN_USAGE${RESET} ${GREEN}${BOLD}${PWD}${GIT_BRANCH:+:(${YELLOW}${GIT_BRANCH}${RESET}${GREEN}${BOLD})${RESET} }> ",
+            "$$ $1 ${} ${A:-x} ${A B} ${${RED}} ${A:+{nested}} ${A:+${B:+x}} ${A:+unclosed",
+        ] {
+            assert_eq!(PromptTemplate::parse(template).to_string(), template);
+        }
+    }
+}